@@ -0,0 +1,76 @@
+//! Compares parsing aggregation responses as a `Response` and as an `AggsResponse`.
+//!
+//! Run with `cargo bench --bench aggs_response`.
+
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate test;
+
+use elastic_responses::{AggsResponse, Response, parse_slice, parse_slice_as};
+use test::Bencher;
+
+/// Builds a response with a 20k bucket terms aggregation, each with a `stats` sub-aggregation,
+/// and `hits` hits with a few hundred bytes of source each.
+fn body(hits: usize) -> Vec<u8> {
+    let hits: Vec<String> = (0..hits)
+        .map(|i| {
+            format!(r#"{{"_index":"logs","_type":"doc","_id":"{}","_score":1.0,"_source":{{"host":"web-{:02}","path":"/api/v1/items/{}","status":200,"bytes":{},"agent":"Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko)","tags":["api","v1","items"],"geo":{{"lat":52.3,"lon":4.9}}}}}}"#,
+                    i, i % 20, i, i * 17)
+        })
+        .collect();
+    let buckets: Vec<String> = (0..20000)
+        .map(|i| {
+            format!(r#"{{"key":"host-{}","doc_count":{},"bytes":{{"count":{},"min":1.0,"max":{}.0,"avg":{}.5,"sum":{}.0}}}}"#,
+                    i, i % 13, i % 13, i % 100, i % 10, i % 1000)
+        })
+        .collect();
+    format!(r#"{{"took":12,"timed_out":false,"_shards":{{"total":5,"successful":5,"failed":0}},"hits":{{"total":20000,"max_score":1.0,"hits":[{}]}},"aggregations":{{"per_host":{{"buckets":[{}]}}}}}}"#,
+            hits.join(","),
+            buckets.join(","))
+        .into_bytes()
+}
+
+#[bench]
+fn parse_size_0_response(b: &mut Bencher) {
+    let body = body(0);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let response: Response = parse_slice(&body).unwrap();
+        response
+    });
+}
+
+#[bench]
+fn parse_size_0_aggs_response(b: &mut Bencher) {
+    let body = body(0);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let response: AggsResponse = parse_slice_as(&body).unwrap();
+        response
+    });
+}
+
+#[bench]
+fn parse_with_hits_response(b: &mut Bencher) {
+    let body = body(1000);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let response: Response = parse_slice(&body).unwrap();
+        response
+    });
+}
+
+#[bench]
+fn parse_with_hits_aggs_response(b: &mut Bencher) {
+    let body = body(1000);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let response: AggsResponse = parse_slice_as(&body).unwrap();
+        response
+    });
+}
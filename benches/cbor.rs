@@ -0,0 +1,58 @@
+//! Compares parsing the same large search response from JSON and from CBOR.
+//!
+//! Run with `cargo bench --features cbor`.
+
+#![cfg(feature = "cbor")]
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate serde_cbor;
+extern crate serde_json;
+extern crate test;
+
+use elastic_responses::Response;
+use test::Bencher;
+
+/// Builds a response with 500 hits and a 50 x 20 terms aggregation of stats.
+fn large_body() -> String {
+    let hits: Vec<String> = (0..500)
+        .map(|i| {
+            format!(r#"{{"_index":"logs","_type":"doc","_id":"{}","_score":1.0,"_source":{{"host":"web-{:02}","bytes":{},"message":"GET /api/items/{} 200"}}}}"#,
+                    i, i % 50, i * 31, i)
+        })
+        .collect();
+    let hosts: Vec<String> = (0..50)
+        .map(|h| {
+            let paths: Vec<String> = (0..20)
+                .map(|p| {
+                    format!(r#"{{"key":"/api/{}","doc_count":{},"bytes":{{"count":{},"min":1.0,"max":{}.0,"avg":{}.5,"sum":{}.0}}}}"#,
+                            p, p + 1, p + 1, p * 100, p * 10, p * 1000)
+                })
+                .collect();
+            format!(r#"{{"key":"web-{:02}","doc_count":{},"paths":{{"buckets":[{}]}}}}"#, h, h * 20, paths.join(","))
+        })
+        .collect();
+
+    format!(r#"{{"took":12,"timed_out":false,"_shards":{{"total":5,"successful":5,"failed":0}},"hits":{{"total":500,"max_score":1.0,"hits":[{}]}},"aggregations":{{"hosts":{{"buckets":[{}]}}}}}}"#,
+            hits.join(","), hosts.join(","))
+}
+
+#[bench]
+fn parse_json(b: &mut Bencher) {
+    let body = large_body();
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let response: Response = serde_json::from_str(&body).unwrap();
+        response
+    });
+}
+
+#[bench]
+fn parse_cbor(b: &mut Bencher) {
+    let value: serde_json::Value = serde_json::from_str(&large_body()).unwrap();
+    let body = serde_cbor::to_vec(&value).unwrap();
+
+    b.bytes = body.len() as u64;
+    b.iter(|| Response::from_cbor_slice(&body).unwrap());
+}
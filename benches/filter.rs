@@ -0,0 +1,57 @@
+//! Compares taking the time series of one host out of a 500 host `terms` aggregation by pruning
+//! the other hosts' buckets while flattening and by filtering the full flatten afterwards.
+//!
+//! Run with `cargo bench --bench filter`.
+
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate serde_json;
+extern crate test;
+
+use elastic_responses::{BucketFilter, FlattenConfig, OwnedRow, Response, RowAccess};
+use test::Bencher;
+
+/// Builds a response with a terms aggregation of 500 hosts, each with a 50 day histogram of
+/// buckets with a couple of metrics.
+fn body() -> String {
+    let hosts: Vec<String> = (0..500)
+        .map(|h| {
+            let days: Vec<String> = (0..50)
+                .map(|d| {
+                    format!(r#"{{"key":{},"key_as_string":"day {}","doc_count":4,"cpu_avg":{{"value":{}.5}},"mem":{{"count":4,"min":1,"max":{},"avg":2,"sum":8}}}}"#,
+                            d * 86400000, d, h % 100, d + 3)
+                })
+                .collect();
+            format!(r#"{{"key":"web-{:03}","doc_count":200,"per_day":{{"buckets":[{}]}}}}"#, h, days.join(","))
+        })
+        .collect();
+    format!(r#"{{"took":7,"timed_out":false,"_shards":{{"total":5,"successful":5,"failed":0}},"hits":{{"total":100000,"max_score":0.0,"hits":[]}},"aggregations":{{"per_host":{{"buckets":[{}]}}}}}}"#,
+            hosts.join(","))
+}
+
+#[bench]
+fn rows_filtered_after(b: &mut Bencher) {
+    let response: Response = serde_json::from_str(&body()).unwrap();
+
+    b.iter(|| {
+        let rows: Vec<OwnedRow> = response.aggs()
+            .rows_with(FlattenConfig::new())
+            .filter(|row| row.get_str("per_host") == Some("web-042"))
+            .collect();
+        assert_eq!(rows.len(), 50);
+        rows
+    });
+}
+
+#[bench]
+fn rows_pruned(b: &mut Bencher) {
+    let response: Response = serde_json::from_str(&body()).unwrap();
+
+    b.iter(|| {
+        let config = FlattenConfig::new().filter_bucket("per_host", BucketFilter::Equals("web-042".to_owned()));
+        let rows: Vec<OwnedRow> = response.aggs().rows_with(config).collect();
+        assert_eq!(rows.len(), 50);
+        rows
+    });
+}
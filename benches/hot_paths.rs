@@ -0,0 +1,60 @@
+//! The hot paths of the crate on large bodies: parsing a 50 MB body of hits and an aggregation
+//! body of 100k buckets, then iterating the rows, writing them as CSV and deserializing them
+//! into structs. The bodies are generated by `support`, and the `hot_paths` test runs each
+//! benchmark once on small ones.
+//!
+//! Run with `cargo bench --bench hot_paths`.
+
+#![feature(test)]
+
+extern crate elastic_responses;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate test;
+
+mod support;
+
+use test::{Bencher, black_box};
+
+const HITS_BYTES: usize = 50 * 1024 * 1024;
+const HOSTS: usize = 1000;
+const HOURS: usize = 100;
+
+#[bench]
+fn parse_50mb_hits(b: &mut Bencher) {
+    let body = support::hits_body(HITS_BYTES, 1);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| support::parse_hits(&body));
+}
+
+#[bench]
+fn parse_100k_buckets(b: &mut Bencher) {
+    let body = support::buckets_body(HOSTS, HOURS, 2);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| black_box(support::parse_aggs(&body)));
+}
+
+#[bench]
+fn flatten_100k_buckets(b: &mut Bencher) {
+    let response = support::parse_aggs(&support::buckets_body(HOSTS, HOURS, 2));
+
+    b.iter(|| support::flatten(&response));
+}
+
+#[bench]
+fn csv_100k_buckets(b: &mut Bencher) {
+    let response = support::parse_aggs(&support::buckets_body(HOSTS, HOURS, 2));
+    let mut out = Vec::new();
+
+    b.iter(|| support::csv(&response, &mut out));
+}
+
+#[bench]
+fn typed_rows_100k_buckets(b: &mut Bencher) {
+    let response = support::parse_aggs(&support::buckets_body(HOSTS, HOURS, 2));
+
+    b.iter(|| support::typed_rows(&response));
+}
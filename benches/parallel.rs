@@ -0,0 +1,51 @@
+//! Compares flattening a large aggregation on one thread and with rayon.
+//!
+//! Run with `cargo bench --features rayon`.
+
+#![cfg(feature = "rayon")]
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate rayon;
+extern crate serde_json;
+extern crate test;
+
+use elastic_responses::{OwnedRow, Response};
+use rayon::prelude::*;
+use test::Bencher;
+
+/// Builds a response with a 500k bucket histogram, each with a `stats` sub-aggregation.
+fn large_response() -> Response {
+    let buckets: Vec<String> = (0..500000)
+        .map(|i| {
+            format!(r#"{{"key":{},"doc_count":{},"bytes":{{"count":{},"min":1.0,"max":{}.0,"avg":{}.5,"sum":{}.0}}}}"#,
+                    i * 60000, i % 13, i % 13, i % 100, i % 10, i % 1000)
+        })
+        .collect();
+    let body = format!(r#"{{"took":12,"timed_out":false,"_shards":{{"total":5,"successful":5,"failed":0}},"hits":{{"total":0,"max_score":null,"hits":[]}},"aggregations":{{"per_minute":{{"buckets":[{}]}}}}}}"#,
+                       buckets.join(","));
+    serde_json::from_str(&body).unwrap()
+}
+
+#[bench]
+fn flatten_sequential(b: &mut Bencher) {
+    let response = large_response();
+
+    b.iter(|| {
+        let rows: Vec<OwnedRow> = response.aggs()
+            .into_iter()
+            .map(|row| row.into_iter().map(|(k, v)| (k.into_owned(), v.clone())).collect())
+            .collect();
+        rows
+    });
+}
+
+#[bench]
+fn flatten_parallel(b: &mut Bencher) {
+    let response = large_response();
+
+    b.iter(|| {
+        let rows: Vec<OwnedRow> = response.aggs().par_rows().collect();
+        rows
+    });
+}
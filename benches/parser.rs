@@ -0,0 +1,63 @@
+//! Compares 1000 sequential parses of a medium response with a fresh body buffer each time and
+//! with a `Parser` that reuses its buffer.
+//!
+//! Run with `cargo bench --bench parser`.
+
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate test;
+
+use elastic_responses::{Parser, Response, parse_slice};
+use std::fs::File;
+use std::io::{self, Read};
+use test::Bencher;
+
+const PARSES: usize = 1000;
+
+fn load_sample() -> Vec<u8> {
+    let mut body = Vec::new();
+    File::open("tests/samples/aggregation_simple.json").unwrap().read_to_end(&mut body).unwrap();
+    body
+}
+
+/// Reader that hands out a body in small chunks, like a network stream.
+struct Chunked<'a>(&'a [u8]);
+
+impl<'a> Read for Chunked<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1460);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+#[bench]
+fn parse_fresh_buffers(b: &mut Bencher) {
+    let body = load_sample();
+
+    b.bytes = (body.len() * PARSES) as u64;
+    b.iter(|| {
+        for _ in 0..PARSES {
+            let mut read = Vec::new();
+            Chunked(&body).read_to_end(&mut read).unwrap();
+            let response: Response = parse_slice(&read).unwrap();
+            test::black_box(response);
+        }
+    });
+}
+
+#[bench]
+fn parse_reused_buffers(b: &mut Bencher) {
+    let body = load_sample();
+    let mut parser = Parser::new();
+
+    b.bytes = (body.len() * PARSES) as u64;
+    b.iter(|| {
+        for _ in 0..PARSES {
+            let response: Response = parser.parse(&mut Chunked(&body)).unwrap();
+            test::black_box(response);
+        }
+    });
+}
@@ -0,0 +1,56 @@
+//! Compares flattening a wide aggregation tree into owned rows with all of its columns and
+//! projected down to three of them.
+//!
+//! Run with `cargo bench --bench projection`.
+
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate serde_json;
+extern crate test;
+
+use elastic_responses::{FlattenConfig, OwnedRow, Response};
+use test::Bencher;
+
+/// Builds a response with a 100 day histogram of 20 hosts each, every host bucket carrying a
+/// dozen metrics that flatten into about 40 columns.
+fn body() -> String {
+    let stats = |n: usize| format!(r#"{{"count":4,"min":{0},"max":{1},"avg":{2},"sum":{3},"sum_of_squares":{4},"variance":1.5,"std_deviation":1.2247,"std_deviation_bounds":{{"upper":{5},"lower":{6}}}}}"#,
+                                   n, n * 3, n * 2, n * 8, n * n * 20, n * 2 + 2, n * 2 - 2);
+    let days: Vec<String> = (0..100)
+        .map(|d| {
+            let hosts: Vec<String> = (0..20)
+                .map(|h| {
+                    let n = d + h + 2;
+                    format!(r#"{{"key":"web-{:02}","doc_count":4,"cpu_avg":{{"value":{}.5}},"cpu_max":{{"value":{}}},"mem_avg":{{"value":{}}},"mem_max":{{"value":{}}},"load_1":{{"value":0.{}}},"load_5":{{"value":0.{}}},"load_15":{{"value":0.{}}},"net_in":{{"value":{}}},"net_out":{{"value":{}}},"disk":{},"io":{},"errors":{{"doc_count":{},"errors_rate":{{"value":0.25}}}}}}"#,
+                            h, n, n * 2, n * 100, n * 150, n % 10, n % 7, n % 5, n * 1000, n * 800, stats(n), stats(n * 2), h % 3)
+                })
+                .collect();
+            format!(r#"{{"key":{},"key_as_string":"day {}","doc_count":80,"per_host":{{"buckets":[{}]}}}}"#,
+                    d * 86400000, d, hosts.join(","))
+        })
+        .collect();
+    format!(r#"{{"took":7,"timed_out":false,"_shards":{{"total":5,"successful":5,"failed":0}},"hits":{{"total":8000,"max_score":0.0,"hits":[]}},"aggregations":{{"per_day":{{"buckets":[{}]}}}}}}"#,
+            days.join(","))
+}
+
+#[bench]
+fn rows_all_columns(b: &mut Bencher) {
+    let response: Response = serde_json::from_str(&body()).unwrap();
+
+    b.iter(|| {
+        let rows: Vec<OwnedRow> = response.aggs().rows_with(FlattenConfig::new()).collect();
+        rows
+    });
+}
+
+#[bench]
+fn rows_projected_columns(b: &mut Bencher) {
+    let response: Response = serde_json::from_str(&body()).unwrap();
+
+    b.iter(|| {
+        let config = FlattenConfig::new().project(&["per_day", "per_host", "cpu_avg"]);
+        let rows: Vec<OwnedRow> = response.aggs().rows_with(config).collect();
+        rows
+    });
+}
@@ -0,0 +1,63 @@
+//! Compares parsing the largest sample responses with serde_json and with simd-json.
+//!
+//! Run with `cargo bench --features simd-json`.
+
+#![cfg(feature = "simd-json")]
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate test;
+
+use elastic_responses::{Response, parse_slice};
+use std::fs::File;
+use std::io::Read;
+use test::Bencher;
+
+fn load_sample(name: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    File::open(format!("tests/samples/{}.json", name)).unwrap().read_to_end(&mut body).unwrap();
+    body
+}
+
+fn bench_serde_json(b: &mut Bencher, name: &str) {
+    let body = load_sample(name);
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let response: Response = parse_slice(&body).unwrap();
+        response
+    });
+}
+
+fn bench_simd_json(b: &mut Bencher, name: &str) {
+    let body = load_sample(name);
+    let mut scratch = body.clone();
+
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        // from_slice_mut leaves the body unusable, so each run parses a fresh copy
+        scratch.copy_from_slice(&body);
+        let response = Response::from_slice_mut(&mut scratch).unwrap();
+        response
+    });
+}
+
+#[bench]
+fn parse_3level_multichild_serde_json(b: &mut Bencher) {
+    bench_serde_json(b, "aggregation_3level_multichild")
+}
+
+#[bench]
+fn parse_3level_multichild_simd_json(b: &mut Bencher) {
+    bench_simd_json(b, "aggregation_3level_multichild")
+}
+
+#[bench]
+fn parse_3level_multistats_serde_json(b: &mut Bencher) {
+    bench_serde_json(b, "aggregation_3level_multistats")
+}
+
+#[bench]
+fn parse_3level_multistats_simd_json(b: &mut Bencher) {
+    bench_simd_json(b, "aggregation_3level_multistats")
+}
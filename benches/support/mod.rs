@@ -0,0 +1,150 @@
+//! The bodies and hot paths of the `hot_paths` benchmarks, shared with the `hot_paths` test
+//! that runs each of them once on small bodies to keep them compiling and working.
+//!
+//! The bodies are generated from a fixed seed rather than checked in, so every run measures the
+//! same bytes without keeping 50 MB of fixtures in the repository.
+
+use elastic_responses::{Response, RowAccess, parse_slice};
+use serde_json::{self, Value};
+use std::io::Write;
+
+/// A xorshift generator, which is all the randomness the bodies need.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift is stuck at zero
+        Rng(seed | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+const WORDS: &'static [&'static str] = &["index", "shard", "query", "bucket", "field", "score", "node", "cluster",
+                                         "mapping", "segment", "replica", "refresh"];
+
+/// Builds a search response with hits of log documents until the body is at least `bytes`
+/// long.
+pub fn hits_body(bytes: usize, seed: u64) -> Vec<u8> {
+    let mut rng = Rng::new(seed);
+    let mut body = Vec::with_capacity(bytes + 1024);
+    body.extend_from_slice(br#"{"took":42,"timed_out":false,"_shards":{"total":5,"successful":5,"failed":0},"hits":{"total":{"value":10000,"relation":"gte"},"max_score":1.0,"hits":["#);
+
+    let mut i = 0;
+    while body.len() < bytes {
+        if i > 0 {
+            body.push(b',');
+        }
+        let message: Vec<&str> = (0..8 + rng.below(40)).map(|_| WORDS[rng.below(WORDS.len() as u64) as usize]).collect();
+        write!(body,
+               r#"{{"_index":"logs-{}","_id":"{}","_score":{}.{},"_source":{{"@timestamp":"2024-05-{:02}T{:02}:{:02}:00Z","host":"web-{:02}","status":{},"bytes":{},"message":"{}","tags":["{}","{}"],"geo":{{"lat":{}.{},"lon":{}.{}}}}}}}"#,
+               rng.below(4), i, rng.below(10), rng.below(1000), 1 + rng.below(28), rng.below(24), rng.below(60),
+               rng.below(50), [200, 201, 404, 500][rng.below(4) as usize], rng.below(100_000), message.join(" "),
+               WORDS[rng.below(12) as usize], WORDS[rng.below(12) as usize], rng.below(90), rng.below(1000),
+               rng.below(180), rng.below(1000))
+            .unwrap();
+        i += 1;
+    }
+    body.extend_from_slice(b"]}}");
+    body
+}
+
+/// Builds an aggregation response with `hosts` terms buckets, each with `hours` `per_hour` date
+/// histogram buckets with an `avg` of `bytes`, for `hosts * hours` rows.
+pub fn buckets_body(hosts: usize, hours: usize, seed: u64) -> Vec<u8> {
+    let mut rng = Rng::new(seed);
+    let mut body = Vec::new();
+    body.extend_from_slice(br#"{"took":310,"timed_out":false,"_shards":{"total":5,"successful":5,"failed":0},"hits":{"total":{"value":10000,"relation":"gte"},"max_score":null,"hits":[]},"aggregations":{"hosts":{"doc_count_error_upper_bound":0,"sum_other_doc_count":0,"buckets":["#);
+
+    for host in 0..hosts {
+        if host > 0 {
+            body.push(b',');
+        }
+        write!(body, r#"{{"key":"host-{}","doc_count":{},"per_hour":{{"buckets":["#, host, rng.below(1_000_000)).unwrap();
+        for hour in 0..hours {
+            if hour > 0 {
+                body.push(b',');
+            }
+            let key = 1_714_521_600_000 + hour as u64 * 3_600_000;
+            write!(body,
+                   r#"{{"key_as_string":"{}","key":{},"doc_count":{},"bytes":{{"value":{}.{}}}}}"#,
+                   key / 1000, key, rng.below(10_000), rng.below(100_000), rng.below(100))
+                .unwrap();
+        }
+        body.extend_from_slice(b"]}}");
+    }
+    body.extend_from_slice(b"]}}}");
+    body
+}
+
+/// Parses a body and returns the number of hits, so the parse isn't optimized away.
+pub fn parse_hits(body: &[u8]) -> usize {
+    let response: Response = parse_slice(body).unwrap();
+    response.hits().len()
+}
+
+/// Parses a body for its aggregations.
+pub fn parse_aggs(body: &[u8]) -> Response {
+    parse_slice(body).unwrap()
+}
+
+/// Iterates over all the rows of the aggregations, and returns the number of cells in them.
+pub fn flatten(response: &Response) -> usize {
+    response.aggs().into_iter().map(|row| row.len()).sum()
+}
+
+/// Writes the rows of the aggregations as CSV, with a header of the columns, the way an
+/// exporter on top of the row iterator would as the crate has no CSV writer of its own.
+pub fn csv(response: &Response, out: &mut Vec<u8>) -> usize {
+    out.clear();
+    let aggs = response.aggs();
+    let columns = aggs.columns();
+    out.extend_from_slice(columns.join(",").as_bytes());
+    out.push(b'\n');
+
+    for row in aggs {
+        for (i, column) in columns.iter().enumerate() {
+            if i > 0 {
+                out.push(b',');
+            }
+            match row.column(column) {
+                Some(&Value::String(ref s)) if s.contains(&[',', '"', '\n'][..]) => {
+                    write!(out, "\"{}\"", s.replace('"', "\"\"")).unwrap()
+                }
+                Some(&Value::String(ref s)) => out.extend_from_slice(s.as_bytes()),
+                Some(&Value::Null) | None => (),
+                Some(value) => write!(out, "{}", value).unwrap()
+            }
+        }
+        out.push(b'\n');
+    }
+    out.len()
+}
+
+/// A row of `buckets_body`, as a consumer would deserialize it.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+pub struct HourRow {
+    pub hosts: String,
+    pub per_hour: u64,
+    pub per_hour_doc_count: u64,
+    pub bytes: Option<f64>
+}
+
+/// Deserializes every row of the aggregations into a `HourRow`, and sums their bytes.
+pub fn typed_rows(response: &Response) -> f64 {
+    response.aggs()
+        .into_iter()
+        .map(|row| serde_json::from_value::<HourRow>(Value::Object(row.into_owned())).unwrap())
+        .filter_map(|row| row.bytes)
+        .sum()
+}
@@ -0,0 +1,69 @@
+//! Compares exporting 10,000 tiny aggregation responses, a handful of `terms` buckets or none at
+//! all, through the fast paths and through the row iterator. The `nested` cases carry an empty
+//! keyed sub-aggregation in each bucket, which gives the same rows but takes the iterator.
+//!
+//! Run with `cargo bench --bench tiny_exports`, and `--features arrow` for the record batches.
+
+#![feature(test)]
+
+extern crate elastic_responses;
+extern crate serde_json;
+extern crate test;
+
+use elastic_responses::Response;
+use test::Bencher;
+
+fn responses(buckets: usize, nested: bool) -> Vec<Response> {
+    (0..10_000)
+        .map(|i| {
+            let buckets: Vec<String> = (0..buckets)
+                .map(|b| {
+                    let keyed = if nested { r#","keyed":{"buckets":{}}"# } else { "" };
+                    format!(r#"{{"key":"host-{}","doc_count":{}{}}}"#, b, i % 100 + b, keyed)
+                })
+                .collect();
+            let body = format!(r#"{{"took":1,"timed_out":false,"_shards":{{"total":1,"successful":1,"failed":0}},"hits":{{"total":{},"max_score":0.0,"hits":[]}},"aggregations":{{"hosts":{{"doc_count_error_upper_bound":0,"sum_other_doc_count":0,"buckets":[{}]}}}}}}"#,
+                               i % 100, buckets.join(","));
+            serde_json::from_str(&body).unwrap()
+        })
+        .collect()
+}
+
+#[bench]
+fn pretty_single_bucket_agg(b: &mut Bencher) {
+    let responses = responses(3, false);
+    b.iter(|| responses.iter().map(|r| r.aggs().pretty().to_string().len()).sum::<usize>());
+}
+
+#[bench]
+fn pretty_single_bucket_agg_nested(b: &mut Bencher) {
+    let responses = responses(3, true);
+    b.iter(|| responses.iter().map(|r| r.aggs().pretty().to_string().len()).sum::<usize>());
+}
+
+#[bench]
+fn pretty_zero_buckets(b: &mut Bencher) {
+    let responses = responses(0, false);
+    b.iter(|| responses.iter().map(|r| r.aggs().pretty().to_string().len()).sum::<usize>());
+}
+
+#[cfg(feature = "arrow")]
+#[bench]
+fn record_batch_single_bucket_agg(b: &mut Bencher) {
+    let responses = responses(3, false);
+    b.iter(|| responses.iter().map(|r| r.aggs().to_record_batch().unwrap().batch().num_rows()).sum::<usize>());
+}
+
+#[cfg(feature = "arrow")]
+#[bench]
+fn record_batch_single_bucket_agg_nested(b: &mut Bencher) {
+    let responses = responses(3, true);
+    b.iter(|| responses.iter().map(|r| r.aggs().to_record_batch().unwrap().batch().num_rows()).sum::<usize>());
+}
+
+#[cfg(feature = "arrow")]
+#[bench]
+fn record_batch_zero_buckets(b: &mut Bencher) {
+    let responses = responses(0, false);
+    b.iter(|| responses.iter().map(|r| r.aggs().to_record_batch().unwrap().batch().num_rows()).sum::<usize>());
+}
@@ -0,0 +1,52 @@
+//! Parses a captured search response and walks its hits and aggregations through the `search`
+//! module, reporting failures with the types of the `error` module.
+//!
+//! Run with `cargo run --example layout [path]`, which defaults to one of the test samples.
+
+extern crate elastic_responses;
+extern crate serde_json;
+
+use elastic_responses::error::Error;
+use elastic_responses::parse_slice;
+use elastic_responses::search::{Hit, Response, TotalHitsRelation};
+use elastic_responses::search::aggregations::RowAccess;
+use serde_json::Value;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+
+fn describe_hit(hit: &Hit<Value>) -> String {
+    format!("{}/{} ({:?})", hit.index(), hit.id(), hit.score())
+}
+
+fn describe_row<R: RowAccess>(row: R, keys: &[String]) -> String {
+    let key: Vec<String> = keys.iter().filter_map(|k| row.key_string(k)).collect();
+    let docs = keys.last().and_then(|k| row.get_f64(&format!("{}_doc_count", k)));
+    format!("{} ({:?} documents)", key.join(" / "), docs)
+}
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| "tests/samples/aggregation_date_terms_avg.json".to_owned());
+    let mut body = Vec::new();
+    File::open(&path).and_then(|mut f| f.read_to_end(&mut body)).unwrap();
+
+    let response: Response = match parse_slice(&body) {
+        Ok(response) => response,
+        Err(Error::Parse(err, context)) => panic!("{} doesn't parse at {:?}: {}", path, context, err),
+        Err(e) => panic!("{} doesn't parse: {}", path, e)
+    };
+
+    println!("took {}ms", response.took());
+    if let Some(total) = response.hits().total() {
+        let bound = if total.relation() == TotalHitsRelation::Eq { "" } else { "at least " };
+        println!("{}{} hits", bound, total.value());
+    }
+    for hit in response.hits() {
+        println!("  {}", describe_hit(hit));
+    }
+
+    let keys = response.aggs().key_columns();
+    for row in response.aggs() {
+        println!("  {}", describe_row(&row, &keys));
+    }
+}
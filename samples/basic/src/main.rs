@@ -12,7 +12,8 @@ extern crate elastic_responses;
 
 use elastic_reqwest::{ElasticClient};
 use elastic_requests::SearchRequest;
-use elastic_responses::Response as EsResponse;
+use elastic_responses::search::Response as EsResponse;
+use elastic_responses::search::aggregations::RowAccess;
 
 fn main() {
 
@@ -83,6 +84,6 @@ fn main() {
 
     //Agregations
     for i in body_as_json.aggs() {
-        println!("{:?}",i);
+        println!("{:?} {:?}: {:?}", i.key_string("type"), i.key_string("index"), i.get_f64("index_doc_count"));
     }
 }
@@ -0,0 +1,81 @@
+//! Responses of the smaller administrative APIs.
+
+use serde_json::Value;
+
+/// Response of requests that only report whether they were acknowledged, such as storing or
+/// deleting a script.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AcknowledgedResponse {
+    acknowledged: bool
+}
+
+impl AcknowledgedResponse {
+    /// Returns `true` if the master acknowledged the change.
+    pub fn acknowledged(&self) -> bool {
+        self.acknowledged
+    }
+}
+
+/// Response of a `_render/template` request.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RenderTemplateResponse {
+    template_output: Value
+}
+
+impl RenderTemplateResponse {
+    /// Returns the rendered search body.
+    pub fn template_output(&self) -> &Value {
+        &self.template_output
+    }
+
+    /// Returns the `query` object of the rendered search body.
+    pub fn query(&self) -> Option<&Value> {
+        self.template_output.find("query")
+    }
+}
+
+/// Response of a `GET _scripts/{id}` request.
+///
+/// An unknown id isn't an error: `found` is `false` and there is no script.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct GetScriptResponse {
+    _id: String,
+    found: bool,
+    script: Option<StoredScript>
+}
+
+impl GetScriptResponse {
+    /// Returns the id of the script.
+    pub fn id(&self) -> &str {
+        &self._id
+    }
+
+    /// Returns `true` if a script is stored under the id.
+    pub fn found(&self) -> bool {
+        self.found
+    }
+
+    /// Returns the stored script, if it was found.
+    pub fn script(&self) -> Option<&StoredScript> {
+        self.script.as_ref()
+    }
+}
+
+/// A stored script or search template.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct StoredScript {
+    lang: String,
+    source: String
+}
+
+impl StoredScript {
+    /// Returns the language of the script, e.g. `painless` or `mustache`.
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// Returns the source of the script.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
@@ -0,0 +1,97 @@
+//! Response of the `_alias` and `_aliases` read APIs.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Response of a get aliases request: the aliases of each index.
+///
+/// Indices without aliases are listed with an empty alias map.
+#[derive(Deserialize, Debug)]
+pub struct AliasesResponse(BTreeMap<String, IndexAliases>);
+
+#[derive(Deserialize, Debug)]
+struct IndexAliases {
+    #[serde(default)]
+    aliases: BTreeMap<String, AliasInfo>
+}
+
+impl AliasesResponse {
+    /// Returns the aliases pointing at `index`, ordered by alias name.
+    pub fn aliases_for_index(&self, index: &str) -> Vec<(&str, &AliasInfo)> {
+        self.0
+            .get(index)
+            .map(|i| i.aliases.iter().map(|(name, info)| (&**name, info)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the indices `alias` points at, ordered by index name.
+    pub fn indices_for_alias(&self, alias: &str) -> Vec<(&str, &AliasInfo)> {
+        self.0
+            .iter()
+            .filter_map(|(index, i)| i.aliases.get(alias).map(|info| (&**index, info)))
+            .collect()
+    }
+
+    /// Returns the index that writes through `alias` go to.
+    ///
+    /// An alias pointing at a single index writes to it unless `is_write_index` is `false`.
+    pub fn write_index(&self, alias: &str) -> Option<&str> {
+        let indices = self.indices_for_alias(alias);
+        if indices.len() == 1 && indices[0].1.is_write_index != Some(false) {
+            return Some(indices[0].0);
+        }
+        indices.iter().find(|&&(_, info)| info.is_write_index == Some(true)).map(|&(index, _)| index)
+    }
+
+    /// Returns the names of all indices in the response.
+    pub fn indices(&self) -> Vec<&str> {
+        self.0.keys().map(|k| &**k).collect()
+    }
+}
+
+/// The properties of an alias on one index.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AliasInfo {
+    filter: Option<Value>,
+    index_routing: Option<String>,
+    search_routing: Option<String>,
+    is_write_index: Option<bool>,
+    is_hidden: Option<bool>
+}
+
+impl AliasInfo {
+    /// Returns the raw filter query of a filtered alias.
+    pub fn filter(&self) -> Option<&Value> {
+        self.filter.as_ref()
+    }
+
+    /// Returns `true` if the alias restricts searches with a filter.
+    pub fn is_filtered(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Returns the routing used when indexing through the alias.
+    pub fn index_routing(&self) -> Option<&str> {
+        self.index_routing.as_ref().map(|r| &**r)
+    }
+
+    /// Returns the routing used when searching through the alias.
+    pub fn search_routing(&self) -> Option<&str> {
+        self.search_routing.as_ref().map(|r| &**r)
+    }
+
+    /// Returns `true` if the alias has index or search routing.
+    pub fn is_routed(&self) -> bool {
+        self.index_routing.is_some() || self.search_routing.is_some()
+    }
+
+    /// Returns the `is_write_index` flag, which is only set explicitly.
+    pub fn is_write_index(&self) -> Option<bool> {
+        self.is_write_index
+    }
+
+    /// Returns the `is_hidden` flag, which is only set explicitly.
+    pub fn is_hidden(&self) -> Option<bool> {
+        self.is_hidden
+    }
+}
@@ -0,0 +1,204 @@
+//! Response of the `_cluster/allocation/explain` API.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::Value;
+
+/// Response of an allocation explain request.
+#[derive(Deserialize, Debug)]
+pub struct AllocationExplainResponse {
+    index: String,
+    shard: u32,
+    primary: bool,
+    current_state: String,
+    unassigned_info: Option<UnassignedInfo>,
+    can_allocate: Option<String>,
+    allocate_explanation: Option<String>,
+    #[serde(default)]
+    node_allocation_decisions: Vec<NodeDecision>
+}
+
+impl AllocationExplainResponse {
+    /// Returns the index of the explained shard.
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+
+    /// Returns the number of the explained shard.
+    pub fn shard(&self) -> u32 {
+        self.shard
+    }
+
+    /// Returns `true` if the explained shard is a primary.
+    pub fn primary(&self) -> bool {
+        self.primary
+    }
+
+    /// Returns the state of the shard, e.g. `unassigned` or `started`.
+    pub fn current_state(&self) -> &str {
+        &self.current_state
+    }
+
+    /// Returns why the shard is unassigned.
+    pub fn unassigned_info(&self) -> Option<&UnassignedInfo> {
+        self.unassigned_info.as_ref()
+    }
+
+    /// Returns whether the shard can be allocated, e.g. `no` or `throttled`.
+    pub fn can_allocate(&self) -> Option<&str> {
+        self.can_allocate.as_ref().map(|c| &**c)
+    }
+
+    /// Returns the summary of the allocation decision.
+    pub fn allocate_explanation(&self) -> Option<&str> {
+        self.allocate_explanation.as_ref().map(|e| &**e)
+    }
+
+    /// Returns the decision taken for each node.
+    pub fn node_allocation_decisions(&self) -> &[NodeDecision] {
+        &self.node_allocation_decisions
+    }
+
+    /// Collects every `NO` decider over all nodes as `(node name, decider, explanation)`.
+    pub fn no_decisions(&self) -> Vec<(&str, &str, &str)> {
+        self.node_allocation_decisions
+            .iter()
+            .flat_map(|n| {
+                n.deciders
+                    .iter()
+                    .filter(|d| d.decision == Decision::No)
+                    .map(move |d| (&*n.node_name, &*d.decider, &*d.explanation))
+            })
+            .collect()
+    }
+}
+
+/// Why and since when a shard is unassigned.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct UnassignedInfo {
+    reason: String,
+    at: String,
+    details: Option<String>,
+    last_allocation_status: Option<String>
+}
+
+impl UnassignedInfo {
+    /// Returns the reason the shard became unassigned, e.g. `NODE_LEFT`.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Returns when the shard became unassigned.
+    pub fn at(&self) -> &str {
+        &self.at
+    }
+
+    /// Returns the details of the reason.
+    pub fn details(&self) -> Option<&str> {
+        self.details.as_ref().map(|d| &**d)
+    }
+
+    /// Returns the result of the last allocation attempt, e.g. `no_attempt`.
+    pub fn last_allocation_status(&self) -> Option<&str> {
+        self.last_allocation_status.as_ref().map(|s| &**s)
+    }
+}
+
+/// The allocation decision for one node.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct NodeDecision {
+    node_id: String,
+    node_name: String,
+    transport_address: Option<String>,
+    node_attributes: Option<Value>,
+    node_decision: String,
+    weight_ranking: Option<u32>,
+    #[serde(default)]
+    deciders: Vec<Decider>
+}
+
+impl NodeDecision {
+    /// Returns the id of the node.
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Returns the name of the node.
+    pub fn node_name(&self) -> &str {
+        &self.node_name
+    }
+
+    /// Returns the transport address of the node.
+    pub fn transport_address(&self) -> Option<&str> {
+        self.transport_address.as_ref().map(|a| &**a)
+    }
+
+    /// Returns the raw attributes of the node.
+    pub fn node_attributes(&self) -> Option<&Value> {
+        self.node_attributes.as_ref()
+    }
+
+    /// Returns the decision for the node, e.g. `no` or `worse_balance`.
+    pub fn node_decision(&self) -> &str {
+        &self.node_decision
+    }
+
+    /// Returns how the node ranks for the shard, lower being better.
+    pub fn weight_ranking(&self) -> Option<u32> {
+        self.weight_ranking
+    }
+
+    /// Returns the deciders that took part in the decision.
+    pub fn deciders(&self) -> &[Decider] {
+        &self.deciders
+    }
+}
+
+/// The verdict of a single allocation decider.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Decider {
+    decider: String,
+    decision: Decision,
+    explanation: String
+}
+
+impl Decider {
+    /// Returns the name of the decider, e.g. `disk_threshold`.
+    pub fn decider(&self) -> &str {
+        &self.decider
+    }
+
+    /// Returns the verdict of the decider.
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+
+    /// Returns why the decider decided so.
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+}
+
+/// The verdict of an allocation decider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// `YES`, the decider allows the shard on the node.
+    Yes,
+    /// `NO`, the decider doesn't allow the shard on the node.
+    No,
+    /// `THROTTLE`, the decider allows the shard on the node but not yet, usually because the
+    /// node is already recovering as many shards as it may at once, so it is allocated later.
+    Throttle
+}
+
+impl Deserialize for Decision {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Decision, D::Error>
+        where D: Deserializer
+    {
+        match &*String::deserialize(deserializer)?.to_uppercase() {
+            "YES" => Ok(Decision::Yes),
+            "NO" => Ok(Decision::No),
+            "THROTTLE" => Ok(Decision::Throttle),
+            other => Err(D::Error::unknown_variant(other))
+        }
+    }
+}
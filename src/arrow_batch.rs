@@ -0,0 +1,191 @@
+//! Export of hit tables and aggregation rows as Arrow record batches.
+
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray, TimestampMillisecondArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use serde_json::Value;
+use std::sync::Arc;
+
+use {Aggregations, HitsTable, KeyType};
+
+/// A record batch along with the warnings raised while inferring its column types.
+#[derive(Debug, Clone)]
+pub struct RecordBatchExport {
+    batch: RecordBatch,
+    warnings: Vec<String>
+}
+
+impl RecordBatchExport {
+    /// Returns the record batch.
+    pub fn batch(&self) -> &RecordBatch {
+        &self.batch
+    }
+
+    /// Returns the record batch, dropping the warnings.
+    pub fn into_batch(self) -> RecordBatch {
+        self.batch
+    }
+
+    /// Returns a warning for each column whose values had conflicting types and were exported
+    /// as `Utf8`.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+impl HitsTable {
+    /// Converts the table into an Arrow record batch, inferring the type of each column.
+    ///
+    /// Columns of booleans, integers, numbers or strings become `Boolean`, `Int64`, `Float64`
+    /// and `Utf8` columns, missing values become nulls, and anything else is exported as JSON
+    /// text in a `Utf8` column.
+    pub fn to_record_batch(&self) -> Result<RecordBatchExport, ArrowError> {
+        let columns = self.column_names()
+            .into_iter()
+            .map(|name| {
+                let values = self.column(name).unwrap_or(&[]).iter().map(Option::as_ref).collect();
+                (name.to_owned(), values, None, false)
+            })
+            .collect();
+
+        build_batch(columns, self.len(), Vec::new())
+    }
+}
+
+impl Aggregations {
+    /// Converts the aggregation rows into an Arrow record batch, with one column per entry of
+    /// `columns()`.
+    ///
+    /// Types are inferred as for `HitsTable::to_record_batch`, except for bucket keys, whose
+    /// columns get the type of `key_types`: keys of different types become `Utf8` along with a
+    /// warning. The integer keys of buckets that come with a `key_as_string`, like those of a
+    /// `date_histogram`, become millisecond `Timestamp` columns.
+    pub fn to_record_batch(&self) -> Result<RecordBatchExport, ArrowError> {
+        let key_types = self.key_types();
+        let (names, values, len) = match self.flat_columns() {
+            Some(flat) => (flat.names, flat.values, flat.len),
+            None => {
+                let names = self.columns();
+                let rows: Vec<_> = self.into_iter().collect();
+                let values = names.iter().map(|name| rows.iter().map(|r| r.get(&**name).map(|v| *v)).collect()).collect();
+                (names, values, rows.len())
+            }
+        };
+        let columns = names.iter()
+            .zip(values)
+            .map(|(name, values)| {
+                let key_type = key_types.get(name);
+                let timestamp = key_type == Some(KeyType::I64) && names.contains(&format!("{}_key_as_string", name));
+                (name.clone(), values, key_type, timestamp)
+            })
+            .collect();
+
+        build_batch(columns, len, key_types.warnings().to_vec())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Inferred {
+    Null,
+    Boolean,
+    Int64,
+    Float64,
+    Utf8,
+    Json
+}
+
+fn infer(values: &[Option<&Value>]) -> (Inferred, bool) {
+    let mut inferred = Inferred::Null;
+    let mut conflict = false;
+
+    for value in values.iter().filter_map(|v| *v) {
+        let ty = match *value {
+            Value::Null => continue,
+            Value::Bool(_) => Inferred::Boolean,
+            Value::I64(_) => Inferred::Int64,
+            Value::U64(n) if n <= i64::max_value() as u64 => Inferred::Int64,
+            Value::U64(_) | Value::F64(_) => Inferred::Float64,
+            Value::String(_) => Inferred::Utf8,
+            Value::Array(_) | Value::Object(_) => Inferred::Json
+        };
+        inferred = match (inferred, ty) {
+            (Inferred::Null, ty) => ty,
+            (a, b) if a == b => a,
+            (Inferred::Int64, Inferred::Float64) | (Inferred::Float64, Inferred::Int64) => Inferred::Float64,
+            _ => {
+                conflict = true;
+                Inferred::Utf8
+            }
+        };
+    }
+
+    (inferred, conflict)
+}
+
+fn non_null(value: &Value) -> Option<&Value> {
+    if value.is_null() { None } else { Some(value) }
+}
+
+fn text(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => s.clone(),
+        ref other => other.to_string()
+    }
+}
+
+/// Builds a batch of `len` rows from `(name, values, key_type, is_timestamp)` columns, where the
+/// type of columns without a key type is inferred from their values.
+fn build_batch(columns: Vec<(String, Vec<Option<&Value>>, Option<KeyType>, bool)>,
+               len: usize,
+               mut warnings: Vec<String>)
+               -> Result<RecordBatchExport, ArrowError> {
+    let mut fields = Vec::new();
+    let mut arrays: Vec<ArrayRef> = Vec::new();
+
+    for (name, values, key_type, timestamp) in columns {
+        let (inferred, conflict) = match key_type {
+            Some(KeyType::Str) => (Inferred::Utf8, false),
+            Some(KeyType::I64) => (Inferred::Int64, false),
+            Some(KeyType::F64) => (Inferred::Float64, false),
+            Some(KeyType::Bool) => (Inferred::Boolean, false),
+            None => infer(&values)
+        };
+        if conflict {
+            warnings.push(format!("column `{}` has values of different types and was exported as Utf8", name));
+        }
+        let values: Vec<Option<&Value>> = values.into_iter().map(|v| v.and_then(non_null)).collect();
+
+        let ints = || values.iter().map(|v| v.and_then(Value::as_i64)).collect::<Vec<_>>();
+        let (data_type, array): (DataType, ArrayRef) = match inferred {
+            Inferred::Int64 if timestamp => {
+                let array = TimestampMillisecondArray::from(ints()).with_timezone("UTC");
+                (DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), Arc::new(array))
+            }
+            Inferred::Int64 => (DataType::Int64, Arc::new(Int64Array::from(ints()))),
+            Inferred::Float64 => {
+                let floats: Vec<_> = values.iter().map(|v| v.and_then(Value::as_f64)).collect();
+                (DataType::Float64, Arc::new(Float64Array::from(floats)))
+            }
+            Inferred::Boolean => {
+                let bools: Vec<_> = values.iter().map(|v| v.and_then(Value::as_bool)).collect();
+                (DataType::Boolean, Arc::new(BooleanArray::from(bools)))
+            }
+            Inferred::Null | Inferred::Utf8 | Inferred::Json => {
+                let strings: Vec<_> = values.iter().map(|v| v.map(text)).collect();
+                (DataType::Utf8, Arc::new(StringArray::from(strings)))
+            }
+        };
+
+        fields.push(Field::new(name, data_type, true));
+        arrays.push(array);
+    }
+
+    let options = RecordBatchOptions::new().with_row_count(Some(len));
+    let batch = RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), arrays, &options)?;
+
+    Ok(RecordBatchExport {
+        batch: batch,
+        warnings: warnings
+    })
+}
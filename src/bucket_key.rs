@@ -0,0 +1,198 @@
+//! Typed bucket keys, so that the keys of `terms` aggregations on numbers or booleans keep their
+//! type through exports.
+
+use serde::ser::{Serialize, Serializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Iter;
+use std::fmt;
+
+use {AggShape, Aggregations};
+
+/// The key of a bucket.
+///
+/// `terms` keys can be strings, longs, doubles or booleans, and `histogram` and
+/// `date_histogram` keys are numbers. Older versions of Elasticsearch can also send a `null` key
+/// for documents without a numeric field. Keys that don't fit any of those, such as an unsigned
+/// long beyond `i64`, are kept as their JSON text so they still render the same.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BucketKey {
+    /// A string key, or the JSON text of a key of any other type.
+    Str(String),
+    /// An integer key.
+    I64(i64),
+    /// A floating point key.
+    F64(f64),
+    /// A boolean key.
+    Bool(bool),
+    /// A `null` key.
+    Null
+}
+
+impl BucketKey {
+    /// Returns the type of the key, or `None` for a `null` key, which fits any type.
+    pub fn key_type(&self) -> Option<KeyType> {
+        match *self {
+            BucketKey::Str(_) => Some(KeyType::Str),
+            BucketKey::I64(_) => Some(KeyType::I64),
+            BucketKey::F64(_) => Some(KeyType::F64),
+            BucketKey::Bool(_) => Some(KeyType::Bool),
+            BucketKey::Null => None
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for BucketKey {
+    fn from(value: &'a Value) -> BucketKey {
+        match *value {
+            Value::String(ref s) => BucketKey::Str(s.clone()),
+            Value::I64(n) => BucketKey::I64(n),
+            Value::U64(n) if n <= i64::max_value() as u64 => BucketKey::I64(n as i64),
+            Value::F64(n) => BucketKey::F64(n),
+            Value::Bool(b) => BucketKey::Bool(b),
+            Value::Null => BucketKey::Null,
+            ref other => BucketKey::Str(other.to_string())
+        }
+    }
+}
+
+impl Serialize for BucketKey {
+    /// Serializes the key as the JSON value it was read from.
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+        where S: Serializer
+    {
+        match *self {
+            BucketKey::Str(ref s) => serializer.serialize_str(s),
+            BucketKey::I64(n) => serializer.serialize_i64(n),
+            BucketKey::F64(n) => serializer.serialize_f64(n),
+            BucketKey::Bool(b) => serializer.serialize_bool(b),
+            BucketKey::Null => serializer.serialize_unit()
+        }
+    }
+}
+
+impl fmt::Display for BucketKey {
+    /// Renders the key so that parsing it back as its type gives the same key, e.g. `1.5` for
+    /// `F64(1.5)` and `-3` for `I64(-3)`. `Null` is rendered as `null`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BucketKey::Str(ref s) => f.write_str(s),
+            BucketKey::I64(n) => write!(f, "{}", n),
+            BucketKey::F64(n) => write!(f, "{}", n),
+            BucketKey::Bool(b) => write!(f, "{}", b),
+            BucketKey::Null => f.write_str("null")
+        }
+    }
+}
+
+/// The type of the keys of a bucket aggregation, see `Aggregations::key_types`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    /// String keys, or keys of different types.
+    Str,
+    /// Integer keys.
+    I64,
+    /// Floating point keys, possibly mixed with integers.
+    F64,
+    /// Boolean keys.
+    Bool
+}
+
+impl KeyType {
+    /// Returns the type both `self` and `other` keys can be represented as: integers and doubles
+    /// are both doubles, and any other mix is strings.
+    fn merge(self, other: KeyType) -> KeyType {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (KeyType::I64, KeyType::F64) | (KeyType::F64, KeyType::I64) => KeyType::F64,
+            _ => KeyType::Str
+        }
+    }
+}
+
+/// The key types of the bucket aggregations of a tree, along with a warning for each one whose
+/// keys had conflicting types.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyTypes {
+    types: BTreeMap<String, KeyType>,
+    warnings: Vec<String>
+}
+
+impl KeyTypes {
+    /// Returns the key type of the bucket aggregation `agg`, if any of its buckets has a key.
+    pub fn get(&self, agg: &str) -> Option<KeyType> {
+        self.types.get(agg).cloned()
+    }
+
+    /// Returns an Iterator over the bucket aggregations and their key types, by name.
+    pub fn iter(&self) -> Iter<String, KeyType> {
+        self.types.iter()
+    }
+
+    /// Returns a warning for each bucket aggregation whose keys had different types, such as
+    /// those of a `terms` aggregation on a script, and are treated as strings.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn add(&mut self, agg: &str, key: &Value) {
+        let ty = match BucketKey::from(key).key_type() {
+            Some(ty) => ty,
+            None => return
+        };
+        let existing = match self.types.get(agg) {
+            Some(&existing) => existing,
+            None => {
+                self.types.insert(agg.to_owned(), ty);
+                return;
+            }
+        };
+
+        let merged = existing.merge(ty);
+        if merged != existing {
+            self.types.insert(agg.to_owned(), merged);
+        }
+        // an aggregation already treated as strings may have had only string keys so far
+        if merged == KeyType::Str && (existing != KeyType::Str || ty != KeyType::Str) {
+            let warning = format!("bucket keys of `{}` have different types and are treated as strings", agg);
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+    }
+}
+
+impl Aggregations {
+    /// Returns the type of the keys of every bucket aggregation in the tree.
+    ///
+    /// The keys of all the buckets of an aggregation are looked at, including those nested in
+    /// different parent buckets. `null` keys are skipped.
+    pub fn key_types(&self) -> KeyTypes {
+        let mut types = KeyTypes::default();
+        if let Some(aggs) = self.0.as_object() {
+            collect_key_types(aggs, &mut types);
+        }
+        types
+    }
+}
+
+fn collect_key_types(aggs: &BTreeMap<String, Value>, types: &mut KeyTypes) {
+    for (name, agg) in aggs {
+        let agg = match agg.as_object() {
+            Some(agg) => agg,
+            None => continue
+        };
+        match AggShape::of(agg) {
+            AggShape::Buckets(buckets) => {
+                for bucket in buckets.iter().filter_map(Value::as_object) {
+                    if let Some(key) = bucket.get("key") {
+                        types.add(name, key);
+                    }
+                    collect_key_types(bucket, types);
+                }
+            }
+            AggShape::SingleBucket => collect_key_types(agg, types),
+            _ => ()
+        }
+    }
+}
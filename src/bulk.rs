@@ -0,0 +1,305 @@
+//! Response of the `_bulk` API.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::{self, Value};
+use std::collections::BTreeMap;
+use std::slice::Iter;
+
+use {ApiError, Error, ProblemKind, ProblemSink, RETRYABLE_STATUSES, WriteResult, parse_slice_as};
+
+/// Response of a bulk request, with one item per operation in request order.
+#[derive(Deserialize, Debug)]
+pub struct BulkResponse {
+    took: u64,
+    errors: bool,
+    items: Vec<BulkItem>,
+    /// The request positions of the items `parse_lenient` left out, in order.
+    #[serde(skip_deserializing)]
+    skipped: Vec<usize>
+}
+
+#[derive(Deserialize)]
+struct LenientBulk {
+    took: u64,
+    errors: bool,
+    items: Vec<Value>
+}
+
+impl BulkResponse {
+    /// Parses a bulk response, leaving out the items that don't parse and recording each of
+    /// them as a `ProblemKind::MalformedItem` in the returned sink, rather than failing.
+    ///
+    /// The path of such a problem is the item's position in the request, e.g. `/items/7`.
+    /// `items` then has fewer entries than the request had operations, but the positions
+    /// `retryable_items` and `partition` give are still those in the request.
+    pub fn parse_lenient(body: &[u8]) -> Result<(BulkResponse, ProblemSink), Error> {
+        let bulk: LenientBulk = parse_slice_as(body)?;
+        let sink = ProblemSink::default();
+        let mut items = Vec::with_capacity(bulk.items.len());
+        let mut skipped = Vec::new();
+        for (position, item) in bulk.items.into_iter().enumerate() {
+            match serde_json::from_value(item.clone()) {
+                Ok(parsed) => items.push(parsed),
+                Err(_) => {
+                    sink.push(format!("/items/{}", position), ProblemKind::MalformedItem, &item);
+                    skipped.push(position);
+                }
+            }
+        }
+
+        let response = BulkResponse {
+            took: bulk.took,
+            errors: bulk.errors,
+            items: items,
+            skipped: skipped
+        };
+        Ok((response, sink))
+    }
+
+    /// Returns the time in milliseconds Elasticsearch took to execute the request.
+    pub fn took(&self) -> u64 {
+        self.took
+    }
+
+    /// Returns `true` if any operation failed.
+    pub fn errors(&self) -> bool {
+        self.errors
+    }
+
+    /// Returns the result of each operation, in request order, less any `parse_lenient` left
+    /// out.
+    pub fn items(&self) -> &[BulkItem] {
+        &self.items
+    }
+
+    /// Iterates over the results of the operations.
+    pub fn iter(&self) -> Iter<BulkItem> {
+        self.items.iter()
+    }
+
+    /// Returns the operations that failed.
+    pub fn failed_items(&self) -> Vec<&BulkItem> {
+        self.items.iter().filter(|i| !i.is_ok()).collect()
+    }
+
+    /// Returns the failed operations that can be sent again, see `BulkItem::is_retryable`, along
+    /// with their positions in the request.
+    pub fn retryable_items(&self) -> Vec<PositionedItem> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|&(_, item)| item.is_retryable())
+            .map(|(index, item)| PositionedItem::new(self.position(index), item))
+            .collect()
+    }
+
+    /// Splits the operations into those that succeeded, those that failed but can be retried
+    /// and those that failed for good, keeping their request order within each group.
+    pub fn partition(&self) -> BulkPartition {
+        let mut partition = BulkPartition {
+            succeeded: Vec::new(),
+            retryable: Vec::new(),
+            fatal: Vec::new()
+        };
+        for (index, item) in self.items.iter().enumerate() {
+            let positioned = PositionedItem::new(self.position(index), item);
+            if positioned.item.is_ok() {
+                partition.succeeded.push(positioned);
+            } else if positioned.item.is_retryable() {
+                partition.retryable.push(positioned);
+            } else {
+                partition.fatal.push(positioned);
+            }
+        }
+        partition
+    }
+
+    /// Returns the position in the request of the item at `index` in `items`.
+    fn position(&self, index: usize) -> usize {
+        let mut position = index;
+        for &skipped in &self.skipped {
+            if skipped <= position {
+                position += 1;
+            }
+        }
+        position
+    }
+}
+
+/// A bulk item along with its position in the request.
+#[derive(Debug, Clone, Copy)]
+pub struct PositionedItem<'a> {
+    /// The position of the operation among the operations of the request, counting from 0,
+    /// which is also its position in `BulkResponse::items` unless `BulkResponse::parse_lenient`
+    /// left out items before it.
+    pub position: usize,
+    /// The kind of operation.
+    pub action: BulkAction,
+    /// The id of the document, if the request or Elasticsearch gave it one.
+    pub id: Option<&'a str>,
+    /// The item itself.
+    pub item: &'a BulkItem
+}
+
+impl<'a> PositionedItem<'a> {
+    fn new(position: usize, item: &'a BulkItem) -> PositionedItem<'a> {
+        PositionedItem {
+            position: position,
+            action: item.action(),
+            id: item.id(),
+            item: item
+        }
+    }
+}
+
+/// The items of a bulk response grouped by outcome, see `BulkResponse::partition`.
+#[derive(Debug, Clone)]
+pub struct BulkPartition<'a> {
+    /// The operations that succeeded.
+    pub succeeded: Vec<PositionedItem<'a>>,
+    /// The operations that failed but may succeed when sent again.
+    pub retryable: Vec<PositionedItem<'a>>,
+    /// The operations that failed and would fail again.
+    pub fatal: Vec<PositionedItem<'a>>
+}
+
+impl<'a> IntoIterator for &'a BulkResponse {
+    type Item = &'a BulkItem;
+    type IntoIter = Iter<'a, BulkItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// The kind of operation of a bulk item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkAction {
+    /// `index`, which writes the document whether or not it exists.
+    Index,
+    /// `create`, which writes the document only if it doesn't exist yet.
+    Create,
+    /// `update`, which changes part of an existing document.
+    Update,
+    /// `delete`, which removes the document.
+    Delete
+}
+
+impl BulkAction {
+    /// Returns the name of the action as used in bulk bodies.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            BulkAction::Index => "index",
+            BulkAction::Create => "create",
+            BulkAction::Update => "update",
+            BulkAction::Delete => "delete"
+        }
+    }
+}
+
+/// The result of a single bulk operation.
+#[derive(Debug)]
+pub struct BulkItem {
+    action: BulkAction,
+    body: BulkItemBody
+}
+
+#[derive(Deserialize, Debug)]
+struct BulkItemBody {
+    _index: String,
+    _type: Option<String>,
+    _id: Option<String>,
+    _version: Option<u64>,
+    _seq_no: Option<u64>,
+    _primary_term: Option<u64>,
+    result: Option<WriteResult>,
+    status: u16,
+    error: Option<ApiError>
+}
+
+impl BulkItem {
+    /// Returns the kind of operation.
+    pub fn action(&self) -> BulkAction {
+        self.action
+    }
+
+    /// Returns the index the operation targeted.
+    pub fn index(&self) -> &str {
+        &self.body._index
+    }
+
+    /// Returns the id of the document; auto-generated ids are reported too.
+    pub fn id(&self) -> Option<&str> {
+        self.body._id.as_ref().map(|id| &**id)
+    }
+
+    /// Returns the version of the document after the operation.
+    pub fn version(&self) -> Option<u64> {
+        self.body._version
+    }
+
+    /// Returns the sequence number assigned to the operation.
+    pub fn seq_no(&self) -> Option<u64> {
+        self.body._seq_no
+    }
+
+    /// Returns the primary term the operation was executed in.
+    pub fn primary_term(&self) -> Option<u64> {
+        self.body._primary_term
+    }
+
+    /// Returns what the operation did to the document; failed operations have no result.
+    pub fn result(&self) -> Option<&WriteResult> {
+        self.body.result.as_ref()
+    }
+
+    /// Returns the HTTP status of the operation.
+    pub fn status(&self) -> u16 {
+        self.body.status
+    }
+
+    /// Returns why the operation failed.
+    pub fn error(&self) -> Option<&ApiError> {
+        self.body.error.as_ref()
+    }
+
+    /// Returns `true` if the operation succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.body.error.is_none() && self.body.status < 300
+    }
+
+    /// Returns `true` if the operation failed in a way that sending it again may fix, like
+    /// `es_rejected_execution_exception` when the write queue is full.
+    ///
+    /// Failures with a status in `RETRYABLE_STATUSES` or an error that `ApiError::is_retryable`
+    /// accepts are retryable. Others, like mapping errors, version conflicts or deleting a
+    /// missing document, aren't.
+    pub fn is_retryable(&self) -> bool {
+        let retryable_error = self.body.error.as_ref().map_or(false, ApiError::is_retryable);
+        !self.is_ok() && (RETRYABLE_STATUSES.contains(&self.body.status) || retryable_error)
+    }
+}
+
+impl Deserialize for BulkItem {
+    fn deserialize<D>(deserializer: &mut D) -> Result<BulkItem, D::Error>
+        where D: Deserializer
+    {
+        let item = BTreeMap::<String, BulkItemBody>::deserialize(deserializer)?;
+        if item.len() != 1 {
+            return Err(D::Error::invalid_length(item.len()));
+        }
+        let (name, body) = item.into_iter().next().unwrap();
+        let action = match &*name {
+            "index" => BulkAction::Index,
+            "create" => BulkAction::Create,
+            "update" => BulkAction::Update,
+            "delete" => BulkAction::Delete,
+            other => return Err(D::Error::unknown_variant(other))
+        };
+
+        Ok(BulkItem {
+            action: action,
+            body: body
+        })
+    }
+}
@@ -0,0 +1,353 @@
+//! Rows of the `_cat` APIs, requested with `format=json`.
+//!
+//! The cat APIs report every value as a string and use `-` for values that aren't set. Typed
+//! rows parse numbers from those strings when they are read, and columns left out with `h=`
+//! read as `None`.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Keys;
+use std::str::FromStr;
+
+/// A row of `_cat/aliases`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CatAlias {
+    alias: String,
+    index: String,
+    #[serde(default)]
+    filter: String,
+    #[serde(rename = "routing.index", default)]
+    routing_index: String,
+    #[serde(rename = "routing.search", default)]
+    routing_search: String,
+    #[serde(default)]
+    is_write_index: String
+}
+
+impl CatAlias {
+    /// Returns the name of the alias.
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    /// Returns the index the alias points at.
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+
+    /// Returns `true` if the alias has a filter.
+    pub fn is_filtered(&self) -> bool {
+        cat_str(&self.filter).is_some()
+    }
+
+    /// Returns the routing used when indexing through the alias.
+    pub fn index_routing(&self) -> Option<&str> {
+        cat_str(&self.routing_index)
+    }
+
+    /// Returns the routing used when searching through the alias.
+    pub fn search_routing(&self) -> Option<&str> {
+        cat_str(&self.routing_search)
+    }
+
+    /// Returns the `is_write_index` flag, if it is set.
+    pub fn is_write_index(&self) -> Option<bool> {
+        cat_parse(&self.is_write_index)
+    }
+}
+
+fn cat_str(value: &str) -> Option<&str> {
+    match value {
+        "" | "-" => None,
+        v => Some(v)
+    }
+}
+
+fn cat_parse<T: FromStr>(value: &str) -> Option<T> {
+    cat_str(value).and_then(|v| v.parse().ok())
+}
+
+/// A cat value kept as a string, which is also accepted as a bare number or boolean.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CatValue(String);
+
+impl CatValue {
+    fn get(&self) -> Option<&str> {
+        cat_str(&self.0)
+    }
+
+    fn parse<T: FromStr>(&self) -> Option<T> {
+        cat_parse(&self.0)
+    }
+}
+
+impl Deserialize for CatValue {
+    fn deserialize<D>(deserializer: &mut D) -> Result<CatValue, D::Error>
+        where D: Deserializer
+    {
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => Ok(CatValue(s)),
+            Value::Null => Ok(CatValue::default()),
+            Value::Bool(b) => Ok(CatValue(b.to_string())),
+            Value::U64(n) => Ok(CatValue(n.to_string())),
+            Value::I64(n) => Ok(CatValue(n.to_string())),
+            Value::F64(n) => Ok(CatValue(n.to_string())),
+            _ => Err(D::Error::invalid_value("expected a cat value"))
+        }
+    }
+}
+
+/// A row of `_cat/count`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CatCount {
+    #[serde(default)]
+    epoch: CatValue,
+    #[serde(default)]
+    timestamp: CatValue,
+    #[serde(default)]
+    count: CatValue
+}
+
+impl CatCount {
+    /// Returns the time of the count in seconds since the epoch.
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch.parse()
+    }
+
+    /// Returns the time of day of the count, as `HH:MM:SS`.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.get()
+    }
+
+    /// Returns the number of documents.
+    pub fn count(&self) -> Option<u64> {
+        self.count.parse()
+    }
+}
+
+/// A row of `_cat/nodes`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CatNode {
+    #[serde(default)]
+    ip: CatValue,
+    #[serde(rename = "heap.percent", default)]
+    heap_percent: CatValue,
+    #[serde(rename = "ram.percent", default)]
+    ram_percent: CatValue,
+    #[serde(default)]
+    cpu: CatValue,
+    #[serde(default)]
+    load_1m: CatValue,
+    #[serde(default)]
+    load_5m: CatValue,
+    #[serde(default)]
+    load_15m: CatValue,
+    #[serde(rename = "node.role", default)]
+    node_role: CatValue,
+    #[serde(default)]
+    master: CatValue,
+    #[serde(default)]
+    name: CatValue
+}
+
+impl CatNode {
+    /// Returns the IP address of the node.
+    pub fn ip(&self) -> Option<&str> {
+        self.ip.get()
+    }
+
+    /// Returns the percentage of the heap in use.
+    pub fn heap_percent(&self) -> Option<u32> {
+        self.heap_percent.parse()
+    }
+
+    /// Returns the percentage of the memory of the machine in use.
+    pub fn ram_percent(&self) -> Option<u32> {
+        self.ram_percent.parse()
+    }
+
+    /// Returns the recent CPU usage of the node, as a percentage.
+    pub fn cpu(&self) -> Option<u32> {
+        self.cpu.parse()
+    }
+
+    /// Returns the load average over the last minute.
+    pub fn load_1m(&self) -> Option<f64> {
+        self.load_1m.parse()
+    }
+
+    /// Returns the load average over the last five minutes.
+    pub fn load_5m(&self) -> Option<f64> {
+        self.load_5m.parse()
+    }
+
+    /// Returns the load average over the last fifteen minutes.
+    pub fn load_15m(&self) -> Option<f64> {
+        self.load_15m.parse()
+    }
+
+    /// Returns the roles of the node, abbreviated to one letter each, like `dim` for a data,
+    /// ingest and master eligible node.
+    pub fn node_role(&self) -> Option<&str> {
+        self.node_role.get()
+    }
+
+    /// Returns `true` if the node has the role abbreviated as `role`, see `node_role`.
+    pub fn has_role(&self, role: char) -> bool {
+        self.node_role().map_or(false, |roles| roles.contains(role))
+    }
+
+    /// Returns `true` if the node is the elected master, which the cat API marks with `*`.
+    pub fn is_master(&self) -> bool {
+        self.master.get() == Some("*")
+    }
+
+    /// Returns the name of the node.
+    pub fn name(&self) -> Option<&str> {
+        self.name.get()
+    }
+}
+
+/// A row of `_cat/health`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CatHealth {
+    #[serde(default)]
+    epoch: CatValue,
+    #[serde(default)]
+    timestamp: CatValue,
+    #[serde(default)]
+    cluster: CatValue,
+    #[serde(default)]
+    status: CatValue,
+    #[serde(rename = "node.total", default)]
+    node_total: CatValue,
+    #[serde(rename = "node.data", default)]
+    node_data: CatValue,
+    #[serde(default)]
+    shards: CatValue,
+    #[serde(default)]
+    pri: CatValue,
+    #[serde(default)]
+    relo: CatValue,
+    #[serde(default)]
+    init: CatValue,
+    #[serde(default)]
+    unassign: CatValue,
+    #[serde(default)]
+    pending_tasks: CatValue,
+    #[serde(default)]
+    active_shards_percent: CatValue
+}
+
+impl CatHealth {
+    /// Returns the time of the check in seconds since the epoch.
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch.parse()
+    }
+
+    /// Returns the time of day of the check, as `HH:MM:SS`.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.get()
+    }
+
+    /// Returns the name of the cluster.
+    pub fn cluster(&self) -> Option<&str> {
+        self.cluster.get()
+    }
+
+    /// Returns the health of the cluster: `green`, `yellow` or `red`.
+    pub fn status(&self) -> Option<&str> {
+        self.status.get()
+    }
+
+    /// Returns the number of nodes in the cluster.
+    pub fn node_total(&self) -> Option<u32> {
+        self.node_total.parse()
+    }
+
+    /// Returns the number of data nodes in the cluster.
+    pub fn node_data(&self) -> Option<u32> {
+        self.node_data.parse()
+    }
+
+    /// Returns the number of active shards, primaries and replicas.
+    pub fn shards(&self) -> Option<u32> {
+        self.shards.parse()
+    }
+
+    /// Returns the number of active primary shards.
+    pub fn primaries(&self) -> Option<u32> {
+        self.pri.parse()
+    }
+
+    /// Returns the number of shards being relocated.
+    pub fn relocating(&self) -> Option<u32> {
+        self.relo.parse()
+    }
+
+    /// Returns the number of shards being initialized.
+    pub fn initializing(&self) -> Option<u32> {
+        self.init.parse()
+    }
+
+    /// Returns the number of unassigned shards.
+    pub fn unassigned(&self) -> Option<u32> {
+        self.unassign.parse()
+    }
+
+    /// Returns the number of cluster state updates waiting to be applied.
+    pub fn pending_tasks(&self) -> Option<u32> {
+        self.pending_tasks.parse()
+    }
+
+    /// Returns the percentage of shards that are active, which the cat API writes like `87.5%`.
+    pub fn active_shards_percent(&self) -> Option<f64> {
+        self.active_shards_percent.get().and_then(|v| v.trim_end_matches('%').parse().ok())
+    }
+}
+
+/// A row of any cat API, for endpoints without a typed row or requests that pick their own
+/// columns with `h=`.
+///
+/// Values are kept as the strings the API reported; bare numbers and booleans are turned into
+/// strings too.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatRow(BTreeMap<String, String>);
+
+impl CatRow {
+    /// Returns the value of `column`, unless it is missing or unset.
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.0.get(column).and_then(|v| cat_str(v))
+    }
+
+    /// Parses the value of `column`, e.g. into a number.
+    pub fn parse<T: FromStr>(&self, column: &str) -> Option<T> {
+        self.0.get(column).and_then(|v| cat_parse(v))
+    }
+
+    /// Returns the names of the columns of the row, sorted alphabetically.
+    pub fn columns(&self) -> Keys<String, String> {
+        self.0.keys()
+    }
+
+    /// Returns the values of the row by column, as they were reported.
+    pub fn into_map(self) -> BTreeMap<String, String> {
+        self.0
+    }
+}
+
+impl From<BTreeMap<String, String>> for CatRow {
+    fn from(values: BTreeMap<String, String>) -> CatRow {
+        CatRow(values)
+    }
+}
+
+impl Deserialize for CatRow {
+    fn deserialize<D>(deserializer: &mut D) -> Result<CatRow, D::Error>
+        where D: Deserializer
+    {
+        let values = BTreeMap::<String, CatValue>::deserialize(deserializer)?;
+        Ok(CatRow(values.into_iter().map(|(k, v)| (k, v.0)).collect()))
+    }
+}
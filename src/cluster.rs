@@ -0,0 +1,337 @@
+//! Responses of the `_cluster/health`, `_cluster/state` and `_cluster/pending_tasks` APIs.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::Value;
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::slice::Iter;
+
+use {Object, typed_section};
+
+/// Response of a pending cluster tasks request.
+#[derive(Deserialize, Debug)]
+pub struct PendingTasksResponse {
+    tasks: Vec<PendingTask>
+}
+
+impl PendingTasksResponse {
+    /// Returns the queued tasks, in the order the master reported them.
+    pub fn tasks(&self) -> &[PendingTask] {
+        &self.tasks
+    }
+
+    /// Iterates over the queued tasks.
+    pub fn iter(&self) -> Iter<PendingTask> {
+        self.tasks.iter()
+    }
+
+    /// Returns the number of queued tasks.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if no tasks are queued.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a PendingTasksResponse {
+    type Item = &'a PendingTask;
+    type IntoIter = Iter<'a, PendingTask>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tasks.iter()
+    }
+}
+
+/// A cluster state update queued on the master.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingTask {
+    insert_order: u64,
+    priority: Priority,
+    source: String,
+    #[serde(default)]
+    executing: bool,
+    time_in_queue_in_millis: u64,
+    time_in_queue: Option<String>
+}
+
+impl PendingTask {
+    /// Returns the position the task was queued at.
+    pub fn insert_order(&self) -> u64 {
+        self.insert_order
+    }
+
+    /// Returns the priority of the task.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Returns what queued the task, e.g. `create-index [logs], cause [api]`.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns `true` if the master is running the task right now.
+    pub fn executing(&self) -> bool {
+        self.executing
+    }
+
+    /// Returns how long the task has been waiting.
+    pub fn time_in_queue_in_millis(&self) -> u64 {
+        self.time_in_queue_in_millis
+    }
+
+    /// Returns the human readable wait time, if it was requested.
+    pub fn time_in_queue(&self) -> Option<&str> {
+        self.time_in_queue.as_ref().map(|t| &**t)
+    }
+}
+
+/// The priority of a pending cluster task, from most to least urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// `IMMEDIATE`, run before anything else.
+    Immediate,
+    /// `URGENT`, e.g. shard started and failed tasks.
+    Urgent,
+    /// `HIGH`, e.g. creating or deleting indices.
+    High,
+    /// `NORMAL`, e.g. updating mappings or settings.
+    Normal,
+    /// `LOW`, e.g. refreshing mappings.
+    Low,
+    /// `LANGUID`, run when nothing else is queued.
+    Languid
+}
+
+impl Deserialize for Priority {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Priority, D::Error>
+        where D: Deserializer
+    {
+        match &*String::deserialize(deserializer)? {
+            "IMMEDIATE" => Ok(Priority::Immediate),
+            "URGENT" => Ok(Priority::Urgent),
+            "HIGH" => Ok(Priority::High),
+            "NORMAL" => Ok(Priority::Normal),
+            "LOW" => Ok(Priority::Low),
+            "LANGUID" => Ok(Priority::Languid),
+            other => Err(D::Error::unknown_variant(other))
+        }
+    }
+}
+
+/// Response of a cluster state request.
+///
+/// Only the parts of the state that were requested are present, so everything is optional.
+/// The index metadata is typed shallowly; `section` gives raw access to the rest, such as
+/// `routing_table` or `nodes`.
+#[derive(Debug)]
+pub struct ClusterStateResponse {
+    cluster_name: Option<String>,
+    cluster_uuid: Option<String>,
+    master_node: Option<String>,
+    indices: BTreeMap<String, IndexMetadata>,
+    sections: Object
+}
+
+impl ClusterStateResponse {
+    /// Returns the name of the cluster.
+    pub fn cluster_name(&self) -> Option<&str> {
+        self.cluster_name.as_ref().map(|n| &**n)
+    }
+
+    /// Returns the uuid of the cluster, from the top level or from `metadata`.
+    pub fn cluster_uuid(&self) -> Option<&str> {
+        self.cluster_uuid.as_ref().map(|u| &**u)
+    }
+
+    /// Returns the id of the elected master node.
+    pub fn master_node(&self) -> Option<&str> {
+        self.master_node.as_ref().map(|n| &**n)
+    }
+
+    /// Returns the metadata of a single index.
+    pub fn index(&self, name: &str) -> Option<&IndexMetadata> {
+        self.indices.get(name)
+    }
+
+    /// Iterates over the index metadata, ordered by index name.
+    ///
+    /// This is empty if `metadata` wasn't requested.
+    pub fn indices(&self) -> btree_map::Iter<String, IndexMetadata> {
+        self.indices.iter()
+    }
+
+    /// Returns any top level section of the state by name, e.g. `routing_table`.
+    pub fn section(&self, name: &str) -> Option<&Value> {
+        self.sections.get(name)
+    }
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    cluster_uuid: Option<String>,
+    #[serde(default)]
+    indices: BTreeMap<String, IndexMetadata>
+}
+
+impl Deserialize for ClusterStateResponse {
+    fn deserialize<D>(deserializer: &mut D) -> Result<ClusterStateResponse, D::Error>
+        where D: Deserializer
+    {
+        let sections = Object::deserialize(deserializer)?;
+        let metadata: Option<Metadata> = typed_section(&sections, "metadata")?;
+        let (metadata_uuid, indices) = match metadata {
+            Some(m) => (m.cluster_uuid, m.indices),
+            None => (None, BTreeMap::new())
+        };
+        let cluster_uuid: Option<String> = typed_section(&sections, "cluster_uuid")?;
+
+        Ok(ClusterStateResponse {
+            cluster_name: typed_section(&sections, "cluster_name")?,
+            cluster_uuid: cluster_uuid.or(metadata_uuid),
+            master_node: typed_section(&sections, "master_node")?,
+            indices: indices,
+            sections: sections
+        })
+    }
+}
+
+/// Whether an index is open or closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexState {
+    /// `open`, the index can be searched and written to.
+    Open,
+    /// `close`, the index is closed and only its metadata is kept in the cluster.
+    Close
+}
+
+impl Deserialize for IndexState {
+    fn deserialize<D>(deserializer: &mut D) -> Result<IndexState, D::Error>
+        where D: Deserializer
+    {
+        match &*String::deserialize(deserializer)? {
+            "open" => Ok(IndexState::Open),
+            "close" => Ok(IndexState::Close),
+            other => Err(D::Error::unknown_variant(other))
+        }
+    }
+}
+
+/// The metadata of an index in the cluster state.
+#[derive(Deserialize, Debug)]
+pub struct IndexMetadata {
+    state: Option<IndexState>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    settings: Option<Value>,
+    mappings: Option<Value>
+}
+
+impl IndexMetadata {
+    /// Returns whether the index is open or closed.
+    pub fn state(&self) -> Option<IndexState> {
+        self.state
+    }
+
+    /// Returns the names of the aliases pointing at the index.
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Returns the raw settings of the index.
+    pub fn settings(&self) -> Option<&Value> {
+        self.settings.as_ref()
+    }
+
+    /// Returns the raw mappings of the index.
+    pub fn mappings(&self) -> Option<&Value> {
+        self.mappings.as_ref()
+    }
+}
+
+/// Response of a cluster health request.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClusterHealthResponse {
+    cluster_name: String,
+    status: String,
+    #[serde(default)]
+    timed_out: bool,
+    number_of_nodes: u64,
+    number_of_data_nodes: u64,
+    active_primary_shards: u64,
+    active_shards: u64,
+    relocating_shards: u64,
+    initializing_shards: u64,
+    unassigned_shards: u64,
+    active_shards_percent_as_number: Option<f64>,
+    discovered_master: Option<bool>,
+    discovered_cluster_manager: Option<bool>
+}
+
+impl ClusterHealthResponse {
+    /// Returns the name of the cluster.
+    pub fn cluster_name(&self) -> &str {
+        &self.cluster_name
+    }
+
+    /// Returns the health of the cluster: `green`, `yellow` or `red`.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Returns `true` if the request timed out waiting for the requested status.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns the number of nodes in the cluster.
+    pub fn number_of_nodes(&self) -> u64 {
+        self.number_of_nodes
+    }
+
+    /// Returns the number of data nodes in the cluster.
+    pub fn number_of_data_nodes(&self) -> u64 {
+        self.number_of_data_nodes
+    }
+
+    /// Returns the number of active primary shards.
+    pub fn active_primary_shards(&self) -> u64 {
+        self.active_primary_shards
+    }
+
+    /// Returns the number of active primary and replica shards.
+    pub fn active_shards(&self) -> u64 {
+        self.active_shards
+    }
+
+    /// Returns the number of shards moving between nodes.
+    pub fn relocating_shards(&self) -> u64 {
+        self.relocating_shards
+    }
+
+    /// Returns the number of shards being initialized.
+    pub fn initializing_shards(&self) -> u64 {
+        self.initializing_shards
+    }
+
+    /// Returns the number of shards not allocated to any node.
+    pub fn unassigned_shards(&self) -> u64 {
+        self.unassigned_shards
+    }
+
+    /// Returns the percentage of active shards, if the cluster reports it.
+    pub fn active_shards_percent(&self) -> Option<f64> {
+        self.active_shards_percent_as_number
+    }
+
+    /// Returns `true` if the cluster has elected a master, if it reports it.
+    ///
+    /// OpenSearch calls the master the cluster manager, and newer versions only send
+    /// `discovered_cluster_manager`, which is read too.
+    pub fn discovered_master(&self) -> Option<bool> {
+        self.discovered_cluster_manager.or(self.discovered_master)
+    }
+}
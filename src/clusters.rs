@@ -0,0 +1,254 @@
+//! The clusters of cross-cluster searches, and why a search returned partial results.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Iter;
+use std::fmt;
+
+use {ResponseOf, ShardFailure, Shards};
+
+/// The `_clusters` summary of a cross-cluster search.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Clusters {
+    total: u32,
+    successful: u32,
+    #[serde(default)]
+    skipped: u32,
+    #[serde(default)]
+    running: u32,
+    #[serde(default)]
+    partial: u32,
+    #[serde(default)]
+    failed: u32,
+    #[serde(default)]
+    details: BTreeMap<String, ClusterDetails>
+}
+
+impl Clusters {
+    /// Returns the number of clusters searched, the local one included.
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Returns the number of clusters every shard of which answered.
+    pub fn successful(&self) -> u32 {
+        self.successful
+    }
+
+    /// Returns the number of remote clusters that were skipped, as remotes with
+    /// `skip_unavailable` are when they can't be reached.
+    pub fn skipped(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Returns the number of clusters still running an async search.
+    pub fn running(&self) -> u32 {
+        self.running
+    }
+
+    /// Returns the number of clusters only some shards of which answered.
+    pub fn partial(&self) -> u32 {
+        self.partial
+    }
+
+    /// Returns the number of clusters that failed.
+    pub fn failed(&self) -> u32 {
+        self.failed
+    }
+
+    /// Returns the details of the cluster `alias`, where `(local)` is the cluster the search was
+    /// sent to.
+    ///
+    /// Details are sent from Elasticsearch 8.10 on.
+    pub fn get(&self, alias: &str) -> Option<&ClusterDetails> {
+        self.details.get(alias)
+    }
+
+    /// Returns an Iterator over the details of the clusters, by alias.
+    pub fn details(&self) -> Iter<String, ClusterDetails> {
+        self.details.iter()
+    }
+}
+
+/// How the search went on one cluster of a cross-cluster search.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClusterDetails {
+    status: String,
+    indices: Option<String>,
+    took: Option<u64>,
+    #[serde(default)]
+    timed_out: bool,
+    _shards: Option<Shards>,
+    #[serde(default)]
+    failures: Vec<ShardFailure>
+}
+
+impl ClusterDetails {
+    /// Returns the status of the search on the cluster: `successful`, `partial`, `skipped`,
+    /// `failed` or `running`.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Returns `true` if the whole cluster answered, or is still answering an async search.
+    pub fn is_complete(&self) -> bool {
+        self.status == "successful" || self.status == "running"
+    }
+
+    /// Returns the index expression searched on the cluster.
+    pub fn indices(&self) -> Option<&str> {
+        self.indices.as_ref().map(|i| &**i)
+    }
+
+    /// Returns the time in milliseconds the cluster took, which skipped clusters don't have.
+    pub fn took(&self) -> Option<u64> {
+        self.took
+    }
+
+    /// Returns `true` if the search timed out on the cluster.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns the summary of the shards searched on the cluster.
+    pub fn shards(&self) -> Option<&Shards> {
+        self._shards.as_ref()
+    }
+
+    /// Returns the failures of the cluster, such as why it couldn't be reached.
+    pub fn failures(&self) -> &[ShardFailure] {
+        &self.failures
+    }
+}
+
+/// A reason the results of a search are incomplete, see `ResponseOf::partial_reasons`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartialReason {
+    /// The search timed out, and has the results of the shards that answered in time.
+    TimedOut,
+    /// A shard stopped collecting hits early, as with `terminate_after`.
+    TerminatedEarly,
+    /// Shards failed, with each of their failures rendered like `logs[3]: type: reason`.
+    ShardsFailed {
+        /// The number of shards that failed.
+        failed: u32,
+        /// The number of shards searched.
+        total: u32,
+        /// The failures, of which there can be fewer than shards.
+        reasons: Vec<String>
+    },
+    /// Clusters of a cross-cluster search were skipped, failed or only partly searched.
+    ClustersSkipped {
+        /// The number of clusters affected.
+        count: u32,
+        /// The alias and status of each of them, when the response has the details.
+        statuses: Vec<(String, String)>
+    }
+}
+
+impl fmt::Display for PartialReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PartialReason::TimedOut => f.write_str("timed out"),
+            PartialReason::TerminatedEarly => f.write_str("terminated early"),
+            PartialReason::ShardsFailed { failed, total, ref reasons } => {
+                write!(f, "{} of {} shards failed", failed, total)?;
+                if !reasons.is_empty() {
+                    write!(f, " ({})", reasons.join(", "))?;
+                }
+                Ok(())
+            }
+            PartialReason::ClustersSkipped { count, ref statuses } => {
+                write!(f, "{} {} incomplete", count, if count == 1 { "cluster" } else { "clusters" })?;
+                if !statuses.is_empty() {
+                    let statuses: Vec<String> = statuses.iter().map(|&(ref alias, ref status)| format!("{}: {}", alias, status)).collect();
+                    write!(f, " ({})", statuses.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The reasons the results of a search are incomplete, see `ResponseOf::partial_reasons`.
+///
+/// They are displayed on a single line separated by `; `, which is empty when the results are
+/// complete.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartialReasons(Vec<PartialReason>);
+
+impl PartialReasons {
+    /// Returns the reasons, in the order timeout, early termination, shards and clusters.
+    pub fn reasons(&self) -> &[PartialReason] {
+        &self.0
+    }
+
+    /// Returns `true` if the results are complete.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for PartialReasons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, reason) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Returns why the results of the search are incomplete: a timeout, early termination,
+    /// failed shards, and remote clusters that were skipped or only partly searched.
+    ///
+    /// A search that times out or loses shards still answers with `200` and the results it
+    /// has, so this is worth logging along with them, e.g. with `to_string()`.
+    pub fn partial_reasons(&self) -> PartialReasons {
+        let mut reasons = Vec::new();
+        if self.timed_out {
+            reasons.push(PartialReason::TimedOut);
+        }
+        if self.terminated_early() {
+            reasons.push(PartialReason::TerminatedEarly);
+        }
+        if self._shards.failed > 0 {
+            reasons.push(PartialReason::ShardsFailed {
+                failed: self._shards.failed,
+                total: self._shards.total,
+                reasons: self._shards.failures.iter().map(render_failure).collect()
+            });
+        }
+
+        if let Some(ref clusters) = self._clusters {
+            let statuses: Vec<(String, String)> = clusters.details
+                .iter()
+                .filter(|&(_, details)| !details.is_complete())
+                .map(|(alias, details)| (alias.clone(), details.status.clone()))
+                .collect();
+            let count = if clusters.details.is_empty() {
+                clusters.skipped + clusters.partial + clusters.failed
+            } else {
+                statuses.len() as u32
+            };
+            if count > 0 {
+                reasons.push(PartialReason::ClustersSkipped {
+                    count: count,
+                    statuses: statuses
+                });
+            }
+        }
+        PartialReasons(reasons)
+    }
+}
+
+fn render_failure(failure: &ShardFailure) -> String {
+    match (failure.index(), failure.shard()) {
+        (Some(index), Some(shard)) => format!("{}[{}]: {}", index, shard, failure.reason()),
+        (Some(index), None) => format!("{}: {}", index, failure.reason()),
+        _ => failure.reason().to_string()
+    }
+}
@@ -0,0 +1,84 @@
+//! The rows of aggregation trees simple enough to lay out column by column, without the row
+//! iterator.
+//!
+//! Most tiny responses are either empty or a single `terms` or `date_histogram` aggregation
+//! without sub-aggregations, and for those the exporters read the bucket array directly. The
+//! columns are the same as the rows of the iterator would have.
+
+use serde_json::Value;
+
+use Aggregations;
+use search::aggregations::root_buckets;
+
+/// The fields of a bucket without sub-aggregations that are columns of its rows, see
+/// `flatten_bucket`, with whether they only are when numeric.
+const BUCKET_FIELDS: &'static [(&'static str, bool)] = &[("key", false),
+                                                         ("key_as_string", false),
+                                                         ("doc_count", false),
+                                                         ("score", true),
+                                                         ("bg_count", true)];
+
+/// The rows of an aggregation tree as columns of values, in the order of `Aggregations::columns`.
+#[derive(Debug)]
+pub(crate) struct FlatColumns<'a> {
+    pub(crate) names: Vec<String>,
+    pub(crate) values: Vec<Vec<Option<&'a Value>>>,
+    pub(crate) len: usize
+}
+
+impl Aggregations {
+    /// Returns the rows as columns if the tree has no rows at all, or if its only bucket
+    /// aggregation at the root has no sub-aggregations, and `None` for anything that needs the
+    /// row iterator.
+    pub(crate) fn flat_columns(&self) -> Option<FlatColumns> {
+        let aggs = self.0.as_object()?;
+        let roots: Vec<_> = aggs.iter()
+            .filter_map(|(name, agg)| root_buckets(agg).map(|(agg, buckets)| (name, agg, buckets)))
+            .collect();
+
+        if roots.iter().all(|&(_, _, buckets)| buckets.is_empty()) {
+            // the columns are only the totals of `significant_terms` roots, if any
+            return Some(FlatColumns::empty(self.columns()));
+        }
+        if roots.len() != 1 {
+            return None;
+        }
+        let (name, agg, buckets) = roots[0];
+        let flat = buckets.iter().all(|b| b.as_object().map_or(false, |b| b.values().all(|v| !v.is_object())));
+        if !flat || agg.contains_key("bg_count") {
+            return None;
+        }
+
+        let mut names = Vec::new();
+        let mut values = Vec::new();
+        for &(field, numeric) in BUCKET_FIELDS {
+            let column: Vec<Option<&Value>> = buckets.iter()
+                .map(|b| b.find(field).and_then(|v| if numeric && !v.is_number() { None } else { Some(v) }))
+                .collect();
+            if column.iter().any(Option::is_some) {
+                names.push(if field == "key" { name.clone() } else { format!("{}_{}", name, field) });
+                values.push(column);
+            }
+        }
+
+        // the names are sorted like a `BTreeSet` of them, as `columns` returns them
+        let mut columns: Vec<(String, Vec<Option<&Value>>)> = names.into_iter().zip(values).collect();
+        columns.sort_by(|a, b| a.0.cmp(&b.0));
+        let (names, values) = columns.into_iter().unzip();
+        Some(FlatColumns {
+            names: names,
+            values: values,
+            len: buckets.len()
+        })
+    }
+}
+
+impl<'a> FlatColumns<'a> {
+    fn empty(names: Vec<String>) -> FlatColumns<'a> {
+        FlatColumns {
+            values: names.iter().map(|_| Vec::new()).collect(),
+            names: names,
+            len: 0
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Parts shared by many kinds of responses.
+
+use ApiError;
+
+/// The shard summary reported in the `_shards` object of a response.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Shards {
+    pub(crate) total: u32,
+    pub(crate) successful: u32,
+    #[serde(default)]
+    pub(crate) skipped: u32,
+    pub(crate) failed: u32,
+    #[serde(default)]
+    pub(crate) failures: Vec<ShardFailure>
+}
+
+impl Shards {
+    /// Returns the number of shards the request was sent to.
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Returns the number of shards that answered successfully.
+    pub fn successful(&self) -> u32 {
+        self.successful
+    }
+
+    /// Returns the number of shards that were skipped, as searches skip shards that can't
+    /// match, e.g. those of indices entirely outside a time range.
+    ///
+    /// Skipped shards count as successful, so they aren't missing from the results.
+    pub fn skipped(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Returns the number of shards that failed.
+    pub fn failed(&self) -> u32 {
+        self.failed
+    }
+
+    /// Returns the failures of the shards that failed.
+    ///
+    /// Searches group failures of the same reason on the same index, so there can be fewer of
+    /// them than `failed` shards.
+    pub fn failures(&self) -> &[ShardFailure] {
+        &self.failures
+    }
+}
+
+/// The failure of a single shard, from the `failures` of a `_shards` object.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ShardFailure {
+    shard: Option<i64>,
+    index: Option<String>,
+    node: Option<String>,
+    reason: ApiError
+}
+
+impl ShardFailure {
+    /// Returns the number of the shard within its index.
+    ///
+    /// Elasticsearch sends `-1` for failures it can't attribute to a shard; those are `None`.
+    pub fn shard(&self) -> Option<u32> {
+        self.shard.and_then(|s| if s >= 0 { Some(s as u32) } else { None })
+    }
+
+    /// Returns the index of the shard.
+    pub fn index(&self) -> Option<&str> {
+        self.index.as_ref().map(|i| &**i)
+    }
+
+    /// Returns the id of the node the shard was on.
+    pub fn node(&self) -> Option<&str> {
+        self.node.as_ref().map(|n| &**n)
+    }
+
+    /// Returns why the shard failed.
+    pub fn reason(&self) -> &ApiError {
+        &self.reason
+    }
+}
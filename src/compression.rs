@@ -0,0 +1,133 @@
+//! Parsing of bodies compressed with `gzip` or `deflate`, as sent by nodes with
+//! `http.compression` enabled and handed on by HTTP clients that don't decompress them.
+
+use flate2::bufread::{DeflateDecoder, ZlibDecoder};
+use flate2::read::MultiGzDecoder;
+use serde::de::Deserialize;
+use std::io::{self, BufRead, BufReader, Read};
+
+use {Error, NdjsonResponses, ResponseOf, ResponseParser};
+use ndjson;
+
+/// The compression of a body, as named by its `Content-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// A `gzip` body, which may hold several gzip members one after the other.
+    Gzip,
+    /// A `deflate` body, in the zlib format HTTP calls for or as a raw deflate stream, which
+    /// some servers send instead.
+    Deflate
+}
+
+impl Compression {
+    /// Returns the compression of a `Content-Encoding` header value, or `None` for `identity`
+    /// and encodings that aren't supported, such as `br`.
+    pub fn from_content_encoding(encoding: &str) -> Option<Compression> {
+        match &*encoding.trim().to_lowercase() {
+            "gzip" | "x-gzip" => Some(Compression::Gzip),
+            "deflate" => Some(Compression::Deflate),
+            _ => None
+        }
+    }
+}
+
+/// A reader of the decompressed bytes of a compressed reader.
+pub struct Decompressed<R: Read>(Decoder<R>);
+
+enum Decoder<R: Read> {
+    Gzip(MultiGzDecoder<R>),
+    Zlib(ZlibDecoder<BufReader<R>>),
+    Deflate(DeflateDecoder<BufReader<R>>)
+}
+
+impl<R: Read> Decompressed<R> {
+    /// Wraps `reader` in a decoder for `compression`.
+    ///
+    /// The first bytes of a `deflate` body are read to tell a zlib stream from a raw one.
+    pub fn new(reader: R, compression: Compression) -> io::Result<Decompressed<R>> {
+        let decoder = match compression {
+            Compression::Gzip => Decoder::Gzip(MultiGzDecoder::new(reader)),
+            Compression::Deflate => {
+                let mut reader = BufReader::new(reader);
+                if is_zlib(reader.fill_buf()?) {
+                    Decoder::Zlib(ZlibDecoder::new(reader))
+                } else {
+                    Decoder::Deflate(DeflateDecoder::new(reader))
+                }
+            }
+        };
+        Ok(Decompressed(decoder))
+    }
+}
+
+impl<R: Read> Read for Decompressed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0 {
+            Decoder::Gzip(ref mut d) => d.read(buf),
+            Decoder::Zlib(ref mut d) => d.read(buf),
+            Decoder::Deflate(ref mut d) => d.read(buf)
+        }
+    }
+}
+
+/// Returns `true` if `header` starts with a zlib header: the deflate method and a check value
+/// that makes the first two bytes a multiple of 31.
+fn is_zlib(header: &[u8]) -> bool {
+    header.len() >= 2 && header[0] & 0x0f == 8 && (u16::from(header[0]) << 8 | u16::from(header[1])) % 31 == 0
+}
+
+impl ResponseParser {
+    /// Parses a compressed response body from a reader, like `parse_reader`.
+    ///
+    /// The body size limit applies to the decompressed body, so a small body that inflates to
+    /// something huge is rejected without being decompressed in full. Anything that goes wrong
+    /// while reading and decompressing, such as a corrupt or truncated stream, is reported as
+    /// `Error::Decompress`.
+    pub fn parse_compressed_reader<R: Read, T: Deserialize>(&self, reader: R, compression: Compression) -> Result<ResponseOf<T>, Error> {
+        let reader = Decompressed::new(reader, compression).map_err(Error::Decompress)?;
+        let body = self.read_body(reader, Error::Decompress)?;
+        self.parse_slice(&body)
+    }
+
+    /// Parses a compressed stream with one search response per line, like
+    /// `responses_from_ndjson`, applying the body size limit to each decompressed line.
+    pub fn responses_from_compressed_ndjson<R: Read, T: Deserialize>(&self, reader: R, compression: Compression)
+                                                                    -> Result<NdjsonResponses<BufReader<Decompressed<R>>, T>, Error> {
+        let reader = Decompressed::new(reader, compression).map_err(Error::Decompress)?;
+        Ok(ndjson::with_parser(BufReader::new(reader), self.clone()).end_on_read_error(Error::Decompress))
+    }
+}
+
+/// Parses a compressed stream with one search response per line, such as a rotated log of
+/// captured responses, like `responses_from_ndjson`.
+///
+/// Errors of reading and decompressing are reported as `Error::Decompress`, and end the
+/// stream, as nothing after a corrupt part of it can be decompressed. A corrupt gzip stream can
+/// still give garbled lines before its checksum fails at the end of the member.
+pub fn responses_from_compressed_ndjson<R: Read, T: Deserialize>(reader: R, compression: Compression)
+                                                                -> Result<NdjsonResponses<BufReader<Decompressed<R>>, T>, Error> {
+    ResponseParser::new().responses_from_compressed_ndjson(reader, compression)
+}
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Parses a `gzip` compressed response body.
+    pub fn from_gzip_slice(body: &[u8]) -> Result<ResponseOf<T>, Error> {
+        ResponseOf::from_gzip_reader(body)
+    }
+
+    /// Parses a `deflate` compressed response body.
+    pub fn from_deflate_slice(body: &[u8]) -> Result<ResponseOf<T>, Error> {
+        ResponseOf::from_deflate_reader(body)
+    }
+
+    /// Parses a `gzip` compressed response body from a reader, see
+    /// `ResponseParser::parse_compressed_reader` to parse it with limits.
+    pub fn from_gzip_reader<R: Read>(reader: R) -> Result<ResponseOf<T>, Error> {
+        ResponseParser::new().parse_compressed_reader(reader, Compression::Gzip)
+    }
+
+    /// Parses a `deflate` compressed response body from a reader.
+    pub fn from_deflate_reader<R: Read>(reader: R) -> Result<ResponseOf<T>, Error> {
+        ResponseParser::new().parse_compressed_reader(reader, Compression::Deflate)
+    }
+}
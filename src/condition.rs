@@ -0,0 +1,166 @@
+//! Threshold conditions on aggregation rows, e.g. the rules of an alert loaded from a config file.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde::ser::{Serialize, Serializer};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use RowAccess;
+
+/// How a column is compared with the threshold of a `Condition`.
+///
+/// It is written as `">"`, `">="`, `"<"`, `"<="`, `"=="` or `"!="` in JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparator {
+    /// `>`
+    Gt,
+    /// `>=`
+    Gte,
+    /// `<`
+    Lt,
+    /// `<=`
+    Lte,
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne
+}
+
+impl Comparator {
+    /// Returns the operator as it is written in JSON.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Comparator::Gt => ">",
+            Comparator::Gte => ">=",
+            Comparator::Lt => "<",
+            Comparator::Lte => "<=",
+            Comparator::Eq => "==",
+            Comparator::Ne => "!="
+        }
+    }
+
+    fn accepts(&self, ordering: Ordering) -> bool {
+        match *self {
+            Comparator::Gt => ordering == Ordering::Greater,
+            Comparator::Gte => ordering != Ordering::Less,
+            Comparator::Lt => ordering == Ordering::Less,
+            Comparator::Lte => ordering != Ordering::Greater,
+            Comparator::Eq => ordering == Ordering::Equal,
+            Comparator::Ne => ordering != Ordering::Equal
+        }
+    }
+}
+
+impl Deserialize for Comparator {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Comparator, D::Error>
+        where D: Deserializer
+    {
+        match &*String::deserialize(deserializer)? {
+            ">" => Ok(Comparator::Gt),
+            ">=" => Ok(Comparator::Gte),
+            "<" => Ok(Comparator::Lt),
+            "<=" => Ok(Comparator::Lte),
+            "==" => Ok(Comparator::Eq),
+            "!=" => Ok(Comparator::Ne),
+            other => Err(D::Error::unknown_variant(other))
+        }
+    }
+}
+
+impl Serialize for Comparator {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// A comparison of a row's column with a threshold, such as `error_rate > 0.05`.
+///
+/// Conditions deserialize from objects like
+/// `{"name": "high errors", "column": "error_rate", "comparator": ">", "threshold": 0.05}`,
+/// where `name` is optional.
+///
+/// Numeric thresholds compare numerically, and column values that are strings holding a number
+/// are coerced. A string threshold compares the bucket key of the column as a string, see
+/// `RowAccess::key_string`, and only supports `==` and `!=`. Rows without the column never match.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Condition {
+    name: Option<String>,
+    column: String,
+    comparator: Comparator,
+    threshold: Value
+}
+
+impl Condition {
+    /// Creates an unnamed condition.
+    pub fn new<C: Into<String>>(column: C, comparator: Comparator, threshold: Value) -> Condition {
+        Condition {
+            name: None,
+            column: column.into(),
+            comparator: comparator,
+            threshold: threshold
+        }
+    }
+
+    /// Sets the name of the condition.
+    pub fn named<N: Into<String>>(mut self, name: N) -> Condition {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Returns the name of the condition, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| &**n)
+    }
+
+    /// Returns the column compared.
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    /// Returns the comparator.
+    pub fn comparator(&self) -> Comparator {
+        self.comparator
+    }
+
+    /// Returns the threshold the column is compared with.
+    pub fn threshold(&self) -> &Value {
+        &self.threshold
+    }
+
+    /// Returns `true` if `row` satisfies the condition.
+    pub fn matches<R: RowAccess>(&self, row: &R) -> bool {
+        if let Value::String(ref threshold) = self.threshold {
+            return match (self.comparator, row.key_string(&self.column)) {
+                (Comparator::Eq, Some(key)) => key == *threshold,
+                (Comparator::Ne, Some(key)) => key != *threshold,
+                _ => false
+            };
+        }
+
+        let value = match row.column(&self.column).and_then(coerce_f64) {
+            Some(value) => value,
+            None => return false
+        };
+        match self.threshold.as_f64().and_then(|threshold| value.partial_cmp(&threshold)) {
+            Some(ordering) => self.comparator.accepts(ordering),
+            None => false
+        }
+    }
+
+    /// Returns the rows that satisfy the condition, in their original order.
+    pub fn evaluate<I>(&self, rows: I) -> Vec<I::Item>
+        where I: IntoIterator,
+              I::Item: RowAccess
+    {
+        rows.into_iter().filter(|row| self.matches(row)).collect()
+    }
+}
+
+fn coerce_f64(value: &Value) -> Option<f64> {
+    match *value {
+        Value::String(ref s) => s.trim().parse().ok(),
+        ref other => other.as_f64()
+    }
+}
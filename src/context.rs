@@ -0,0 +1,181 @@
+//! Locating parse errors within a response body, so they can be reported with the path of the
+//! offending element rather than just a line and column.
+
+use serde_json;
+use std::fmt;
+
+/// How many bytes of the body are shown on each side of an error.
+const SNIPPET_RADIUS: usize = 30;
+
+/// Where in a JSON body parsing failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseContext {
+    path: String,
+    snippet: String,
+    line: usize,
+    column: usize
+}
+
+impl ParseContext {
+    /// Locates the position serde_json reported for `err` within `body`, returning `None` when
+    /// the error doesn't have a position.
+    pub fn locate(body: &[u8], err: &serde_json::Error) -> Option<ParseContext> {
+        let (line, column) = match *err {
+            serde_json::Error::Syntax(_, line, column) if line > 0 => (line, column),
+            _ => return None
+        };
+        if body.is_empty() {
+            return None;
+        }
+
+        // serde_json reports the number of bytes consumed on the line, so the error is at the
+        // last byte it read.
+        let line_start = body.split(|&b| b == b'\n').take(line - 1).map(|l| l.len() + 1).sum::<usize>();
+        let offset = (line_start + column).saturating_sub(1).min(body.len() - 1);
+
+        Some(ParseContext {
+            path: path_at(body, offset),
+            snippet: snippet(body, offset),
+            line: line,
+            column: column
+        })
+    }
+
+    /// Returns a JSON pointer to the element that failed to parse, such as
+    /// `/aggregations/by_host/buckets/3/cpu/value`, or `""` for the whole body.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the JSON surrounding the error, on a single line.
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+
+    /// Returns the line of the error, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column of the error.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for ParseContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at `{}` (line {}, column {}) near `{}`", self.path, self.line, self.column, self.snippet)
+    }
+}
+
+enum Frame {
+    Key(Option<String>),
+    Index(usize)
+}
+
+fn pointer(stack: &[Frame]) -> String {
+    let mut pointer = String::new();
+    for frame in stack {
+        match *frame {
+            Frame::Key(Some(ref key)) => {
+                pointer.push('/');
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            }
+            Frame::Index(i) => {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+            }
+            Frame::Key(None) => ()
+        }
+    }
+    pointer
+}
+
+/// Scans the JSON tokens of `body` up to `offset`, returning the pointer of the element there.
+fn path_at(body: &[u8], offset: usize) -> String {
+    let mut stack = Vec::new();
+    let mut i = 0;
+
+    while i < body.len() {
+        match body[i] {
+            b'{' | b'[' => {
+                if i >= offset {
+                    return pointer(&stack);
+                }
+                stack.push(if body[i] == b'{' { Frame::Key(None) } else { Frame::Index(0) });
+                i += 1;
+            }
+            b'}' | b']' => {
+                if i >= offset {
+                    let len = stack.len().saturating_sub(1);
+                    return pointer(&stack[..len]);
+                }
+                stack.pop();
+                i += 1;
+            }
+            b',' => {
+                match stack.last_mut() {
+                    Some(&mut Frame::Index(ref mut n)) => *n += 1,
+                    Some(&mut Frame::Key(ref mut key)) => *key = None,
+                    None => ()
+                }
+                i += 1;
+            }
+            b'"' => {
+                let end = string_end(body, i);
+                if let Some(&mut Frame::Key(ref mut key)) = stack.last_mut() {
+                    if key.is_none() {
+                        *key = Some(serde_json::from_slice(&body[i..end + 1]).unwrap_or_default());
+                    }
+                }
+                if offset <= end {
+                    return pointer(&stack);
+                }
+                i = end + 1;
+            }
+            b' ' | b'\t' | b'\n' | b'\r' | b':' => i += 1,
+            _ => {
+                let end = body[i..]
+                    .iter()
+                    .position(|&b| b"{}[],:\" \t\n\r".contains(&b))
+                    .map_or(body.len(), |n| i + n);
+                if offset < end {
+                    return pointer(&stack);
+                }
+                i = end;
+            }
+        }
+    }
+
+    pointer(&stack)
+}
+
+/// Returns the index of the quote closing the string starting at `start`, or the last index of
+/// the body if it isn't closed.
+fn string_end(body: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < body.len() {
+        match body[i] {
+            b'\\' => i += 2,
+            b'"' => return i,
+            _ => i += 1
+        }
+    }
+    body.len() - 1
+}
+
+fn snippet(body: &[u8], offset: usize) -> String {
+    let is_continuation = |i: usize| i < body.len() && body[i] & 0xC0 == 0x80;
+
+    let mut start = offset.saturating_sub(SNIPPET_RADIUS);
+    while is_continuation(start) {
+        start += 1;
+    }
+    let mut end = (offset + SNIPPET_RADIUS + 1).min(body.len());
+    while is_continuation(end) {
+        end += 1;
+    }
+
+    String::from_utf8_lossy(&body[start..end]).split_whitespace().collect::<Vec<_>>().join(" ")
+}
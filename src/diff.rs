@@ -0,0 +1,228 @@
+//! Comparing two snapshots of the same aggregations, e.g. for alerting on what changed between
+//! polls.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use {Aggregations, OwnedRow, to_owned_row};
+
+/// The differences between two snapshots of the same aggregations, see `Aggregations::diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggDiff {
+    added: Vec<OwnedRow>,
+    removed: Vec<OwnedRow>,
+    changed: Vec<BucketChange>
+}
+
+impl AggDiff {
+    /// Returns the rows whose buckets are only in the current snapshot, in their current order.
+    pub fn added(&self) -> &[OwnedRow] {
+        &self.added
+    }
+
+    /// Returns the rows whose buckets are only in the previous snapshot, in their previous order.
+    pub fn removed(&self) -> &[OwnedRow] {
+        &self.removed
+    }
+
+    /// Returns the buckets found in both snapshots with different metrics, in their current order.
+    pub fn changed(&self) -> &[BucketChange] {
+        &self.changed
+    }
+
+    /// Returns `true` if the snapshots have the same buckets with the same metrics.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A bucket whose metrics differ between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketChange {
+    key: BTreeMap<String, String>,
+    row: OwnedRow,
+    metrics: BTreeMap<String, MetricChange>
+}
+
+impl BucketChange {
+    /// Returns the normalized bucket keys the rows were matched on, by key column.
+    pub fn key(&self) -> &BTreeMap<String, String> {
+        &self.key
+    }
+
+    /// Returns the current row of the bucket.
+    pub fn row(&self) -> &OwnedRow {
+        &self.row
+    }
+
+    /// Returns the metrics that changed, by column.
+    pub fn metrics(&self) -> &BTreeMap<String, MetricChange> {
+        &self.metrics
+    }
+
+    /// Returns the change of a single metric, if it changed.
+    pub fn metric(&self, column: &str) -> Option<&MetricChange> {
+        self.metrics.get(column)
+    }
+}
+
+/// The previous and current value of a metric, either of which is `None` when the row didn't have
+/// the column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricChange {
+    old: Option<Value>,
+    new: Option<Value>
+}
+
+impl MetricChange {
+    /// Returns the previous value.
+    pub fn old(&self) -> Option<&Value> {
+        self.old.as_ref()
+    }
+
+    /// Returns the current value.
+    pub fn new(&self) -> Option<&Value> {
+        self.new.as_ref()
+    }
+
+    /// Returns `new - old` when both values are numbers.
+    pub fn delta(&self) -> Option<f64> {
+        match (self.old.as_ref().and_then(Value::as_f64), self.new.as_ref().and_then(Value::as_f64)) {
+            (Some(old), Some(new)) => Some(new - old),
+            _ => None
+        }
+    }
+}
+
+impl Aggregations {
+    /// Compares these aggregations with a `previous` snapshot of the same request.
+    ///
+    /// Rows are matched on the bucket key columns of either snapshot, see `key_columns`, and every
+    /// other column is compared as a metric. Use `diff_by` to choose the key columns instead.
+    pub fn diff(&self, previous: &Aggregations) -> AggDiff {
+        let keys: BTreeSet<String> = self.key_columns().into_iter().chain(previous.key_columns()).collect();
+        let keys: Vec<&str> = keys.iter().map(|k| &**k).collect();
+        self.diff_by(previous, &keys)
+    }
+
+    /// Compares these aggregations with a `previous` snapshot, matching rows on `key_columns`.
+    ///
+    /// Keys are normalized before matching: numbers compare by value whether they were parsed as
+    /// integers or floats, and the `{column}_key_as_string` of a key column is only used when the
+    /// key itself is missing, so a date histogram still matches if its `format` changed. Those
+    /// `key_as_string` columns aren't compared as metrics. When several rows share a key, the
+    /// first one is used.
+    ///
+    /// Rows are aligned by key and never by position, so buckets reordered by a `bucket_sort`
+    /// still match, and buckets it cut off or a `bucket_selector` removed are reported as
+    /// removed, not as changes to the buckets that took their place.
+    pub fn diff_by(&self, previous: &Aggregations, key_columns: &[&str]) -> AggDiff {
+        let current = keyed_rows(self, key_columns);
+        let previous = keyed_rows(previous, key_columns);
+
+        let previous_keys: BTreeMap<&Vec<Option<String>>, &OwnedRow> =
+            previous.iter().rev().map(|&(ref key, ref row)| (key, row)).collect();
+        let current_keys: BTreeSet<&Vec<Option<String>>> = current.iter().map(|&(ref key, _)| key).collect();
+
+        let mut diff = AggDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new()
+        };
+        let mut seen = BTreeSet::new();
+
+        for &(ref key, ref row) in &current {
+            if !seen.insert(key) {
+                debug! ("DIFF: Skipping duplicate bucket {:?}", key);
+                continue;
+            }
+            match previous_keys.get(key) {
+                None => diff.added.push(row.clone()),
+                Some(old) => {
+                    let metrics = changed_metrics(old, row, key_columns);
+                    if !metrics.is_empty() {
+                        diff.changed.push(BucketChange {
+                            key: key_columns.iter()
+                                .zip(key)
+                                .filter_map(|(column, key)| key.as_ref().map(|k| ((*column).to_owned(), k.clone())))
+                                .collect(),
+                            row: row.clone(),
+                            metrics: metrics
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        for &(ref key, ref row) in &previous {
+            if seen.insert(key) && !current_keys.contains(key) {
+                diff.removed.push(row.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+fn keyed_rows(aggs: &Aggregations, key_columns: &[&str]) -> Vec<(Vec<Option<String>>, OwnedRow)> {
+    aggs.into_iter()
+        .map(|row| {
+            let row = to_owned_row(&row);
+            let key = key_columns.iter().map(|column| normalized_key(&row, column)).collect();
+            (key, row)
+        })
+        .collect()
+}
+
+fn normalized_key(row: &OwnedRow, column: &str) -> Option<String> {
+    let value = match row.get(column) {
+        Some(&Value::Null) | None => row.get(&format!("{}_key_as_string", column)),
+        value => value
+    };
+
+    match value {
+        Some(&Value::String(ref s)) => Some(s.clone()),
+        Some(&Value::U64(n)) => Some(n.to_string()),
+        Some(&Value::I64(n)) => Some(n.to_string()),
+        Some(&Value::F64(n)) if n.fract() == 0.0 && n.abs() < 1e15 => Some((n as i64).to_string()),
+        Some(&Value::F64(n)) => Some(n.to_string()),
+        Some(&Value::Bool(b)) => Some(b.to_string()),
+        _ => None
+    }
+}
+
+fn changed_metrics(old: &OwnedRow, new: &OwnedRow, key_columns: &[&str]) -> BTreeMap<String, MetricChange> {
+    let is_key = |column: &str| {
+        key_columns.iter().any(|k| column == *k || column == format!("{}_key_as_string", k))
+    };
+
+    old.keys()
+        .chain(new.keys())
+        .filter(|column| !is_key(column))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|column| {
+            let (a, b) = (old.get(column), new.get(column));
+            if same_value(a, b) {
+                return None;
+            }
+            Some((column.clone(),
+                  MetricChange {
+                      old: a.cloned(),
+                      new: b.cloned()
+                  }))
+        })
+        .collect()
+}
+
+fn same_value(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => match (a.as_f64(), b.as_f64()) {
+            (Some(x), Some(y)) => x == y,
+            _ => a == b
+        },
+        (None, None) => true,
+        _ => false
+    }
+}
@@ -0,0 +1,112 @@
+//! Deserializing the hits of a search over several indices into different document types.
+
+use serde::Deserialize;
+use serde_json::{self, Value};
+
+use {Error, ResponseOf};
+
+impl ResponseOf<Value> {
+    /// Converts every hit with `f`, called with the hit's `_index` and `_source`, e.g. to pick the
+    /// variant of an enum based on the index a document comes from.
+    ///
+    /// Hits without a source are passed `null`. The first error returned by `f` is returned.
+    pub fn hits_dispatch<F, E>(&self, f: F) -> Result<Vec<E>, Error>
+        where F: Fn(&str, &Value) -> Result<E, serde_json::Error>
+    {
+        let null = Value::Null;
+        self.hits()
+            .iter()
+            .map(|hit| f(hit.index(), hit.source().unwrap_or(&null)).map_err(Error::Json))
+            .collect()
+    }
+}
+
+type Parser<'a, E> = Box<Fn(&Value) -> Result<E, serde_json::Error> + 'a>;
+
+/// Maps index patterns to the functions parsing the sources of their documents, see
+/// `ResponseOf::hits_dispatch`.
+///
+/// Patterns are index names that may contain `*` wildcards, such as `logs-*`. The first pattern
+/// added that matches an index is used.
+pub struct TypedByIndex<'a, E> {
+    routes: Vec<(String, Parser<'a, E>)>
+}
+
+impl<'a, E> TypedByIndex<'a, E> {
+    /// Creates a mapping without any patterns.
+    pub fn new() -> TypedByIndex<'a, E> {
+        TypedByIndex { routes: Vec::new() }
+    }
+
+    /// Parses the sources of indices matching `pattern` with `f`.
+    pub fn index<P, F>(mut self, pattern: P, f: F) -> TypedByIndex<'a, E>
+        where P: Into<String>,
+              F: Fn(&Value) -> Result<E, serde_json::Error> + 'a
+    {
+        self.routes.push((pattern.into(), Box::new(f)));
+        self
+    }
+
+    /// Deserializes the sources of indices matching `pattern` into `U`, and converts them with
+    /// `f`, e.g. an enum variant constructor.
+    pub fn source<P, U, F>(self, pattern: P, f: F) -> TypedByIndex<'a, E>
+        where P: Into<String>,
+              U: Deserialize,
+              F: Fn(U) -> E + 'a
+    {
+        self.index(pattern, move |source| serde_json::from_value(source.clone()).map(&f))
+    }
+
+    /// Returns `true` if a pattern matches `index`.
+    pub fn matches(&self, index: &str) -> bool {
+        self.routes.iter().any(|&(ref pattern, _)| matches_pattern(pattern, index))
+    }
+
+    /// Parses the source of a document from `index`.
+    pub fn parse(&self, index: &str, id: &str, source: &Value) -> Result<E, Error> {
+        match self.routes.iter().find(|&&(ref pattern, _)| matches_pattern(pattern, index)) {
+            Some(&(_, ref parse)) => parse(source).map_err(Error::Json),
+            None => Err(Error::UnmatchedIndex(index.to_owned(), id.to_owned()))
+        }
+    }
+
+    /// Parses the sources of all the hits of `response`, failing on the first hit from an index
+    /// that doesn't match any pattern.
+    pub fn parse_hits(&self, response: &ResponseOf<Value>) -> Result<Vec<E>, Error> {
+        let null = Value::Null;
+        response.hits()
+            .iter()
+            .map(|hit| self.parse(hit.index(), hit.id(), hit.source().unwrap_or(&null)))
+            .collect()
+    }
+}
+
+impl<'a, E> Default for TypedByIndex<'a, E> {
+    fn default() -> TypedByIndex<'a, E> {
+        TypedByIndex::new()
+    }
+}
+
+/// Matches `index` against a pattern where `*` matches any number of characters.
+fn matches_pattern(pattern: &str, index: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !index.starts_with(first) {
+        return false;
+    }
+
+    let mut rest = &index[first.len()..];
+    let parts: Vec<&str> = parts.collect();
+    let last = match parts.last() {
+        Some(last) => *last,
+        None => return rest.is_empty()
+    };
+
+    for part in &parts[..parts.len() - 1] {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false
+        }
+    }
+    rest.ends_with(last)
+}
@@ -0,0 +1,68 @@
+//! Aggregations nested in one of the same name, such as a `by_host` below `by_host`, whose
+//! columns would collide in the rows they share.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use {AggShape, Aggregations};
+use walk::each_agg;
+
+/// What the rows do about an aggregation nested in one of the same name, see
+/// `FlattenConfig::duplicate_names`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateNames {
+    /// The columns of the inner aggregation overwrite those of the outer one.
+    Overwrite,
+    /// `Aggregations::try_rows_with` fails with `AggError::DuplicateName`.
+    Error,
+    /// The inner aggregation is named after its depth, so the `by_host` terms nested in the
+    /// `by_host` at the root give the `by_host.1` and `by_host.1_doc_count` columns.
+    Disambiguate
+}
+
+impl Default for DuplicateNames {
+    fn default() -> DuplicateNames {
+        DuplicateNames::Overwrite
+    }
+}
+
+impl Aggregations {
+    /// Returns the path to the first aggregation nested in one of the same name, like
+    /// `by_host>by_host`.
+    pub(crate) fn first_duplicate_name(&self) -> Option<String> {
+        let mut first = None;
+        each_duplicate(self, &mut |path, _| if first.is_none() {
+            first = Some(path.join(">"));
+        });
+        first
+    }
+
+    /// Returns the names of the aggregations nested in one of the same name, by the address of
+    /// their name in the tree, with their depth appended.
+    pub(crate) fn duplicate_renames(&self) -> HashMap<usize, Arc<str>> {
+        let mut renames = HashMap::new();
+        let mut shared: HashMap<String, Arc<str>> = HashMap::new();
+        each_duplicate(self, &mut |path, name| {
+            let renamed = format!("{}.{}", name, path.len() - 1);
+            let renamed = shared.entry(renamed.clone()).or_insert_with(|| Arc::from(renamed)).clone();
+            renames.insert(name.as_ptr() as usize, renamed);
+        });
+        renames
+    }
+}
+
+/// Calls `f` with the path to every aggregation that the rows take columns from whose name is
+/// already on the path, and with its name in the tree.
+fn each_duplicate<'a, F>(aggs: &'a Aggregations, f: &mut F)
+    where F: FnMut(&[&'a str], &'a String)
+{
+    each_agg(aggs, &mut |path, name, shape| {
+        if let AggShape::KeyedBuckets(_) = *shape {
+            return;
+        }
+        // empty names share an address, and can't be told apart by it
+        if !name.is_empty() && path[..path.len() - 1].contains(&&**name) {
+            f(path, name);
+        }
+    });
+}
@@ -0,0 +1,145 @@
+//! Editing owned aggregation trees, e.g. to hand a cleaned up tree on to a consumer that expects
+//! Elasticsearch's JSON.
+
+use serde_json::Value;
+
+use {AggShape, BucketKey, Object, OwnedAggregations};
+
+impl OwnedAggregations {
+    /// Removes the buckets of every aggregation called `agg` whose key is `key`, returning how
+    /// many were removed.
+    ///
+    /// A bucket matches if its `key_as_string` or its key rendered like `BucketKey` does is
+    /// `key`, so date histogram buckets can be removed by either, and keyed buckets are
+    /// matched on their name. Aggregations of that name are looked for at every level.
+    pub fn remove_bucket(&mut self, agg: &str, key: &str) -> usize {
+        self.retain_buckets(agg, |name, bucket| name.map_or(true, |n| n != key) && !has_key(bucket, key))
+    }
+
+    /// Keeps only the buckets of every aggregation called `agg` for which `f` returns `true`,
+    /// returning how many were removed.
+    ///
+    /// `f` is called with the name of the bucket when the aggregation was requested with
+    /// `keyed: true`, and with the bucket itself.
+    pub fn retain_buckets<F>(&mut self, agg: &str, mut f: F) -> usize
+        where F: FnMut(Option<&str>, &Value) -> bool
+    {
+        match (self.0).0.as_object_mut() {
+            Some(aggs) => retain_in(aggs, agg, &mut f),
+            None => 0
+        }
+    }
+
+    /// Renames every aggregation called `old` to `new`, returning how many were renamed.
+    ///
+    /// An aggregation already called `new` next to one called `old` is replaced by it.
+    pub fn rename_agg(&mut self, old: &str, new: &str) -> usize {
+        match (self.0).0.as_object_mut() {
+            Some(aggs) if old != new => rename_in(aggs, old, new),
+            _ => 0
+        }
+    }
+
+    /// Returns the tree as JSON in the shape Elasticsearch sends, to put under `aggregations`.
+    pub fn to_value(&self) -> Value {
+        (self.0).0.clone()
+    }
+}
+
+fn has_key(bucket: &Value, key: &str) -> bool {
+    let rendered = |field: &str| bucket.find(field).map(|k| BucketKey::from(k).to_string());
+    rendered("key_as_string").map_or(false, |k| k == key) || rendered("key").map_or(false, |k| k == key)
+}
+
+/// Applies `f` to the buckets of the aggregations called `agg` among `aggs` and below them.
+fn retain_in<F>(aggs: &mut Object, agg: &str, f: &mut F) -> usize
+    where F: FnMut(Option<&str>, &Value) -> bool
+{
+    let mut removed = 0;
+    for (name, child) in aggs.iter_mut() {
+        let child = match child.as_object_mut() {
+            Some(child) => child,
+            None => continue
+        };
+        let single = match AggShape::of(child) {
+            AggShape::SingleBucket => true,
+            AggShape::Buckets(_) | AggShape::KeyedBuckets(_) => false,
+            _ => continue
+        };
+        if single {
+            removed += retain_in(child, agg, f);
+            continue;
+        }
+
+        match child.get_mut("buckets") {
+            Some(&mut Value::Array(ref mut buckets)) => {
+                if name == agg {
+                    let before = buckets.len();
+                    buckets.retain(|bucket| f(None, bucket));
+                    removed += before - buckets.len();
+                }
+                for bucket in buckets.iter_mut().filter_map(Value::as_object_mut) {
+                    removed += retain_in(bucket, agg, f);
+                }
+            }
+            Some(&mut Value::Object(ref mut buckets)) => {
+                if name == agg {
+                    let rejected: Vec<String> = buckets.iter()
+                        .filter(|&(key, bucket)| !f(Some(key), bucket))
+                        .map(|(key, _)| key.clone())
+                        .collect();
+                    for key in &rejected {
+                        buckets.remove(key);
+                    }
+                    removed += rejected.len();
+                }
+                for bucket in buckets.values_mut().filter_map(Value::as_object_mut) {
+                    removed += retain_in(bucket, agg, f);
+                }
+            }
+            _ => ()
+        }
+    }
+    removed
+}
+
+/// Renames the aggregations called `old` among `aggs` and below them.
+fn rename_in(aggs: &mut Object, old: &str, new: &str) -> usize {
+    let mut renamed = 0;
+    // buckets also hold their `key` and `doc_count`, which are left alone
+    if old != "key" && aggs.get(old).map_or(false, Value::is_object) {
+        let agg = aggs.remove(old).unwrap();
+        aggs.insert(new.to_owned(), agg);
+        renamed += 1;
+    }
+
+    for (name, child) in aggs.iter_mut() {
+        // bucket keys of composite aggregations are objects, but not aggregations
+        if name == "key" {
+            continue;
+        }
+        let child = match child.as_object_mut() {
+            Some(child) => child,
+            None => continue
+        };
+        match AggShape::of(child) {
+            AggShape::SingleBucket => renamed += rename_in(child, old, new),
+            AggShape::Buckets(_) | AggShape::KeyedBuckets(_) => {
+                let buckets: Vec<&mut Object> = match child.get_mut("buckets") {
+                    Some(&mut Value::Array(ref mut buckets)) => {
+                        buckets.iter_mut().filter_map(Value::as_object_mut).collect()
+                    }
+                    Some(&mut Value::Object(ref mut buckets)) => {
+                        buckets.values_mut().filter_map(Value::as_object_mut).collect()
+                    }
+                    _ => Vec::new()
+                };
+                for bucket in buckets {
+                    renamed += rename_in(bucket, old, new);
+                }
+            }
+            _ => ()
+        }
+    }
+    renamed
+}
@@ -0,0 +1,412 @@
+//! Errors of parsing responses, and errors reported by Elasticsearch itself.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::{self, Value};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use {BodyFormat, Flavor, Limit, Object, ParseContext};
+
+quick_error! {
+    /// Errors that can occur while parsing a response body.
+    #[derive(Debug)]
+    pub enum Error {
+        /// The body isn't valid JSON, or doesn't match the shape of the response type.
+        Json(err: serde_json::Error) {
+            from()
+            description("failed to parse response body")
+            display("failed to parse response body: {}", err)
+            cause(err)
+        }
+        /// The body doesn't match the shape of the response type, at a known position.
+        Parse(err: serde_json::Error, context: ParseContext) {
+            description("failed to parse response body")
+            display("failed to parse response body {}: {}", context, err)
+            cause(err)
+        }
+        /// Elasticsearch answered with an error object rather than the expected response, along
+        /// with the `status` of the body, if it has one.
+        Api(status: Option<u16>, err: ApiError) {
+            description("the request failed")
+            display("request failed{}: {}", status.map_or(String::new(), |s| format!(" with status {}", s)), err)
+            cause(err)
+        }
+        /// A line of a newline-delimited stream isn't a valid response, with the error parsing
+        /// it as a body gave: `Error::Json`, `Error::Parse` or `Error::Api`.
+        Line(line: usize, err: Box<Error>) {
+            description("failed to parse response line")
+            display("failed to parse response on line {}: {}", line, err)
+            cause(&**err)
+        }
+        /// The stream of responses couldn't be read.
+        Io(err: io::Error) {
+            from()
+            description("failed to read responses")
+            display("failed to read responses: {}", err)
+            cause(err)
+        }
+        /// A compressed body couldn't be decompressed, see `Compression`.
+        Decompress(err: io::Error) {
+            description("failed to decompress response body")
+            display("failed to decompress response body: {}", err)
+            cause(err)
+        }
+        /// A response is larger than a limit of its `ResponseParser`.
+        LimitExceeded { kind: Limit, limit: u64 } {
+            description("response exceeds a size limit")
+            display("response exceeds the limit of {} {}", limit, kind)
+        }
+        /// An aggregation, or a query of the `profile` if the path starts with `/profile`, is
+        /// nested more levels deep than a `ResponseParser` allows, see
+        /// `ResponseParser::strict_depth`.
+        DepthExceeded { depth: usize, path: String } {
+            description("aggregations are nested too deep")
+            display("{} at `{}` is nested {} levels deep",
+                    if path.starts_with("/profile/") { "query" } else { "aggregation" }, path, depth)
+        }
+        /// An entry of the `buckets` of an aggregation isn't a bucket, see
+        /// `ResponseParser::strict_buckets`.
+        InvalidBucket(path: String) {
+            description("bucket is not an object")
+            display("bucket at `{}` is not an object", path)
+        }
+        /// A hit comes from an index without a registered document type, see `TypedByIndex`.
+        UnmatchedIndex(index: String, id: String) {
+            description("no document type registered for the index of a hit")
+            display("no document type registered for index `{}` of hit `{}`", index, id)
+        }
+        /// A source lacks required fields of the type it is deserialized into, listed by their
+        /// path, see `Hit::parse_source_partial`.
+        MissingFields(fields: Vec<String>) {
+            description("source is missing required fields")
+            display("source is missing required fields: {}", fields.join(", "))
+        }
+        /// The total of a scroll page drifted from that of the first page by more than the
+        /// tolerance of the `ScrollStitcher`.
+        TotalDrift { first: u64, latest: u64 } {
+            description("scroll total changed between pages")
+            display("scroll total changed from {} to {} hits", first, latest)
+        }
+    }
+}
+
+/// An error returned by the Elasticsearch API, such as the `error` object of a failed request
+/// or the per-item failures of bulk style responses.
+///
+/// The well-known `type`, `reason`, `caused_by` and `root_cause` fields are parsed; everything
+/// else the error carries is available through `get`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    ty: String,
+    reason: Option<String>,
+    caused_by: Option<Box<ApiError>>,
+    root_cause: Vec<ApiError>,
+    fields: Object
+}
+
+impl ApiError {
+    /// Parses an error from its JSON object, returning `None` when it isn't an object with a
+    /// `type`.
+    pub fn from_value(value: &Value) -> Option<ApiError> {
+        let o = match value.as_object() {
+            Some(o) => o,
+            None => return None
+        };
+        let ty = match o.get("type").and_then(Value::as_str) {
+            Some(ty) => ty.to_owned(),
+            None => return None
+        };
+
+        Some(ApiError {
+            ty: ty,
+            reason: o.get("reason").and_then(Value::as_str).map(str::to_owned),
+            caused_by: o.get("caused_by").and_then(ApiError::from_value).map(Box::new),
+            root_cause: o.get("root_cause")
+                .and_then(Value::as_array)
+                .map(|causes| causes.iter().filter_map(ApiError::from_value).collect())
+                .unwrap_or_default(),
+            fields: o.clone()
+        })
+    }
+
+    /// Returns the type of the error, e.g. `index_not_found_exception`.
+    pub fn error_type(&self) -> &str {
+        &self.ty
+    }
+
+    /// Returns the human readable reason for the error.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_ref().map(|r| &**r)
+    }
+
+    /// Returns the error that caused this one.
+    pub fn caused_by(&self) -> Option<&ApiError> {
+        self.caused_by.as_ref().map(|c| &**c)
+    }
+
+    /// Returns the root causes Elasticsearch reported for the error.
+    pub fn root_causes(&self) -> &[ApiError] {
+        &self.root_cause
+    }
+
+    /// Returns any field of the error object, e.g. `index` or `shard`.
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.fields.get(field)
+    }
+
+    /// Returns `true` if the request may succeed when retried later, e.g. after a rejected
+    /// execution, a timeout, or a transient circuit breaker trip.
+    ///
+    /// The error, its `caused_by` chain and its root causes are all checked against
+    /// `RETRYABLE_TYPES`. Circuit breaker errors with a `PERMANENT` durability aren't retryable.
+    pub fn is_retryable(&self) -> bool {
+        self.is_retryable_for(Flavor::Elasticsearch)
+    }
+
+    /// Like `is_retryable`, but for an error sent by `flavor`, whose renamed error types are
+    /// classified like the Elasticsearch types they replace.
+    pub fn is_retryable_for(&self, flavor: Flavor) -> bool {
+        self.causes().iter().any(|e| e.is_retryable_type(flavor))
+    }
+
+    /// Like `is_retryable`, but `f` can override the classification by returning `Some`.
+    pub fn is_retryable_with<F>(&self, f: F) -> bool
+        where F: Fn(&ApiError) -> Option<bool>
+    {
+        f(self).unwrap_or_else(|| self.is_retryable())
+    }
+
+    /// Returns `true` if the request itself is at fault, e.g. it can't be parsed or doesn't match
+    /// the mapping, so retrying it won't help.
+    ///
+    /// The error, its `caused_by` chain and its root causes are checked against
+    /// `CLIENT_ERROR_TYPES`.
+    pub fn is_client_error(&self) -> bool {
+        self.is_client_error_for(Flavor::Elasticsearch)
+    }
+
+    /// Like `is_client_error`, but for an error sent by `flavor`, see `is_retryable_for`.
+    pub fn is_client_error_for(&self, flavor: Flavor) -> bool {
+        self.causes().iter().any(|e| CLIENT_ERROR_TYPES.contains(&flavor.canonical_error_type(&e.ty)))
+    }
+
+    /// Like `is_client_error`, but `f` can override the classification by returning `Some`.
+    pub fn is_client_error_with<F>(&self, f: F) -> bool
+        where F: Fn(&ApiError) -> Option<bool>
+    {
+        f(self).unwrap_or_else(|| self.is_client_error())
+    }
+
+    /// Returns the details of the first `circuit_breaking_exception` among this error, its
+    /// `caused_by` chain and its root causes.
+    pub fn circuit_breaker(&self) -> Option<CircuitBreaker> {
+        self.causes()
+            .into_iter()
+            .filter(|e| e.ty == "circuit_breaking_exception")
+            .filter_map(CircuitBreaker::from_error)
+            .next()
+    }
+
+    fn is_retryable_type(&self, flavor: Flavor) -> bool {
+        if self.ty == "circuit_breaking_exception" {
+            return CircuitBreaker::from_error(self).map_or(true, |c| !c.is_permanent());
+        }
+        let ty = flavor.canonical_error_type(&self.ty);
+        RETRYABLE_TYPES.contains(&ty) || ty.ends_with("_timeout_exception")
+    }
+
+    /// Returns this error followed by its `caused_by` chain and its root causes.
+    pub(crate) fn causes(&self) -> Vec<&ApiError> {
+        let mut causes = vec![self];
+        let mut cause = self.caused_by();
+        while let Some(c) = cause {
+            causes.push(c);
+            cause = c.caused_by();
+        }
+        causes.extend(self.root_cause.iter());
+        causes
+    }
+}
+
+/// Error types that are worth retrying, see `ApiError::is_retryable`.
+///
+/// Types ending in `_timeout_exception` are retryable as well.
+pub const RETRYABLE_TYPES: &'static [&'static str] = &["es_rejected_execution_exception",
+                                                       "circuit_breaking_exception",
+                                                       "timeout_exception",
+                                                       "node_not_connected_exception",
+                                                       "node_disconnected_exception",
+                                                       "no_shard_available_action_exception",
+                                                       "unavailable_shards_exception",
+                                                       "primary_missing_action_exception"];
+
+/// Error types caused by the request itself, see `ApiError::is_client_error`.
+pub const CLIENT_ERROR_TYPES: &'static [&'static str] = &["mapper_parsing_exception",
+                                                          "mapper_exception",
+                                                          "strict_dynamic_mapping_exception",
+                                                          "document_parsing_exception",
+                                                          "parsing_exception",
+                                                          "parse_exception",
+                                                          "x_content_parse_exception",
+                                                          "json_parse_exception",
+                                                          "illegal_argument_exception",
+                                                          "action_request_validation_exception",
+                                                          "query_shard_exception"];
+
+/// HTTP status codes that are worth retrying, see `ResponseError::is_retryable`.
+pub const RETRYABLE_STATUSES: &'static [u16] = &[429, 502, 503, 504];
+
+/// The memory figures of a `circuit_breaking_exception`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreaker {
+    bytes_wanted: u64,
+    bytes_limit: u64,
+    durability: Option<String>
+}
+
+impl CircuitBreaker {
+    fn from_error(err: &ApiError) -> Option<CircuitBreaker> {
+        match (err.get("bytes_wanted").and_then(Value::as_u64), err.get("bytes_limit").and_then(Value::as_u64)) {
+            (Some(wanted), Some(limit)) => {
+                Some(CircuitBreaker {
+                    bytes_wanted: wanted,
+                    bytes_limit: limit,
+                    durability: err.get("durability").and_then(Value::as_str).map(str::to_owned)
+                })
+            }
+            _ => None
+        }
+    }
+
+    /// Returns the memory the request would have used, in bytes.
+    pub fn bytes_wanted(&self) -> u64 {
+        self.bytes_wanted
+    }
+
+    /// Returns the limit of the breaker that tripped, in bytes.
+    pub fn bytes_limit(&self) -> u64 {
+        self.bytes_limit
+    }
+
+    /// Returns the durability of the trip, `TRANSIENT` or `PERMANENT`, when Elasticsearch
+    /// reported one.
+    pub fn durability(&self) -> Option<&str> {
+        self.durability.as_ref().map(|d| &**d)
+    }
+
+    /// Returns `true` if the breaker won't reset by itself, so retrying is pointless.
+    pub fn is_permanent(&self) -> bool {
+        self.durability() == Some("PERMANENT")
+    }
+}
+
+impl Deserialize for ApiError {
+    fn deserialize<D>(deserializer: &mut D) -> Result<ApiError, D::Error>
+        where D: Deserializer
+    {
+        let value = Value::deserialize(deserializer)?;
+        ApiError::from_value(&value).ok_or_else(|| D::Error::invalid_value("expected an error object with a `type`"))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reason {
+            Some(ref reason) => write!(f, "{}: {}", self.ty, reason),
+            None => write!(f, "{}", self.ty)
+        }
+    }
+}
+
+impl StdError for ApiError {
+    fn description(&self) -> &str {
+        self.reason().unwrap_or(&self.ty)
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        self.caused_by().map(|c| c as &StdError)
+    }
+}
+
+quick_error! {
+    /// Errors that can occur while turning an HTTP response into a response type.
+    #[derive(Debug)]
+    pub enum ResponseError {
+        /// Elasticsearch answered with an error object.
+        Api(status: u16, err: ApiError) {
+            description("the request failed")
+            display("request failed with status {}: {}", status, err)
+            cause(err)
+        }
+        /// The status code isn't one expected for the response type, and the body doesn't
+        /// carry an error object.
+        Status(status: u16) {
+            description("unexpected status code")
+            display("unexpected status code {}", status)
+        }
+        /// An endpoint that answers with an empty body sent a body.
+        UnexpectedBody(status: u16) {
+            description("unexpected response body")
+            display("unexpected body in response with status {}", status)
+        }
+        /// The body isn't valid JSON, or doesn't match the shape of the response type.
+        Json(err: serde_json::Error) {
+            from()
+            description("failed to parse response body")
+            display("failed to parse response body: {}", err)
+            cause(err)
+        }
+        /// The body doesn't match the shape of the response type, at a known position.
+        Parse(err: serde_json::Error, context: ParseContext) {
+            description("failed to parse response body")
+            display("failed to parse response body {}: {}", context, err)
+            cause(err)
+        }
+        /// The body isn't valid CBOR, or doesn't match the shape of the response type.
+        Cbor(message: String) {
+            description("failed to parse CBOR response body")
+            display("failed to parse CBOR response body: {}", message)
+        }
+        /// The body is in a format that can't be parsed, such as SMILE, or CBOR without the
+        /// `cbor` feature.
+        UnsupportedFormat(format: BodyFormat) {
+            description("unsupported response body format")
+            display("unsupported response body format: {:?}", format)
+        }
+    }
+}
+
+impl ResponseError {
+    /// Returns `true` if the request may succeed when retried later.
+    ///
+    /// Errors with a status code in `RETRYABLE_STATUSES` are retryable, as are API errors that
+    /// `ApiError::is_retryable` classifies so.
+    pub fn is_retryable(&self) -> bool {
+        self.is_retryable_with(|_, _| None)
+    }
+
+    /// Like `is_retryable`, but `f` can override the classification by returning `Some`. It's
+    /// called with the status code and API error, if any, of requests that failed.
+    pub fn is_retryable_with<F>(&self, f: F) -> bool
+        where F: Fn(u16, Option<&ApiError>) -> Option<bool>
+    {
+        match *self {
+            ResponseError::Api(status, ref err) => {
+                f(status, Some(err)).unwrap_or_else(|| RETRYABLE_STATUSES.contains(&status) || err.is_retryable())
+            }
+            ResponseError::Status(status) => f(status, None).unwrap_or_else(|| RETRYABLE_STATUSES.contains(&status)),
+            _ => false
+        }
+    }
+
+    /// Returns `true` if the request itself is at fault, either with a `400` status or an API
+    /// error that `ApiError::is_client_error` classifies so.
+    pub fn is_client_error(&self) -> bool {
+        match *self {
+            ResponseError::Api(status, ref err) => status == 400 || err.is_client_error(),
+            ResponseError::Status(status) => status == 400,
+            _ => false
+        }
+    }
+}
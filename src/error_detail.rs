@@ -0,0 +1,286 @@
+//! Typed details of script and mapping errors, which Elasticsearch mostly spells out in nested
+//! fields and reason strings.
+
+use serde_json::Value;
+
+use ApiError;
+
+/// Error types of documents that don't fit the mapping, see `ApiError::mapper_error`.
+const MAPPER_ERROR_TYPES: &'static [&'static str] = &["mapper_parsing_exception",
+                                                      "document_parsing_exception",
+                                                      "strict_dynamic_mapping_exception",
+                                                      "mapper_exception",
+                                                      "illegal_argument_exception"];
+
+/// Where in its source a script failed, in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptPosition {
+    /// The character the error was found at.
+    pub offset: usize,
+    /// The start of the statement the error is in.
+    pub start: usize,
+    /// The end of the statement the error is in.
+    pub end: usize
+}
+
+/// A `script_exception`, raised when a script fails to compile or fails while running.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    reason: Option<String>,
+    lang: Option<String>,
+    script: Option<String>,
+    script_stack: Vec<String>,
+    position: Option<ScriptPosition>,
+    caused_by: Option<ApiError>
+}
+
+impl ScriptError {
+    fn from_error(err: &ApiError) -> ScriptError {
+        let position = err.get("position").and_then(|p| {
+            let field = |name: &str| p.find(name).and_then(Value::as_u64).map(|n| n as usize);
+            match (field("offset"), field("start"), field("end")) {
+                (Some(offset), Some(start), Some(end)) => {
+                    Some(ScriptPosition {
+                        offset: offset,
+                        start: start,
+                        end: end
+                    })
+                }
+                _ => None
+            }
+        });
+
+        ScriptError {
+            reason: err.reason().map(str::to_owned),
+            lang: err.get("lang").and_then(Value::as_str).map(str::to_owned),
+            script: err.get("script").and_then(Value::as_str).map(str::to_owned),
+            script_stack: err.get("script_stack")
+                .and_then(Value::as_array)
+                .map(|lines| lines.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+                .unwrap_or_default(),
+            position: position,
+            caused_by: err.caused_by().cloned()
+        }
+    }
+
+    /// Returns the reason of the failure, `compile error` or `runtime error`.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_ref().map(|r| &**r)
+    }
+
+    /// Returns `true` if the script didn't compile, as opposed to failing while it ran.
+    pub fn is_compile_error(&self) -> bool {
+        self.reason() == Some("compile error")
+    }
+
+    /// Returns the language of the script, e.g. `painless`.
+    pub fn lang(&self) -> Option<&str> {
+        self.lang.as_ref().map(|l| &**l)
+    }
+
+    /// Returns the source of the script, which Elasticsearch may have shortened.
+    pub fn script(&self) -> Option<&str> {
+        self.script.as_ref().map(|s| &**s)
+    }
+
+    /// Returns the lines of the stack, Java frames first for runtime errors, then the script
+    /// around the failure and a line pointing at it with `^---- HERE`.
+    pub fn script_stack(&self) -> &[String] {
+        &self.script_stack
+    }
+
+    /// Returns where in the script it failed.
+    pub fn position(&self) -> Option<ScriptPosition> {
+        self.position
+    }
+
+    /// Returns the statement of the script the failure is in, from `start` to `end` of the
+    /// position.
+    pub fn statement(&self) -> Option<String> {
+        match (self.script(), self.position) {
+            (Some(script), Some(position)) if position.start <= position.end => {
+                Some(script.chars().skip(position.start).take(position.end - position.start).collect())
+            }
+            _ => None
+        }
+    }
+
+    /// Returns the error behind the failure, e.g. an `illegal_argument_exception` describing a
+    /// syntax error or a `null_pointer_exception` thrown while running.
+    pub fn caused_by(&self) -> Option<&ApiError> {
+        self.caused_by.as_ref()
+    }
+}
+
+/// A document or mapping update that doesn't fit the mapping of an index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapperError {
+    error_type: String,
+    field: String,
+    expected_type: Option<String>,
+    provided_type: Option<String>,
+    document_id: Option<String>,
+    value_preview: Option<String>
+}
+
+impl MapperError {
+    fn from_error(err: &ApiError) -> Option<MapperError> {
+        let reason = err.reason().unwrap_or("");
+        let mut parsed = parse_mapper_reason(reason)?;
+        if let Some(field) = err.get("field").and_then(Value::as_str) {
+            parsed.field = field.to_owned();
+        }
+        parsed.error_type = err.error_type().to_owned();
+        Some(parsed)
+    }
+
+    /// Returns the type of the error, e.g. `mapper_parsing_exception`.
+    pub fn error_type(&self) -> &str {
+        &self.error_type
+    }
+
+    /// Returns the offending field, as named in the reason.
+    ///
+    /// This is the full path of the field, e.g. `user.age`, except for fields rejected by a
+    /// strict mapping, which are named relative to the object they were found in.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the type the mapping has for the field, e.g. `long`.
+    pub fn expected_type(&self) -> Option<&str> {
+        self.expected_type.as_ref().map(|t| &**t)
+    }
+
+    /// Returns the type a mapping update tried to give the field, e.g. `text`.
+    ///
+    /// Documents that fail to parse don't say what they sent, so this is only known for
+    /// conflicting mapping updates.
+    pub fn provided_type(&self) -> Option<&str> {
+        self.provided_type.as_ref().map(|t| &**t)
+    }
+
+    /// Returns the id of the document that didn't parse, when Elasticsearch named it.
+    pub fn document_id(&self) -> Option<&str> {
+        self.document_id.as_ref().map(|i| &**i)
+    }
+
+    /// Returns the start of the value that didn't parse, when Elasticsearch quoted it.
+    pub fn value_preview(&self) -> Option<&str> {
+        self.value_preview.as_ref().map(|v| &**v)
+    }
+}
+
+impl ApiError {
+    /// Returns the details of the first `script_exception` among this error, its `caused_by`
+    /// chain, its root causes and the reasons of its failed shards.
+    ///
+    /// The root causes of a failed search carry the script but not what went wrong with it,
+    /// so an exception that has a `caused_by` is preferred.
+    pub fn script_error(&self) -> Option<ScriptError> {
+        let mut candidates: Vec<ApiError> = self.causes().into_iter().cloned().collect();
+        candidates.extend(self.shard_reasons());
+        let scripts: Vec<&ApiError> = candidates.iter().filter(|e| e.error_type() == "script_exception").collect();
+
+        scripts.iter()
+            .find(|e| e.caused_by().is_some())
+            .or_else(|| scripts.first())
+            .map(|e| ScriptError::from_error(e))
+    }
+
+    /// Returns the details of the first mapping error among this error, its `caused_by` chain
+    /// and its root causes, such as a value that doesn't parse as the type of its field or a
+    /// mapping update that changes the type of a field.
+    ///
+    /// The field and types are read from the reason, so an error whose reason isn't in one of
+    /// the known forms is skipped, as is an `illegal_argument_exception` not about a mapping.
+    pub fn mapper_error(&self) -> Option<MapperError> {
+        self.causes()
+            .into_iter()
+            .filter(|e| MAPPER_ERROR_TYPES.contains(&e.error_type()))
+            .filter_map(MapperError::from_error)
+            .next()
+    }
+
+    /// Returns the reasons of the `failed_shards` of a search phase error.
+    fn shard_reasons(&self) -> Vec<ApiError> {
+        self.get("failed_shards")
+            .and_then(Value::as_array)
+            .map(|shards| shards.iter().filter_map(|s| s.find("reason")).filter_map(ApiError::from_value).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the text between the brackets right after `prefix` in `s`, and what follows them.
+fn bracketed_after<'a>(s: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    let rest = &s[s.find(prefix)? + prefix.len()..];
+    if !rest.starts_with('[') {
+        return None;
+    }
+    let end = rest.find(']')?;
+    Some((&rest[1..end], &rest[end + 1..]))
+}
+
+/// Returns the text between the quotes right after `prefix` in `s`, up to the last quote as
+/// the text can contain quotes itself.
+fn quoted_after<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = &s[s.find(prefix)? + prefix.len()..];
+    if !rest.starts_with('\'') {
+        return None;
+    }
+    let end = rest.rfind('\'')?;
+    if end == 0 { None } else { Some(&rest[1..end]) }
+}
+
+fn mapper_error(field: &str, expected: Option<&str>, provided: Option<&str>) -> MapperError {
+    MapperError {
+        error_type: String::new(),
+        field: field.to_owned(),
+        expected_type: expected.map(str::to_owned),
+        provided_type: provided.map(str::to_owned),
+        document_id: None,
+        value_preview: None
+    }
+}
+
+/// Parses the field and types out of the reason of a mapping error, in the forms the versions
+/// of Elasticsearch write them:
+///
+/// - `failed to parse field [age] of type [long] in document with id '1'. Preview of field's
+///    value: 'abc'`, with the id and preview added in 7.x and a `[1:8]` location in 8.x
+/// - `failed to parse [age]`, before 6.x
+/// - `mapper [age] cannot be changed from type [long] to [text]`
+/// - `mapper [age] of different type, current_type [long], merged_type [text]`, in 6.x
+/// - `mapping set to strict, dynamic introduction of [age] within [user] is not allowed`
+/// - `object mapping for [user] tried to parse field [user] as object, but found a concrete value`
+fn parse_mapper_reason(reason: &str) -> Option<MapperError> {
+    if let Some((field, rest)) = bracketed_after(reason, "failed to parse field ") {
+        let expected = bracketed_after(rest, " of type ").map(|(ty, _)| ty);
+        let mut err = mapper_error(field, expected, None);
+        err.document_id = quoted_after(rest, "in document with id ").map(|id| {
+            // the preview follows the id in the same sentence
+            id.split('\'').next().unwrap_or(id).to_owned()
+        });
+        err.value_preview = quoted_after(rest, "Preview of field's value: ").map(str::to_owned);
+        return Some(err);
+    }
+    if let Some((field, _)) = bracketed_after(reason, "failed to parse ") {
+        return Some(mapper_error(field, None, None));
+    }
+    if let Some((field, rest)) = bracketed_after(reason, "mapper ") {
+        if let Some((from, rest)) = bracketed_after(rest, " cannot be changed from type ") {
+            return Some(mapper_error(field, Some(from), bracketed_after(rest, " to ").map(|(to, _)| to)));
+        }
+        if let Some((current, rest)) = bracketed_after(rest, "current_type ") {
+            return Some(mapper_error(field, Some(current), bracketed_after(rest, "merged_type ").map(|(m, _)| m)));
+        }
+        return None;
+    }
+    if let Some((field, _)) = bracketed_after(reason, "dynamic introduction of ") {
+        return Some(mapper_error(field, None, None));
+    }
+    if let Some((field, _)) = bracketed_after(reason, "object mapping for ") {
+        return Some(mapper_error(field, Some("object"), None));
+    }
+    None
+}
@@ -0,0 +1,278 @@
+//! Transforming the values of aggregation rows as they are flattened.
+
+use serde_json::Value;
+use std::fmt;
+
+use {AggError, AggregationIterator, Aggregations, BucketKey, DuplicateNames, Object, OwnedRow, to_owned_row};
+use intern::Interner;
+use interval::BucketWidths;
+
+type ColumnMap<'f> = Box<Fn(f64) -> f64 + 'f>;
+
+/// Changes applied to every row by `Aggregations::rows_with`.
+#[derive(Default)]
+pub struct FlattenConfig<'f> {
+    maps: Vec<(String, ColumnMap<'f>)>,
+    skip_null_keys: bool,
+    projection: Option<Vec<String>>,
+    filters: Vec<(String, BucketFilter)>,
+    bucket_width: bool,
+    duplicates: DuplicateNames
+}
+
+/// Which buckets of an aggregation the rows come from, see `FlattenConfig::filter_bucket`.
+///
+/// Keys are compared as they are rendered by `BucketKey`, so a numeric key `3` matches `"3"`.
+/// A bucket with a `key_as_string`, such as that of a `date_histogram`, matches by either.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BucketFilter {
+    /// Buckets whose key is the string.
+    Equals(String),
+    /// Buckets whose key starts with the string.
+    Prefix(String),
+    /// Buckets whose key is any of the strings.
+    OneOf(Vec<String>)
+}
+
+impl BucketFilter {
+    fn matches_key(&self, key: &str) -> bool {
+        match *self {
+            BucketFilter::Equals(ref k) => key == k,
+            BucketFilter::Prefix(ref prefix) => key.starts_with(&**prefix),
+            BucketFilter::OneOf(ref keys) => keys.iter().any(|k| k == key)
+        }
+    }
+
+    /// Returns `true` if the key or the `key_as_string` of `bucket` matches.
+    pub(crate) fn matches(&self, bucket: &Object) -> bool {
+        ["key", "key_as_string"].iter()
+            .filter_map(|field| bucket.get(*field))
+            .any(|key| self.matches_key(&BucketKey::from(key).to_string()))
+    }
+}
+
+impl<'f> FlattenConfig<'f> {
+    /// Creates a config that leaves rows as they are.
+    pub fn new() -> FlattenConfig<'f> {
+        FlattenConfig::default()
+    }
+
+    /// Replaces the value of `column` with `f` of it, e.g. to turn the fractions of a
+    /// `normalize` pipeline with `percent_of_sum` into percentages.
+    ///
+    /// Only numbers are mapped, and the results are always floats; a `null` or any other value,
+    /// such as a `key_as_string`, is left as it is. Mappings of the same column are applied in
+    /// the order they were added.
+    pub fn map_column<C, F>(mut self, column: C, f: F) -> FlattenConfig<'f>
+        where C: Into<String>,
+              F: Fn(f64) -> f64 + 'f
+    {
+        self.maps.push((column.into(), Box::new(f)));
+        self
+    }
+
+    /// Leaves out bucket key columns whose key is `null`, rather than keeping them as `null`.
+    ///
+    /// Older versions of Elasticsearch can send a `null` key for the bucket of documents
+    /// without a numeric field; the other columns of that bucket, such as its `doc_count`, are
+    /// kept.
+    pub fn skip_null_keys(mut self) -> FlattenConfig<'f> {
+        self.skip_null_keys = true;
+        self
+    }
+
+    /// Keeps only `columns` in the rows, e.g. `&["per_day", "per_host", "cpu_avg"]`.
+    ///
+    /// Other columns are never inserted into the rows, which saves building them for wide trees.
+    /// That includes the `{agg}_doc_count` and `{agg}_key_as_string` columns of buckets, unless
+    /// they are listed too; buckets are still descended into whether their key is kept or not.
+    /// Projecting again replaces the columns of the earlier projection.
+    ///
+    /// Columns that none of the rows can have aren't checked here, as the config doesn't know
+    /// the tree yet. Only `Aggregations::try_rows_with` reports them, as
+    /// `AggError::UnknownColumns`; everywhere else, `rows_with` included, they are ignored.
+    pub fn project(mut self, columns: &[&str]) -> FlattenConfig<'f> {
+        self.projection = Some(columns.iter().map(|&c| c.to_owned()).collect());
+        self
+    }
+
+    /// Only descends into the buckets of the aggregation `agg` that match `filter`, e.g.
+    /// `filter_bucket("per_host", BucketFilter::Equals("web-01".to_owned()))`.
+    ///
+    /// Other buckets are skipped along with everything below them, rather than flattened and
+    /// thrown away. `agg` is matched at every level it appears, and several filters, on the same
+    /// or different aggregations, must all match.
+    pub fn filter_bucket<A: Into<String>>(mut self, agg: A, filter: BucketFilter) -> FlattenConfig<'f> {
+        self.filters.push((agg.into(), filter));
+        self
+    }
+
+    /// Adds a `{agg}_width` column to the rows of `histogram`, `date_histogram` and
+    /// `auto_date_histogram` buckets, for charts that draw each bucket as wide as it is.
+    ///
+    /// The width is the `interval` of an `auto_date_histogram` when it has a fixed length, and
+    /// otherwise inferred from the keys: evenly spaced buckets all get the distance between
+    /// them, see `Aggregations::inferred_interval`, while unevenly spaced ones each get the
+    /// distance to the next key, and the last one the most common distance. A histogram with a
+    /// single bucket and no `interval` has no width to go by and gets no column.
+    ///
+    /// The width is found through the key of the bucket, so a projection has to keep the key
+    /// column of the histogram for its width column to be filled in.
+    pub fn emit_bucket_width(mut self, emit: bool) -> FlattenConfig<'f> {
+        self.bucket_width = emit;
+        self
+    }
+
+    /// Sets what the rows do about an aggregation nested in one of the same name, whose columns
+    /// would collide with those of the outer one, which Elasticsearch allows.
+    ///
+    /// By default the columns of the inner aggregation overwrite those of the outer one, see
+    /// `DuplicateNames`. Disambiguated names take the place of the original ones everywhere,
+    /// including in `MappedRows::columns`, projections and `map_column`; aggregations of the same
+    /// name that aren't nested in one another, such as in sibling buckets, keep their name.
+    pub fn duplicate_names(mut self, duplicates: DuplicateNames) -> FlattenConfig<'f> {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Returns the names the rows build their columns with.
+    fn names<'a>(&self, aggs: &'a Aggregations, projected: bool) -> Interner<'a> {
+        let names = match self.projection {
+            Some(ref projection) if projected => Interner::projected(projection.iter().cloned().collect()),
+            _ => Interner::new()
+        };
+        match self.duplicates {
+            DuplicateNames::Disambiguate => names.with_renames(aggs.duplicate_renames()),
+            DuplicateNames::Overwrite | DuplicateNames::Error => names
+        }
+    }
+
+    fn apply(&self, row: &mut OwnedRow, key_columns: &[String]) {
+        for column in key_columns {
+            if row.get(column).map_or(false, Value::is_null) {
+                row.remove(column);
+            }
+        }
+        for &(ref column, ref f) in &self.maps {
+            if let Some(value) = row.get_mut(column) {
+                if let Some(n) = value.as_f64() {
+                    *value = Value::F64(f(n));
+                }
+            }
+        }
+    }
+}
+
+impl<'f> fmt::Debug for FlattenConfig<'f> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FlattenConfig")
+            .field("mapped_columns", &self.maps.iter().map(|&(ref column, _)| column).collect::<Vec<_>>())
+            .field("skip_null_keys", &self.skip_null_keys)
+            .field("projection", &self.projection)
+            .field("filters", &self.filters)
+            .field("bucket_width", &self.bucket_width)
+            .field("duplicates", &self.duplicates)
+            .finish()
+    }
+}
+
+impl Aggregations {
+    /// Returns an Iterator over the rows of the aggregations with the changes of `config`
+    /// applied to each of them.
+    ///
+    /// The rows are the same as those of the plain iterator, in the same order, but owned, as
+    /// mapped values don't exist in the tree. Projected columns that don't exist are left out
+    /// like any other column; use `try_rows_with` to have them reported.
+    pub fn rows_with<'a, 'f>(&'a self, config: FlattenConfig<'f>) -> MappedRows<'a, 'f> {
+        let key_columns = if config.skip_null_keys {
+            self.collect_columns_with(config.names(self, false)).0.into_iter().collect()
+        } else {
+            Vec::new()
+        };
+        let mut rows = AggregationIterator::with_names(self, config.names(self, true));
+        rows.filters = config.filters.clone();
+        let widths = if config.bucket_width { Some(BucketWidths::of(self)) } else { None };
+        MappedRows {
+            aggs: self,
+            rows: rows,
+            config: config,
+            key_columns: key_columns,
+            widths: widths
+        }
+    }
+
+    /// Returns the rows like `rows_with`, but fails with `AggError::UnknownColumns` if `config`
+    /// projects columns that none of the rows can have, listing those that they can.
+    ///
+    /// With `DuplicateNames::Error`, it also fails with `AggError::DuplicateName` if an
+    /// aggregation is nested in one of the same name; `rows_with`, which can't fail, overwrites
+    /// their columns then.
+    pub fn try_rows_with<'a, 'f>(&'a self, config: FlattenConfig<'f>) -> Result<MappedRows<'a, 'f>, AggError> {
+        if config.duplicates == DuplicateNames::Error {
+            if let Some(path) = self.first_duplicate_name() {
+                return Err(AggError::DuplicateName(path));
+            }
+        }
+        if let Some(ref projection) = config.projection {
+            let mut available: Vec<String> = self.collect_columns_with(config.names(self, false)).1.into_iter().collect();
+            if config.bucket_width {
+                available.extend(BucketWidths::of(self).columns());
+                available.sort();
+            }
+            let unknown: Vec<String> = projection.iter().filter(|c| !available.contains(c)).cloned().collect();
+            if !unknown.is_empty() {
+                return Err(AggError::UnknownColumns(unknown, available));
+            }
+        }
+        Ok(self.rows_with(config))
+    }
+}
+
+/// Iterator over transformed rows, see `Aggregations::rows_with`.
+#[derive(Debug)]
+pub struct MappedRows<'a, 'f> {
+    aggs: &'a Aggregations,
+    rows: AggregationIterator<'a>,
+    config: FlattenConfig<'f>,
+    key_columns: Vec<String>,
+    widths: Option<BucketWidths>
+}
+
+impl<'a, 'f> MappedRows<'a, 'f> {
+    /// Returns the names of the columns the rows can contain, sorted alphabetically: those of
+    /// the projection if there is one, and `Aggregations::columns` otherwise.
+    ///
+    /// Exporters can take this as the schema of the rows. Projected columns that don't exist
+    /// aren't listed, and the columns of disambiguated aggregations are listed by their new
+    /// names, see `FlattenConfig::duplicate_names`.
+    pub fn columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = self.aggs.collect_columns_with(self.config.names(self.aggs, false)).1.into_iter().collect();
+        if let Some(ref widths) = self.widths {
+            columns.extend(widths.columns());
+            columns.sort();
+        }
+        match self.config.projection {
+            Some(ref projection) => columns.into_iter().filter(|c| projection.contains(c)).collect(),
+            None => columns
+        }
+    }
+}
+
+impl<'a, 'f> Iterator for MappedRows<'a, 'f> {
+    type Item = OwnedRow;
+
+    fn next(&mut self) -> Option<OwnedRow> {
+        self.rows.next().map(|raw| {
+            let mut row = to_owned_row(&raw);
+            if let Some(ref widths) = self.widths {
+                for &(ref column, width) in raw.values().filter_map(|&key| widths.get(key)) {
+                    if self.config.projection.as_ref().map_or(true, |p| p.contains(column)) {
+                        row.insert(column.clone(), Value::F64(width));
+                    }
+                }
+            }
+            self.config.apply(&mut row, &self.key_columns);
+            row
+        })
+    }
+}
@@ -0,0 +1,143 @@
+//! Telling Elasticsearch and OpenSearch clusters apart, for the few places their responses differ.
+
+/// The search engine a response comes from.
+///
+/// OpenSearch forked from Elasticsearch 7.10 and their responses parse into the same types, but
+/// OpenSearch renamed some error types, see `OPENSEARCH_ERROR_TYPES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// Elasticsearch.
+    Elasticsearch,
+    /// OpenSearch.
+    OpenSearch
+}
+
+impl Flavor {
+    /// Returns the Elasticsearch name of the error type `ty`, which is `ty` itself unless this is
+    /// `OpenSearch` and `ty` is one of the types it renamed.
+    pub fn canonical_error_type<'a>(&self, ty: &'a str) -> &'a str {
+        match *self {
+            Flavor::Elasticsearch => ty,
+            Flavor::OpenSearch => {
+                OPENSEARCH_ERROR_TYPES.iter()
+                    .find(|&&(renamed, _)| renamed == ty)
+                    .map_or(ty, |&(_, original)| original)
+            }
+        }
+    }
+}
+
+impl Default for Flavor {
+    fn default() -> Flavor {
+        Flavor::Elasticsearch
+    }
+}
+
+/// Error types OpenSearch sends under another name than Elasticsearch, with the Elasticsearch
+/// name they are classified as, see `ApiError::is_retryable_for`.
+pub const OPENSEARCH_ERROR_TYPES: &'static [(&'static str, &'static str)] =
+    &[("opensearch_rejected_execution_exception", "es_rejected_execution_exception"),
+      ("rejected_execution_exception", "es_rejected_execution_exception"),
+      ("opensearch_parse_exception", "parse_exception"),
+      ("opensearch_timeout_exception", "timeout_exception"),
+      ("cluster_manager_not_discovered_exception", "master_not_discovered_exception"),
+      ("not_cluster_manager_exception", "not_master_exception")];
+
+/// Response of a ping, the `GET /` request, which tells which cluster and version answered.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PingResponse {
+    name: String,
+    cluster_name: String,
+    cluster_uuid: Option<String>,
+    version: VersionInfo,
+    tagline: Option<String>
+}
+
+impl PingResponse {
+    /// Returns the name of the node that answered.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the cluster.
+    pub fn cluster_name(&self) -> &str {
+        &self.cluster_name
+    }
+
+    /// Returns the UUID of the cluster, which versions before 5.0 don't send.
+    pub fn cluster_uuid(&self) -> Option<&str> {
+        self.cluster_uuid.as_ref().map(|u| &**u)
+    }
+
+    /// Returns the version of the node.
+    pub fn version(&self) -> &VersionInfo {
+        &self.version
+    }
+
+    /// Returns the tagline, e.g. `You Know, for Search`.
+    pub fn tagline(&self) -> Option<&str> {
+        self.tagline.as_ref().map(|t| &**t)
+    }
+
+    /// Returns whether the node is Elasticsearch or OpenSearch.
+    ///
+    /// OpenSearch sets the `distribution` of its version to `opensearch`, which Elasticsearch
+    /// never sends.
+    pub fn flavor(&self) -> Flavor {
+        match self.version.distribution() {
+            Some("opensearch") => Flavor::OpenSearch,
+            _ => Flavor::Elasticsearch
+        }
+    }
+}
+
+/// The `version` of a `PingResponse`.
+///
+/// Elasticsearch and OpenSearch each send some fields the other doesn't; those are `None` for the
+/// other.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VersionInfo {
+    number: String,
+    distribution: Option<String>,
+    build_flavor: Option<String>,
+    build_hash: Option<String>,
+    lucene_version: Option<String>,
+    minimum_wire_compatibility_version: Option<String>
+}
+
+impl VersionInfo {
+    /// Returns the version number, e.g. `8.11.1` or, for OpenSearch, `2.11.0`.
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    /// Returns the distribution, `opensearch` for OpenSearch and `None` for Elasticsearch.
+    pub fn distribution(&self) -> Option<&str> {
+        self.distribution.as_ref().map(|d| &**d)
+    }
+
+    /// Returns the build flavor of Elasticsearch, e.g. `default` or `oss`.
+    pub fn build_flavor(&self) -> Option<&str> {
+        self.build_flavor.as_ref().map(|f| &**f)
+    }
+
+    /// Returns the commit the node was built from.
+    pub fn build_hash(&self) -> Option<&str> {
+        self.build_hash.as_ref().map(|h| &**h)
+    }
+
+    /// Returns the version of Lucene the node runs.
+    pub fn lucene_version(&self) -> Option<&str> {
+        self.lucene_version.as_ref().map(|v| &**v)
+    }
+
+    /// Returns the oldest version the node can talk to over the transport protocol.
+    pub fn minimum_wire_compatibility_version(&self) -> Option<&str> {
+        self.minimum_wire_compatibility_version.as_ref().map(|v| &**v)
+    }
+
+    /// Returns the major version, the first part of the version number.
+    pub fn major(&self) -> Option<u64> {
+        self.number.split('.').next().and_then(|major| major.parse().ok())
+    }
+}
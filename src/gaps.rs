@@ -0,0 +1,91 @@
+//! Filling in the intervals a `date_histogram` leaves out, for continuous time series.
+
+use serde_json::Value;
+use std::time::Duration;
+
+use {AggError, Aggregations, OwnedRow, to_owned_row};
+
+/// What the rows of filled in intervals hold, see `Aggregations::fill_gaps`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillStrategy {
+    /// A `doc_count` of `0` and no metrics.
+    Zero,
+    /// A `null` for the `doc_count` and every metric of the bucket before.
+    Null,
+    /// The rows of the bucket before, repeated with the key of the interval.
+    PreviousValue
+}
+
+impl Aggregations {
+    /// Returns the rows of the outermost `date_histogram` or `histogram` aggregation `agg`, see
+    /// `rows_for`, with rows inserted for the intervals Elasticsearch left out because they had
+    /// no documents, as it does unless the request set `min_doc_count: 0`.
+    ///
+    /// Keys must be whole numbers like the epoch milliseconds of a `date_histogram`, and the
+    /// buckets sorted by key, which they are by default. Without an `interval`, the smallest
+    /// distance between two keys is used. Only fixed intervals are supported: calendar intervals
+    /// such as a month don't have the same length every time, and fail with
+    /// `AggError::IrregularInterval`. The rows inserted have no `key_as_string`.
+    pub fn fill_gaps(&self, agg: &str, interval: Option<Duration>, fill: FillStrategy) -> Result<Vec<OwnedRow>, AggError> {
+        // rows below the same bucket come one after the other
+        let mut buckets: Vec<(i64, Vec<OwnedRow>)> = Vec::new();
+        for row in self.rows_for(agg)? {
+            let key = match row.get(agg).and_then(|key| key.as_i64()) {
+                Some(key) => key,
+                None => return Err(AggError::NotEpochMillis(agg.to_owned()))
+            };
+            let row = to_owned_row(&row);
+            match buckets.last_mut() {
+                Some(&mut (last, ref mut rows)) if last == key => rows.push(row),
+                _ => buckets.push((key, vec![row]))
+            }
+        }
+
+        let interval = match interval {
+            Some(interval) => interval.as_secs() as i64 * 1000 + (interval.subsec_nanos() / 1_000_000) as i64,
+            None => buckets.windows(2).map(|pair| pair[1].0 - pair[0].0).min().unwrap_or(1)
+        };
+        let mut filled = Vec::new();
+        for i in 0..buckets.len() {
+            if i > 0 {
+                let (previous, ref previous_rows) = buckets[i - 1];
+                let gap = buckets[i].0 - previous;
+                if interval <= 0 || gap <= 0 || gap % interval != 0 {
+                    return Err(AggError::IrregularInterval(agg.to_owned(), gap, interval));
+                }
+                let mut key = previous + interval;
+                while key < buckets[i].0 {
+                    filled.extend(fill_rows(agg, key, previous_rows, fill));
+                    key += interval;
+                }
+            }
+            filled.extend(buckets[i].1.iter().cloned());
+        }
+        Ok(filled)
+    }
+}
+
+/// Returns the rows of the interval at `key` that had no bucket, following the bucket whose rows
+/// are `previous`.
+fn fill_rows(agg: &str, key: i64, previous: &[OwnedRow], fill: FillStrategy) -> Vec<OwnedRow> {
+    let key_as_string = format!("{}_key_as_string", agg);
+    let doc_count = format!("{}_doc_count", agg);
+    let with_key = |mut row: OwnedRow| {
+        row.remove(&key_as_string);
+        row.insert(agg.to_owned(), if key < 0 { Value::I64(key) } else { Value::U64(key as u64) });
+        row
+    };
+
+    match fill {
+        FillStrategy::Zero => {
+            let mut row = OwnedRow::new();
+            row.insert(doc_count, Value::U64(0));
+            vec![with_key(row)]
+        }
+        FillStrategy::Null => {
+            let row = previous[0].keys().map(|column| (column.clone(), Value::Null)).collect();
+            vec![with_key(row)]
+        }
+        FillStrategy::PreviousValue => previous.iter().cloned().map(with_key).collect()
+    }
+}
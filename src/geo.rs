@@ -0,0 +1,80 @@
+//! Geo points, as found in documents and geo aggregations.
+
+use serde_json::Value;
+
+use Aggregations;
+
+/// A latitude/longitude pair.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    /// Latitude in degrees.
+    pub lat: f64,
+    /// Longitude in degrees.
+    pub lon: f64
+}
+
+impl GeoPoint {
+    /// Reads a geo point in any of the formats Elasticsearch accepts for one:
+    ///
+    /// - an object, `{"lat": 41.12, "lon": -71.34}`
+    /// - an array in GeoJSON order, longitude first: `[-71.34, 41.12]`
+    /// - a string, latitude first: `"41.12,-71.34"`
+    ///
+    /// Returns `None` for anything else, including geohashes.
+    pub fn from_value(value: &Value) -> Option<GeoPoint> {
+        match *value {
+            Value::Object(ref o) => {
+                match (o.get("lat").and_then(Value::as_f64), o.get("lon").and_then(Value::as_f64)) {
+                    (Some(lat), Some(lon)) => Some(GeoPoint { lat: lat, lon: lon }),
+                    _ => None
+                }
+            },
+            Value::Array(ref a) if a.len() == 2 => {
+                match (a[1].as_f64(), a[0].as_f64()) {
+                    (Some(lat), Some(lon)) => Some(GeoPoint { lat: lat, lon: lon }),
+                    _ => None
+                }
+            },
+            Value::String(ref s) => {
+                let mut parts = s.splitn(2, ',').map(|p| p.trim().parse::<f64>());
+                match (parts.next(), parts.next()) {
+                    (Some(Ok(lat)), Some(Ok(lon))) => Some(GeoPoint { lat: lat, lon: lon }),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+impl Aggregations {
+    fn root_agg(&self, agg: &str) -> Option<&Value> {
+        self.0.as_object().and_then(|o| o.get(agg))
+    }
+
+    /// Returns the centroid of the root `geo_centroid` aggregation `agg`.
+    ///
+    /// `geo_centroid`s nested in buckets are flattened into a `{agg}_location` column instead,
+    /// see `RowAccess::get_geopoint`.
+    pub fn geo_centroid(&self, agg: &str) -> Option<GeoPoint> {
+        self.root_agg(agg)
+            .and_then(|a| a.find("location"))
+            .and_then(GeoPoint::from_value)
+    }
+
+    /// Returns the top left and bottom right corners of the root `geo_bounds` aggregation `agg`.
+    ///
+    /// `geo_bounds` nested in buckets are flattened into `{agg}_top_left` and
+    /// `{agg}_bottom_right` columns instead.
+    pub fn geo_bounds(&self, agg: &str) -> Option<(GeoPoint, GeoPoint)> {
+        let bounds = match self.root_agg(agg).and_then(|a| a.find("bounds")) {
+            Some(bounds) => bounds,
+            None => return None
+        };
+        match (bounds.find("top_left").and_then(GeoPoint::from_value),
+               bounds.find("bottom_right").and_then(GeoPoint::from_value)) {
+            (Some(top_left), Some(bottom_right)) => Some((top_left, bottom_right)),
+            _ => None
+        }
+    }
+}
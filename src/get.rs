@@ -0,0 +1,58 @@
+//! Response of the get document API.
+
+/// Response of a get document request, generic over the document source.
+///
+/// A missing document isn't an error: `found` is `false` and there is no source.
+#[derive(Deserialize, Debug)]
+pub struct GetResponse<T> {
+    _index: String,
+    _type: Option<String>,
+    _id: String,
+    _version: Option<u64>,
+    _seq_no: Option<u64>,
+    _primary_term: Option<u64>,
+    found: bool,
+    _source: Option<T>
+}
+
+impl<T> GetResponse<T> {
+    /// Returns the index the document was looked up in.
+    pub fn index(&self) -> &str {
+        &self._index
+    }
+
+    /// Returns the id of the document.
+    pub fn id(&self) -> &str {
+        &self._id
+    }
+
+    /// Returns the version of the document, if it was found.
+    pub fn version(&self) -> Option<u64> {
+        self._version
+    }
+
+    /// Returns the sequence number of the last change to the document.
+    pub fn seq_no(&self) -> Option<u64> {
+        self._seq_no
+    }
+
+    /// Returns the primary term of the last change to the document.
+    pub fn primary_term(&self) -> Option<u64> {
+        self._primary_term
+    }
+
+    /// Returns `true` if the document exists.
+    pub fn found(&self) -> bool {
+        self.found
+    }
+
+    /// Returns the source of the document, if it was found and not excluded.
+    pub fn source(&self) -> Option<&T> {
+        self._source.as_ref()
+    }
+
+    /// Returns the source of the document, consuming the response.
+    pub fn into_source(self) -> Option<T> {
+        self._source
+    }
+}
@@ -0,0 +1,252 @@
+//! Parsing of responses from their HTTP status code and body.
+//!
+//! Bodies are JSON, or CBOR with the `cbor` feature. SMILE bodies are recognized by their
+//! `Content-Type` but not decoded, and are reported as `ResponseError::UnsupportedFormat`.
+
+use serde::de::{Deserialize, Error as DeError};
+use serde_json::{self, Value};
+use std::ops::Deref;
+
+#[cfg(feature = "cbor")]
+use serde_cbor;
+
+use {AcknowledgedResponse, AliasesResponse, AllocationExplainResponse, BulkResponse, ByQueryResponse,
+     ClusterHealthResponse, ClusterStateResponse, Error, GetResponse, GetScriptResponse, IlmExplainResponse,
+     IndexStatsResponse, PendingTasksResponse, PingResponse, RankEvalResponse, RenderTemplateResponse,
+     ResponseError, ResponseOf, ResponseParser, SimulatePipelineResponse, TaskResponse, TermsEnumResponse,
+     ValidateQueryResponse, Warning};
+use {embedded_error, parse_typed};
+
+/// The format of a response body, as announced by its `Content-Type`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyFormat {
+    /// JSON, for `application/json` and any content type that isn't one of the others.
+    Json,
+    /// CBOR, for `application/cbor` and `application/vnd.elasticsearch+cbor`, which is only
+    /// decoded with the `cbor` feature.
+    #[cfg_attr(feature = "cbor", doc = "")]
+    #[cfg_attr(feature = "cbor", doc = " Search responses can also be parsed from CBOR with")]
+    #[cfg_attr(feature = "cbor", doc = " `ResponseOf::from_cbor_slice`.")]
+    Cbor,
+    /// SMILE, for `application/smile` and `application/vnd.elasticsearch+smile`, which is
+    /// detected but not decoded: parsing it is a `ResponseError::UnsupportedFormat`.
+    Smile
+}
+
+impl BodyFormat {
+    /// Picks the format for a `Content-Type` value; anything that isn't CBOR or SMILE is
+    /// treated as JSON.
+    pub fn from_content_type(content_type: &str) -> BodyFormat {
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+        match &*mime {
+            "application/cbor" | "application/vnd.elasticsearch+cbor" => BodyFormat::Cbor,
+            "application/smile" | "application/vnd.elasticsearch+smile" => BodyFormat::Smile,
+            _ => BodyFormat::Json
+        }
+    }
+}
+
+/// Response types that can be built from an HTTP status code and body.
+pub trait FromHttpResponse: Sized {
+    /// Builds the response, or the error the status code and body describe.
+    fn from_http_response(status: u16, format: BodyFormat, body: &[u8]) -> Result<Self, ResponseError>;
+}
+
+/// Parses an HTTP response into `T`.
+///
+/// For types with a JSON body, a `2xx` status parses the body; any other status is reported as
+/// `ResponseError::Api` when the body carries an `error` object, and otherwise still parses
+/// the body if it has the shape of `T`, as with `{"found": false}` on a `404`. Body-less
+/// endpoints are handled by `ExistsResponse`.
+///
+/// The body is expected to be JSON; use `parse_http_response_with_headers` to follow the
+/// `Content-Type` of the response.
+pub fn parse_http_response<T: FromHttpResponse>(status: u16, body: &[u8]) -> Result<T, ResponseError> {
+    T::from_http_response(status, BodyFormat::Json, body)
+}
+
+/// Parses an HTTP response into `T` like `parse_http_response`, in the format its
+/// `Content-Type` header announces, keeping the warnings sent in `Warning` headers.
+///
+/// Header names are matched case-insensitively; warnings that can't be parsed are skipped.
+pub fn parse_http_response_with_headers<T, I, K, V>(status: u16,
+                                                    headers: I,
+                                                    body: &[u8])
+                                                    -> Result<HttpResponse<T>, ResponseError>
+    where T: FromHttpResponse,
+          I: IntoIterator<Item = (K, V)>,
+          K: AsRef<str>,
+          V: AsRef<str>
+{
+    let mut format = BodyFormat::Json;
+    let mut warnings = Vec::new();
+    for (name, value) in headers {
+        let name = name.as_ref();
+        if name.eq_ignore_ascii_case("warning") {
+            warnings.extend(Warning::parse_header(value.as_ref()));
+        } else if name.eq_ignore_ascii_case("content-type") {
+            format = BodyFormat::from_content_type(value.as_ref());
+        }
+    }
+
+    let response = T::from_http_response(status, format, body)?;
+
+    Ok(HttpResponse {
+        response: response,
+        warnings: warnings
+    })
+}
+
+/// A parsed response along with what was learnt from its headers.
+///
+/// Dereferences to the response itself.
+#[derive(Debug)]
+pub struct HttpResponse<T> {
+    response: T,
+    warnings: Vec<Warning>
+}
+
+impl<T> HttpResponse<T> {
+    /// Returns the warnings Elasticsearch sent, in header order.
+    pub fn deprecation_warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns the parsed response, dropping the warnings.
+    pub fn into_inner(self) -> T {
+        self.response
+    }
+}
+
+impl<T> Deref for HttpResponse<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.response
+    }
+}
+
+fn decode<T: Deserialize>(status: u16, format: BodyFormat, body: &[u8]) -> Result<T, ResponseError> {
+    match format {
+        BodyFormat::Json => {
+            parse_typed(body, &ResponseParser::new()).map_err(|err| match err {
+                Error::Parse(err, context) => ResponseError::Parse(err, context),
+                Error::Api(body_status, err) => ResponseError::Api(body_status.unwrap_or(status), err),
+                Error::Json(err) => ResponseError::Json(err),
+                other => ResponseError::Json(serde_json::Error::custom(other.to_string()))
+            })
+        }
+        BodyFormat::Cbor => decode_cbor(body),
+        BodyFormat::Smile => Err(ResponseError::UnsupportedFormat(format))
+    }
+}
+
+/// Decodes a CBOR body into a `Value` first, so that serde_json fills in missing optional
+/// fields the same way it does for JSON bodies.
+#[cfg(feature = "cbor")]
+pub fn decode_cbor<T: Deserialize>(body: &[u8]) -> Result<T, ResponseError> {
+    let value: Value = serde_cbor::from_slice(body).map_err(|e| ResponseError::Cbor(e.to_string()))?;
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(not(feature = "cbor"))]
+fn decode_cbor<T: Deserialize>(_: &[u8]) -> Result<T, ResponseError> {
+    Err(ResponseError::UnsupportedFormat(BodyFormat::Cbor))
+}
+
+fn parse_body<T: Deserialize>(status: u16, format: BodyFormat, body: &[u8]) -> Result<T, ResponseError> {
+    if status >= 200 && status < 300 {
+        return decode(status, format, body);
+    }
+
+    let value: Value = match decode(status, format, body) {
+        Ok(value) => value,
+        Err(ResponseError::UnsupportedFormat(format)) => return Err(ResponseError::UnsupportedFormat(format)),
+        Err(_) => return Err(ResponseError::Status(status))
+    };
+    if let Some((body_status, err)) = embedded_error(&value) {
+        return Err(ResponseError::Api(body_status.unwrap_or(status), err));
+    }
+    serde_json::from_value(value).map_err(|_| ResponseError::Status(status))
+}
+
+macro_rules! body_responses {
+    ($($ty:ty),*) => {
+        $(
+            impl FromHttpResponse for $ty {
+                fn from_http_response(status: u16, format: BodyFormat, body: &[u8]) -> Result<Self, ResponseError> {
+                    parse_body(status, format, body)
+                }
+            }
+        )*
+    }
+}
+
+body_responses!(Value,
+                AcknowledgedResponse,
+                AliasesResponse,
+                AllocationExplainResponse,
+                BulkResponse,
+                ByQueryResponse,
+                ClusterHealthResponse,
+                ClusterStateResponse,
+                GetScriptResponse,
+                IlmExplainResponse,
+                IndexStatsResponse,
+                PendingTasksResponse,
+                PingResponse,
+                RankEvalResponse,
+                RenderTemplateResponse,
+                TaskResponse,
+                TermsEnumResponse,
+                ValidateQueryResponse);
+
+impl<T: Deserialize> FromHttpResponse for ResponseOf<T> {
+    fn from_http_response(status: u16, format: BodyFormat, body: &[u8]) -> Result<Self, ResponseError> {
+        parse_body(status, format, body)
+    }
+}
+
+impl<T: Deserialize> FromHttpResponse for GetResponse<T> {
+    fn from_http_response(status: u16, format: BodyFormat, body: &[u8]) -> Result<Self, ResponseError> {
+        parse_body(status, format, body)
+    }
+}
+
+impl<T: Deserialize> FromHttpResponse for SimulatePipelineResponse<T> {
+    fn from_http_response(status: u16, format: BodyFormat, body: &[u8]) -> Result<Self, ResponseError> {
+        parse_body(status, format, body)
+    }
+}
+
+/// Response of body-less `HEAD` requests, such as checking whether an index or document exists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExistsResponse(bool);
+
+impl ExistsResponse {
+    /// Builds the response from the status code: `200` exists, `404` doesn't, anything else is
+    /// an error.
+    pub fn from_status(status: u16) -> Result<ExistsResponse, ResponseError> {
+        match status {
+            200 => Ok(ExistsResponse(true)),
+            404 => Ok(ExistsResponse(false)),
+            _ => Err(ResponseError::Status(status))
+        }
+    }
+
+    /// Returns `true` if the index or document exists.
+    pub fn exists(&self) -> bool {
+        self.0
+    }
+}
+
+impl FromHttpResponse for ExistsResponse {
+    /// Answers from the status code alone; a body other than whitespace is an error.
+    fn from_http_response(status: u16, _: BodyFormat, body: &[u8]) -> Result<Self, ResponseError> {
+        let exists = ExistsResponse::from_status(status)?;
+        if body.iter().any(|b| !b.is_ascii_whitespace()) {
+            return Err(ResponseError::UnexpectedBody(status));
+        }
+        Ok(exists)
+    }
+}
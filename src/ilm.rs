@@ -0,0 +1,163 @@
+//! Response of the `_ilm/explain` API.
+
+use serde::de::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+
+use ApiError;
+
+/// Response of an index lifecycle explain request.
+#[derive(Deserialize, Debug)]
+pub struct IlmExplainResponse {
+    indices: BTreeMap<String, IlmIndex>
+}
+
+impl IlmExplainResponse {
+    /// Returns the lifecycle state of a single index.
+    pub fn index(&self, name: &str) -> Option<&IlmIndex> {
+        self.indices.get(name)
+    }
+
+    /// Iterates over the lifecycle state of all indices, ordered by index name.
+    pub fn indices(&self) -> btree_map::Iter<String, IlmIndex> {
+        self.indices.iter()
+    }
+
+    /// Returns the names of the indices whose current step failed.
+    pub fn failed_indices(&self) -> Vec<&str> {
+        self.indices.iter().filter(|&(_, i)| i.is_failed()).map(|(name, _)| &**name).collect()
+    }
+}
+
+/// The lifecycle state of an index.
+///
+/// Unmanaged indices only have `index` and `managed` set.
+#[derive(Deserialize, Debug)]
+pub struct IlmIndex {
+    index: String,
+    managed: bool,
+    policy: Option<String>,
+    phase: Option<String>,
+    action: Option<String>,
+    step: Option<String>,
+    failed_step: Option<String>,
+    step_info: Option<StepInfo>,
+    is_auto_retryable_error: Option<bool>,
+    failed_step_retry_count: Option<u64>,
+    age: Option<String>,
+    lifecycle_date_millis: Option<u64>,
+    phase_time_millis: Option<u64>,
+    action_time_millis: Option<u64>,
+    step_time_millis: Option<u64>
+}
+
+impl IlmIndex {
+    /// Returns the name of the index.
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+
+    /// Returns `true` if a lifecycle policy manages the index.
+    pub fn managed(&self) -> bool {
+        self.managed
+    }
+
+    /// Returns the name of the policy managing the index.
+    pub fn policy(&self) -> Option<&str> {
+        self.policy.as_ref().map(|p| &**p)
+    }
+
+    /// Returns the current phase, e.g. `hot`.
+    pub fn phase(&self) -> Option<&str> {
+        self.phase.as_ref().map(|p| &**p)
+    }
+
+    /// Returns the current action, e.g. `rollover`.
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_ref().map(|a| &**a)
+    }
+
+    /// Returns the current step, which is `ERROR` when a step failed.
+    pub fn step(&self) -> Option<&str> {
+        self.step.as_ref().map(|s| &**s)
+    }
+
+    /// Returns `true` if the index is stuck in the `ERROR` step.
+    pub fn is_failed(&self) -> bool {
+        self.step() == Some("ERROR")
+    }
+
+    /// Returns the step that failed, when the index is in the `ERROR` step.
+    pub fn failed_step(&self) -> Option<&str> {
+        self.failed_step.as_ref().map(|s| &**s)
+    }
+
+    /// Returns the error that made the step fail.
+    pub fn error(&self) -> Option<&ApiError> {
+        if !self.is_failed() {
+            return None;
+        }
+        self.step_info.as_ref().and_then(|i| i.error.as_ref())
+    }
+
+    /// Returns the raw `step_info`, which also carries progress messages for steps that wait.
+    pub fn step_info(&self) -> Option<&Value> {
+        self.step_info.as_ref().map(|i| &i.value)
+    }
+
+    /// Returns whether ILM retries the failed step by itself.
+    pub fn is_auto_retryable_error(&self) -> Option<bool> {
+        self.is_auto_retryable_error
+    }
+
+    /// Returns how many times the failed step was retried.
+    pub fn failed_step_retry_count(&self) -> Option<u64> {
+        self.failed_step_retry_count
+    }
+
+    /// Returns the age of the index as used for phase transitions, e.g. `7.5d`.
+    pub fn age(&self) -> Option<&str> {
+        self.age.as_ref().map(|a| &**a)
+    }
+
+    /// Returns when the lifecycle of the index started, in epoch millis.
+    pub fn lifecycle_date_millis(&self) -> Option<u64> {
+        self.lifecycle_date_millis
+    }
+
+    /// Returns when the index entered the current phase, in epoch millis.
+    pub fn phase_time_millis(&self) -> Option<u64> {
+        self.phase_time_millis
+    }
+
+    /// Returns when the index entered the current action, in epoch millis.
+    pub fn action_time_millis(&self) -> Option<u64> {
+        self.action_time_millis
+    }
+
+    /// Returns when the index entered the current step, in epoch millis.
+    pub fn step_time_millis(&self) -> Option<u64> {
+        self.step_time_millis
+    }
+}
+
+#[derive(Debug)]
+struct StepInfo {
+    value: Value,
+    error: Option<ApiError>
+}
+
+impl Deserialize for StepInfo {
+    fn deserialize<D>(deserializer: &mut D) -> Result<StepInfo, D::Error>
+        where D: Deserializer
+    {
+        let value = Value::deserialize(deserializer)?;
+        let error = ApiError::from_value(&value);
+
+        Ok(StepInfo {
+            value: value,
+            error: error
+        })
+    }
+}
@@ -0,0 +1,252 @@
+//! Response of the `_stats` API.
+
+use serde::de::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+
+use {Object, Shards, typed_section};
+
+/// Response of an index stats request.
+#[derive(Deserialize, Debug)]
+pub struct IndexStatsResponse {
+    _shards: Shards,
+    _all: IndexStats,
+    #[serde(default)]
+    indices: BTreeMap<String, IndexStats>
+}
+
+impl IndexStatsResponse {
+    /// Returns the shard summary of the request.
+    pub fn shards(&self) -> &Shards {
+        &self._shards
+    }
+
+    /// Returns the stats rolled up over all requested indices.
+    pub fn all(&self) -> &IndexStats {
+        &self._all
+    }
+
+    /// Returns the stats of a single index.
+    pub fn index(&self, name: &str) -> Option<&IndexStats> {
+        self.indices.get(name)
+    }
+
+    /// Iterates over the stats of all indices, ordered by index name.
+    pub fn indices(&self) -> btree_map::Iter<String, IndexStats> {
+        self.indices.iter()
+    }
+}
+
+/// The stats of an index, or of all indices, over primaries and over all copies.
+#[derive(Deserialize, Debug)]
+pub struct IndexStats {
+    uuid: Option<String>,
+    #[serde(default)]
+    primaries: StatsSections,
+    #[serde(default)]
+    total: StatsSections
+}
+
+impl IndexStats {
+    /// Returns the uuid of the index; not set for the `_all` rollup.
+    pub fn uuid(&self) -> Option<&str> {
+        self.uuid.as_ref().map(|u| &**u)
+    }
+
+    /// Returns the stats over primary shards only.
+    pub fn primaries(&self) -> &StatsSections {
+        &self.primaries
+    }
+
+    /// Returns the stats over primaries and replicas.
+    pub fn total(&self) -> &StatsSections {
+        &self.total
+    }
+
+    /// Returns the `docs` section of the total stats.
+    pub fn docs(&self) -> Option<&DocsStats> {
+        self.total.docs()
+    }
+
+    /// Returns the `store` section of the total stats.
+    pub fn store(&self) -> Option<&StoreStats> {
+        self.total.store()
+    }
+
+    /// Returns the `indexing` section of the total stats.
+    pub fn indexing(&self) -> Option<&IndexingStats> {
+        self.total.indexing()
+    }
+
+    /// Returns the `search` section of the total stats.
+    pub fn search(&self) -> Option<&SearchStats> {
+        self.total.search()
+    }
+}
+
+/// The sections of a stats object, some of which are typed.
+///
+/// Sections are only present for the metrics that were requested; any section, typed or not,
+/// is available raw through `section`.
+#[derive(Debug, Default)]
+pub struct StatsSections {
+    docs: Option<DocsStats>,
+    store: Option<StoreStats>,
+    indexing: Option<IndexingStats>,
+    search: Option<SearchStats>,
+    sections: Object
+}
+
+impl StatsSections {
+    /// Returns the document counts.
+    pub fn docs(&self) -> Option<&DocsStats> {
+        self.docs.as_ref()
+    }
+
+    /// Returns the store size.
+    pub fn store(&self) -> Option<&StoreStats> {
+        self.store.as_ref()
+    }
+
+    /// Returns the indexing counters.
+    pub fn indexing(&self) -> Option<&IndexingStats> {
+        self.indexing.as_ref()
+    }
+
+    /// Returns the search counters.
+    pub fn search(&self) -> Option<&SearchStats> {
+        self.search.as_ref()
+    }
+
+    /// Returns any section by name, e.g. `segments` or `merges`.
+    pub fn section(&self, name: &str) -> Option<&Value> {
+        self.sections.get(name)
+    }
+}
+
+impl Deserialize for StatsSections {
+    fn deserialize<D>(deserializer: &mut D) -> Result<StatsSections, D::Error>
+        where D: Deserializer
+    {
+        let sections = Object::deserialize(deserializer)?;
+
+        Ok(StatsSections {
+            docs: typed_section(&sections, "docs")?,
+            store: typed_section(&sections, "store")?,
+            indexing: typed_section(&sections, "indexing")?,
+            search: typed_section(&sections, "search")?,
+            sections: sections
+        })
+    }
+}
+
+/// The `docs` section of index stats.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DocsStats {
+    count: u64,
+    #[serde(default)]
+    deleted: u64
+}
+
+impl DocsStats {
+    /// Returns the number of live documents, nested documents included.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the number of deleted documents not yet merged away.
+    pub fn deleted(&self) -> u64 {
+        self.deleted
+    }
+}
+
+/// The `store` section of index stats.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct StoreStats {
+    size_in_bytes: u64
+}
+
+impl StoreStats {
+    /// Returns the size of the index on disk.
+    pub fn size_in_bytes(&self) -> u64 {
+        self.size_in_bytes
+    }
+}
+
+/// The `indexing` section of index stats.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct IndexingStats {
+    #[serde(default)]
+    index_total: u64,
+    #[serde(default)]
+    index_time_in_millis: u64,
+    #[serde(default)]
+    index_failed: u64,
+    #[serde(default)]
+    delete_total: u64
+}
+
+impl IndexingStats {
+    /// Returns the number of indexing operations.
+    pub fn index_total(&self) -> u64 {
+        self.index_total
+    }
+
+    /// Returns the time spent indexing.
+    pub fn index_time_in_millis(&self) -> u64 {
+        self.index_time_in_millis
+    }
+
+    /// Returns the number of failed indexing operations.
+    pub fn index_failed(&self) -> u64 {
+        self.index_failed
+    }
+
+    /// Returns the number of delete operations.
+    pub fn delete_total(&self) -> u64 {
+        self.delete_total
+    }
+}
+
+/// The `search` section of index stats.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SearchStats {
+    #[serde(default)]
+    query_total: u64,
+    #[serde(default)]
+    query_time_in_millis: u64,
+    #[serde(default)]
+    fetch_total: u64,
+    #[serde(default)]
+    fetch_time_in_millis: u64,
+    #[serde(default)]
+    scroll_total: u64
+}
+
+impl SearchStats {
+    /// Returns the number of query phases run.
+    pub fn query_total(&self) -> u64 {
+        self.query_total
+    }
+
+    /// Returns the time spent in query phases.
+    pub fn query_time_in_millis(&self) -> u64 {
+        self.query_time_in_millis
+    }
+
+    /// Returns the number of fetch phases run.
+    pub fn fetch_total(&self) -> u64 {
+        self.fetch_total
+    }
+
+    /// Returns the time spent in fetch phases.
+    pub fn fetch_time_in_millis(&self) -> u64 {
+        self.fetch_time_in_millis
+    }
+
+    /// Returns the number of scrolls.
+    pub fn scroll_total(&self) -> u64 {
+        self.scroll_total
+    }
+}
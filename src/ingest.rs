@@ -0,0 +1,160 @@
+//! Response of the `_ingest/pipeline/_simulate` API.
+
+use std::slice::Iter;
+
+use ApiError;
+
+/// Response of a pipeline simulation, generic over the document source.
+///
+/// Each simulated document carries either its final `doc` or, with `verbose`, the result of
+/// each processor.
+#[derive(Deserialize, Debug)]
+pub struct SimulatePipelineResponse<T> {
+    docs: Vec<SimulatedDoc<T>>
+}
+
+impl<T> SimulatePipelineResponse<T> {
+    /// Returns the simulated documents, in the order they were sent.
+    pub fn docs(&self) -> &[SimulatedDoc<T>] {
+        &self.docs
+    }
+
+    /// Iterates over the simulated documents.
+    pub fn iter(&self) -> Iter<SimulatedDoc<T>> {
+        self.docs.iter()
+    }
+
+    /// Returns `true` if any document, or any processor of any document, failed.
+    pub fn has_failures(&self) -> bool {
+        self.docs.iter().any(SimulatedDoc::is_failed)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SimulatePipelineResponse<T> {
+    type Item = &'a SimulatedDoc<T>;
+    type IntoIter = Iter<'a, SimulatedDoc<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.docs.iter()
+    }
+}
+
+/// The outcome of simulating one document.
+#[derive(Deserialize, Debug)]
+pub struct SimulatedDoc<T> {
+    doc: Option<IngestDoc<T>>,
+    error: Option<ApiError>,
+    processor_results: Option<Vec<ProcessorResult<T>>>
+}
+
+impl<T> SimulatedDoc<T> {
+    /// Returns the document as it left the pipeline.
+    ///
+    /// In verbose mode this is the document produced by the last processor that produced one.
+    pub fn doc(&self) -> Option<&IngestDoc<T>> {
+        self.doc.as_ref().or_else(|| self.processor_results().iter().rev().filter_map(|p| p.doc.as_ref()).next())
+    }
+
+    /// Returns the error that failed the whole document.
+    pub fn error(&self) -> Option<&ApiError> {
+        self.error.as_ref()
+    }
+
+    /// Returns the result of each processor; empty unless the simulation was verbose.
+    pub fn processor_results(&self) -> &[ProcessorResult<T>] {
+        self.processor_results.as_ref().map(|p| &**p).unwrap_or(&[])
+    }
+
+    /// Returns the processors that failed on this document.
+    pub fn failed_processors(&self) -> Vec<&ProcessorResult<T>> {
+        self.processor_results().iter().filter(|p| p.is_failed()).collect()
+    }
+
+    /// Returns `true` if the document or any of its processors failed.
+    pub fn is_failed(&self) -> bool {
+        self.error.is_some() || self.processor_results().iter().any(ProcessorResult::is_failed)
+    }
+}
+
+/// The result of one processor in a verbose simulation.
+#[derive(Deserialize, Debug)]
+pub struct ProcessorResult<T> {
+    processor_type: Option<String>,
+    tag: Option<String>,
+    status: Option<String>,
+    doc: Option<IngestDoc<T>>,
+    error: Option<ApiError>
+}
+
+impl<T> ProcessorResult<T> {
+    /// Returns the type of the processor, e.g. `set` or `grok`.
+    pub fn processor_type(&self) -> Option<&str> {
+        self.processor_type.as_ref().map(|t| &**t)
+    }
+
+    /// Returns the tag of the processor.
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_ref().map(|t| &**t)
+    }
+
+    /// Returns the status of the processor, e.g. `success`, `skipped` or `error`.
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_ref().map(|s| &**s)
+    }
+
+    /// Returns the document after the processor ran.
+    pub fn doc(&self) -> Option<&IngestDoc<T>> {
+        self.doc.as_ref()
+    }
+
+    /// Returns the error the processor raised.
+    ///
+    /// This is also set for processors with `ignore_failure`, whose status is `error_ignored`.
+    pub fn error(&self) -> Option<&ApiError> {
+        self.error.as_ref()
+    }
+
+    /// Returns `true` if the processor failed the document.
+    pub fn is_failed(&self) -> bool {
+        match self.status() {
+            Some(status) => status == "error",
+            None => self.error.is_some()
+        }
+    }
+}
+
+/// A document as seen by an ingest pipeline.
+#[derive(Deserialize, Debug)]
+pub struct IngestDoc<T> {
+    _index: Option<String>,
+    _id: Option<String>,
+    _source: T,
+    _ingest: Option<IngestMetadata>
+}
+
+#[derive(Deserialize, Debug)]
+struct IngestMetadata {
+    timestamp: Option<String>
+}
+
+impl<T> IngestDoc<T> {
+    /// Returns the index the document would be written to.
+    pub fn index(&self) -> Option<&str> {
+        self._index.as_ref().map(|i| &**i)
+    }
+
+    /// Returns the id of the document.
+    pub fn id(&self) -> Option<&str> {
+        self._id.as_ref().map(|i| &**i)
+    }
+
+    /// Returns the transformed source.
+    pub fn source(&self) -> &T {
+        &self._source
+    }
+
+    /// Returns the `_ingest.timestamp` of the document.
+    pub fn timestamp(&self) -> Option<&str> {
+        self._ingest.as_ref().and_then(|i| i.timestamp.as_ref()).map(|t| &**t)
+    }
+}
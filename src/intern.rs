@@ -0,0 +1,195 @@
+//! Column names shared between the rows of an aggregation tree.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// The name of a column in a row produced while iterating `Aggregations`.
+///
+/// Names that appear as they are in the aggregation tree borrow from it. Names made of an
+/// aggregation name and a suffix, like `{agg}_doc_count`, are built once per iterator and shared
+/// by all of its rows, so they aren't allocated again for every row.
+///
+/// It dereferences to `str`, and rows can be looked up by `&str`.
+#[derive(Clone)]
+pub struct ColumnName<'a>(Name<'a>);
+
+#[derive(Clone)]
+enum Name<'a> {
+    Borrowed(&'a str),
+    Interned(Arc<str>)
+}
+
+impl<'a> ColumnName<'a> {
+    fn borrowed(name: &'a str) -> ColumnName<'a> {
+        ColumnName(Name::Borrowed(name))
+    }
+
+    /// Returns the name as a `String`.
+    pub fn into_owned(self) -> String {
+        (*self).to_owned()
+    }
+}
+
+impl<'a> Deref for ColumnName<'a> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self.0 {
+            Name::Borrowed(name) => name,
+            Name::Interned(ref name) => name
+        }
+    }
+}
+
+impl<'a> Borrow<str> for ColumnName<'a> {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<'a> AsRef<str> for ColumnName<'a> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<'a> PartialEq for ColumnName<'a> {
+    fn eq(&self, other: &ColumnName) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a> Eq for ColumnName<'a> {}
+
+impl<'a> PartialOrd for ColumnName<'a> {
+    fn partial_cmp(&self, other: &ColumnName<'a>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for ColumnName<'a> {
+    fn cmp(&self, other: &ColumnName<'a>) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<'a> Hash for ColumnName<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<'a> PartialEq<str> for ColumnName<'a> {
+    fn eq(&self, other: &str) -> bool {
+        &**self == other
+    }
+}
+
+impl<'a, 'b> PartialEq<&'b str> for ColumnName<'a> {
+    fn eq(&self, other: &&'b str) -> bool {
+        &**self == *other
+    }
+}
+
+impl<'a> PartialEq<String> for ColumnName<'a> {
+    fn eq(&self, other: &String) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a> PartialEq<ColumnName<'a>> for String {
+    fn eq(&self, other: &ColumnName<'a>) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, 'b> PartialEq<ColumnName<'a>> for &'b str {
+    fn eq(&self, other: &ColumnName<'a>) -> bool {
+        *self == &**other
+    }
+}
+
+impl<'a> fmt::Debug for ColumnName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a> fmt::Display for ColumnName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// Builds the composite column names of an iterator, keyed by aggregation name and suffix, and
+/// knows which columns its rows keep.
+#[derive(Debug, Default)]
+pub struct Interner<'a> {
+    names: HashMap<(&'a str, &'static str), Arc<str>>,
+    projection: Option<HashSet<String>>,
+    /// The names that replace those of aggregations nested in one of the same name, by the
+    /// address of their name in the tree, see `DuplicateNames::Disambiguate`.
+    renames: HashMap<usize, Arc<str>>,
+    renamed: HashMap<(Arc<str>, &'static str), Arc<str>>
+}
+
+impl<'a> Interner<'a> {
+    pub fn new() -> Interner<'a> {
+        Interner::default()
+    }
+
+    /// Creates an interner for rows that only keep the columns in `projection`.
+    pub fn projected(projection: HashSet<String>) -> Interner<'a> {
+        Interner {
+            projection: Some(projection),
+            ..Interner::default()
+        }
+    }
+
+    /// Replaces the aggregation names of the tree found in `renames`, by their address.
+    pub fn with_renames(mut self, renames: HashMap<usize, Arc<str>>) -> Interner<'a> {
+        self.renames = renames;
+        self
+    }
+
+    fn renamed(&self, name: &str) -> Option<&Arc<str>> {
+        if self.renames.is_empty() {
+            return None;
+        }
+        self.renames.get(&(name.as_ptr() as usize))
+    }
+
+    /// Returns `true` if rows keep the column `name`.
+    pub fn keeps(&self, name: &str) -> bool {
+        self.projection.as_ref().map_or(true, |p| p.contains(name))
+    }
+
+    /// Returns the column name of an aggregation or bucket field taken from the tree as is.
+    pub fn borrowed(&self, name: &'a str) -> ColumnName<'a> {
+        match self.renamed(name) {
+            Some(renamed) => ColumnName(Name::Interned(renamed.clone())),
+            None => ColumnName::borrowed(name)
+        }
+    }
+
+    /// Returns the column `{agg}_{suffix}`, building it only the first time it is asked for.
+    pub fn get(&mut self, agg: &'a str, suffix: &'static str) -> ColumnName<'a> {
+        if let Some(renamed) = self.renamed(agg).cloned() {
+            let name = self.renamed
+                .entry((renamed.clone(), suffix))
+                .or_insert_with(|| Arc::from(format!("{}_{}", renamed, suffix)))
+                .clone();
+            return ColumnName(Name::Interned(name));
+        }
+        let name = self.names
+            .entry((agg, suffix))
+            .or_insert_with(|| Arc::from(format!("{}_{}", agg, suffix)))
+            .clone();
+        ColumnName(Name::Interned(name))
+    }
+}
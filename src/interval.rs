@@ -0,0 +1,141 @@
+//! The intervals of `histogram` and `date_histogram` aggregations, which responses rarely spell
+//! out, and the width of each of their buckets.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use {AggShape, Aggregations, Object};
+
+/// The widths of the buckets of the histograms of a tree, by the address of their key in it.
+///
+/// Rows borrow their key from the tree, so the key of a row finds the width of its bucket even
+/// when histograms of the same name are nested in different parent buckets.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BucketWidths {
+    widths: HashMap<usize, (String, f64)>
+}
+
+impl BucketWidths {
+    pub(crate) fn of(aggs: &Aggregations) -> BucketWidths {
+        let mut widths = BucketWidths::default();
+        if let Some(aggs) = aggs.0.as_object() {
+            each_histogram(aggs, &mut |name, agg, keys| {
+                let fixed = agg.get("interval").and_then(Value::as_str).and_then(fixed_interval_millis);
+                let mode = mode_of(&deltas(&keys));
+                let regular = keys.windows(2).all(|pair| Some(pair[1].1 - pair[0].1) == mode);
+                for (i, &(key, n)) in keys.iter().enumerate() {
+                    let next = keys.get(i + 1).map(|&(_, next)| next - n);
+                    let width = match (fixed, regular) {
+                        (Some(fixed), _) => Some(fixed),
+                        (None, true) => mode,
+                        (None, false) => next.or(mode)
+                    };
+                    if let Some(width) = width {
+                        widths.widths.insert(key as *const Value as usize, (format!("{}_width", name), width));
+                    }
+                }
+            });
+        }
+        widths
+    }
+
+    /// Returns the width column and width of the bucket whose key is `key`.
+    pub(crate) fn get(&self, key: &Value) -> Option<&(String, f64)> {
+        self.widths.get(&(key as *const Value as usize))
+    }
+
+    /// Returns the names of the width columns.
+    pub(crate) fn columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = self.widths.values().map(|&(ref column, _)| column.clone()).collect();
+        columns.sort();
+        columns.dedup();
+        columns
+    }
+}
+
+impl Aggregations {
+    /// Returns the interval of the `histogram` or `date_histogram` aggregation `agg`, as the most
+    /// common distance between the keys of consecutive buckets.
+    ///
+    /// Buckets below different parent buckets are only compared with their siblings, and ties
+    /// go to the smallest distance. This is `None` if no aggregation called `agg` has two
+    /// numeric keys in ascending order, as a single bucket has no spacing to go by.
+    pub fn inferred_interval(&self, agg: &str) -> Option<f64> {
+        let mut all = Vec::new();
+        if let Some(aggs) = self.0.as_object() {
+            each_histogram(aggs, &mut |name, _, keys| if name == agg {
+                all.extend(deltas(&keys));
+            });
+        }
+        mode_of(&all)
+    }
+}
+
+fn deltas(keys: &[(&Value, f64)]) -> Vec<f64> {
+    keys.windows(2).map(|pair| pair[1].1 - pair[0].1).collect()
+}
+
+/// Returns the most common of `values`, the smallest of them on a tie.
+fn mode_of(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mut best: Option<(f64, usize)> = None;
+    let mut i = 0;
+    while i < sorted.len() {
+        let run = sorted[i..].iter().take_while(|&&v| v == sorted[i]).count();
+        if best.map_or(true, |(_, count)| run > count) {
+            best = Some((sorted[i], run));
+        }
+        i += run;
+    }
+    best.map(|(value, _)| value)
+}
+
+/// Returns the length in milliseconds of the `interval` of an `auto_date_histogram`, like `1d`
+/// or `30m`, or `None` for calendar intervals such as `1M` whose length varies.
+fn fixed_interval_millis(interval: &str) -> Option<f64> {
+    let split = interval.find(|c: char| !c.is_digit(10))?;
+    let (n, unit) = interval.split_at(split);
+    let n: f64 = n.parse().ok()?;
+    let millis = match unit {
+        "ms" => 1.0,
+        "s" => 1000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        "d" => 86_400_000.0,
+        _ => return None
+    };
+    Some(n * millis)
+}
+
+/// Calls `f` with the name, the object and the numeric keys of every aggregation at and below
+/// `aggs` that looks like a histogram: its buckets all have numeric keys in ascending order, and
+/// it doesn't have the counts of a `terms` aggregation.
+fn each_histogram<'a, F>(aggs: &'a Object, f: &mut F)
+    where F: FnMut(&'a String, &'a Object, Vec<(&'a Value, f64)>)
+{
+    for (name, agg) in aggs {
+        let agg = match agg.as_object() {
+            Some(agg) => agg,
+            None => continue
+        };
+        match AggShape::of(agg) {
+            AggShape::Buckets(buckets) => {
+                let keys: Vec<(&Value, f64)> = buckets.iter()
+                    .filter_map(|b| b.find("key"))
+                    .filter_map(|k| k.as_f64().map(|n| (k, n)))
+                    .collect();
+                let terms = ["sum_other_doc_count", "doc_count_error_upper_bound", "bg_count"].iter()
+                    .any(|field| agg.contains_key(*field));
+                if !terms && keys.len() == buckets.len() && keys.windows(2).all(|pair| pair[0].1 < pair[1].1) {
+                    f(name, agg, keys);
+                }
+                for bucket in buckets.iter().filter_map(Value::as_object) {
+                    each_histogram(bucket, f);
+                }
+            }
+            AggShape::SingleBucket => each_histogram(agg, f),
+            _ => ()
+        }
+    }
+}
@@ -4,27 +4,55 @@
 //! convenient iterators to step through the results returned. It is designed to work
 //! with [`elastic-reqwest`](https://github.com/elastic-rs/elastic-hyper/).
 //!
+//! ## Layout
+//!
+//! - `search` has the response of the `_search` API, with its `HitsEnvelope` and `Hit`s.
+//! - `search::aggregations` flattens the aggregations of a search response into rows.
+//! - `bulk` has the response of the `_bulk` API.
+//! - `error` has the errors of parsing a body, and the errors Elasticsearch reports itself.
+//! - `common` has the parts shared between responses of different APIs, such as `Shards`.
+//!
+//! The types of these modules are also exported at the root of the crate, along with the
+//! responses of the other APIs. `prelude` has those most uses need.
+//!
 //! ## Usage
 //!
 //! Query your Elasticsearch Cluster, then iterate through the results
 //!
-//! ```no_run
+//! ```
+//! extern crate elastic_responses;
 //!
-//! // Send a request (omitted, see `samples/basic`, and read the response.
-//! let mut res = client.elastic_req(&params, SearchRequest::for_index("_all", body)).unwrap();
+//! use elastic_responses::prelude::*;
 //!
-//! //Parse body to JSON as an elastic_responses::Response object
-//! let body_as_json: EsResponse = res.json().unwrap();
+//! fn main() {
+//!     // Send a request (omitted, see `samples/basic`) and read the response body.
+//!     let body = br#"{
+//!         "took": 3,
+//!         "timed_out": false,
+//!         "_shards": { "total": 1, "successful": 1, "failed": 0 },
+//!         "hits": {
+//!             "total": 1,
+//!             "max_score": 1.0,
+//!             "hits": [
+//!                 { "_index": "logs", "_type": "doc", "_id": "1", "_score": 1.0, "_source": { "level": "warn" } }
+//!             ]
+//!         },
+//!         "aggregations": {
+//!             "levels": { "buckets": [ { "key": "warn", "doc_count": 1 } ] }
+//!         }
+//!     }"#;
 //!
-//! //Use hits() or aggs() iterators
-//! //Hits
-//! for i in body_as_json.hits() {
-//!   println!("{:?}",i);
-//! }
+//!     // Parse the body as an elastic_responses::search::Response
+//!     let response: Response = parse_slice(body).unwrap();
+//!
+//!     // Use hits() or aggs() iterators
+//!     for hit in response.hits() {
+//!         println!("{}: {:?}", hit.id(), hit.source());
+//!     }
 //!
-//! //Agregations
-//! for i in body_as_json.aggs() {
-//!   println!("{:?}",i);
+//!     for row in response.aggs() {
+//!         println!("{:?}: {:?}", row.key_string("levels"), row.get_f64("levels_doc_count"));
+//!     }
 //! }
 //! ```
 
@@ -37,17 +65,178 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 
+#[macro_use]
+extern crate quick_error;
+
+#[macro_use]
 extern crate serde;
 extern crate serde_json;
 
+#[cfg(feature = "chrono")]
+extern crate chrono;
+
+#[cfg(feature = "cbor")]
+extern crate serde_cbor;
+
+#[cfg(feature = "arrow")]
+extern crate arrow;
+
+#[cfg(feature = "compression")]
+extern crate flate2;
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+#[cfg(feature = "simd-json")]
+extern crate simd_json;
+
 extern crate slog_stdlog;
 extern crate slog_envlogger;
 
-use serde::Deserialize;
+use serde::de::{Deserialize, Error as DeError};
 use serde_json::Value;
-use std::borrow::Cow;
 use std::collections::BTreeMap;
-use std::slice::Iter;
+
+use search::aggregations::{AggShape, to_owned_row};
+
+mod admin;
+mod aliases;
+mod allocation;
+#[cfg(feature = "arrow")]
+mod arrow_batch;
+mod bucket_key;
+pub mod bulk;
+mod cat;
+mod cluster;
+mod clusters;
+mod columnar;
+#[cfg(feature = "compression")]
+mod compression;
+pub mod common;
+mod condition;
+mod context;
+mod diff;
+mod dispatch;
+mod duplicate;
+mod edit;
+pub mod error;
+mod error_detail;
+mod flatten;
+mod flavor;
+mod gaps;
+mod geo;
+mod get;
+mod http;
+mod ilm;
+mod index_stats;
+mod ingest;
+mod intern;
+mod interval;
+mod limit;
+mod merge;
+mod ndjson;
+mod observe;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod parser;
+mod path;
+mod pivot;
+pub mod prelude;
+mod pretty;
+mod problem;
+mod profile;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod rank_eval;
+mod raw;
+mod rewrite;
+mod scroll;
+pub mod search;
+#[cfg(feature = "simd-json")]
+mod simd;
+mod sort;
+mod stats;
+mod table;
+mod tasks;
+mod terms_enum;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod typed;
+mod validate;
+mod validate_query;
+mod walk;
+mod warning;
+mod write;
+
+pub use admin::{AcknowledgedResponse, GetScriptResponse, RenderTemplateResponse, StoredScript};
+pub use aliases::{AliasInfo, AliasesResponse};
+pub use allocation::{AllocationExplainResponse, Decider, Decision, NodeDecision, UnassignedInfo};
+#[cfg(feature = "arrow")]
+pub use arrow_batch::RecordBatchExport;
+pub use bucket_key::{BucketKey, KeyType, KeyTypes};
+pub use bulk::{BulkAction, BulkItem, BulkPartition, BulkResponse, PositionedItem};
+pub use cat::{CatAlias, CatCount, CatHealth, CatNode, CatRow};
+pub use cluster::{ClusterHealthResponse, ClusterStateResponse, IndexMetadata, IndexState, PendingTask,
+                  PendingTasksResponse, Priority};
+pub use clusters::{ClusterDetails, Clusters, PartialReason, PartialReasons};
+pub use common::{ShardFailure, Shards};
+#[cfg(feature = "compression")]
+pub use compression::{Compression, Decompressed, responses_from_compressed_ndjson};
+pub use condition::{Comparator, Condition};
+pub use context::ParseContext;
+pub use diff::{AggDiff, BucketChange, MetricChange};
+pub use dispatch::TypedByIndex;
+pub use duplicate::DuplicateNames;
+pub use error::{ApiError, CLIENT_ERROR_TYPES, CircuitBreaker, Error, RETRYABLE_STATUSES, RETRYABLE_TYPES, ResponseError};
+pub use error_detail::{MapperError, ScriptError, ScriptPosition};
+pub use flatten::{BucketFilter, FlattenConfig, MappedRows};
+pub use flavor::{Flavor, OPENSEARCH_ERROR_TYPES, PingResponse, VersionInfo};
+pub use gaps::FillStrategy;
+pub use geo::GeoPoint;
+pub use get::GetResponse;
+pub use http::{BodyFormat, ExistsResponse, FromHttpResponse, HttpResponse, parse_http_response,
+               parse_http_response_with_headers};
+pub use ilm::{IlmExplainResponse, IlmIndex};
+pub use index_stats::{DocsStats, IndexStats, IndexStatsResponse, IndexingStats, SearchStats, StatsSections,
+                      StoreStats};
+pub use ingest::{IngestDoc, ProcessorResult, SimulatePipelineResponse, SimulatedDoc};
+pub use intern::ColumnName;
+pub use limit::{DEFAULT_MAX_DEPTH, Limit, ResponseParser};
+pub use merge::MergeStrategy;
+pub use ndjson::{NdjsonResponses, responses_from_ndjson};
+pub use observe::{NoopObserver, RecordingObserver, ResponseMeta, ResponseObserver};
+#[cfg(feature = "rayon")]
+pub use parallel::{ParBucketRows, ParRows};
+pub use parser::Parser;
+pub use pivot::{Pivot, PivotError, pivot_rows};
+pub use pretty::{PrettyAggregations, PrettyResponse};
+pub use problem::{LenientRows, Problem, ProblemKind, ProblemSink};
+pub use profile::{Profile, QueryProfile, SearchProfile, ShardProfile};
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusLines;
+pub use rank_eval::{DocRef, QueryDetails, RankEvalResponse, RatedHit};
+pub use rewrite::{Clause, ClauseKind, Occur, ParsedQuery, RewrittenQuery};
+pub use scroll::{Dedup, DedupHits, ScrollStitcher};
+pub use search::{AggsResponse, Hit, HitSizeStats, Hits, HitsAboveScore, HitsEnvelope, MsearchParts, MsearchResponse,
+                 MsearchResponseOf, MsearchSlot, MsearchSlots, PageInfo, PushSlot, RankedHits, Response, ResponseOf,
+                 ScoreStats, TotalHits, TotalHitsRelation};
+#[allow(deprecated)]
+pub use search::EsResponse;
+pub use search::aggregations::{AggError, AggregationIterator, Aggregations, OwnedAggregations, OwnedRow, Row, RowAccess,
+                               RowColumns, TryAggregationIterator};
+#[allow(deprecated)]
+pub use search::aggregations::RowData;
+pub use sort::{Order, merge_sorted_hits, sort_hits_by_path};
+pub use stats::ColumnStats;
+pub use table::HitsTable;
+pub use tasks::{BulkByScrollStatus, ByQueryResponse, TaskInfo, TaskResponse};
+pub use terms_enum::TermsEnumResponse;
+pub use typed::{AggValue, DateHistogramAgg, DateHistogramBucket, FilterAgg, HistogramAgg, HistogramBucket, SingleValueAgg,
+                StatsAgg, TermsAgg, TermsBucket};
+pub use validate::ValidationWarning;
+pub use validate_query::{QueryExplanation, ValidateQueryResponse};
+pub use warning::Warning;
+pub use write::{DocWriteResponse, WriteResult};
 
 //let mut i = deserialized.aggs().unwrap().into_iter();
 //
@@ -63,219 +252,93 @@ use std::slice::Iter;
 //}
 
 
-#[derive(Deserialize, Debug)]
-struct Shards {
-    total: u32,
-    successful: u32,
-    failed: u32
-}
-
-/// Struct to hold the search's Hits, serializable to type `T` or `serde_json::Value`
-#[derive(Deserialize, Debug)]
-pub struct Hits<T: Deserialize> {
-    total: u64,
-    max_score: u64,
-    hits: Vec<T>
-}
-
-impl<T: Deserialize> Hits<T> {
-    fn hits(&self) -> &Vec<T> {
-        // JPG http://stackoverflow.com/q/40006219/155423
-        &self.hits
-    }
+/// Parses a response body from a slice of bytes.
+///
+/// Any body is either parsed or reported as an `Error`; malformed aggregations that still
+/// deserialize are surfaced later while iterating, see `Aggregations::try_rows`.
+pub fn parse_slice<T: Deserialize>(body: &[u8]) -> Result<ResponseOf<T>, Error> {
+    parse_slice_as(body)
 }
 
-#[derive(Deserialize, Debug)]
-struct Hit {
-    _index: String
-}
-
-/// Main `struct` of the crate, provides access to the `hits` and `aggs` iterators.
-#[derive(Deserialize, Debug)]
-pub struct ResponseOf<T: Deserialize> {
-    took: u64,
-    timed_out: bool,
-    _shards: Shards,
-    hits: Hits<T>,
-    aggregations: Option<Aggregations>,
-    status: Option<u16>
-}
-
-pub type Response = ResponseOf<Value>;
-
-impl<T: Deserialize> ResponseOf<T> {
-    /// Returns an Iterator to the search results or hits of the response.
-    pub fn hits(&self) -> &Vec<T> {
-        &self.hits.hits()
-    }
-
-    /// Returns an Iterator to the search results or aggregations part of the response.
-    ///
-    /// This Iterator transforms the tree-like JSON object into a row/table based format for use with standard iterator adaptors.
-    pub fn aggs(&self) -> &Aggregations {
-        //FIXME: Create empty aggregation, remove unwrap()
-        self.aggregations.as_ref().unwrap()
-    }
+/// Parses a body from a slice of bytes into any type, such as a response struct with typed
+/// aggregations.
+///
+/// Errors are reported as `Error::Parse` with the path of the element that failed whenever
+/// serde_json knows where it failed.
+pub fn parse_slice_as<T: Deserialize>(body: &[u8]) -> Result<T, Error> {
+    parse_typed(body, &ResponseParser::new())
 }
 
-/// Type Struct to hold a generic `serde_json::Value` tree of the Aggregation results.
-#[derive(Deserialize, Debug)]
-pub struct Aggregations(Value);
-
-impl<'a> IntoIterator for &'a Aggregations {
-    type Item = RowData<'a>;
-    type IntoIter = AggregationIterator<'a>;
-
-    fn into_iter(self) -> AggregationIterator<'a> {
-        AggregationIterator::new(self)
+/// Parses a body into `T`, the way every function parsing a whole body does.
+///
+/// The body size limit of `limits` is checked first. A body that doesn't parse is reported as
+/// `Error::Api` when it is an error object Elasticsearch sent instead of a response, and
+/// otherwise with the path of the element that failed, if serde_json knows where that was.
+/// The body is only looked at again once parsing failed, so none of this costs anything for
+/// bodies that parse, and a `status` next to a valid response is never mistaken for an error.
+pub(crate) fn parse_typed<T: Deserialize>(body: &[u8], limits: &ResponseParser) -> Result<T, Error> {
+    if let Some(max) = limits.body_limit() {
+        if body.len() as u64 > max {
+            return Err(Error::LimitExceeded {
+                kind: Limit::BodyBytes,
+                limit: max
+            });
+        }
     }
-}
 
-/// Aggregator that traverses the results from Elasticsearch's Aggregations and returns a result
-/// row by row in a table-styled fashion.
-#[derive(Debug)]
-pub struct AggregationIterator<'a> {
-    current_row: Option<RowData<'a>>,
-    current_row_finished: bool,
-    iter_stack: Vec<(Option<&'a String>, Iter<'a, Value>)>,
-    aggregations: &'a Aggregations
+    serde_json::from_slice(body).map_err(|err| {
+        let value: Option<Value> = serde_json::from_slice(body).ok();
+        if let Some((status, api)) = value.as_ref().and_then(embedded_error) {
+            return Error::Api(status, api);
+        }
+        match ParseContext::locate(body, &err) {
+            Some(context) => Error::Parse(err, context),
+            None => Error::Json(err)
+        }
+    })
 }
 
-impl<'a> AggregationIterator<'a> {
-    fn new(a: &'a Aggregations) -> AggregationIterator<'a> {
-        let o = a.0.as_object()
-            .expect("Not implemented, we only cater for bucket objects");
-        //FIXME: Bad for lib // JPG: quick-error
-
-        let s = o.into_iter().filter_map(|(key, child)| {
-            child.as_object()
-                .and_then(|child| child.get("buckets"))
-                .and_then(Value::as_array)
-                .map(|array| (Some(key), array.iter()))
-        }).collect();
-
-        AggregationIterator {
-            current_row: None,
-            current_row_finished: false,
-            iter_stack: s,
-            aggregations: a
-        }
-    }
+/// Returns the `error` object of a body, along with the body's own `status` if it has one.
+///
+/// Elasticsearch puts the status next to the error, and proxies that always answer `200` may
+/// still pass it through, so callers prefer it over the status of the transport.
+pub(crate) fn embedded_error(body: &Value) -> Option<(Option<u16>, ApiError)> {
+    let err = match body.find("error").and_then(ApiError::from_value) {
+        Some(err) => err,
+        None => return None
+    };
+    let status = body.find("status").and_then(Value::as_u64).map(|s| s as u16);
+    Some((status, err))
 }
 
 type Object = BTreeMap<String, Value>;
-type RowData<'a> = BTreeMap<Cow<'a, str>, &'a Value>;
 
-fn insert_value<'a>(fieldname: &str, json_object: &'a Object, keyname: &str, rowdata: &mut RowData<'a>) {
-    if let Some(v) = json_object.get(fieldname) {
-        let field_name = format!("{}_{}", keyname, fieldname);
-        debug! ("ITER: Insert value! {} {:?}", field_name, v);
-        rowdata.insert(Cow::Owned(field_name), v);
+/// Deserializes the `name` field of a raw object, for responses that keep the raw object around.
+fn typed_section<T: Deserialize, E: DeError>(object: &Object, name: &str) -> Result<Option<T>, E> {
+    match object.get(name) {
+        Some(v) => serde_json::from_value(v.clone()).map(Some).map_err(|e| E::custom(e.to_string())),
+        None => Ok(None)
     }
 }
 
-impl<'a> Iterator for AggregationIterator<'a> {
-    type Item = RowData<'a>;
-
-    fn next(&mut self) -> Option<RowData<'a>> {
-        if self.current_row.is_none() {
-            //New row
-            self.current_row = Some(BTreeMap::new())
-        }
-
-        loop {
-            if let Some(mut i) = self.iter_stack.pop() {
-                let n = i.1.next();
-
-                //FIXME: can this fail?
-                let active_name = &i.0.unwrap();
-
-                //Iterate down?
-                let mut has_buckets = false;
-                //Save
-                self.iter_stack.push(i);
-
-                debug! ("ITER: Depth {}", self.iter_stack.len());
-                //FIXME: Move this, to be able to process first line too
-                if let Some(n) = n {
-                    if let Some(ref mut row) = self.current_row {
-                        debug! ("ITER: Row: {:?}", row);
-
-                        for (key, value) in n.as_object().expect("Shouldn't get here!") {
-                            if let Some(c) = value.as_object() {
-                                //Child Aggregation
-                                if let Some(buckets) = c.get("buckets") {
-                                    has_buckets = true;
-                                    if let Value::Array(ref a) = *buckets {
-                                        self.iter_stack.push((Some(key), a.iter()));
-                                    }
-                                    continue;
-                                }
-                                //Simple Value Aggregation Name
-                                if let Some(v) = c.get("value") {
-                                    debug! ("ITER: Insert value! {} {:?}", key, v);
-                                    row.insert(Cow::Borrowed(key), v);
-                                    continue;
-                                }
-                                //Stats fields
-                                insert_value("count", c, key, row);
-                                insert_value("min", c, key, row);
-                                insert_value("max", c, key, row);
-                                insert_value("avg", c, key, row);
-                                insert_value("sum", c, key, row);
-                                insert_value("sum_of_squares", c, key, row);
-                                insert_value("variance", c, key, row);
-                                insert_value("std_deviation", c, key, row);
-
-                                if c.contains_key("std_deviation_bounds") {
-                                    if let Some(child_values) = c.get("std_deviation_bounds").unwrap().as_object() {
-                                        let u = child_values.get("upper");
-                                        let l = child_values.get("lower");
-                                        let un = format!("{}_std_deviation_bounds_upper", key);
-                                        let ln = format!("{}_std_deviation_bounds_lower", key);
-                                        debug! ("ITER: Insert std_dev_bounds! {} {} u: {:?} l: {:?}", un, ln, u.unwrap(), l.unwrap());
-                                        row.insert(Cow::Owned(un), u.unwrap());
-                                        row.insert(Cow::Owned(ln), l.unwrap());
-                                    }
-                                }
-                            }
-
-                            if key == "key" {
-                                //Bucket Aggregation Name
-                                debug! ("ITER: Insert bucket! {} {:?}", active_name, value);
-                                row.insert(Cow::Borrowed(active_name), value);
-                            } else if key == "doc_count" {
-                                //Bucket Aggregation Count
-                                debug! ("ITER: Insert bucket count! {} {:?}", active_name, value);
-                                let field_name = format!("{}_doc_count", active_name);
-                                row.insert(Cow::Owned(field_name), value);
-                            }
-                        }
-                    }
-                } else {
-                    //Was nothing here, exit
-                    debug! ("ITER: Exit!");
-                    self.iter_stack.pop();
-                    continue;
-                }
-
-                if !has_buckets {
-                    debug! ("ITER: Bucketless!");
-                    break;
-                } else {
-                    debug! ("ITER: Dive!");
-                }
-            } else {
-                debug! ("ITER: Done!");
-                self.current_row = None;
-                break;
-            };
-        }
-
-        match self.current_row {
-            //FIXME: Refactor to avoid this clone()
-            Some(ref x) => Some(x.clone()),
-            None => None
-        }
-    }
-}
+/// Items the modules keep to themselves, which can't be reached from outside the crate.
+///
+/// ```compile_fail
+/// use elastic_responses::search::aggregations::AggShape;
+/// ```
+///
+/// ```compile_fail
+/// use elastic_responses::search::aggregations::to_owned_row;
+/// ```
+///
+/// ```compile_fail
+/// extern crate elastic_responses;
+/// extern crate serde_json;
+///
+/// fn tree(aggs: &elastic_responses::Aggregations) -> &serde_json::Value {
+///     &aggs.0
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(doctest)]
+pub struct PrivateItems;
@@ -0,0 +1,387 @@
+//! Limits on the size of responses, to fail fast on bodies too large to handle safely.
+
+use serde::de::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::sync::Arc;
+use std::time::Instant;
+
+use {AggShape, Error, NdjsonResponses, Object, ResponseMeta, ResponseObserver, ResponseOf, parse_typed};
+use ndjson;
+use validate::segment;
+
+/// The limits of a `ResponseParser`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    /// The size of a body, or of a line of a newline-delimited stream.
+    BodyBytes,
+    /// The number of hits in a response.
+    Hits,
+    /// The number of buckets in all the aggregations of a response.
+    Buckets
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Limit::BodyBytes => "body bytes",
+            Limit::Hits => "hits",
+            Limit::Buckets => "buckets"
+        })
+    }
+}
+
+/// Parses responses while enforcing limits on their size, reporting responses that are too
+/// large as `Error::LimitExceeded`.
+///
+/// The body size is checked before anything is parsed, and readers are no longer read from once
+/// it is exceeded, so an oversized body is never buffered in full. The hit and bucket limits are
+/// checked as soon as each response is parsed.
+///
+/// Aggregations nested deeper than `DEFAULT_MAX_DEPTH` levels are cut off, see `max_depth`.
+///
+/// An observer can be installed with `observer` to record metrics of every parsed response.
+#[derive(Clone, Default)]
+pub struct ResponseParser {
+    max_body_bytes: Option<u64>,
+    max_hits: Option<usize>,
+    max_buckets: Option<usize>,
+    max_depth: Option<usize>,
+    strict_buckets: bool,
+    strict_depth: bool,
+    observer: Option<Arc<ResponseObserver>>
+}
+
+impl fmt::Debug for ResponseParser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResponseParser")
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("max_hits", &self.max_hits)
+            .field("max_buckets", &self.max_buckets)
+            .field("max_depth", &self.depth_limit())
+            .field("strict_buckets", &self.strict_buckets)
+            .field("strict_depth", &self.strict_depth)
+            .field("observer", &self.observer.as_ref().map(|_| "ResponseObserver"))
+            .finish()
+    }
+}
+
+impl ResponseParser {
+    /// Creates a parser without any size limits.
+    pub fn new() -> ResponseParser {
+        ResponseParser::default()
+    }
+
+    /// Limits the size of bodies, or of each line of a newline-delimited stream, in bytes.
+    pub fn max_body_bytes(mut self, max: u64) -> ResponseParser {
+        self.max_body_bytes = Some(max);
+        self
+    }
+
+    /// Limits the number of hits in a response.
+    pub fn max_hits(mut self, max: usize) -> ResponseParser {
+        self.max_hits = Some(max);
+        self
+    }
+
+    /// Limits the number of buckets in all the aggregations of a response, counting the buckets
+    /// of sub-aggregations as well.
+    pub fn max_buckets(mut self, max: usize) -> ResponseParser {
+        self.max_buckets = Some(max);
+        self
+    }
+
+    /// Limits how deep bucket aggregations can be nested, counting the outermost ones as the first
+    /// level. The default is `DEFAULT_MAX_DEPTH`.
+    ///
+    /// Aggregation trees are walked recursively in places, so a broken or hostile response nested
+    /// thousands of levels deep could overflow the stack. The bucket aggregations below the last
+    /// level allowed are removed from the tree, and a warning is logged, unless the depth is
+    /// strict.
+    ///
+    /// serde_json itself refuses JSON nested more than 128 values deep, about 40 levels of
+    /// aggregations, so the default mostly matters for trees that weren't parsed from JSON, like
+    /// those of CBOR bodies, see `limit_depth`.
+    ///
+    /// The queries of a search `profile` are limited the same way, counting the queries the
+    /// search was rewritten into as the first level.
+    pub fn max_depth(mut self, max: usize) -> ResponseParser {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Rejects responses with aggregations nested deeper than `max_depth` with
+    /// `Error::DepthExceeded`, instead of cutting them off.
+    pub fn strict_depth(mut self) -> ResponseParser {
+        self.strict_depth = true;
+        self
+    }
+
+    /// Rejects responses with entries in `buckets` that aren't buckets, such as a stray `null`
+    /// or number, with `Error::InvalidBucket`.
+    ///
+    /// By default such entries are skipped by the rows, listed by `Aggregations::validate` and
+    /// counted in the `stray_buckets` of the `ResponseMeta`.
+    pub fn strict_buckets(mut self) -> ResponseParser {
+        self.strict_buckets = true;
+        self
+    }
+
+    /// Passes the metadata of every response parsed from now on to `observer`.
+    pub fn observer(mut self, observer: Arc<ResponseObserver>) -> ResponseParser {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Parses a response body from a slice of bytes, like `parse_slice`.
+    pub fn parse_slice<T: Deserialize>(&self, body: &[u8]) -> Result<ResponseOf<T>, Error> {
+        let started = Instant::now();
+        let mut response = parse_typed(body, self)?;
+        self.limit_depth(&mut response)?;
+        self.observe(&response, body.len() as u64, started);
+        self.check(&response)?;
+        Ok(response)
+    }
+
+    /// Parses a response body from a reader, which is read from no further than the body size
+    /// limit.
+    pub fn parse_reader<R: Read, T: Deserialize>(&self, reader: R) -> Result<ResponseOf<T>, Error> {
+        let body = self.read_body(reader, Error::Io)?;
+        self.parse_slice(&body)
+    }
+
+    /// Reads a body from `reader` no further than the body size limit, reporting the errors of
+    /// the reader with `read_error`.
+    pub(crate) fn read_body<R: Read>(&self, reader: R, read_error: fn(io::Error) -> Error) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::new();
+        match self.max_body_bytes {
+            Some(max) => {
+                // a single extra byte tells a body of exactly the limit apart from a larger one
+                reader.take(max + 1).read_to_end(&mut body).map_err(read_error)?;
+                if body.len() as u64 > max {
+                    return Err(exceeded(Limit::BodyBytes, max));
+                }
+            }
+            None => {
+                let mut reader = reader;
+                reader.read_to_end(&mut body).map_err(read_error)?;
+            }
+        }
+        Ok(body)
+    }
+
+    /// Parses a stream with one search response per line, like `responses_from_ndjson`, applying
+    /// the body size limit to each line.
+    ///
+    /// Oversized lines are reported without being buffered, and the lines after them are still
+    /// parsed.
+    pub fn responses_from_ndjson<R: BufRead, T: Deserialize>(&self, reader: R) -> NdjsonResponses<R, T> {
+        ndjson::with_parser(reader, self.clone())
+    }
+
+    /// Checks the hit and bucket limits of a parsed response, and its buckets and depth if they
+    /// are strict.
+    pub fn check<T: Deserialize>(&self, response: &ResponseOf<T>) -> Result<(), Error> {
+        // first, as the other checks walk the tree recursively
+        if self.strict_depth {
+            self.check_depth(response)?;
+        }
+        if let Some(max) = self.max_hits {
+            if response.hits().len() > max {
+                return Err(exceeded(Limit::Hits, max as u64));
+            }
+        }
+        if let Some(max) = self.max_buckets {
+            let buckets = response.aggs().0.as_object().map_or(0, |aggs| count_buckets(aggs, max));
+            if buckets > max {
+                return Err(exceeded(Limit::Buckets, max as u64));
+            }
+        }
+        if self.strict_buckets {
+            if let Some(stray) = response.aggs().stray_buckets().into_iter().next() {
+                return Err(Error::InvalidBucket(stray.path().to_owned()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the aggregations nested deeper than the depth limit from a response or, if the
+    /// depth is strict, fails with `Error::DepthExceeded`. The queries of its `profile` are held
+    /// to the same limit.
+    ///
+    /// Responses parsed by the parser are limited before anything else walks their tree; this is
+    /// for responses parsed in other ways.
+    pub fn limit_depth<T: Deserialize>(&self, response: &mut ResponseOf<T>) -> Result<(), Error> {
+        if self.strict_depth {
+            return self.check_depth(response);
+        }
+        let max = self.depth_limit();
+        if let Some(aggs) = response.aggregations.0.as_object_mut() {
+            let removed = cut_off(aggs, max);
+            if removed > 0 {
+                warn! ("removed {} aggregations nested deeper than {} levels", removed, max);
+            }
+        }
+        if let Some(ref mut profile) = response.profile {
+            let removed = profile.cut_off(max);
+            if removed > 0 {
+                warn! ("removed {} profiled queries nested deeper than {} levels", removed, max);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_depth<T: Deserialize>(&self, response: &ResponseOf<T>) -> Result<(), Error> {
+        let max = self.depth_limit();
+        let aggs = response.aggs().0.as_object().and_then(|aggs| too_deep(aggs, max));
+        match aggs.or_else(|| response.profile().and_then(|profile| profile.too_deep(max))) {
+            Some((depth, path)) => {
+                Err(Error::DepthExceeded {
+                    depth: depth,
+                    path: path
+                })
+            }
+            None => Ok(())
+        }
+    }
+
+    fn depth_limit(&self) -> usize {
+        self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Returns the body size limit, if there is one.
+    pub fn body_limit(&self) -> Option<u64> {
+        self.max_body_bytes
+    }
+
+    /// Passes the metadata of a response parsed since `started` to the observer, if there is one.
+    pub(crate) fn observe<T: Deserialize>(&self, response: &ResponseOf<T>, body_bytes: u64, started: Instant) {
+        if let Some(ref observer) = self.observer {
+            observer.observe(&ResponseMeta::of(response, body_bytes, started));
+        }
+    }
+}
+
+fn exceeded(kind: Limit, limit: u64) -> Error {
+    Error::LimitExceeded {
+        kind: kind,
+        limit: limit
+    }
+}
+
+/// How deep aggregations can be nested by default, see `ResponseParser::max_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Returns the depth and path of the first bucket aggregation below `aggs` nested deeper than
+/// `max` levels.
+///
+/// The tree is walked with a stack of its own rather than recursively, as it may be too deep to
+/// recurse into.
+fn too_deep(aggs: &Object, max: usize) -> Option<(usize, String)> {
+    let mut stack = vec![(1, String::new(), aggs)];
+    while let Some((depth, path, aggs)) = stack.pop() {
+        for (name, agg) in aggs.iter().filter(|&(name, _)| name != "key" && name != "meta") {
+            let agg = match agg.as_object() {
+                Some(agg) => agg,
+                None => continue
+            };
+            let path = segment(&path, name);
+            match AggShape::of(agg) {
+                AggShape::Buckets(_) | AggShape::KeyedBuckets(_) | AggShape::SingleBucket if depth > max => {
+                    return Some((depth, path));
+                }
+                AggShape::Buckets(buckets) => {
+                    let path = segment(&path, "buckets");
+                    for (i, bucket) in buckets.iter().enumerate() {
+                        if let Some(bucket) = bucket.as_object() {
+                            stack.push((depth + 1, segment(&path, &i.to_string()), bucket));
+                        }
+                    }
+                }
+                AggShape::KeyedBuckets(buckets) => {
+                    let path = segment(&path, "buckets");
+                    for (key, bucket) in buckets {
+                        if let Some(bucket) = bucket.as_object() {
+                            stack.push((depth + 1, segment(&path, key), bucket));
+                        }
+                    }
+                }
+                AggShape::SingleBucket => stack.push((depth + 1, path, agg)),
+                _ => ()
+            }
+        }
+    }
+    None
+}
+
+/// Removes the bucket aggregations below `aggs` nested deeper than `max` levels, returning how
+/// many were removed. Like `too_deep`, it doesn't recurse.
+fn cut_off(aggs: &mut Object, max: usize) -> usize {
+    let is_bucket_agg = |agg: &Value| match agg.as_object().map(AggShape::of) {
+        Some(AggShape::Buckets(_)) | Some(AggShape::KeyedBuckets(_)) | Some(AggShape::SingleBucket) => true,
+        _ => false
+    };
+
+    let mut removed = 0;
+    let mut stack = vec![(1, aggs)];
+    while let Some((depth, aggs)) = stack.pop() {
+        if depth > max {
+            let too_deep: Vec<String> = aggs.iter()
+                .filter(|&(name, agg)| name != "key" && name != "meta" && is_bucket_agg(agg))
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in &too_deep {
+                aggs.remove(name);
+            }
+            removed += too_deep.len();
+            continue;
+        }
+
+        for (name, agg) in aggs.iter_mut() {
+            if name == "key" || name == "meta" || !is_bucket_agg(agg) {
+                continue;
+            }
+            let agg = agg.as_object_mut().unwrap();
+            let single = match AggShape::of(agg) {
+                AggShape::SingleBucket => true,
+                _ => false
+            };
+            if single {
+                stack.push((depth + 1, agg));
+                continue;
+            }
+            match agg.get_mut("buckets") {
+                Some(&mut Value::Array(ref mut buckets)) => {
+                    stack.extend(buckets.iter_mut().filter_map(Value::as_object_mut).map(|b| (depth + 1, b)));
+                }
+                Some(&mut Value::Object(ref mut buckets)) => {
+                    stack.extend(buckets.values_mut().filter_map(Value::as_object_mut).map(|b| (depth + 1, b)));
+                }
+                _ => ()
+            }
+        }
+    }
+    removed
+}
+
+/// Counts the buckets below the aggregations of `aggs`, stopping once there are more than `max`.
+fn count_buckets(aggs: &Object, max: usize) -> usize {
+    let mut count = 0;
+    for agg in aggs.values().filter_map(Value::as_object) {
+        if count > max {
+            break;
+        }
+        count += match AggShape::of(agg) {
+            AggShape::Buckets(buckets) => {
+                buckets.len() + buckets.iter().filter_map(Value::as_object).map(|b| count_buckets(b, max)).sum::<usize>()
+            }
+            AggShape::KeyedBuckets(buckets) => {
+                buckets.len() + buckets.values().filter_map(Value::as_object).map(|b| count_buckets(b, max)).sum::<usize>()
+            }
+            AggShape::SingleBucket => count_buckets(agg, max),
+            _ => 0
+        };
+    }
+    count
+}
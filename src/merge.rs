@@ -0,0 +1,215 @@
+//! Combining the aggregations of separate responses, e.g. when fanning one search out over
+//! several indices.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use {AggShape, Aggregations, Object, OwnedAggregations};
+
+/// How metric values are combined when the same metric appears on both sides of a merge.
+///
+/// Fields of stats-like metrics with an obvious combination (`count`, `sum`, `min`, `max`,
+/// `sum_of_squares` and `avg` weighted by `count`) are always merged that way; the strategy
+/// applies to single `value` metrics and any other numeric stats field.
+#[derive(Clone, Copy)]
+pub enum MergeStrategy {
+    /// Add the values together.
+    Sum,
+    /// Keep the smaller value.
+    Min,
+    /// Keep the larger value.
+    Max,
+    /// Average the values, weighted by the `doc_count` of the bucket each was found in.
+    ///
+    /// Root level metrics have no bucket and are weighted equally.
+    WeightedMean,
+    /// Combine the values with a function of `(left, left_doc_count, right, right_doc_count)`.
+    Custom(fn(f64, u64, f64, u64) -> f64)
+}
+
+impl MergeStrategy {
+    fn reduce(&self, a: f64, wa: u64, b: f64, wb: u64) -> f64 {
+        match *self {
+            MergeStrategy::Sum => a + b,
+            MergeStrategy::Min => a.min(b),
+            MergeStrategy::Max => a.max(b),
+            MergeStrategy::WeightedMean => {
+                if wa + wb == 0 {
+                    (a + b) / 2.0
+                } else {
+                    (a * wa as f64 + b * wb as f64) / (wa + wb) as f64
+                }
+            },
+            MergeStrategy::Custom(f) => f(a, wa, b, wb)
+        }
+    }
+}
+
+impl Aggregations {
+    /// Merges these aggregations with `other`.
+    ///
+    /// Buckets are matched on their `key`: the `doc_count`s of matching buckets are added and
+    /// their sub-aggregations merged recursively, while buckets found on only one side are kept
+    /// as they are. Merged bucket lists contain the buckets of `self` in their original order,
+    /// followed by those only found in `other`. Keyed buckets are matched on their name, and
+    /// single bucket aggregations such as `filter` are merged like a matching bucket.
+    pub fn merge(&self, other: &Aggregations, strategy: MergeStrategy) -> OwnedAggregations {
+        let merged = match (self.0.as_object(), other.0.as_object()) {
+            (Some(a), Some(b)) => Value::Object(merge_aggs(a, 0, b, 0, strategy)),
+            _ => self.0.clone()
+        };
+        OwnedAggregations(Aggregations(merged))
+    }
+}
+
+fn merge_aggs(a: &Object, wa: u64, b: &Object, wb: u64, strategy: MergeStrategy) -> Object {
+    let mut merged = a.clone();
+
+    for (name, bv) in b {
+        let value = match a.get(name) {
+            Some(av) => merge_agg(av, wa, bv, wb, strategy),
+            None => bv.clone()
+        };
+        merged.insert(name.clone(), value);
+    }
+
+    merged
+}
+
+fn merge_agg(a: &Value, wa: u64, b: &Value, wb: u64, strategy: MergeStrategy) -> Value {
+    let (ao, bo) = match (a.as_object(), b.as_object()) {
+        (Some(ao), Some(bo)) => (ao, bo),
+        _ => return a.clone()
+    };
+    // e.g. `filter` or `nested`, which hold sub-aggregations like a bucket does
+    if let (AggShape::SingleBucket, AggShape::SingleBucket) = (AggShape::of(ao), AggShape::of(bo)) {
+        return merge_bucket(a, b, strategy);
+    }
+
+    let mut merged = BTreeMap::new();
+    let counts = (ao.get("count").and_then(Value::as_u64), bo.get("count").and_then(Value::as_u64));
+
+    for (field, av) in ao {
+        let value = match bo.get(field) {
+            None => av.clone(),
+            Some(bv) => match &**field {
+                "buckets" => merge_buckets(av, bv, strategy),
+                "count" | "sum" | "sum_of_squares" | "doc_count" | "doc_count_error_upper_bound" |
+                "sum_other_doc_count" => add_numbers(av, bv),
+                "min" => merge_numbers(av, bv, f64::min),
+                "max" => merge_numbers(av, bv, f64::max),
+                "avg" => match counts {
+                    (Some(ca), Some(cb)) => merge_numbers(av, bv, |x, y| MergeStrategy::WeightedMean.reduce(x, ca, y, cb)),
+                    _ => merge_numbers(av, bv, |x, y| strategy.reduce(x, wa, y, wb))
+                },
+                _ => merge_numbers(av, bv, |x, y| strategy.reduce(x, wa, y, wb))
+            }
+        };
+        merged.insert(field.clone(), value);
+    }
+    for (field, bv) in bo {
+        if !merged.contains_key(field) {
+            merged.insert(field.clone(), bv.clone());
+        }
+    }
+
+    Value::Object(merged)
+}
+
+fn merge_buckets(a: &Value, b: &Value, strategy: MergeStrategy) -> Value {
+    let (aa, ba) = match (a, b) {
+        (&Value::Array(ref aa), &Value::Array(ref ba)) => (aa, ba),
+        (&Value::Object(ref ao), &Value::Object(ref bo)) => return merge_keyed_buckets(ao, bo, strategy),
+        _ => return a.clone()
+    };
+
+    let key = |bucket: &Value| bucket.as_object().and_then(|o| o.get("key")).cloned();
+
+    let mut merged: Vec<Value> = aa.iter().map(|ab| {
+        let matching = key(ab).and_then(|k| ba.iter().find(|bb| key(bb).as_ref() == Some(&k)));
+        match matching {
+            Some(bb) => merge_bucket(ab, bb, strategy),
+            None => ab.clone()
+        }
+    }).collect();
+
+    for bb in ba {
+        let k = key(bb);
+        if k.is_none() || !aa.iter().any(|ab| key(ab) == k) {
+            merged.push(bb.clone());
+        }
+    }
+
+    Value::Array(merged)
+}
+
+fn merge_keyed_buckets(a: &Object, b: &Object, strategy: MergeStrategy) -> Value {
+    let mut merged = a.clone();
+    for (key, bb) in b {
+        let bucket = match a.get(key) {
+            Some(ab) => merge_bucket(ab, bb, strategy),
+            None => bb.clone()
+        };
+        merged.insert(key.clone(), bucket);
+    }
+
+    Value::Object(merged)
+}
+
+fn merge_bucket(a: &Value, b: &Value, strategy: MergeStrategy) -> Value {
+    let (ao, bo) = match (a.as_object(), b.as_object()) {
+        (Some(ao), Some(bo)) => (ao, bo),
+        _ => return a.clone()
+    };
+
+    let wa = ao.get("doc_count").and_then(Value::as_u64).unwrap_or(0);
+    let wb = bo.get("doc_count").and_then(Value::as_u64).unwrap_or(0);
+
+    let mut merged = merge_aggs(ao, wa, bo, wb, strategy);
+    for field in &["key", "key_as_string"] {
+        if let Some(v) = ao.get(*field) {
+            merged.insert((*field).to_owned(), v.clone());
+        }
+    }
+    merged.insert("doc_count".to_owned(), add_numbers(&Value::U64(wa), &Value::U64(wb)));
+
+    Value::Object(merged)
+}
+
+/// Adds two numbers, exactly if both are integers, unless their sum overflows and is added as
+/// floats like `merge_numbers` does.
+fn add_numbers(a: &Value, b: &Value) -> Value {
+    let sum = match (a, b) {
+        (&Value::U64(x), &Value::U64(y)) => x.checked_add(y).map(Value::U64),
+        (&Value::I64(x), &Value::I64(y)) => x.checked_add(y).map(Value::I64),
+        (&Value::U64(x), &Value::I64(y)) | (&Value::I64(y), &Value::U64(x)) if x <= i64::max_value() as u64 => {
+            (x as i64).checked_add(y).map(Value::I64)
+        },
+        _ => None
+    };
+    sum.unwrap_or_else(|| merge_numbers(a, b, |x, y| x + y))
+}
+
+/// Combines two numbers with `f`, keeping integers as integers where possible. A null or
+/// non-numeric side gives way to the other one.
+fn merge_numbers<F>(a: &Value, b: &Value, f: F) -> Value
+    where F: Fn(f64, f64) -> f64
+{
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => {
+            let r = f(x, y);
+            // the largest integers round up to 2^64 and 2^63 as floats, which don't fit
+            if a.is_u64() && b.is_u64() && r >= 0.0 && r < u64::max_value() as f64 && r.fract() == 0.0 {
+                Value::U64(r as u64)
+            } else if (a.is_i64() || a.is_u64()) && (b.is_i64() || b.is_u64()) && r >= i64::min_value() as f64 &&
+                      r < i64::max_value() as f64 && r.fract() == 0.0 {
+                Value::I64(r as i64)
+            } else {
+                Value::F64(r)
+            }
+        },
+        (Some(_), None) => a.clone(),
+        (None, Some(_)) => b.clone(),
+        (None, None) => a.clone()
+    }
+}
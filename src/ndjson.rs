@@ -0,0 +1,141 @@
+//! Parsing of newline-delimited streams of search responses.
+
+use serde::de::Deserialize;
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+
+use {Error, Limit, ResponseOf, ResponseParser};
+
+/// Parses a stream with one search response per line, such as a log of captured responses.
+///
+/// Each line is parsed on its own like a body given to `parse_slice`, so a corrupt line is
+/// reported as an `Error::Line` with its line number and doesn't stop the lines after it.
+/// Lines beyond the limits of the parser are reported with the errors of those limits, as they
+/// are for bodies. Blank lines are skipped.
+pub fn responses_from_ndjson<R: BufRead, T: Deserialize>(reader: R) -> NdjsonResponses<R, T> {
+    with_parser(reader, ResponseParser::new())
+}
+
+/// Parses a newline-delimited stream, applying the limits of `parser` to each line.
+pub fn with_parser<R: BufRead, T: Deserialize>(reader: R, parser: ResponseParser) -> NdjsonResponses<R, T> {
+    NdjsonResponses {
+        reader: reader,
+        parser: parser,
+        line: 0,
+        read_error: Error::Io,
+        end_on_read_error: false,
+        ended: false,
+        _marker: PhantomData
+    }
+}
+
+/// Iterator over the responses of a newline-delimited stream, see `responses_from_ndjson`.
+pub struct NdjsonResponses<R, T> {
+    reader: R,
+    parser: ResponseParser,
+    line: usize,
+    read_error: fn(io::Error) -> Error,
+    end_on_read_error: bool,
+    ended: bool,
+    _marker: PhantomData<T>
+}
+
+impl<R: BufRead, T: Deserialize> NdjsonResponses<R, T> {
+    /// Reports the errors of the reader with `read_error` rather than as `Error::Io`, and ends
+    /// the stream after the first of them, for readers that can't go on after an error.
+    #[cfg(feature = "compression")]
+    pub(crate) fn end_on_read_error(mut self, read_error: fn(io::Error) -> Error) -> NdjsonResponses<R, T> {
+        self.read_error = read_error;
+        self.end_on_read_error = true;
+        self
+    }
+
+    /// Reads the next line without its line ending, or a `Skipped` line: one longer than the
+    /// body size limit, whose rest is skipped, or one that isn't UTF-8.
+    fn read_line(&mut self) -> io::Result<Option<Result<String, Skipped>>> {
+        let mut buf = Vec::new();
+        let read = match self.parser.body_limit() {
+            Some(max) => (&mut self.reader).take(max + 1).read_until(b'\n', &mut buf)?,
+            None => self.reader.read_until(b'\n', &mut buf)?
+        };
+        if read == 0 {
+            return Ok(None);
+        }
+
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+        } else if self.parser.body_limit().map_or(false, |max| buf.len() as u64 > max) {
+            self.skip_line()?;
+            return Ok(Some(Err(Skipped::TooLong)));
+        }
+
+        Ok(Some(String::from_utf8(buf).map_err(|e| Skipped::NotUtf8(io::Error::new(io::ErrorKind::InvalidData, e)))))
+    }
+
+    fn skip_line(&mut self) -> io::Result<()> {
+        loop {
+            let (done, used) = {
+                let available = self.reader.fill_buf()?;
+                match available.iter().position(|&b| b == b'\n') {
+                    Some(at) => (true, at + 1),
+                    None => (available.is_empty(), available.len())
+                }
+            };
+            self.reader.consume(used);
+            if done {
+                return Ok(());
+            }
+        }
+    }
+}
+
+enum Skipped {
+    TooLong,
+    NotUtf8(io::Error)
+}
+
+impl<R: BufRead, T: Deserialize> Iterator for NdjsonResponses<R, T> {
+    type Item = Result<ResponseOf<T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        loop {
+            let line = match self.read_line() {
+                Ok(Some(Ok(line))) => line,
+                Ok(Some(Err(Skipped::NotUtf8(e)))) => {
+                    self.line += 1;
+                    return Some(Err(Error::Io(e)));
+                }
+                Ok(Some(Err(Skipped::TooLong))) => {
+                    self.line += 1;
+                    return Some(Err(Error::LimitExceeded {
+                        kind: Limit::BodyBytes,
+                        limit: self.parser.body_limit().unwrap_or(0)
+                    }));
+                }
+                Ok(None) => return None,
+                Err(e) => {
+                    self.ended = self.end_on_read_error;
+                    return Some(Err((self.read_error)(e)));
+                }
+            };
+            self.line += 1;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let line_number = self.line;
+            // like any body, so the line can be an error object or fail at a path
+            return Some(self.parser.parse_slice(line.as_bytes()).map_err(|e| match e {
+                e @ Error::Json(_) | e @ Error::Parse(..) | e @ Error::Api(..) => Error::Line(line_number, Box::new(e)),
+                e => e
+            }));
+        }
+    }
+}
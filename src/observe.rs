@@ -0,0 +1,80 @@
+//! Hooks for recording metrics of the responses a `ResponseParser` parses.
+
+use serde::de::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ResponseOf;
+
+/// What is known about a response once it is parsed, see `ResponseObserver`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponseMeta {
+    /// The time Elasticsearch spent on the request, in milliseconds.
+    pub took: u64,
+    /// The total number of hits, if it was tracked.
+    pub total_hits: Option<u64>,
+    /// The number of shards that failed.
+    pub shard_failures: u32,
+    /// The size of the body, in bytes.
+    pub body_bytes: u64,
+    /// The time spent parsing the body, including reading it when it came from a reader.
+    pub parse_duration: Duration,
+    /// The number of entries of `buckets` that aren't buckets, which the rows skip.
+    pub stray_buckets: usize
+}
+
+impl ResponseMeta {
+    pub(crate) fn of<T: Deserialize>(response: &ResponseOf<T>, body_bytes: u64, started: Instant) -> ResponseMeta {
+        ResponseMeta {
+            took: response.took(),
+            total_hits: response.hits().total().map(|total| total.value()),
+            shard_failures: response._shards.failed(),
+            body_bytes: body_bytes,
+            parse_duration: started.elapsed(),
+            stray_buckets: response.aggs().stray_buckets().len()
+        }
+    }
+}
+
+/// Receives the metadata of every response parsed by a `ResponseParser` it is installed on, to
+/// feed a metrics library with.
+///
+/// Responses are observed as soon as they are parsed, before the limits of the parser are
+/// checked, so responses rejected for their size are observed too. Bodies that aren't valid
+/// responses aren't.
+pub trait ResponseObserver: Send + Sync {
+    /// Records the metadata of a parsed response.
+    fn observe(&self, meta: &ResponseMeta);
+}
+
+/// An observer that ignores every response.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NoopObserver;
+
+impl ResponseObserver for NoopObserver {
+    fn observe(&self, _meta: &ResponseMeta) {}
+}
+
+/// An observer that keeps the metadata of every response, for tests.
+#[derive(Debug, Default)]
+pub struct RecordingObserver {
+    records: Mutex<Vec<ResponseMeta>>
+}
+
+impl RecordingObserver {
+    /// Creates an observer that hasn't recorded anything.
+    pub fn new() -> RecordingObserver {
+        RecordingObserver::default()
+    }
+
+    /// Returns the metadata recorded so far, in the order the responses were parsed.
+    pub fn records(&self) -> Vec<ResponseMeta> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl ResponseObserver for RecordingObserver {
+    fn observe(&self, meta: &ResponseMeta) {
+        self.records.lock().unwrap().push(*meta);
+    }
+}
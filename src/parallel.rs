@@ -0,0 +1,99 @@
+//! Flattening the rows of large aggregations on several threads with rayon.
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::iter::plumbing::{Consumer, ProducerCallback, UnindexedConsumer};
+use serde_json::Value;
+use std::mem;
+
+use {AggregationIterator, Aggregations, Object, OwnedRow, to_owned_row};
+use intern::Interner;
+use search::aggregations::{root_buckets, root_row};
+
+impl Aggregations {
+    /// Returns a rayon `ParallelIterator` over the rows of the aggregations, flattening the
+    /// outermost buckets on different threads.
+    ///
+    /// `collect` returns the same rows as the sequential iterator, in the same order.
+    pub fn par_rows(&self) -> ParRows {
+        ParRows(self.par_rows_by_bucket())
+    }
+
+    /// Returns a rayon `IndexedParallelIterator` with the rows of each outermost bucket, in the
+    /// order of the sequential iterator.
+    ///
+    /// Unlike `par_rows`, it can be used with `enumerate` to tag rows with the index of their
+    /// bucket, or with `collect_into_vec`.
+    pub fn par_rows_by_bucket(&self) -> ParBucketRows {
+        let mut roots = Vec::new();
+        if let Some(o) = self.0.as_object() {
+            for (key, (child, buckets)) in o.iter().filter_map(|(key, child)| root_buckets(child).map(|r| (key, r))) {
+                roots.extend((0..buckets.len()).map(|i| (key, child, &buckets[i..i + 1])));
+            }
+        }
+
+        ParBucketRows { roots: roots }
+    }
+}
+
+/// Parallel iterator over the rows of the aggregations, see `Aggregations::par_rows`.
+#[derive(Debug)]
+pub struct ParRows<'a>(ParBucketRows<'a>);
+
+impl<'a> ParallelIterator for ParRows<'a> {
+    type Item = OwnedRow;
+
+    fn drive_unindexed<C: UnindexedConsumer<OwnedRow>>(self, consumer: C) -> C::Result {
+        self.0.flat_map_iter(|rows| rows).drive_unindexed(consumer)
+    }
+}
+
+/// Parallel iterator over the rows of each outermost bucket, see
+/// `Aggregations::par_rows_by_bucket`.
+#[derive(Debug)]
+pub struct ParBucketRows<'a> {
+    roots: Vec<(&'a String, &'a Object, &'a [Value])>
+}
+
+impl<'a> ParBucketRows<'a> {
+    fn into_rows(self) -> impl IndexedParallelIterator<Item = Vec<OwnedRow>> + 'a {
+        self.roots
+            .into_par_iter()
+            .map_init(Interner::new, |names, (name, agg, bucket)| bucket_rows(name, agg, bucket, names))
+    }
+}
+
+impl<'a> ParallelIterator for ParBucketRows<'a> {
+    type Item = Vec<OwnedRow>;
+
+    fn drive_unindexed<C: UnindexedConsumer<Vec<OwnedRow>>>(self, consumer: C) -> C::Result {
+        self.into_rows().drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.roots.len())
+    }
+}
+
+impl<'a> IndexedParallelIterator for ParBucketRows<'a> {
+    fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    fn drive<C: Consumer<Vec<OwnedRow>>>(self, consumer: C) -> C::Result {
+        self.into_rows().drive(consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Vec<OwnedRow>>>(self, callback: CB) -> CB::Output {
+        self.into_rows().with_producer(callback)
+    }
+}
+
+/// Flattens a single outermost bucket, lending the column names of the thread's earlier buckets
+/// to its iterator.
+fn bucket_rows<'a>(name: &'a String, agg: &'a Object, bucket: &'a [Value], names: &mut Interner<'a>) -> Vec<OwnedRow> {
+    let row = root_row(name, agg, names);
+    let mut rows = AggregationIterator::over(name, bucket, row, mem::replace(names, Interner::new()));
+    let owned = rows.by_ref().map(|row| to_owned_row(&row)).collect();
+    *names = rows.names;
+    owned
+}
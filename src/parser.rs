@@ -0,0 +1,75 @@
+//! A parser that keeps its buffers between responses.
+
+use serde::de::Deserialize;
+use serde_json::Value;
+use std::io::Read;
+
+use {Error, HitsTable, Limit, ResponseOf, ResponseParser};
+
+/// Parses many responses in a row, reusing the same buffers for each of them.
+///
+/// Responses are read into a body buffer that is cleared rather than freed between calls, and
+/// `hits_table` refills the same columns every time. Parsed responses own their data, while the
+/// table is borrowed from the parser, so it has to be dropped before the next call.
+///
+/// The buffers keep the capacity of the largest response seen; `shrink` releases it.
+#[derive(Debug, Default)]
+pub struct Parser {
+    limits: ResponseParser,
+    body: Vec<u8>,
+    table: HitsTable
+}
+
+impl Parser {
+    /// Creates a parser without any limits.
+    pub fn new() -> Parser {
+        Parser::default()
+    }
+
+    /// Creates a parser that enforces the limits of `limits` on every response.
+    pub fn with_limits(limits: ResponseParser) -> Parser {
+        Parser {
+            limits: limits,
+            ..Parser::default()
+        }
+    }
+
+    /// Reads a response body from `reader` into the body buffer and parses it.
+    ///
+    /// The reader is read from no further than the body size limit.
+    pub fn parse<T: Deserialize, R: Read>(&mut self, reader: &mut R) -> Result<ResponseOf<T>, Error> {
+        self.body.clear();
+        match self.limits.body_limit() {
+            Some(max) => {
+                reader.take(max + 1).read_to_end(&mut self.body)?;
+                if self.body.len() as u64 > max {
+                    return Err(Error::LimitExceeded {
+                        kind: Limit::BodyBytes,
+                        limit: max
+                    });
+                }
+            }
+            None => {
+                reader.read_to_end(&mut self.body)?;
+            }
+        }
+        self.limits.parse_slice(&self.body)
+    }
+
+    /// Extracts source paths of every hit into the parser's table, like `ResponseOf::hits_table`.
+    pub fn hits_table(&mut self, response: &ResponseOf<Value>, paths: &[&str]) -> &HitsTable {
+        self.table.fill(response, paths);
+        &self.table
+    }
+
+    /// Returns the body of the last response, as it was read.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Frees the memory the buffers hold on to.
+    pub fn shrink(&mut self) {
+        self.body = Vec::new();
+        self.table = HitsTable::default();
+    }
+}
@@ -0,0 +1,133 @@
+//! Dotted path lookups into document sources.
+
+use serde_json::Value;
+
+use Hit;
+
+impl Hit<Value> {
+    /// Looks up a single value of the source by path, like `user.address.city`.
+    ///
+    /// Segments are separated by dots, numeric segments index into arrays, and keys that
+    /// contain dots themselves are written in brackets, like `labels["app.kubernetes.io/name"]`.
+    /// Returns `None` if nothing is found or the path is malformed.
+    pub fn source_path(&self, path: &str) -> Option<&Value> {
+        let segments = parse_path(path)?;
+        let mut value = self.source()?;
+        for segment in &segments {
+            value = match *value {
+                Value::Object(ref o) => o.get(segment)?,
+                Value::Array(ref a) => a.get(segment.parse::<usize>().ok()?)?,
+                _ => return None
+            };
+        }
+        Some(value)
+    }
+
+    /// Looks up all values of the source that match a path, fanning out over arrays the way
+    /// Elasticsearch extracts fields.
+    ///
+    /// A non-numeric segment applied to an array is applied to each of its elements, and
+    /// arrays found at the end of the path are flattened into their elements.
+    pub fn source_path_all(&self, path: &str) -> Vec<&Value> {
+        let (segments, source) = match (parse_path(path), self.source()) {
+            (Some(segments), Some(source)) => (segments, source),
+            _ => return Vec::new()
+        };
+
+        let mut found = Vec::new();
+        collect_path(source, &segments, &mut found);
+        found
+    }
+}
+
+fn collect_path<'a>(value: &'a Value, segments: &[String], found: &mut Vec<&'a Value>) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            match *value {
+                Value::Array(ref a) => {
+                    for v in a {
+                        collect_path(v, segments, found);
+                    }
+                }
+                _ => found.push(value)
+            }
+            return;
+        }
+    };
+
+    match *value {
+        Value::Object(ref o) => {
+            if let Some(v) = o.get(segment) {
+                collect_path(v, rest, found);
+            }
+        }
+        Value::Array(ref a) => {
+            match segment.parse::<usize>() {
+                Ok(i) => {
+                    if let Some(v) = a.get(i) {
+                        collect_path(v, rest, found);
+                    }
+                }
+                Err(_) => {
+                    for v in a {
+                        collect_path(v, segments, found);
+                    }
+                }
+            }
+        }
+        _ => ()
+    }
+}
+
+/// Splits a path into its segments, unquoting bracketed keys.
+fn parse_path(path: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    if chars.peek().is_none() {
+        return Some(segments);
+    }
+
+    loop {
+        if chars.peek() == Some(&'[') {
+            chars.next();
+            let mut key = String::new();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => key.push(chars.next()?),
+                        c => key.push(c)
+                    }
+                }
+            } else {
+                while chars.peek().map_or(false, |&c| c != ']') {
+                    key.push(chars.next().unwrap());
+                }
+            }
+            if chars.next() != Some(']') {
+                return None;
+            }
+            segments.push(key);
+        } else {
+            let mut key = String::new();
+            while chars.peek().map_or(false, |&c| c != '.' && c != '[') {
+                key.push(chars.next().unwrap());
+            }
+            if key.is_empty() {
+                return None;
+            }
+            segments.push(key);
+        }
+
+        match chars.peek() {
+            None => return Some(segments),
+            Some(&'.') => {
+                chars.next();
+            }
+            Some(&'[') => (),
+            Some(_) => return None
+        }
+    }
+}
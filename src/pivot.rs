@@ -0,0 +1,62 @@
+//! Turning flattened rows into nested maps keyed by bucket keys, e.g. for charting.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use {Aggregations, RowAccess};
+
+/// An outer key -> inner key -> value map, see `Aggregations::pivot`.
+pub type Pivot = BTreeMap<String, BTreeMap<String, Value>>;
+
+quick_error! {
+    /// Problems encountered while pivoting rows.
+    #[derive(Debug, PartialEq)]
+    pub enum PivotError {
+        /// Two rows have the same pair of keys but different values.
+        Conflict(outer: String, inner: String) {
+            description("conflicting values for the same pivot cell")
+            display("conflicting values for `{}` -> `{}`", outer, inner)
+        }
+    }
+}
+
+impl Aggregations {
+    /// Pivots the rows into a map of `keys[0]` bucket keys to maps of `keys[1]` bucket keys to
+    /// the value of `value_column`.
+    ///
+    /// Bucket keys are rendered with `RowAccess::key_string`. Rows missing either key or the
+    /// value column are left out, so a missing inner key is simply absent from its map.
+    ///
+    /// Cells are placed by key, not by the position of their bucket, so the buckets of outer
+    /// keys don't need to line up: those pruned by `bucket_selector` or `bucket_sort` leave
+    /// their cells absent without shifting any others.
+    pub fn pivot(&self, keys: &[&str; 2], value_column: &str) -> Result<Pivot, PivotError> {
+        pivot_rows(self, keys, value_column)
+    }
+}
+
+/// Pivots any rows, borrowed or owned, the same way as `Aggregations::pivot`.
+pub fn pivot_rows<I>(rows: I, keys: &[&str; 2], value_column: &str) -> Result<Pivot, PivotError>
+    where I: IntoIterator,
+          I::Item: RowAccess
+{
+    let mut pivot = Pivot::new();
+
+    for row in rows {
+        let (outer, inner, value) = match (row.key_string(keys[0]), row.key_string(keys[1]), row.column(value_column)) {
+            (Some(outer), Some(inner), Some(value)) => (outer, inner, value.clone()),
+            _ => continue
+        };
+
+        let cells = pivot.entry(outer.clone()).or_insert_with(BTreeMap::new);
+        if let Some(existing) = cells.get(&inner) {
+            if *existing != value {
+                return Err(PivotError::Conflict(outer, inner));
+            }
+            continue;
+        }
+        cells.insert(inner, value);
+    }
+
+    Ok(pivot)
+}
@@ -0,0 +1,34 @@
+//! The types most uses of the crate need, to import in one go.
+//!
+//! ```
+//! use elastic_responses::prelude::*;
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! # fn main() {
+//! let mut body = Vec::new();
+//! File::open("tests/samples/aggregation_simple.json").unwrap().read_to_end(&mut body).unwrap();
+//!
+//! let response: Response = parse_slice(&body).unwrap();
+//! assert_eq!(response.hits().iter().count(), 0);
+//!
+//! let aggs: &Aggregations = response.aggs();
+//! let rows: Vec<OwnedRow> = aggs.rows_with(FlattenConfig::new().project(&["hosts", "hosts_doc_count"])).collect();
+//! assert_eq!(rows[0].get_str("hosts"), Some("0060e0634ff8"));
+//! assert_eq!(rows[0].get_f64("hosts_doc_count"), Some(49.0));
+//!
+//! match parse_slice_as::<BulkResponse>(br#"{"error": {"type": "index_not_found_exception"}, "status": 404}"#) {
+//!     Err(Error::Api(status, ref err)) => {
+//!         assert_eq!(status, Some(404));
+//!         assert_eq!(err.error_type(), "index_not_found_exception");
+//!     }
+//!     other => panic!("expected an API error, got {:?}", other)
+//! }
+//! # }
+//! ```
+
+#[allow(deprecated)]
+pub use EsResponse;
+pub use {AggError, AggValue, Aggregations, ApiError, BulkItem, BulkResponse, DocWriteResponse, Error, FlattenConfig,
+         FromHttpResponse, GetResponse, Hit, Hits, HitsEnvelope, OwnedRow, Response, ResponseError, ResponseOf,
+         ResponseParser, Row, RowAccess, parse_slice, parse_slice_as};
@@ -0,0 +1,164 @@
+//! Human oriented summaries of responses, for debugging and logging.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::cmp;
+use std::fmt;
+
+use {Aggregations, ResponseOf, RowAccess, TotalHits, TotalHitsRelation};
+
+const DEFAULT_MAX_ROWS: usize = 10;
+const MAX_CELL_WIDTH: usize = 32;
+
+/// Displays a one line summary of a response, followed by a table of its aggregations.
+///
+/// Created with `ResponseOf::pretty`.
+pub struct PrettyResponse<'a, T: 'a + Deserialize> {
+    response: &'a ResponseOf<T>,
+    max_rows: usize
+}
+
+impl<'a, T: 'a + Deserialize> PrettyResponse<'a, T> {
+    /// Sets the number of aggregation rows to show, `10` by default.
+    pub fn max_rows(mut self, max_rows: usize) -> PrettyResponse<'a, T> {
+        self.max_rows = max_rows;
+        self
+    }
+}
+
+impl<'a, T: 'a + Deserialize> fmt::Display for PrettyResponse<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r = self.response;
+        let total = match r.hits.total() {
+            Some(TotalHits { value, relation: TotalHitsRelation::Eq }) => value.to_string(),
+            Some(TotalHits { value, relation: TotalHitsRelation::Gte }) => format!(">= {}", value),
+            None => "untracked".to_owned()
+        };
+
+        write!(f, "took {}ms{}, {} hits, shards {}/{} successful, {} failed",
+               r.took,
+               if r.timed_out { " (timed out)" } else { "" },
+               total,
+               r._shards.successful,
+               r._shards.total,
+               r._shards.failed)?;
+
+        let names: Vec<&str> = r.aggregations.0.as_object()
+            .map(|o| o.keys().map(|k| &**k).collect())
+            .unwrap_or_default();
+        if !names.is_empty() {
+            write!(f, ", aggregations: {}\n", names.join(", "))?;
+            write!(f, "{}", r.aggregations.pretty().max_rows(self.max_rows))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Displays the rows of an aggregation tree as a table with aligned columns.
+///
+/// Only the first rows are shown, followed by a count of the rows left out, and long values are
+/// cut short, so even a huge tree produces a bounded amount of output. Created with
+/// `Aggregations::pretty`.
+pub struct PrettyAggregations<'a> {
+    aggs: &'a Aggregations,
+    max_rows: usize
+}
+
+impl<'a> PrettyAggregations<'a> {
+    /// Sets the number of rows to show, `10` by default.
+    pub fn max_rows(mut self, max_rows: usize) -> PrettyAggregations<'a> {
+        self.max_rows = max_rows;
+        self
+    }
+}
+
+fn cell(value: Option<&Value>) -> String {
+    let s = match value {
+        None | Some(&Value::Null) => String::new(),
+        Some(&Value::String(ref s)) => s.clone(),
+        Some(v) => v.to_string()
+    };
+
+    if s.chars().count() > MAX_CELL_WIDTH {
+        let mut cut: String = s.chars().take(MAX_CELL_WIDTH - 3).collect();
+        cut.push_str("...");
+        cut
+    } else {
+        s
+    }
+}
+
+fn write_line(f: &mut fmt::Formatter, cells: &[String], widths: &[usize]) -> fmt::Result {
+    let mut line = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if i > 0 {
+            line.push_str("  ");
+        }
+        line.push_str(cell);
+        for _ in cell.chars().count()..*width {
+            line.push(' ');
+        }
+    }
+    write!(f, "{}\n", line.trim_end())
+}
+
+impl<'a> fmt::Display for PrettyAggregations<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (columns, shown, remaining) = match self.aggs.flat_columns() {
+            Some(flat) => {
+                let shown: Vec<Vec<String>> = (0..cmp::min(flat.len, self.max_rows))
+                    .map(|i| flat.values.iter().map(|values| cell(values[i])).collect())
+                    .collect();
+                let remaining = flat.len - shown.len();
+                (flat.names, shown, remaining)
+            }
+            None => {
+                let columns = self.aggs.columns();
+                let mut rows = self.aggs.into_iter();
+                let shown: Vec<Vec<String>> = rows.by_ref()
+                    .take(self.max_rows)
+                    .map(|row| columns.iter().map(|c| cell(row.column(c))).collect())
+                    .collect();
+                (columns, shown, rows.count())
+            }
+        };
+
+        let header: Vec<String> = columns.iter().map(|c| cell(Some(&Value::String(c.clone())))).collect();
+        let widths: Vec<usize> = (0..columns.len()).map(|i| {
+            shown.iter().map(|row| row[i].chars().count()).fold(header[i].chars().count(), cmp::max)
+        }).collect();
+        let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+
+        write_line(f, &header, &widths)?;
+        write_line(f, &rule, &widths)?;
+        for row in &shown {
+            write_line(f, row, &widths)?;
+        }
+        if remaining > 0 {
+            write!(f, "... {} more rows\n", remaining)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Returns a human oriented summary of the response that implements `Display`.
+    pub fn pretty(&self) -> PrettyResponse<T> {
+        PrettyResponse {
+            response: self,
+            max_rows: DEFAULT_MAX_ROWS
+        }
+    }
+}
+
+impl Aggregations {
+    /// Returns a table of the rows that implements `Display`.
+    pub fn pretty(&self) -> PrettyAggregations {
+        PrettyAggregations {
+            aggs: self,
+            max_rows: DEFAULT_MAX_ROWS
+        }
+    }
+}
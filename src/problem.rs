@@ -0,0 +1,239 @@
+//! Collecting the problems of a response while still making the most of it, see
+//! `Aggregations::rows_lenient` and `BulkResponse::parse_lenient`.
+
+use serde_json::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use {AggShape, AggregationIterator, Aggregations, Object};
+use Row;
+use validate::segment;
+
+/// The longest snippet of the offending JSON kept with a problem, in bytes.
+const MAX_SNIPPET: usize = 120;
+
+/// What was wrong with a part of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// The aggregations root isn't an object, so there are no rows.
+    RootNotObject,
+    /// An entry of a `buckets` array isn't an object, and has no rows.
+    BucketNotObject,
+    /// A bucket's `key` is `null`; its rows are kept with a `null` key column.
+    NullKey,
+    /// An aggregation matches none of the known shapes and isn't in the rows.
+    UnknownShape,
+    /// A `buckets` field is neither an array nor an object, and has no rows.
+    MalformedBuckets,
+    /// A bulk item doesn't parse, and is left out of the items.
+    MalformedItem
+}
+
+/// A problem found in a response, with where it was and what it looked like.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    path: String,
+    kind: ProblemKind,
+    snippet: String
+}
+
+impl Problem {
+    fn new(path: String, kind: ProblemKind, value: &Value) -> Problem {
+        let mut snippet = value.to_string();
+        if snippet.len() > MAX_SNIPPET {
+            let mut end = MAX_SNIPPET;
+            while !snippet.is_char_boundary(end) {
+                end -= 1;
+            }
+            snippet.truncate(end);
+            snippet.push_str("...");
+        }
+        Problem {
+            path: path,
+            kind: kind,
+            snippet: snippet
+        }
+    }
+
+    /// Returns a JSON pointer to the offending part, such as `/per_day/buckets/3` within the
+    /// aggregations or `/items/7` within a bulk response.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns what was wrong.
+    pub fn kind(&self) -> ProblemKind {
+        self.kind
+    }
+
+    /// Returns the start of the offending JSON.
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} at `{}`: {}", self.kind, self.path, self.snippet)
+    }
+}
+
+/// The problems found while going through a response, shared with the iterator that finds them.
+///
+/// A sink handed back along with an iterator fills up as the iterator goes, so it is complete
+/// once the iterator is done.
+#[derive(Debug, Clone, Default)]
+pub struct ProblemSink(Rc<RefCell<Vec<Problem>>>);
+
+impl ProblemSink {
+    /// Returns the problems found so far.
+    pub fn problems(&self) -> Vec<Problem> {
+        self.0.borrow().clone()
+    }
+
+    /// Returns how many problems were found so far.
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Returns `true` if no problems were found so far.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    pub(crate) fn push(&self, path: String, kind: ProblemKind, value: &Value) {
+        self.0.borrow_mut().push(Problem::new(path, kind, value));
+    }
+}
+
+impl Aggregations {
+    /// Returns an Iterator over the rows of the aggregations that records what it can't make
+    /// sense of in the returned sink instead of failing or silently skipping it.
+    ///
+    /// The rows are those of the plain iterator. Buckets that aren't objects, `buckets` that are
+    /// neither an array nor an object and aggregations of unknown shapes are left out of them,
+    /// and buckets with a `null` key are kept; each is recorded as a `Problem` once the iterator
+    /// gets to it.
+    pub fn rows_lenient(&self) -> (LenientRows, ProblemSink) {
+        let sink = ProblemSink::default();
+        match self.0.as_object() {
+            Some(aggs) => {
+                for (name, agg) in aggs {
+                    check_agg(&segment("", name), agg, &sink);
+                }
+            }
+            None => sink.push(String::new(), ProblemKind::RootNotObject, &self.0)
+        }
+
+        let rows = LenientRows {
+            rows: AggregationIterator::with_names(self, Default::default()),
+            root: &self.0,
+            sink: sink.clone()
+        };
+        (rows, sink)
+    }
+}
+
+/// Iterator over the rows of aggregations that records their problems, see
+/// `Aggregations::rows_lenient`.
+#[derive(Debug)]
+pub struct LenientRows<'a> {
+    rows: AggregationIterator<'a>,
+    root: &'a Value,
+    sink: ProblemSink
+}
+
+impl<'a> Iterator for LenientRows<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Row<'a>> {
+        loop {
+            let visited = match self.rows.next_visit() {
+                Some(visited) => visited,
+                None => return None
+            };
+            match visited.1 {
+                Ok(Some(row)) => {
+                    self.check_bucket(visited.0);
+                    return Some(Row::new(row));
+                }
+                Ok(None) => self.check_bucket(visited.0),
+                Err(_) => {
+                    let path = path_to(self.root, visited.0).unwrap_or_default();
+                    self.sink.push(path, ProblemKind::BucketNotObject, visited.0);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> LenientRows<'a> {
+    /// Records the problems of a bucket the iterator went through and of its sub-aggregations,
+    /// but not of the buckets below it, which the iterator gets to on its own.
+    fn check_bucket(&self, bucket: &'a Value) {
+        let object = match bucket.as_object() {
+            Some(object) => object,
+            None => return
+        };
+        let path = || path_to(self.root, bucket).unwrap_or_default();
+        if object.get("key").map_or(false, Value::is_null) {
+            self.sink.push(segment(&path(), "key"), ProblemKind::NullKey, bucket);
+        }
+        if object.values().any(|agg| agg.as_object().map_or(false, |agg| has_problem(agg))) {
+            let path = path();
+            for (name, agg) in object.iter().filter(|&(name, _)| name != "key" && name != "meta") {
+                check_agg(&segment(&path, name), agg, &self.sink);
+            }
+        }
+    }
+}
+
+/// Returns `true` if an aggregation, or one nested in it without buckets in between, is of an
+/// unknown or malformed shape.
+fn has_problem(agg: &Object) -> bool {
+    match AggShape::of(agg) {
+        AggShape::Unknown | AggShape::MalformedBuckets => true,
+        AggShape::SingleBucket => agg.values().any(|v| v.as_object().map_or(false, has_problem)),
+        _ => false
+    }
+}
+
+/// Records an aggregation of an unknown or malformed shape at `path`, looking into single bucket
+/// aggregations but not below buckets.
+fn check_agg(path: &str, agg: &Value, sink: &ProblemSink) {
+    let object = match agg.as_object() {
+        Some(object) => object,
+        None => return
+    };
+    match AggShape::of(object) {
+        AggShape::Unknown => sink.push(path.to_owned(), ProblemKind::UnknownShape, agg),
+        AggShape::MalformedBuckets => sink.push(segment(path, "buckets"), ProblemKind::MalformedBuckets, agg),
+        AggShape::SingleBucket => {
+            for (name, child) in object.iter().filter(|&(name, _)| name != "meta") {
+                check_agg(&segment(path, name), child, sink);
+            }
+        }
+        _ => ()
+    }
+}
+
+/// Returns the JSON pointer of `target` within `value`, found by address, as problems are rare
+/// enough not to keep track of the path of every bucket.
+fn path_to(value: &Value, target: &Value) -> Option<String> {
+    if value as *const Value == target as *const Value {
+        return Some(String::new());
+    }
+    match *value {
+        Value::Object(ref object) => {
+            object.iter().filter_map(|(name, child)| path_to(child, target).map(|p| format!("{}{}", segment("", name), p))).next()
+        }
+        Value::Array(ref values) => {
+            values.iter()
+                .enumerate()
+                .filter_map(|(i, child)| path_to(child, target).map(|p| format!("/{}{}", i, p)))
+                .next()
+        }
+        _ => None
+    }
+}
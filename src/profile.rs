@@ -0,0 +1,159 @@
+//! The `profile` section of a search sent with `profile: true`.
+
+use serde::Deserialize;
+
+use {ResponseOf, RewrittenQuery};
+
+/// The profile of a search, with the time spent on each shard.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Profile {
+    #[serde(default)]
+    shards: Vec<ShardProfile>
+}
+
+impl Profile {
+    /// Returns the profiles of the shards searched.
+    pub fn shards(&self) -> &[ShardProfile] {
+        &self.shards
+    }
+}
+
+/// The profile of the search on one shard.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ShardProfile {
+    id: String,
+    #[serde(default)]
+    searches: Vec<SearchProfile>
+}
+
+impl ShardProfile {
+    /// Returns the shard as `[node][index][shard]`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the profiles of the queries run on the shard, of which there are several when
+    /// a query such as `global` runs another search.
+    pub fn searches(&self) -> &[SearchProfile] {
+        &self.searches
+    }
+}
+
+/// The profile of a query run on a shard.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchProfile {
+    #[serde(default)]
+    query: Vec<QueryProfile>,
+    #[serde(default)]
+    rewrite_time: u64
+}
+
+impl SearchProfile {
+    /// Returns the profiles of the Lucene queries the query was rewritten into.
+    pub fn queries(&self) -> &[QueryProfile] {
+        &self.query
+    }
+
+    /// Returns the time in nanoseconds spent rewriting the query.
+    pub fn rewrite_time(&self) -> u64 {
+        self.rewrite_time
+    }
+}
+
+/// The profile of a Lucene query and the queries it runs.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct QueryProfile {
+    #[serde(rename = "type")]
+    query_type: String,
+    description: RewrittenQuery,
+    time_in_nanos: u64,
+    #[serde(default)]
+    children: Vec<QueryProfile>
+}
+
+impl QueryProfile {
+    /// Returns the Lucene class of the query, like `BooleanQuery` or `TermQuery`.
+    pub fn query_type(&self) -> &str {
+        &self.query_type
+    }
+
+    /// Returns the query as Lucene writes it out.
+    pub fn rewritten(&self) -> &RewrittenQuery {
+        &self.description
+    }
+
+    /// Returns the time in nanoseconds spent on the query, its children included.
+    pub fn time_in_nanos(&self) -> u64 {
+        self.time_in_nanos
+    }
+
+    /// Returns the profiles of the queries the query runs, such as the clauses of a boolean
+    /// query.
+    pub fn children(&self) -> &[QueryProfile] {
+        &self.children
+    }
+}
+
+impl Profile {
+    /// Removes the queries nested deeper than `max` levels, counting the queries a search was
+    /// rewritten into as the first, returning how many were removed, their children included.
+    ///
+    /// The tree is walked with a stack of its own, like the aggregations are by `ResponseParser`.
+    pub(crate) fn cut_off(&mut self, max: usize) -> usize {
+        let mut removed = 0;
+        let mut stack: Vec<(usize, &mut Vec<QueryProfile>)> = self.shards
+            .iter_mut()
+            .flat_map(|shard| shard.searches.iter_mut())
+            .map(|search| (1, &mut search.query))
+            .collect();
+        while let Some((depth, queries)) = stack.pop() {
+            if depth > max {
+                removed += count_queries(queries);
+                queries.clear();
+                continue;
+            }
+            for query in queries {
+                stack.push((depth + 1, &mut query.children));
+            }
+        }
+        removed
+    }
+
+    /// Returns the depth and path of the first query nested deeper than `max` levels, like
+    /// `cut_off` counts them.
+    pub(crate) fn too_deep(&self, max: usize) -> Option<(usize, String)> {
+        let mut stack = Vec::new();
+        for (i, shard) in self.shards.iter().enumerate() {
+            for (j, search) in shard.searches.iter().enumerate() {
+                stack.push((1, format!("/profile/shards/{}/searches/{}/query", i, j), &search.query));
+            }
+        }
+        while let Some((depth, path, queries)) = stack.pop() {
+            for (i, query) in queries.iter().enumerate() {
+                let path = format!("{}/{}", path, i);
+                if depth > max {
+                    return Some((depth, path));
+                }
+                stack.push((depth + 1, format!("{}/children", path), &query.children));
+            }
+        }
+        None
+    }
+}
+
+fn count_queries(queries: &[QueryProfile]) -> usize {
+    let mut count = 0;
+    let mut stack = vec![queries];
+    while let Some(queries) = stack.pop() {
+        count += queries.len();
+        stack.extend(queries.iter().map(|query| &*query.children));
+    }
+    count
+}
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Returns the profile of the search, if it was sent with `profile: true`.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+}
@@ -0,0 +1,108 @@
+//! Formatting aggregation rows as lines of the Prometheus text exposition format.
+
+use serde_json::Value;
+
+use {Aggregations, BucketKey, RowAccess};
+
+/// How rows are turned into Prometheus metric lines, see `Aggregations::to_prometheus`.
+///
+/// ```ignore
+/// let lines = PrometheusLines::new("http_latency_ms", "latency")
+///     .label("region")
+///     .label("status")
+///     .timestamp(1528000000000)
+///     .render(response.aggs());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrometheusLines<'c> {
+    metric: &'c str,
+    value_column: &'c str,
+    labels: Vec<&'c str>,
+    timestamp: Option<i64>
+}
+
+impl<'c> PrometheusLines<'c> {
+    /// Creates lines named `metric` with the values of `value_column` and no labels.
+    pub fn new(metric: &'c str, value_column: &'c str) -> PrometheusLines<'c> {
+        PrometheusLines {
+            metric: metric,
+            value_column: value_column,
+            labels: Vec::new(),
+            timestamp: None
+        }
+    }
+
+    /// Adds the values of `column` as a label of the same name.
+    pub fn label(mut self, column: &'c str) -> PrometheusLines<'c> {
+        self.labels.push(column);
+        self
+    }
+
+    /// Appends `timestamp`, in milliseconds since the epoch, to every line.
+    pub fn timestamp(mut self, timestamp: i64) -> PrometheusLines<'c> {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Returns a line for every row of `aggs` that has a number in the value column.
+    ///
+    /// Rows without one are skipped. Labels are rendered like `BucketKey`, so numeric bucket
+    /// keys read the same as they do elsewhere, and a row without a value for a label, or with
+    /// `null`, leaves that label out.
+    pub fn render(&self, aggs: &Aggregations) -> String {
+        let mut lines = String::new();
+        for row in aggs {
+            let value = match row.column(self.value_column) {
+                Some(value) if value.is_number() => BucketKey::from(value),
+                _ => continue
+            };
+
+            lines.push_str(self.metric);
+            let labels: Vec<String> = self.labels
+                .iter()
+                .filter_map(|&label| match row.column(label) {
+                    Some(&Value::Null) | None => None,
+                    Some(value) => Some(format!("{}=\"{}\"", label, escape(&BucketKey::from(value).to_string())))
+                })
+                .collect();
+            if !labels.is_empty() {
+                lines.push('{');
+                lines.push_str(&labels.join(","));
+                lines.push('}');
+            }
+
+            lines.push_str(&format!(" {}", value));
+            if let Some(timestamp) = self.timestamp {
+                lines.push_str(&format!(" {}", timestamp));
+            }
+            lines.push('\n');
+        }
+        lines
+    }
+}
+
+impl Aggregations {
+    /// Formats the rows as Prometheus metric lines such as `metric{label="x"} 42`, with the
+    /// values of `value_column` and a label for each of `label_columns`.
+    ///
+    /// This is `PrometheusLines::render`, which can also append a timestamp to the lines.
+    pub fn to_prometheus(&self, metric: &str, value_column: &str, label_columns: &[&str]) -> String {
+        label_columns.iter()
+            .fold(PrometheusLines::new(metric, value_column), |lines, &label| lines.label(label))
+            .render(self)
+    }
+}
+
+/// Escapes a label value, in which backslashes, double quotes and line feeds must be escaped.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
@@ -0,0 +1,126 @@
+//! Response of the `_rank_eval` API.
+
+use serde::de::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use {ApiError, Hit};
+
+/// Response of a ranking evaluation.
+#[derive(Deserialize, Debug)]
+pub struct RankEvalResponse {
+    metric_score: f64,
+    #[serde(default)]
+    details: BTreeMap<String, QueryDetails>,
+    #[serde(default)]
+    failures: BTreeMap<String, Failure>
+}
+
+impl RankEvalResponse {
+    /// Returns the overall score of the evaluation.
+    pub fn metric_score(&self) -> f64 {
+        self.metric_score
+    }
+
+    /// Returns the details of each successfully evaluated query, by query id.
+    pub fn details(&self) -> &BTreeMap<String, QueryDetails> {
+        &self.details
+    }
+
+    /// Returns the details of query `id`, if it was evaluated successfully.
+    pub fn query(&self, id: &str) -> Option<&QueryDetails> {
+        self.details.get(id)
+    }
+
+    /// Returns the queries that failed to evaluate with their errors.
+    pub fn failures(&self) -> BTreeMap<&str, &ApiError> {
+        self.failures.iter().map(|(id, f)| (&**id, &f.0)).collect()
+    }
+}
+
+/// The evaluation of a single query.
+#[derive(Deserialize, Debug)]
+pub struct QueryDetails {
+    metric_score: f64,
+    #[serde(default)]
+    unrated_docs: Vec<DocRef>,
+    #[serde(default)]
+    hits: Vec<RatedHit>,
+    metric_details: Option<Value>
+}
+
+impl QueryDetails {
+    /// Returns the score of this query.
+    pub fn metric_score(&self) -> f64 {
+        self.metric_score
+    }
+
+    /// Returns the documents the query returned that have no rating.
+    pub fn unrated_docs(&self) -> &[DocRef] {
+        &self.unrated_docs
+    }
+
+    /// Returns the hits of the query with their ratings.
+    pub fn hits(&self) -> &[RatedHit] {
+        &self.hits
+    }
+
+    /// Returns the metric specific details, e.g. `relevant_docs_retrieved` for precision.
+    pub fn metric_details(&self) -> Option<&Value> {
+        self.metric_details.as_ref()
+    }
+}
+
+/// The index and id of a document.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DocRef {
+    _index: String,
+    _id: String
+}
+
+impl DocRef {
+    /// Returns the name of the index the document is in.
+    pub fn index(&self) -> &str {
+        &self._index
+    }
+
+    /// Returns the id of the document.
+    pub fn id(&self) -> &str {
+        &self._id
+    }
+}
+
+/// A hit returned by an evaluated query, with the rating it was given.
+#[derive(Deserialize, Debug)]
+pub struct RatedHit {
+    hit: Hit<Value>,
+    rating: Option<i64>
+}
+
+impl RatedHit {
+    /// Returns the hit; its source isn't included by the rank eval API.
+    pub fn hit(&self) -> &Hit<Value> {
+        &self.hit
+    }
+
+    /// Returns the rating of the hit, or `None` if it is unrated.
+    pub fn rating(&self) -> Option<i64> {
+        self.rating
+    }
+}
+
+/// A failed query, reported either as a bare error or wrapped in an `error` object.
+#[derive(Debug)]
+struct Failure(ApiError);
+
+impl Deserialize for Failure {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Failure, D::Error>
+        where D: Deserializer
+    {
+        let value = Value::deserialize(deserializer)?;
+        let error = value.find("error").unwrap_or(&value);
+        ApiError::from_value(error)
+            .map(Failure)
+            .ok_or_else(|| ::serde::de::Error::invalid_value("expected an error object with a `type`"))
+    }
+}
@@ -0,0 +1,77 @@
+//! Access to the JSON of parts of a response, e.g. to forward them verbatim.
+
+use serde_json::{self, Value};
+
+use {Aggregations, Hit};
+
+impl Aggregations {
+    /// Returns the whole aggregation tree as it was parsed.
+    pub fn raw(&self) -> &Value {
+        &self.0
+    }
+
+    /// Returns the result of the root aggregation `name` as it was parsed.
+    pub fn raw_agg(&self, name: &str) -> Option<&Value> {
+        self.0.find(name)
+    }
+
+    /// Serializes the whole aggregation tree as indented JSON.
+    pub fn to_string_pretty(&self) -> String {
+        pretty(&self.0)
+    }
+}
+
+impl Hit<Value> {
+    /// Returns the hit as JSON, with its metadata, `_source`, `fields` and anything else it
+    /// carried such as `highlight`, `sort` or `inner_hits`.
+    ///
+    /// Unlike the aggregations, the parts of a hit are parsed separately, so this builds a new
+    /// value rather than borrowing one. A missing `_score` is written as `null`, the way
+    /// Elasticsearch reports it when sorting on a field.
+    ///
+    /// Everything is cloned, which adds up for hits with large sources or vector fields; use
+    /// `into_raw` when the hit isn't needed afterwards.
+    pub fn raw(&self) -> Value {
+        let mut hit = self.extra.clone();
+        hit.insert("_index".to_owned(), Value::String(self._index.clone()));
+        if let Some(ref ty) = self._type {
+            hit.insert("_type".to_owned(), Value::String(ty.clone()));
+        }
+        hit.insert("_id".to_owned(), Value::String(self._id.clone()));
+        hit.insert("_score".to_owned(), self._score.map_or(Value::Null, Value::F64));
+        if let Some(ref source) = self._source {
+            hit.insert("_source".to_owned(), source.clone());
+        }
+        if let Some(ref fields) = self.fields {
+            hit.insert("fields".to_owned(), Value::Object(fields.values.clone()));
+        }
+        Value::Object(hit)
+    }
+
+    /// Turns the hit into JSON like `raw`, moving its parts rather than cloning them.
+    pub fn into_raw(self) -> Value {
+        let mut hit = self.extra;
+        hit.insert("_index".to_owned(), Value::String(self._index));
+        if let Some(ty) = self._type {
+            hit.insert("_type".to_owned(), Value::String(ty));
+        }
+        hit.insert("_id".to_owned(), Value::String(self._id));
+        hit.insert("_score".to_owned(), self._score.map_or(Value::Null, Value::F64));
+        if let Some(source) = self._source {
+            hit.insert("_source".to_owned(), source);
+        }
+        if let Some(fields) = self.fields {
+            hit.insert("fields".to_owned(), Value::Object(fields.values));
+        }
+        Value::Object(hit)
+    }
+
+    /// Serializes the hit as indented JSON, see `raw`.
+    pub fn to_string_pretty(&self) -> String {
+        pretty(&self.raw())
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("JSON values always serialize")
+}
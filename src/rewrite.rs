@@ -0,0 +1,214 @@
+//! Rewritten queries, as Lucene writes them out in the explanations of `_validate/query` and the
+//! descriptions of the profile of a search, like `+user:kimchy #ConstantScore(tag:rust)`.
+
+use std::ops::Range;
+
+/// A query rewritten by Elasticsearch into Lucene queries, in the format of their `toString`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RewrittenQuery(String);
+
+impl RewrittenQuery {
+    /// Wraps a rewritten query, e.g. one logged elsewhere.
+    pub fn new<S: Into<String>>(query: S) -> RewrittenQuery {
+        RewrittenQuery(query.into())
+    }
+
+    /// Returns the query as it was sent.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Splits the query into its clauses, e.g. to highlight them.
+    ///
+    /// It's best effort: the format is meant for people, not for parsing, and queries of
+    /// plugins can write anything. Clauses are only split at the top level, so groups and
+    /// wrapped queries such as `ConstantScore(...)` are single clauses that can be split again
+    /// with `RewrittenQuery::new`. A query with unbalanced brackets or quotes is `Raw`.
+    pub fn parse(&self) -> ParsedQuery {
+        match clauses(&self.0) {
+            Some(clauses) => ParsedQuery::Clauses(clauses),
+            None => ParsedQuery::Raw(&self.0)
+        }
+    }
+}
+
+/// A rewritten query split into clauses, see `RewrittenQuery::parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedQuery<'a> {
+    /// The clauses at the top level of the query, in order.
+    Clauses(Vec<Clause<'a>>),
+    /// A query that couldn't be split, as it was sent.
+    Raw(&'a str)
+}
+
+/// How a clause of a boolean query is to occur in matching documents, as written before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occur {
+    /// `+`, the clause must match and counts towards the score.
+    Must,
+    /// `#`, the clause must match but doesn't count towards the score.
+    Filter,
+    /// `-`, the clause must not match.
+    MustNot,
+    /// No prefix, the clause should match, and scores higher if it does.
+    Should
+}
+
+/// What a clause of a rewritten query is, as far as its text tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClauseKind {
+    /// A term, or a pattern such as a prefix, wildcard or fuzzy term.
+    Term,
+    /// A phrase in quotes.
+    Phrase,
+    /// A range in `[]` or `{}`.
+    Range,
+    /// A boolean query in parentheses, possibly followed by its `~` minimum of clauses that
+    /// should match.
+    Group,
+    /// A query written out by its name, such as `ConstantScore(...)` or `MatchNoDocsQuery("")`.
+    Query
+}
+
+/// A clause of a rewritten query, see `RewrittenQuery::parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause<'a> {
+    /// The bytes of the whole clause in the query, its prefix and boost included.
+    pub span: Range<usize>,
+    /// The prefix of the clause.
+    pub occur: Occur,
+    /// The field the clause is on, if it's written as `field:...`.
+    pub field: Option<&'a str>,
+    /// The clause without its prefix, field and boost, like `kimchy` or `"quick fox"`.
+    pub text: &'a str,
+    /// What the clause is.
+    pub kind: ClauseKind,
+    /// The `^` boost of the clause, if it has one.
+    pub boost: Option<f64>
+}
+
+/// Splits `query` into clauses at the top level, or returns `None` where it's unbalanced.
+fn clauses(query: &str) -> Option<Vec<Clause>> {
+    let bytes = query.as_bytes();
+    let mut clauses = Vec::new();
+    let mut at = 0;
+    loop {
+        while at < bytes.len() && bytes[at] == b' ' {
+            at += 1;
+        }
+        if at == bytes.len() {
+            break;
+        }
+        let start = at;
+        let end = clause_end(query, start)?;
+        clauses.push(clause(query, start..end)?);
+        at = end;
+    }
+    if clauses.is_empty() { None } else { Some(clauses) }
+}
+
+/// Returns the end of the clause starting at `start`: the first space outside of brackets and
+/// quotes, unless it's the space before the `[field=...]` of a query such as
+/// `FieldExistsQuery [field=user]`.
+fn clause_end(query: &str, start: usize) -> Option<usize> {
+    let bytes = query.as_bytes();
+    let mut closing = Vec::new();
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'"' => i = string_end(bytes, i)?,
+            b'(' => closing.push(b')'),
+            b'[' => closing.push(b']'),
+            b'{' => closing.push(b'}'),
+            c @ b')' | c @ b']' | c @ b'}' => {
+                // ranges may be half open, like `[1 TO 5}`
+                match closing.pop() {
+                    Some(expected) if expected == c || (c != b')' && expected != b')') => (),
+                    _ => return None
+                }
+            }
+            b' ' if closing.is_empty() => {
+                let named = !query[start..i].contains(':') && bytes[start].is_ascii_alphabetic();
+                if !(named && bytes.get(i + 1) == Some(&b'[')) {
+                    return Some(i);
+                }
+            }
+            _ => ()
+        }
+        i += 1;
+    }
+    if closing.is_empty() { Some(bytes.len()) } else { None }
+}
+
+/// Returns the index of the quote that closes the string opening at `open`.
+fn string_end(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut i = open + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'"' => return Some(i),
+            _ => ()
+        }
+        i += 1;
+    }
+    None
+}
+
+fn clause(query: &str, span: Range<usize>) -> Option<Clause> {
+    let mut body = &query[span.clone()];
+    let occur = match body.as_bytes()[0] {
+        b'+' => Occur::Must,
+        b'#' => Occur::Filter,
+        b'-' if body.len() > 1 && !body.as_bytes()[1].is_ascii_digit() => Occur::MustNot,
+        _ => Occur::Should
+    };
+    if occur != Occur::Should {
+        body = &body[1..];
+    }
+    if body.is_empty() {
+        return None;
+    }
+
+    let mut boost = None;
+    if let Some(caret) = body.rfind('^') {
+        let after = &body[caret + 1..];
+        // a boost is only ever written after the whole clause, never inside quotes or brackets
+        if let Ok(value) = after.parse::<f64>() {
+            if !after.contains(&['"', ')', ']', '}'][..]) {
+                boost = Some(value);
+                body = &body[..caret];
+            }
+        }
+    }
+
+    let (field, text) = match field_end(body) {
+        Some(colon) => (Some(&body[..colon]), &body[colon + 1..]),
+        None => (None, body)
+    };
+    if text.is_empty() {
+        return None;
+    }
+    let kind = match text.as_bytes()[0] {
+        b'"' => ClauseKind::Phrase,
+        b'[' | b'{' => ClauseKind::Range,
+        b'(' => ClauseKind::Group,
+        _ if field.is_none() && text.contains(&['(', '['][..]) => ClauseKind::Query,
+        _ => ClauseKind::Term
+    };
+
+    Some(Clause {
+        span: span,
+        occur: occur,
+        field: field,
+        text: text,
+        kind: kind,
+        boost: boost
+    })
+}
+
+/// Returns the colon that ends the field of a clause, which comes before any bracket or quote.
+fn field_end(body: &str) -> Option<usize> {
+    let end = body.find(&[':', '(', '[', '{', '"'][..])?;
+    if body.as_bytes()[end] == b':' && end > 0 { Some(end) } else { None }
+}
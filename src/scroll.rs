@@ -0,0 +1,265 @@
+//! Stitching the pages of a scroll search back together.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Borrow;
+use std::collections::{HashSet, VecDeque};
+use std::collections::vec_deque::{Drain, Iter};
+use std::marker::PhantomData;
+
+use {Error, Hit, Order, RankedHits, ResponseOf, merge_sorted_hits};
+
+/// Collects the hits of consecutive scroll responses into a single stream.
+///
+/// Pages are `push`ed as they arrive, and their hits can be read with `hits()` or removed with
+/// `drain()` at any point, so memory only grows with the hits that haven't been drained yet.
+#[derive(Debug)]
+pub struct ScrollStitcher<T: Deserialize> {
+    hits: VecDeque<Hit<T>>,
+    scroll_id: Option<String>,
+    took: u64,
+    pages: usize,
+    drained: u64,
+    finished: bool,
+    dedup: Option<DedupHits>,
+    first_total: Option<u64>,
+    latest_total: Option<u64>,
+    drift_tolerance: Option<u64>,
+    sort: Option<SortBy<T>>
+}
+
+/// The field the pages of a scroll are sorted by, with how to merge a page into the hits of the
+/// earlier ones.
+#[derive(Debug)]
+struct SortBy<T: Deserialize> {
+    path: String,
+    order: Order,
+    merge: MergePage<T>
+}
+
+type MergePage<T> = fn(&mut VecDeque<Hit<T>>, Vec<Hit<T>>, &str, Order);
+
+impl<T: Deserialize> ScrollStitcher<T> {
+    /// Creates a stitcher without any pages.
+    pub fn new() -> ScrollStitcher<T> {
+        ScrollStitcher {
+            hits: VecDeque::new(),
+            scroll_id: None,
+            took: 0,
+            pages: 0,
+            drained: 0,
+            finished: false,
+            dedup: None,
+            first_total: None,
+            latest_total: None,
+            drift_tolerance: None,
+            sort: None
+        }
+    }
+
+    /// Skips hits whose `_index` and `_id` were already pushed with an earlier page, as can
+    /// happen when scrolling an index that is written to.
+    pub fn dedup_by_id(mut self) -> Self {
+        self.dedup = Some(DedupHits::new());
+        self
+    }
+
+    /// Makes `push` fail when the total of a page differs from that of the first page by more
+    /// than `tolerance` hits.
+    pub fn strict_total(mut self, tolerance: u64) -> Self {
+        self.drift_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Adds the next page of the scroll.
+    ///
+    /// A page without any hits marks the scroll as finished.
+    ///
+    /// With `strict_total`, a page whose total drifted too far is rejected with
+    /// `Error::TotalDrift`, leaving the stitcher as it was.
+    pub fn push(&mut self, response: ResponseOf<T>) -> Result<(), Error> {
+        let ResponseOf { took, hits, _scroll_id, .. } = response;
+
+        // pages sent without `track_total_hits` don't have a total to compare
+        if let Some(total) = hits.total.map(|t| t.value()) {
+            let first = self.first_total.unwrap_or(total);
+            if let Some(tolerance) = self.drift_tolerance {
+                let drift = if total > first { total - first } else { first - total };
+                if drift > tolerance {
+                    return Err(Error::TotalDrift {
+                        first: first,
+                        latest: total
+                    });
+                }
+            }
+            self.first_total = Some(first);
+            self.latest_total = Some(total);
+        }
+
+        self.took += took;
+        self.pages += 1;
+        if _scroll_id.is_some() {
+            self.scroll_id = _scroll_id;
+        }
+        if hits.hits.is_empty() {
+            self.finished = true;
+        }
+        let page: Vec<Hit<T>> = match self.dedup {
+            Some(ref mut dedup) => dedup.filter(hits.hits).collect(),
+            None => hits.hits
+        };
+        match self.sort {
+            Some(ref sort) => (sort.merge)(&mut self.hits, page, &sort.path, sort.order),
+            None => self.hits.extend(page)
+        }
+        Ok(())
+    }
+
+    /// Returns an Iterator over the hits pushed so far that haven't been drained.
+    pub fn hits(&self) -> Iter<Hit<T>> {
+        self.hits.iter()
+    }
+
+    /// Returns an Iterator over the hits that haven't been drained, paired with their 1-based
+    /// rank in the whole scroll.
+    ///
+    /// Ranks keep counting across pages and drains: after 25 hits were drained, the first hit
+    /// left has rank 26. Duplicates skipped by `dedup_by_id` don't take up a rank.
+    pub fn ranked_hits(&self) -> RankedHits<Iter<Hit<T>>> {
+        RankedHits::new(self.hits.iter(), self.drained)
+    }
+
+    /// Removes and returns the hits pushed so far.
+    pub fn drain(&mut self) -> Drain<Hit<T>> {
+        self.drained += self.hits.len() as u64;
+        self.hits.drain(..)
+    }
+
+    /// Returns the scroll id of the latest page, to request the next one with.
+    pub fn scroll_id(&self) -> Option<&str> {
+        self.scroll_id.as_ref().map(|id| &**id)
+    }
+
+    /// Returns the sum of the `took` times of all pages.
+    pub fn took(&self) -> u64 {
+        self.took
+    }
+
+    /// Returns the number of pages pushed.
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    /// Returns `true` once a page without any hits has been pushed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the totals of the first and the latest page when they differ, as they can when
+    /// documents are written to the index during the scroll.
+    pub fn total_drift(&self) -> Option<(u64, u64)> {
+        match (self.first_total, self.latest_total) {
+            (Some(first), Some(latest)) if first != latest => Some((first, latest)),
+            _ => None
+        }
+    }
+
+    /// Returns the number of duplicate hits skipped, see `dedup_by_id`.
+    pub fn duplicates_skipped(&self) -> usize {
+        self.dedup.as_ref().map_or(0, DedupHits::duplicates_skipped)
+    }
+}
+
+impl ScrollStitcher<Value> {
+    /// Declares that the pages are each sorted by the value at the dotted `path` of the source,
+    /// as when the hits of several sliced scrolls are pushed into one stitcher.
+    ///
+    /// Each page pushed is then merged into the hits that haven't been drained yet instead of
+    /// being appended, see `merge_sorted_hits`, so `hits()` stays in order across pages. Hits
+    /// that were already drained are not looked at again.
+    pub fn sort_by_path(mut self, path: &str, order: Order) -> Self {
+        self.sort = Some(SortBy {
+            path: path.to_owned(),
+            order: order,
+            merge: merge_page
+        });
+        self
+    }
+}
+
+fn merge_page(hits: &mut VecDeque<Hit<Value>>, page: Vec<Hit<Value>>, path: &str, order: Order) {
+    let pending: Vec<Hit<Value>> = hits.drain(..).collect();
+    hits.extend(merge_sorted_hits(vec![pending, page], path, order));
+}
+
+impl<T: Deserialize> Default for ScrollStitcher<T> {
+    fn default() -> ScrollStitcher<T> {
+        ScrollStitcher::new()
+    }
+}
+
+/// Filters out hits whose `_index` and `_id` have been seen before, across any number of
+/// hit iterators.
+#[derive(Debug, Default)]
+pub struct DedupHits {
+    seen: HashSet<(String, String)>,
+    skipped: usize
+}
+
+impl DedupHits {
+    /// Creates a filter that hasn't seen any hits.
+    pub fn new() -> DedupHits {
+        DedupHits::default()
+    }
+
+    /// Records `hit` as seen, returning `false` if it had been seen before.
+    pub fn insert<T: Deserialize>(&mut self, hit: &Hit<T>) -> bool {
+        let new = self.seen.insert((hit.index().to_owned(), hit.id().to_owned()));
+        if !new {
+            self.skipped += 1;
+        }
+        new
+    }
+
+    /// Wraps an iterator of hits, or of references to hits, skipping the ones seen before.
+    pub fn filter<I, H, T>(&mut self, hits: I) -> Dedup<I::IntoIter, T>
+        where I: IntoIterator<Item = H>,
+              H: Borrow<Hit<T>>,
+              T: Deserialize
+    {
+        Dedup {
+            dedup: self,
+            hits: hits.into_iter(),
+            _marker: PhantomData
+        }
+    }
+
+    /// Returns the number of hits skipped so far.
+    pub fn duplicates_skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+/// Iterator over the hits not seen before, see `DedupHits::filter`.
+pub struct Dedup<'a, I, T> {
+    dedup: &'a mut DedupHits,
+    hits: I,
+    _marker: PhantomData<T>
+}
+
+impl<'a, I, H, T> Iterator for Dedup<'a, I, T>
+    where I: Iterator<Item = H>,
+          H: Borrow<Hit<T>>,
+          T: Deserialize
+{
+    type Item = H;
+
+    fn next(&mut self) -> Option<H> {
+        loop {
+            let hit = self.hits.next()?;
+            if self.dedup.insert(hit.borrow()) {
+                return Some(hit);
+            }
+        }
+    }
+}
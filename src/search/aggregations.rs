@@ -0,0 +1,851 @@
+//! Aggregation trees, and their rows.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::btree_map;
+use std::ops::{Deref, DerefMut};
+use std::slice::{self, Iter};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, TimeZone, Utc};
+
+use serde::de::Deserialize;
+
+use {BucketFilter, BucketKey, ColumnName, Error, GeoPoint, Object};
+use intern::Interner;
+use super::HitsEnvelope;
+
+/// Type Struct to hold a generic `serde_json::Value` tree of the Aggregation results.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Aggregations(pub(crate) Value);
+
+impl Default for Aggregations {
+    fn default() -> Aggregations {
+        Aggregations(Value::Object(BTreeMap::new()))
+    }
+}
+
+impl Aggregations {
+    /// Returns the names of all the columns that rows of these aggregations can contain, sorted
+    /// alphabetically.
+    ///
+    /// Individual rows only contain the columns for values present in their buckets, so a row
+    /// may have fewer keys than are listed here, but never any others.
+    pub fn columns(&self) -> Vec<String> {
+        self.collect_columns().1.into_iter().collect()
+    }
+
+    /// Returns the names of the bucket aggregations, whose bucket keys are stored in the column
+    /// of the same name, sorted alphabetically.
+    pub fn key_columns(&self) -> Vec<String> {
+        self.collect_columns().0.into_iter().collect()
+    }
+
+    fn collect_columns(&self) -> (BTreeSet<String>, BTreeSet<String>) {
+        self.collect_columns_with(Interner::new())
+    }
+
+    /// Returns the bucket key columns and all the columns of the rows built with `names`.
+    pub(crate) fn collect_columns_with<'a>(&'a self, mut names: Interner<'a>) -> (BTreeSet<String>, BTreeSet<String>) {
+        let mut keys = BTreeSet::new();
+        let mut columns = BTreeSet::new();
+        if let Some(o) = self.0.as_object() {
+            for (key, (child, buckets)) in o.iter().filter_map(|(key, child)| root_buckets(child).map(|r| (key, r))) {
+                // a single bucket aggregation is the bucket, which has no key
+                if child.contains_key("buckets") {
+                    keys.insert(names.borrowed(key).into_owned());
+                }
+                columns.extend(root_row(key, child, &mut names).into_iter().map(|(k, _)| k.into_owned()));
+                collect_columns(key, buckets, &mut keys, &mut columns, &mut names);
+            }
+        }
+        (keys, columns)
+    }
+
+    /// Returns the hits of every `top_hits` aggregation called `agg`, in the order of the rows.
+    ///
+    /// The hits are parsed like those of the search itself, so their `total` can be a number or
+    /// an object whichever the outer one is, see `TotalHits`.
+    pub fn top_hits<T: Deserialize>(&self, agg: &str) -> Result<Vec<HitsEnvelope<T>>, Error> {
+        let mut found = Vec::new();
+        if let Some(aggs) = self.0.as_object() {
+            collect_top_hits(aggs, agg, &mut found);
+        }
+        found.into_iter().map(|hits| Ok(::serde_json::from_value(hits.clone())?)).collect()
+    }
+
+    /// Returns `true` if there are any aggregations at all, empty or not.
+    pub fn has_aggregations(&self) -> bool {
+        self.0.as_object().map_or(false, |aggs| !aggs.is_empty())
+    }
+
+    /// Returns `true` if none of the aggregations found anything.
+    ///
+    /// Bucket aggregations are empty when all of their buckets, keyed or not, have a `doc_count`
+    /// of `0`, as do single bucket aggregations such as `filter` or `missing`. Metrics are empty
+    /// when their value is `null` or they counted no values, as they do over no documents. A
+    /// single `value` of `0`, like the `sum` of no documents, is not empty: it can't be told
+    /// apart from a real `0`. Aggregations of unknown shapes are never empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.as_object().map_or(true, |aggs| aggs.values().all(agg_is_empty))
+    }
+
+    /// Returns an Iterator over the rows of the aggregations that yields an `Err` for every
+    /// part of the tree that can't be flattened, instead of silently skipping it.
+    pub fn try_rows(&self) -> TryAggregationIterator {
+        TryAggregationIterator(AggregationIterator::new(self))
+    }
+
+    /// Returns an Iterator over the rows of the root aggregation `agg` alone, leaving the other
+    /// root aggregations unvisited.
+    ///
+    /// The rows are those of the full iterator that come from `agg`, flattened the same way.
+    /// It's an error if there is no bucket or single bucket aggregation called `agg` at the root.
+    pub fn rows_for<'a>(&'a self, agg: &str) -> Result<AggregationIterator<'a>, AggError> {
+        let aggs = match self.0.as_object() {
+            Some(aggs) => aggs,
+            None => return Err(AggError::RootNotObject)
+        };
+        for (name, child) in aggs.iter().filter(|&(name, _)| name == agg) {
+            if let Some((child, buckets)) = root_buckets(child) {
+                let mut names = Interner::new();
+                let row = root_row(name, child, &mut names);
+                return Ok(AggregationIterator::over(name, buckets, row, names));
+            }
+        }
+        Err(AggError::UnknownAggregation(agg.to_owned()))
+    }
+}
+
+impl<'a> IntoIterator for &'a Aggregations {
+    type Item = Row<'a>;
+    type IntoIter = AggregationIterator<'a>;
+
+    fn into_iter(self) -> AggregationIterator<'a> {
+        AggregationIterator::new(self)
+    }
+}
+
+/// An aggregation tree that doesn't belong to a response, such as the result of
+/// `Aggregations::merge`.
+///
+/// It dereferences to `Aggregations`, so it can be iterated and inspected the same way.
+#[derive(Debug, Clone)]
+pub struct OwnedAggregations(pub(crate) Aggregations);
+
+impl OwnedAggregations {
+    /// Returns the underlying `serde_json::Value` tree.
+    pub fn into_value(self) -> Value {
+        (self.0).0
+    }
+}
+
+impl<'a> From<&'a Aggregations> for OwnedAggregations {
+    fn from(aggs: &'a Aggregations) -> OwnedAggregations {
+        OwnedAggregations(aggs.clone())
+    }
+}
+
+impl Deref for OwnedAggregations {
+    type Target = Aggregations;
+
+    fn deref(&self) -> &Aggregations {
+        &self.0
+    }
+}
+
+impl<'a> IntoIterator for &'a OwnedAggregations {
+    type Item = Row<'a>;
+    type IntoIter = AggregationIterator<'a>;
+
+    fn into_iter(self) -> AggregationIterator<'a> {
+        AggregationIterator::new(&self.0)
+    }
+}
+
+quick_error! {
+    /// Problems encountered while flattening an aggregation tree.
+    #[derive(Debug, PartialEq)]
+    pub enum AggError {
+        /// The root of the aggregations isn't a JSON object.
+        RootNotObject {
+            description("aggregations root is not an object")
+        }
+        /// An entry in a `buckets` array isn't a JSON object.
+        BucketNotObject(agg: String) {
+            description("bucket is not an object")
+            display("bucket in aggregation `{}` is not an object", agg)
+        }
+        /// There is no bucket or single bucket aggregation of the name at the root, see
+        /// `Aggregations::rows_for`.
+        UnknownAggregation(agg: String) {
+            description("no such root bucket aggregation")
+            display("no bucket aggregation named `{}` at the root", agg)
+        }
+        /// Columns were projected that no row of the aggregations can have, see
+        /// `Aggregations::try_rows_with`.
+        UnknownColumns(unknown: Vec<String>, available: Vec<String>) {
+            description("projected columns do not exist")
+            display("no columns named {}; the rows can have {}", unknown.join(", "), available.join(", "))
+        }
+        /// An aggregation is nested in one of the same name, at the path joined by `>`, see
+        /// `DuplicateNames::Error`.
+        DuplicateName(path: String) {
+            description("aggregation nested in one of the same name")
+            display("aggregation `{}` is nested in one of the same name, so their columns collide", path)
+        }
+        /// The keys of a histogram aren't whole numbers, see `Aggregations::fill_gaps`.
+        NotEpochMillis(agg: String) {
+            description("bucket keys are not epoch milliseconds")
+            display("bucket keys of `{}` are not epoch milliseconds", agg)
+        }
+        /// Two buckets of a histogram are a distance apart in milliseconds that isn't a multiple
+        /// of the interval, also in milliseconds, see `Aggregations::fill_gaps`.
+        IrregularInterval(agg: String, gap: i64, interval: i64) {
+            description("histogram buckets are not a fixed interval apart")
+            display("buckets of `{}` are {}ms apart, which is not a multiple of the {}ms interval; \
+                     only fixed intervals are supported, not calendar intervals such as months", agg, gap, interval)
+        }
+    }
+}
+
+/// Aggregator that traverses the results from Elasticsearch's Aggregations and returns a result
+/// row by row in a table-styled fashion.
+///
+/// Rows come out depth first: the buckets of each aggregation are visited in the order of their
+/// `buckets` array, and all rows below a bucket come before those of the next bucket. Sibling
+/// bucket aggregations are visited in alphabetical order of their names, which is also the order
+/// their objects are kept in once parsed.
+///
+/// Each row holds the columns of its innermost bucket and of the buckets it is nested in, and
+/// nothing of the neighbouring buckets. Single bucket aggregations such as `filter` or `missing`
+/// below a bucket are part of it: their `doc_count` is the `{name}_doc_count` column, and their
+/// sub-aggregations are flattened as if they were the bucket's own. At the root, they are a
+/// bucket of their own without a key. Bucket keys are kept as they are, so a `null` key is a
+/// `null` column, see `FlattenConfig::skip_null_keys`.
+///
+/// Nothing is assumed about how many buckets there are: pipeline aggregations such as
+/// `bucket_selector` and `bucket_sort` can leave fewer than the `doc_count` of the parent implies,
+/// and different numbers below siblings. A bucket whose bucket sub-aggregations were all emptied
+/// that way has no rows.
+#[derive(Debug)]
+pub struct AggregationIterator<'a> {
+    /// The buckets left to visit at each level of nesting, with the columns of the bucket they are
+    /// in.
+    iter_stack: Vec<(&'a String, Iter<'a, Value>, RowMap<'a>)>,
+    pub(crate) names: Interner<'a>,
+    /// The buckets to descend into, by aggregation name, see `FlattenConfig::filter_bucket`.
+    pub(crate) filters: Vec<(String, BucketFilter)>,
+    root_error: bool
+}
+
+impl<'a> AggregationIterator<'a> {
+    fn new(a: &'a Aggregations) -> AggregationIterator<'a> {
+        AggregationIterator::with_names(a, Interner::new())
+    }
+
+    /// Creates an iterator over all the rows of `a` that builds their columns with `names`.
+    pub(crate) fn with_names(a: &'a Aggregations, mut names: Interner<'a>) -> AggregationIterator<'a> {
+        let (s, root_error) = match a.0.as_object() {
+            Some(o) => {
+                // the stack is popped from the end, so the first aggregation goes last
+                let s = o.into_iter().rev().filter_map(|(key, child)| {
+                    root_buckets(child).map(|(child, buckets)| (key, buckets.iter(), root_row(key, child, &mut names)))
+                }).collect();
+                (s, false)
+            },
+            None => (Vec::new(), true)
+        };
+
+        AggregationIterator {
+            iter_stack: s,
+            names: names,
+            filters: Vec::new(),
+            root_error: root_error
+        }
+    }
+
+    /// Creates an iterator over the rows below `buckets` only, starting from `row` and reusing the
+    /// column names of `names`.
+    pub(crate) fn over(name: &'a String, buckets: &'a [Value], row: RowMap<'a>, names: Interner<'a>) -> AggregationIterator<'a> {
+        AggregationIterator {
+            iter_stack: vec![(name, buckets.iter(), row)],
+            names: names,
+            filters: Vec::new(),
+            root_error: false
+        }
+    }
+
+    fn next_result(&mut self) -> Option<Result<Row<'a>, AggError>> {
+        if self.root_error {
+            self.root_error = false;
+            return Some(Err(AggError::RootNotObject));
+        }
+
+        loop {
+            match self.next_visit() {
+                Some((_, Ok(Some(row)))) => return Some(Ok(Row::new(row))),
+                Some((_, Ok(None))) => continue,
+                Some((_, Err(e))) => return Some(Err(e)),
+                None => return None
+            }
+        }
+    }
+
+    /// Visits the next bucket, returning it along with its row if it is the innermost bucket of
+    /// the row, or `None` if the iterator descends into its bucket sub-aggregations next.
+    pub(crate) fn next_visit(&mut self) -> Option<(&'a Value, Result<Option<RowMap<'a>>, AggError>)> {
+        loop {
+            let next = match self.iter_stack.last_mut() {
+                Some(&mut (name, ref mut buckets, ref parent)) => buckets.next().map(|b| (name, b, parent.clone())),
+                None => {
+                    debug! ("ITER: Done!");
+                    return None;
+                }
+            };
+            let (active_name, value, mut row) = match next {
+                Some(next) => next,
+                None => {
+                    debug! ("ITER: Exit!");
+                    self.iter_stack.pop();
+                    continue;
+                }
+            };
+
+            debug! ("ITER: Depth {}", self.iter_stack.len());
+            let bucket = match value.as_object() {
+                Some(bucket) => bucket,
+                None => {
+                    debug! ("ITER: Bucket in {} is not an object: {:?}", active_name, value);
+                    return Some((value, Err(AggError::BucketNotObject(active_name.clone()))));
+                }
+            };
+            if self.filters.iter().any(|&(ref agg, ref filter)| agg == active_name && !filter.matches(bucket)) {
+                debug! ("ITER: Pruned!");
+                continue;
+            }
+
+            let children = flatten_bucket(active_name, bucket, &mut row, &mut self.names);
+            if children.is_empty() {
+                debug! ("ITER: Bucketless!");
+                return Some((value, Ok(Some(row))));
+            }
+
+            // the same goes for sibling aggregations below the bucket
+            debug! ("ITER: Dive!");
+            for (key, buckets) in children.into_iter().rev() {
+                self.iter_stack.push((key, buckets.iter(), row.clone()));
+            }
+            return Some((value, Ok(None)));
+        }
+    }
+}
+
+pub(crate) type RowMap<'a> = BTreeMap<ColumnName<'a>, &'a Value>;
+
+/// The map of columns the rows of `Aggregations` were before they were `Row`s.
+#[deprecated(since = "0.2.2",
+             note = "rows are `Row`s now, read their columns with `RowAccess` and `Row::columns`; \
+                     rows dereference to this map until the next release only")]
+pub type RowData<'a> = BTreeMap<ColumnName<'a>, &'a Value>;
+
+/// A row of an aggregation tree: the columns of a bucket and of the buckets it is nested in,
+/// borrowing their names and values from the tree.
+///
+/// Columns are read through `RowAccess`, or `value` to keep the borrow of the tree rather than
+/// of the row, and listed in alphabetical order by `columns`. For code written against the
+/// `RowData` map rows used to be, a row dereferences to it for one more release.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Row<'a> {
+    columns: RowMap<'a>
+}
+
+impl<'a> Row<'a> {
+    pub(crate) fn new(columns: RowMap<'a>) -> Row<'a> {
+        Row { columns: columns }
+    }
+
+    /// Returns the value of `column`, if the row has it.
+    pub fn value(&self, column: &str) -> Option<&'a Value> {
+        self.columns.get(column).map(|v| *v)
+    }
+
+    /// Returns `true` if the row has `column`.
+    pub fn contains(&self, column: &str) -> bool {
+        self.columns.contains_key(column)
+    }
+
+    /// Returns an Iterator over the columns and their values, in alphabetical order.
+    pub fn columns<'r>(&'r self) -> RowColumns<'r, 'a> {
+        RowColumns(self.columns.iter())
+    }
+
+    /// Returns the number of columns.
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns `true` if the row has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Returns the row with column names and values of its own.
+    pub fn into_owned(self) -> OwnedRow {
+        to_owned_row(&self.columns)
+    }
+}
+
+#[allow(deprecated)]
+impl<'a> Deref for Row<'a> {
+    type Target = RowData<'a>;
+
+    fn deref(&self) -> &RowData<'a> {
+        &self.columns
+    }
+}
+
+#[allow(deprecated)]
+impl<'a> DerefMut for Row<'a> {
+    fn deref_mut(&mut self) -> &mut RowData<'a> {
+        &mut self.columns
+    }
+}
+
+impl<'a> IntoIterator for Row<'a> {
+    type Item = (ColumnName<'a>, &'a Value);
+    type IntoIter = btree_map::IntoIter<ColumnName<'a>, &'a Value>;
+
+    fn into_iter(self) -> btree_map::IntoIter<ColumnName<'a>, &'a Value> {
+        self.columns.into_iter()
+    }
+}
+
+impl<'r, 'a> IntoIterator for &'r Row<'a> {
+    type Item = (&'r ColumnName<'a>, &'r &'a Value);
+    type IntoIter = btree_map::Iter<'r, ColumnName<'a>, &'a Value>;
+
+    fn into_iter(self) -> btree_map::Iter<'r, ColumnName<'a>, &'a Value> {
+        self.columns.iter()
+    }
+}
+
+/// Iterator over the columns of a `Row`, see `Row::columns`.
+#[derive(Debug, Clone)]
+pub struct RowColumns<'r, 'a: 'r>(btree_map::Iter<'r, ColumnName<'a>, &'a Value>);
+
+impl<'r, 'a> Iterator for RowColumns<'r, 'a> {
+    type Item = (&'r str, &'a Value);
+
+    fn next(&mut self) -> Option<(&'r str, &'a Value)> {
+        self.0.next().map(|(name, value)| (&**name, *value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// A row of the aggregations that owns its column names and values.
+pub type OwnedRow = BTreeMap<String, Value>;
+
+/// Read access to the columns of a row, implemented for both the borrowed rows produced while
+/// iterating `Aggregations` and for `OwnedRow`s.
+pub trait RowAccess {
+    /// Returns the value of `column`, if the row has it.
+    fn column(&self, column: &str) -> Option<&Value>;
+
+    /// Returns the value of `column` as a number, if it is one.
+    fn get_f64(&self, column: &str) -> Option<f64> {
+        self.column(column).and_then(Value::as_f64)
+    }
+
+    /// Returns the value of `column` as a string, if it is one.
+    fn get_str(&self, column: &str) -> Option<&str> {
+        self.column(column).and_then(Value::as_str)
+    }
+
+    /// Returns the value of `column` as a geo point, in any of the formats accepted by
+    /// `GeoPoint::from_value`.
+    fn get_geopoint(&self, column: &str) -> Option<GeoPoint> {
+        self.column(column).and_then(GeoPoint::from_value)
+    }
+
+    /// Returns how much more common the bucket of the `significant_terms` or `significant_text`
+    /// aggregation `agg` is in the foreground set than in the background set, that is
+    /// `(doc_count / subset_size) / (bg_count / superset_size)`.
+    ///
+    /// Returns `None` if the row lacks any of those columns or either share would divide by `0`.
+    fn significance_lift(&self, agg: &str) -> Option<f64> {
+        let get = |suffix: &str| self.get_f64(&format!("{}_{}", agg, suffix));
+        let (doc_count, subset_size) = (get("doc_count")?, get("subset_size")?);
+        let (bg_count, superset_size) = (get("bg_count")?, get("superset_size")?);
+        if subset_size == 0.0 || superset_size == 0.0 || bg_count == 0.0 {
+            return None;
+        }
+        Some((doc_count / subset_size) / (bg_count / superset_size))
+    }
+
+    /// Returns the key of the bucket aggregation `agg` along with its type.
+    fn bucket_key(&self, agg: &str) -> Option<BucketKey> {
+        self.column(agg).map(BucketKey::from)
+    }
+
+    /// Returns the key of the bucket aggregation `agg` rendered as a string.
+    ///
+    /// The bucket's `key_as_string` is preferred when Elasticsearch provided one, as it does for
+    /// date histograms. Otherwise string keys are used as they are, and numbers and booleans are
+    /// rendered the same way they appear in JSON.
+    fn key_string(&self, agg: &str) -> Option<String> {
+        if let Some(s) = self.get_str(&format!("{}_key_as_string", agg)) {
+            return Some(s.to_owned());
+        }
+        match self.column(agg) {
+            Some(&Value::String(ref s)) => Some(s.clone()),
+            Some(&Value::U64(n)) => Some(n.to_string()),
+            Some(&Value::I64(n)) => Some(n.to_string()),
+            Some(&Value::F64(n)) => Some(n.to_string()),
+            Some(&Value::Bool(b)) => Some(b.to_string()),
+            _ => None
+        }
+    }
+
+    /// Returns the value of `column` as a UTC date.
+    ///
+    /// Numbers are taken to be milliseconds since the epoch, as date histogram keys are, and
+    /// strings are parsed as RFC 3339 dates. When the row doesn't have `column`, the
+    /// `{column}_key_as_string` column of a date histogram bucket is tried instead.
+    #[cfg(feature = "chrono")]
+    fn get_datetime(&self, column: &str) -> Option<DateTime<Utc>> {
+        let value = match self.column(column) {
+            Some(v) => v,
+            None => match self.column(&format!("{}_key_as_string", column)) {
+                Some(v) => v,
+                None => return None
+            }
+        };
+
+        let millis = match *value {
+            Value::I64(ms) => ms,
+            Value::U64(ms) if ms <= i64::max_value() as u64 => ms as i64,
+            Value::F64(ms) => return Utc.timestamp_opt((ms / 1000.0).floor() as i64, ((ms % 1000.0 + 1000.0) % 1000.0 * 1_000_000.0) as u32).single(),
+            Value::String(ref s) => return DateTime::parse_from_rfc3339(s).ok().map(|d| d.with_timezone(&Utc)),
+            _ => return None
+        };
+        Utc.timestamp_millis_opt(millis).single()
+    }
+}
+
+impl<'a> RowAccess for RowMap<'a> {
+    fn column(&self, column: &str) -> Option<&Value> {
+        self.get(column).map(|v| *v)
+    }
+}
+
+impl<'a> RowAccess for Row<'a> {
+    fn column(&self, column: &str) -> Option<&Value> {
+        self.value(column)
+    }
+}
+
+impl RowAccess for OwnedRow {
+    fn column(&self, column: &str) -> Option<&Value> {
+        self.get(column)
+    }
+}
+
+impl<'r, R: RowAccess> RowAccess for &'r R {
+    fn column(&self, column: &str) -> Option<&Value> {
+        (**self).column(column)
+    }
+}
+
+pub(crate) fn to_owned_row(row: &RowMap) -> OwnedRow {
+    row.iter().map(|(k, v)| (k.clone().into_owned(), (*v).clone())).collect()
+}
+
+fn insert_value<'a>(fieldname: &str, json_object: &'a Object, keyname: &'a str, suffix: &'static str, rowdata: &mut RowMap<'a>, names: &mut Interner<'a>) {
+    if let Some(v) = json_object.get(fieldname) {
+        let field_name = names.get(keyname, suffix);
+        debug! ("ITER: Insert value! {} {:?}", field_name, v);
+        insert_column(rowdata, names, field_name, v);
+    }
+}
+
+/// Inserts `value` into `row` as `column`, unless the iterator's projection leaves it out.
+fn insert_column<'a>(row: &mut RowMap<'a>, names: &Interner<'a>, column: ColumnName<'a>, value: &'a Value) {
+    if names.keeps(&column) {
+        row.insert(column, value);
+    }
+}
+
+/// The shapes of aggregation results that are recognized, shared by the row iterator and
+/// `Aggregations::validate`.
+pub(crate) enum AggShape<'a> {
+    /// A bucket aggregation such as `terms` or `date_histogram`.
+    Buckets(&'a Vec<Value>),
+    /// A bucket aggregation requested with `keyed: true`, which the rows don't include.
+    KeyedBuckets(&'a Object),
+    /// A `buckets` field that is neither an array nor an object.
+    MalformedBuckets,
+    /// A single value metric or pipeline aggregation, e.g. `avg` or `max_bucket`.
+    Value(&'a Value),
+    /// A multi-value metric with `stats` fields.
+    Stats,
+    /// A `geo_centroid` or `geo_bounds` metric.
+    Geo,
+    /// A `percentiles` or `percentile_ranks` metric.
+    Percentiles,
+    /// A `top_hits` metric.
+    TopHits,
+    /// A single bucket aggregation such as `filter`, `missing` or `nested`, whose
+    /// sub-aggregations sit next to its `doc_count`.
+    SingleBucket,
+    /// Anything else.
+    Unknown
+}
+
+impl<'a> AggShape<'a> {
+    pub(crate) fn of(agg: &'a Object) -> AggShape<'a> {
+        let is_number = |field: &str| agg.get(field).map_or(false, |v| v.is_number() || v.is_null());
+
+        match agg.get("buckets") {
+            Some(&Value::Array(ref buckets)) => return AggShape::Buckets(buckets),
+            Some(&Value::Object(ref buckets)) => return AggShape::KeyedBuckets(buckets),
+            Some(_) => return AggShape::MalformedBuckets,
+            None => ()
+        }
+        if let Some(v) = agg.get("value") {
+            return AggShape::Value(v);
+        }
+
+        if STATS_FIELDS.iter().any(|f| is_number(f)) {
+            AggShape::Stats
+        } else if agg.contains_key("location") || agg.contains_key("bounds") {
+            AggShape::Geo
+        } else if agg.contains_key("values") {
+            AggShape::Percentiles
+        } else if agg.get("hits").map_or(false, Value::is_object) {
+            AggShape::TopHits
+        } else if is_number("doc_count") {
+            AggShape::SingleBucket
+        } else {
+            AggShape::Unknown
+        }
+    }
+}
+
+const STATS_FIELDS: &'static [&'static str] = &["count", "min", "max", "avg", "sum"];
+
+/// Collects the `hits` of the `top_hits` aggregations called `agg` among `aggs` and below them.
+fn collect_top_hits<'a>(aggs: &'a Object, agg: &str, found: &mut Vec<&'a Value>) {
+    for (name, child) in aggs {
+        let child = match child.as_object() {
+            Some(child) => child,
+            None => continue
+        };
+        match AggShape::of(child) {
+            AggShape::TopHits if name == agg => found.extend(child.get("hits")),
+            AggShape::Buckets(buckets) => {
+                for bucket in buckets.iter().filter_map(Value::as_object) {
+                    collect_top_hits(bucket, agg, found);
+                }
+            }
+            AggShape::SingleBucket => collect_top_hits(child, agg, found),
+            _ => ()
+        }
+    }
+}
+
+/// Returns `true` if the aggregation `agg`, or the sub-aggregation of a bucket, found nothing.
+fn agg_is_empty(agg: &Value) -> bool {
+    let agg = match agg.as_object() {
+        Some(agg) => agg,
+        // the `key`, `key_as_string` and `doc_count` of a bucket
+        None => return true
+    };
+
+    match AggShape::of(agg) {
+        AggShape::Buckets(buckets) => buckets.iter().all(|b| b.as_object().map_or(false, bucket_is_empty)),
+        AggShape::KeyedBuckets(buckets) => buckets.values().all(|b| b.as_object().map_or(false, bucket_is_empty)),
+        AggShape::SingleBucket => bucket_is_empty(agg),
+        AggShape::Value(value) => value.is_null(),
+        AggShape::Stats => agg.get("count").map_or(true, |count| count.is_null() || count.as_f64() == Some(0.0)),
+        AggShape::Percentiles => match agg.get("values") {
+            Some(&Value::Object(ref values)) => values.values().all(Value::is_null),
+            Some(&Value::Array(ref values)) => values.iter().all(|p| p.find("value").map_or(true, Value::is_null)),
+            _ => true
+        },
+        AggShape::Geo => agg.get("location").or_else(|| agg.get("bounds")).map_or(true, Value::is_null),
+        AggShape::TopHits => {
+            agg.get("hits").and_then(|hits| hits.find("hits")).and_then(Value::as_array).map_or(true, |h| h.is_empty())
+        }
+        AggShape::MalformedBuckets | AggShape::Unknown => false
+    }
+}
+
+/// Returns `true` if a bucket has no documents or, if it doesn't say, none of its
+/// sub-aggregations found anything.
+fn bucket_is_empty(bucket: &Object) -> bool {
+    match bucket.get("doc_count").and_then(Value::as_u64) {
+        Some(count) => count == 0,
+        None => bucket.values().all(agg_is_empty)
+    }
+}
+
+/// Inserts the values of a single bucket into `row`, returning the child aggregations that carry
+/// `buckets` of their own so the caller can descend into them.
+pub(crate) fn flatten_bucket<'a>(active_name: &'a String, bucket: &'a Object, row: &mut RowMap<'a>, names: &mut Interner<'a>) -> Vec<(&'a String, &'a Vec<Value>)> {
+    let mut children = Vec::new();
+
+    for (key, value) in bucket {
+        if let Some(c) = value.as_object() {
+            match AggShape::of(c) {
+                //Child Aggregation
+                AggShape::Buckets(a) => {
+                    insert_totals(key, c, row, names);
+                    children.push((key, a));
+                    continue;
+                },
+                AggShape::KeyedBuckets(_) | AggShape::MalformedBuckets => continue,
+                //Single Bucket Aggregation, e.g. `filter` or `missing`, flattened into this bucket
+                AggShape::SingleBucket => {
+                    children.extend(flatten_bucket(key, c, row, names));
+                    continue;
+                },
+                //Simple Value Aggregation Name
+                AggShape::Value(v) => {
+                    debug! ("ITER: Insert value! {} {:?}", key, v);
+                    insert_column(row, names, names.borrowed(key), v);
+                    continue;
+                },
+                //Top Hits, whose envelope has a `total` and `max_score` of its own
+                AggShape::TopHits => {
+                    if let Some(hits) = c.get("hits").and_then(Value::as_object) {
+                        // a number before Elasticsearch 7, and `{"value": .., "relation": ..}` since
+                        let total = hits.get("total").and_then(|t| if t.is_object() { t.find("value") } else { Some(t) });
+                        if let Some(total) = total {
+                            let column = names.get(key, "total");
+                            insert_column(row, names, column, total);
+                        }
+                        insert_value("max_score", hits, key, "max_score", row, names);
+                    }
+                    continue;
+                },
+                _ => ()
+            }
+            //Stats fields
+            for field in &["count", "min", "max", "avg", "sum", "sum_of_squares", "variance", "std_deviation"] {
+                insert_value(field, c, key, field, row, names);
+            }
+
+            if let Some(child_values) = c.get("std_deviation_bounds").and_then(Value::as_object) {
+                insert_value("upper", child_values, key, "std_deviation_bounds_upper", row, names);
+                insert_value("lower", child_values, key, "std_deviation_bounds_lower", row, names);
+            }
+
+            //Geo fields
+            insert_value("location", c, key, "location", row, names);
+            if let Some(bounds) = c.get("bounds").and_then(Value::as_object) {
+                insert_value("top_left", bounds, key, "top_left", row, names);
+                insert_value("bottom_right", bounds, key, "bottom_right", row, names);
+            }
+        }
+
+        if key == "key" {
+            //Bucket Aggregation Name
+            debug! ("ITER: Insert bucket! {} {:?}", active_name, value);
+            insert_column(row, names, names.borrowed(active_name), value);
+        } else if key == "key_as_string" {
+            //Formatted Bucket Aggregation Name
+            debug! ("ITER: Insert bucket string! {} {:?}", active_name, value);
+            let column = names.get(active_name, "key_as_string");
+            insert_column(row, names, column, value);
+        } else if key == "doc_count" {
+            //Bucket Aggregation Count
+            debug! ("ITER: Insert bucket count! {} {:?}", active_name, value);
+            let column = names.get(active_name, "doc_count");
+            insert_column(row, names, column, value);
+        } else if (key == "score" || key == "bg_count") && value.is_number() {
+            //Significant Terms Bucket Score and Background Count
+            let suffix = if key == "score" { "score" } else { "bg_count" };
+            let column = names.get(active_name, suffix);
+            insert_column(row, names, column, value);
+        }
+    }
+
+    children
+}
+
+/// Returns the outermost aggregation `agg` as an object with the buckets its rows descend from:
+/// those of a bucket aggregation, or the aggregation itself for a single bucket one such as
+/// `filter`. Other aggregations have no rows.
+pub(crate) fn root_buckets<'a>(agg: &'a Value) -> Option<(&'a Object, &'a [Value])> {
+    let object = agg.as_object()?;
+    match AggShape::of(object) {
+        AggShape::Buckets(buckets) => Some((object, buckets)),
+        AggShape::SingleBucket => Some((object, slice::from_ref(agg))),
+        _ => None
+    }
+}
+
+/// Returns the row the rows of the outermost bucket aggregation `name` start from.
+pub(crate) fn root_row<'a>(name: &'a String, agg: &'a Object, names: &mut Interner<'a>) -> RowMap<'a> {
+    let mut row = RowMap::new();
+    insert_totals(name, agg, &mut row, names);
+    row
+}
+
+/// Inserts the sizes of the foreground and background sets of a `significant_terms` or
+/// `significant_text` aggregation, its `doc_count` and `bg_count`, into the rows of its buckets.
+fn insert_totals<'a>(name: &'a String, agg: &'a Object, row: &mut RowMap<'a>, names: &mut Interner<'a>) {
+    if agg.contains_key("bg_count") {
+        insert_value("doc_count", agg, name, "subset_size", row, names);
+        insert_value("bg_count", agg, name, "superset_size", row, names);
+    }
+}
+
+/// Collects the bucket aggregation names below `buckets` into `keys`, and their column names and
+/// those of `buckets` into `columns`.
+fn collect_columns<'a>(active_name: &'a String, buckets: &'a [Value], keys: &mut BTreeSet<String>, columns: &mut BTreeSet<String>, names: &mut Interner<'a>) {
+    for bucket in buckets.iter().filter_map(Value::as_object) {
+        let mut row = BTreeMap::new();
+        let children = flatten_bucket(active_name, bucket, &mut row, names);
+        columns.extend(row.into_iter().map(|(k, _)| k.into_owned()));
+        for (key, child) in children {
+            keys.insert(names.borrowed(key).into_owned());
+            collect_columns(key, child, keys, columns, names);
+        }
+    }
+}
+
+impl<'a> Iterator for AggregationIterator<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Row<'a>> {
+        loop {
+            match self.next_result() {
+                Some(Ok(row)) => return Some(row),
+                Some(Err(e)) => {
+                    debug! ("ITER: Skipping: {}", e);
+                    continue;
+                },
+                None => return None
+            }
+        }
+    }
+}
+
+/// Aggregator that yields each row of the aggregations as a `Result`, reporting malformed parts
+/// of the tree as an `AggError` rather than skipping them.
+#[derive(Debug)]
+pub struct TryAggregationIterator<'a>(AggregationIterator<'a>);
+
+impl<'a> Iterator for TryAggregationIterator<'a> {
+    type Item = Result<Row<'a>, AggError>;
+
+    fn next(&mut self) -> Option<Result<Row<'a>, AggError>> {
+        self.0.next_result()
+    }
+}
+
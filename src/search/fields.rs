@@ -0,0 +1,208 @@
+//! Deserializing documents from the `fields` of hits, as returned by the retrieve fields API.
+
+use serde::de::{self, Deserialize, Error as DeError};
+use serde::de::value::ValueDeserializer;
+use serde_json::{self, Value};
+use std::collections::BTreeMap;
+
+use {Error, Object};
+use super::Hit;
+use super::partial::MissingField;
+
+impl<T: Deserialize> Hit<T> {
+    /// Deserializes the hit's `fields` section into `U`, as the document representation of
+    /// searches that request `fields` rather than `_source`.
+    ///
+    /// Elasticsearch returns every field as an array of values, keyed by its full dotted name
+    /// like `host.name`, with dates formatted as requested. A single element array is unwrapped
+    /// for a field of `U` that isn't a sequence, so `"bytes": [512]` can be read into a `u64`
+    /// while `"tags": ["prod"]` is still read into a `Vec<String>`. The same goes for the fields
+    /// of the objects of `nested` fields, which are returned as arrays of objects themselves.
+    /// Fields of `U` that are `Option`s are `None` when the hit doesn't have them, and a hit
+    /// without `fields` is treated like one with no fields at all.
+    pub fn fields_doc<U: Deserialize>(&self) -> Result<U, Error> {
+        let empty = BTreeMap::new();
+        let fields = self.fields.as_ref().map_or(&empty, |f| &f.values);
+        Ok(U::deserialize(&mut FieldsMap(fields))?)
+    }
+}
+
+/// Deserializes the `fields` section of a hit, which is always an object.
+struct FieldsMap<'v>(&'v Object);
+
+impl<'v> de::Deserializer for FieldsMap<'v> {
+    type Error = serde_json::Error;
+
+    fn deserialize<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        visitor.visit_map(UnwrappingMap {
+            entries: self.0.iter(),
+            value: None
+        })
+    }
+
+    forward_to_deserialize! {
+        bool usize u8 u16 u32 u64 isize i8 i16 i32 i64 f32 f64 char str string
+        unit option seq seq_fixed_size bytes map unit_struct newtype_struct tuple_struct
+        struct struct_field tuple enum ignored_any
+    }
+}
+
+/// Deserializes a borrowed `Value`, unwrapping single element arrays for anything but a sequence.
+struct Unwrapping<'v>(&'v Value);
+
+impl<'v> Unwrapping<'v> {
+    /// Returns the only element of an array of one, and the value itself otherwise.
+    fn single(&self) -> Unwrapping<'v> {
+        match *self.0 {
+            Value::Array(ref values) if values.len() == 1 => Unwrapping(&values[0]),
+            _ => Unwrapping(self.0)
+        }
+    }
+}
+
+/// Implements the hints of `Deserializer` that don't ask for a sequence by deserializing the
+/// only element of an array of one.
+macro_rules! unwrap_single {
+    ($($method:ident),*) => {
+        $(
+            fn $method<V: de::Visitor>(&mut self, visitor: V) -> Result<V::Value, serde_json::Error> {
+                de::Deserializer::deserialize(&mut self.single(), visitor)
+            }
+        )*
+    }
+}
+
+impl<'v> de::Deserializer for Unwrapping<'v> {
+    type Error = serde_json::Error;
+
+    fn deserialize<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        match *self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::I64(n) => visitor.visit_i64(n),
+            Value::U64(n) => visitor.visit_u64(n),
+            Value::F64(n) => visitor.visit_f64(n),
+            Value::String(ref s) => visitor.visit_str(s),
+            Value::Array(ref values) => {
+                visitor.visit_seq(UnwrappingSeq {
+                    values: values.iter(),
+                    len: values.len()
+                })
+            }
+            Value::Object(ref object) => {
+                visitor.visit_map(UnwrappingMap {
+                    entries: object.iter(),
+                    value: None
+                })
+            }
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        // the value itself is left as it is, for the type inside the `Option` to unwrap or not
+        match *self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(&mut self, _name: &'static str, mut visitor: V) -> Result<V::Value, serde_json::Error>
+        where V: de::Visitor
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V>(&mut self, _name: &'static str, visitor: V) -> Result<V::Value, serde_json::Error>
+        where V: de::Visitor
+    {
+        de::Deserializer::deserialize(&mut self.single(), visitor)
+    }
+
+    fn deserialize_struct<V>(&mut self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, serde_json::Error>
+        where V: de::Visitor
+    {
+        de::Deserializer::deserialize(&mut self.single(), visitor)
+    }
+
+    fn deserialize_enum<V>(&mut self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, serde_json::Error>
+        where V: de::EnumVisitor
+    {
+        let value = self.single().0.clone();
+        de::Deserializer::deserialize_enum(&mut serde_json::value::Deserializer::new(value), name, variants, visitor)
+    }
+
+    unwrap_single! {
+        deserialize_bool, deserialize_usize, deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64,
+        deserialize_isize, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64, deserialize_f32,
+        deserialize_f64, deserialize_char, deserialize_str, deserialize_string, deserialize_unit, deserialize_map
+    }
+
+    forward_to_deserialize! {
+        seq seq_fixed_size bytes tuple_struct struct_field tuple ignored_any
+    }
+}
+
+struct UnwrappingSeq<'v> {
+    values: ::std::slice::Iter<'v, Value>,
+    len: usize
+}
+
+impl<'v> de::SeqVisitor for UnwrappingSeq<'v> {
+    type Error = serde_json::Error;
+
+    fn visit<T: Deserialize>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        match self.values.next() {
+            Some(value) => {
+                self.len -= 1;
+                T::deserialize(&mut Unwrapping(value)).map(Some)
+            }
+            None => Ok(None)
+        }
+    }
+
+    fn end(&mut self) -> Result<(), serde_json::Error> {
+        match self.len {
+            0 => Ok(()),
+            remaining => Err(serde_json::Error::invalid_length(remaining))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+struct UnwrappingMap<'v> {
+    entries: ::std::collections::btree_map::Iter<'v, String, Value>,
+    value: Option<&'v Value>
+}
+
+impl<'v> de::MapVisitor for UnwrappingMap<'v> {
+    type Error = serde_json::Error;
+
+    fn visit_key<K: Deserialize>(&mut self) -> Result<Option<K>, serde_json::Error> {
+        let (key, value) = match self.entries.next() {
+            Some(entry) => entry,
+            None => return Ok(None)
+        };
+        self.value = Some(value);
+        K::deserialize(&mut ValueDeserializer::<serde_json::Error>::into_deserializer(key.as_str())).map(Some)
+    }
+
+    fn visit_value<V: Deserialize>(&mut self) -> Result<V, serde_json::Error> {
+        let value = self.value.take().ok_or_else(|| serde_json::Error::custom("value without a key"))?;
+        V::deserialize(&mut Unwrapping(value))
+    }
+
+    fn end(&mut self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+
+    fn missing_field<V: Deserialize>(&mut self, field: &'static str) -> Result<V, serde_json::Error> {
+        V::deserialize(&mut MissingField(field))
+    }
+}
@@ -0,0 +1,691 @@
+//! Search responses, with their hits and aggregations.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError, MapVisitor, Visitor};
+use serde::de::impls::IgnoredAny;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::slice::Iter;
+
+use {Clusters, Error, Object, Profile, Shards};
+#[cfg(feature = "cbor")]
+use {ResponseError, http};
+#[cfg(feature = "simd-json")]
+use simd;
+use self::aggregations::Aggregations;
+
+pub mod aggregations;
+mod fields;
+mod msearch;
+mod partial;
+mod score;
+mod size;
+mod vector;
+
+pub use self::msearch::{MsearchParts, MsearchResponse, MsearchResponseOf, MsearchSlot, MsearchSlots, PushSlot};
+pub use self::score::{HitsAboveScore, ScoreStats};
+pub use self::size::HitSizeStats;
+
+/// How the `value` of `TotalHits` relates to the real number of matching documents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TotalHitsRelation {
+    /// The total is exact.
+    Eq,
+    /// The total is a lower bound, as reported by Elasticsearch 7+ when counting stopped early.
+    Gte
+}
+
+/// The total number of hits matching a search.
+///
+/// Parses both the plain number used before Elasticsearch 7 and the newer
+/// `{"value": .., "relation": ..}` object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TotalHits {
+    pub(crate) value: u64,
+    pub(crate) relation: TotalHitsRelation
+}
+
+impl TotalHits {
+    /// The number of matching documents, or the lower bound on it.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Whether `value` is exact or a lower bound.
+    pub fn relation(&self) -> TotalHitsRelation {
+        self.relation
+    }
+}
+
+impl Deserialize for TotalHits {
+    fn deserialize<D>(deserializer: &mut D) -> Result<TotalHits, D::Error>
+        where D: Deserializer
+    {
+        struct TotalHitsVisitor;
+
+        impl Visitor for TotalHitsVisitor {
+            type Value = TotalHits;
+
+            fn visit_u64<E>(&mut self, value: u64) -> Result<TotalHits, E>
+                where E: DeError
+            {
+                Ok(TotalHits { value: value, relation: TotalHitsRelation::Eq })
+            }
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> Result<TotalHits, V::Error>
+                where V: MapVisitor
+            {
+                let mut value = None;
+                let mut relation = TotalHitsRelation::Eq;
+
+                while let Some(key) = visitor.visit_key::<String>()? {
+                    match &*key {
+                        "value" => value = Some(visitor.visit_value()?),
+                        "relation" => {
+                            relation = match &*visitor.visit_value::<String>()? {
+                                "eq" => TotalHitsRelation::Eq,
+                                "gte" => TotalHitsRelation::Gte,
+                                other => return Err(V::Error::invalid_value(other))
+                            }
+                        },
+                        _ => {
+                            visitor.visit_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                visitor.end()?;
+
+                let value = match value {
+                    Some(value) => value,
+                    None => visitor.missing_field("value")?
+                };
+
+                Ok(TotalHits { value: value, relation: relation })
+            }
+        }
+
+        deserializer.deserialize(TotalHitsVisitor)
+    }
+}
+
+/// Where a page of hits sits within the full result set, see `ResponseOf::page_info`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageInfo {
+    /// The total number of hits, which may be a lower bound.
+    pub total: u64,
+    /// The number of hits on this page.
+    pub returned: u64,
+    /// Whether requesting the next page could return more hits.
+    pub has_more: bool,
+    /// The `from` to request the next page with, if there is one.
+    pub next_from: Option<u64>
+}
+
+/// Iterator over hits and their 1-based rank, see `ResponseOf::ranked_hits`.
+#[derive(Debug, Clone)]
+pub struct RankedHits<I> {
+    hits: I,
+    rank: u64
+}
+
+impl<I> RankedHits<I> {
+    /// Ranks `hits` as if `from` hits came before them.
+    pub(crate) fn new(hits: I, from: u64) -> RankedHits<I> {
+        RankedHits {
+            hits: hits,
+            rank: from
+        }
+    }
+}
+
+impl<'a, T: Deserialize + 'a, I: Iterator<Item = &'a Hit<T>>> Iterator for RankedHits<I> {
+    type Item = (u64, &'a Hit<T>);
+
+    fn next(&mut self) -> Option<(u64, &'a Hit<T>)> {
+        let hit = self.hits.next()?;
+        self.rank += 1;
+        Some((self.rank, hit))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.hits.size_hint()
+    }
+}
+
+impl<'a, T: Deserialize + 'a, I: ExactSizeIterator<Item = &'a Hit<T>>> ExactSizeIterator for RankedHits<I> {}
+
+/// The envelope of a page of hits: the `hits` of a search, and those of an inner hits or
+/// `top_hits` aggregation, with their sources deserialized to type `T` or `serde_json::Value`.
+///
+/// The `total` is read the way of the Elasticsearch version that sent it, see `TotalHits`, so
+/// code written against one of the envelopes works against all three.
+#[derive(Deserialize, Debug)]
+pub struct HitsEnvelope<T: Deserialize> {
+    pub(crate) total: Option<TotalHits>,
+    pub(crate) max_score: Option<f64>,
+    pub(crate) hits: Vec<Hit<T>>,
+    #[serde(skip_deserializing)]
+    pub(crate) missing: bool
+}
+
+/// The envelope of the hits of a search, by the name it had before inner and top hits shared it.
+pub type Hits<T> = HitsEnvelope<T>;
+
+impl<T: Deserialize> HitsEnvelope<T> {
+    /// The hits of a response without a `hits` object, which don't allocate.
+    fn missing() -> HitsEnvelope<T> {
+        HitsEnvelope {
+            total: None,
+            max_score: None,
+            hits: Vec::new(),
+            missing: true
+        }
+    }
+
+    /// Returns the total number of hits matching the search.
+    ///
+    /// It's `None` when the search was sent with `track_total_hits: false`.
+    pub fn total(&self) -> Option<TotalHits> {
+        self.total
+    }
+
+    /// Returns the total number of hits only if it's exact.
+    ///
+    /// With `track_total_hits` set to a number, Elasticsearch stops counting at that number and
+    /// reports it with `relation: gte`, in which case this is `None`.
+    pub fn exact_total(&self) -> Option<u64> {
+        match self.total {
+            Some(TotalHits { value, relation: TotalHitsRelation::Eq }) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Returns the highest score of any hit, if the hits were scored.
+    pub fn max_score(&self) -> Option<f64> {
+        self.max_score
+    }
+
+    /// Returns the hits on this page.
+    pub fn hits(&self) -> &[Hit<T>] {
+        &self.hits
+    }
+
+    /// Returns an Iterator over the hits on this page.
+    pub fn iter(&self) -> Iter<Hit<T>> {
+        // JPG http://stackoverflow.com/q/40006219/155423
+        self.hits.iter()
+    }
+
+    /// Returns the number of hits on this page.
+    pub fn len(&self) -> usize {
+        self.hits.len()
+    }
+
+    /// Returns `true` if there are no hits on this page.
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+}
+
+impl<'a, T: Deserialize> IntoIterator for &'a HitsEnvelope<T> {
+    type Item = &'a Hit<T>;
+    type IntoIter = Iter<'a, Hit<T>>;
+
+    fn into_iter(self) -> Iter<'a, Hit<T>> {
+        self.iter()
+    }
+}
+
+/// A single search result: the metadata of a matching document, and its `_source` deserialized
+/// to type `T`.
+#[derive(Debug)]
+pub struct Hit<T: Deserialize> {
+    pub(crate) _index: String,
+    pub(crate) _type: Option<String>,
+    pub(crate) _id: String,
+    pub(crate) _score: Option<f64>,
+    pub(crate) _source: Option<T>,
+    pub(crate) fields: Option<Fields>,
+    /// Everything else the hit carries, such as `highlight` or `sort`.
+    pub(crate) extra: Object
+}
+
+impl<T: Deserialize> Deserialize for Hit<T> {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Hit<T>, D::Error>
+        where D: Deserializer
+    {
+        struct HitVisitor<T>(PhantomData<T>);
+
+        impl<T: Deserialize> Visitor for HitVisitor<T> {
+            type Value = Hit<T>;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> Result<Hit<T>, V::Error>
+                where V: MapVisitor
+            {
+                let (mut index, mut ty, mut id) = (None, None, None);
+                let (mut score, mut source, mut fields) = (None, None, None);
+                let mut extra = Object::new();
+
+                while let Some(key) = visitor.visit_key::<String>()? {
+                    match &*key {
+                        "_index" => index = Some(visitor.visit_value()?),
+                        "_type" => ty = visitor.visit_value()?,
+                        "_id" => id = Some(visitor.visit_value()?),
+                        "_score" => score = visitor.visit_value()?,
+                        "_source" => source = visitor.visit_value()?,
+                        "fields" => fields = visitor.visit_value()?,
+                        _ => {
+                            let value = visitor.visit_value()?;
+                            extra.insert(key, value);
+                        }
+                    }
+                }
+                visitor.end()?;
+
+                let index = match index {
+                    Some(index) => index,
+                    None => visitor.missing_field("_index")?
+                };
+                let id = match id {
+                    Some(id) => id,
+                    None => visitor.missing_field("_id")?
+                };
+
+                Ok(Hit {
+                    _index: index,
+                    _type: ty,
+                    _id: id,
+                    _score: score,
+                    _source: source,
+                    fields: fields,
+                    extra: extra
+                })
+            }
+        }
+
+        deserializer.deserialize_map(HitVisitor(PhantomData))
+    }
+}
+
+impl<T: Deserialize> Hit<T> {
+    /// Returns the name of the index the document is in.
+    pub fn index(&self) -> &str {
+        &self._index
+    }
+
+    /// Returns the id of the document.
+    pub fn id(&self) -> &str {
+        &self._id
+    }
+
+    /// Returns the mapping type of the document, if it has one that matters.
+    ///
+    /// Elasticsearch 6 sends the type of the document, 7 always sends `_doc` as types are gone,
+    /// and 8 leaves it out, so `None` is returned for both `_doc` and no type at all.
+    pub fn doc_type(&self) -> Option<&str> {
+        self.raw_type().filter(|ty| *ty != "_doc")
+    }
+
+    /// Returns the `_type` of the hit exactly as it was sent, including `_doc`.
+    pub fn raw_type(&self) -> Option<&str> {
+        self._type.as_ref().map(|ty| &**ty)
+    }
+
+    /// Returns the relevance score of the hit, which is absent when sorting on a field.
+    pub fn score(&self) -> Option<f64> {
+        self._score
+    }
+
+    /// Returns the document source, unless `_source` was disabled or filtered out entirely.
+    pub fn source(&self) -> Option<&T> {
+        self._source.as_ref()
+    }
+
+    /// Returns the value of `name` in the hit's `fields` section, as requested with
+    /// `stored_fields`, `docvalue_fields` or `script_fields`.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.fields.as_ref().and_then(|f| f.values.get(name))
+    }
+
+    /// Returns all values of `name` in the hit's `fields` section.
+    ///
+    /// Elasticsearch always returns fields as arrays; this is empty if the hit has no value
+    /// for the field.
+    pub fn field_values(&self, name: &str) -> &[Value] {
+        match self.field(name) {
+            Some(&Value::Array(ref values)) => values,
+            Some(value) => ::std::slice::from_ref(value),
+            None => &[]
+        }
+    }
+
+    /// Returns the first value of `name` in the hit's `fields` section.
+    pub fn field_first(&self, name: &str) -> Option<&Value> {
+        self.field_values(name).first()
+    }
+
+    /// Returns the first value of `name` in the hit's `fields` section, if it is a string.
+    ///
+    /// Dates requested with a `format` in `docvalue_fields` are returned as strings too.
+    pub fn field_first_str(&self, name: &str) -> Option<&str> {
+        self.field_first(name).and_then(Value::as_str)
+    }
+
+    /// Deserializes the output of script field `name` into `U`.
+    ///
+    /// Elasticsearch wraps a single script result in an array, so the only element of an array
+    /// is tried as `U` first, and the array as it is only if `U` rejects the element. A script
+    /// returning an object can be read as a struct, and one returning a list of numbers as a
+    /// `Vec`, even a list of one. A `U` that accepts both, such as `Value`, gets the element.
+    pub fn script_field<U: Deserialize>(&self, name: &str) -> Result<Option<U>, Error> {
+        let value = match self.field(name) {
+            Some(value) => value,
+            None => return Ok(None)
+        };
+
+        if let Value::Array(ref values) = *value {
+            if values.len() == 1 {
+                if let Ok(single) = serde_json::from_value(values[0].clone()) {
+                    return Ok(Some(single));
+                }
+            }
+        }
+        Ok(Some(serde_json::from_value(value.clone())?))
+    }
+
+    /// Returns the slots of the documents in a percolate query that this stored query matched.
+    ///
+    /// Only present for hits of a percolate query.
+    pub fn percolator_slots(&self) -> Option<&[u64]> {
+        self.fields.as_ref().and_then(|f| f.percolator_slots.as_ref()).map(|s| &**s)
+    }
+
+    /// Parses the inner hits `name` of the hit, as requested with `inner_hits` on a `nested`,
+    /// `has_child` or `has_parent` query or with `collapse`, or `None` if the hit has none by
+    /// that name.
+    ///
+    /// The sources of the inner hits are deserialized to `U`, which needn't be the type of the
+    /// outer hits, e.g. for the comments nested in a post.
+    pub fn inner_hits<U: Deserialize>(&self, name: &str) -> Result<Option<HitsEnvelope<U>>, Error> {
+        match self.extra.get("inner_hits").and_then(|inner| inner.find(name)).and_then(|inner| inner.find("hits")) {
+            Some(hits) => Ok(Some(serde_json::from_value(hits.clone())?)),
+            None => Ok(None)
+        }
+    }
+}
+
+/// The `fields` section of a hit.
+#[derive(Debug)]
+pub(crate) struct Fields {
+    pub(crate) values: Object,
+    pub(crate) percolator_slots: Option<Vec<u64>>
+}
+
+impl Deserialize for Fields {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Fields, D::Error>
+        where D: Deserializer
+    {
+        let values = Object::deserialize(deserializer)?;
+        let percolator_slots = values.get("_percolator_document_slot")
+            .and_then(Value::as_array)
+            .map(|slots| slots.iter().filter_map(Value::as_u64).collect());
+
+        Ok(Fields {
+            values: values,
+            percolator_slots: percolator_slots
+        })
+    }
+}
+
+/// Main `struct` of the crate, provides access to the `hits` and `aggs` iterators.
+#[derive(Deserialize, Debug)]
+pub struct ResponseOf<T: Deserialize> {
+    pub(crate) took: u64,
+    pub(crate) timed_out: bool,
+    pub(crate) _shards: Shards,
+    #[serde(default = "HitsEnvelope::missing")]
+    pub(crate) hits: HitsEnvelope<T>,
+    #[serde(default)]
+    pub(crate) aggregations: Aggregations,
+    pub(crate) status: Option<u16>,
+    pub(crate) _scroll_id: Option<String>,
+    pub(crate) _clusters: Option<Clusters>,
+    pub(crate) terminated_early: Option<bool>,
+    pub(crate) profile: Option<Profile>
+}
+
+pub type Response = ResponseOf<Value>;
+
+/// The name `Response` was published under in the first examples of the crate.
+#[deprecated(since = "0.2.2", note = "use `Response`")]
+pub type EsResponse = Response;
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Parses a response sent as CBOR, as requested with `Accept: application/cbor`.
+    ///
+    /// The body is converted into the same `Value` tree as JSON bodies before it is parsed.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor_slice(body: &[u8]) -> Result<ResponseOf<T>, ResponseError> {
+        http::decode_cbor(body)
+    }
+
+    /// Parses a response body with simd-json, which scans large bodies such as big scroll pages
+    /// faster than serde_json.
+    ///
+    /// simd-json unescapes strings in place, so the body is taken mutably and its contents are
+    /// unspecified afterwards. Errors don't carry a `ParseContext`.
+    #[cfg(feature = "simd-json")]
+    pub fn from_slice_mut(body: &mut [u8]) -> Result<ResponseOf<T>, Error> {
+        simd::decode(body)
+    }
+
+    /// Returns the time in milliseconds Elasticsearch took to execute the search.
+    pub fn took(&self) -> u64 {
+        self.took
+    }
+
+    /// Returns `true` if the search timed out before every shard answered, so the results are
+    /// those of the shards that did.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns the summary of the shards the search ran on, including those that failed.
+    pub fn shards(&self) -> &Shards {
+        &self._shards
+    }
+
+    /// Returns the summary of the clusters a cross-cluster search ran on.
+    pub fn clusters(&self) -> Option<&Clusters> {
+        self._clusters.as_ref()
+    }
+
+    /// Returns `true` if a shard stopped collecting hits before going through all of them, as
+    /// searches with `terminate_after` do.
+    pub fn terminated_early(&self) -> bool {
+        self.terminated_early.unwrap_or(false)
+    }
+
+    /// Returns the HTTP status that came in the body, if any.
+    ///
+    /// Elasticsearch includes it in the responses of a multi search from version 6, and next to
+    /// the `error` of failed requests, which are reported as `Error::Api` instead of parsing.
+    /// Proxies that answer every request with `200` may add it to other responses as well.
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Returns the id to fetch the next page with, if this is a scroll response.
+    pub fn scroll_id(&self) -> Option<&str> {
+        self._scroll_id.as_ref().map(|id| &**id)
+    }
+
+    /// Returns an Iterator to the search results or hits of the response.
+    ///
+    /// Responses without a `hits` object, such as some aggregation-only responses, have no hits
+    /// and no total.
+    pub fn hits(&self) -> &HitsEnvelope<T> {
+        &self.hits
+    }
+
+    /// Returns the number of hits on this page, as opposed to the total.
+    pub fn count_returned(&self) -> usize {
+        self.hits.len()
+    }
+
+    /// Returns `true` if the response has aggregations, even if they are empty.
+    pub fn has_aggregations(&self) -> bool {
+        self.aggregations.has_aggregations()
+    }
+
+    /// Returns `true` if there are no hits on this page and the aggregations found nothing,
+    /// see `Aggregations::is_empty`.
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty() && self.aggregations.is_empty()
+    }
+
+    /// Takes the hits on this page out of the response, so they can be kept without cloning.
+    pub fn into_hits(self) -> Vec<Hit<T>> {
+        self.hits.hits
+    }
+
+    /// Returns `true` if the response has a `hits` object, even if it has no hits in it.
+    pub fn has_hits(&self) -> bool {
+        !self.hits.missing
+    }
+
+    /// Groups the hits by the index they come from, keeping the hit order within each index.
+    pub fn hits_by_index(&self) -> BTreeMap<&str, Vec<&Hit<T>>> {
+        let mut groups = BTreeMap::new();
+        for hit in self.hits.iter() {
+            groups.entry(hit.index()).or_insert_with(Vec::new).push(hit);
+        }
+        groups
+    }
+
+    /// Returns the distinct names of the indices the hits come from, in order of first hit.
+    pub fn indices(&self) -> Vec<&str> {
+        let mut indices: Vec<&str> = Vec::new();
+        for hit in self.hits.iter() {
+            if !indices.contains(&hit.index()) {
+                indices.push(hit.index());
+            }
+        }
+        indices
+    }
+
+    /// Returns the first value of field `name` of every hit, in hit order, with `None` for hits
+    /// that don't have the field.
+    pub fn field_column(&self, name: &str) -> Vec<Option<&Value>> {
+        self.hits.iter().map(|h| h.field_first(name)).collect()
+    }
+
+    /// Describes the page of hits in this response, given the `from` and `size` it was
+    /// requested with.
+    ///
+    /// When the total is a lower bound (`relation: gte`) a full page is always assumed to have
+    /// more hits after it. A page without any hits never has more. When the total wasn't tracked
+    /// at all, it is taken to be a lower bound of `from` plus the hits on this page.
+    pub fn page_info(&self, from: u64, size: u64) -> PageInfo {
+        let returned = self.hits.len() as u64;
+        let total = self.hits.total().unwrap_or(TotalHits {
+            value: from + returned,
+            relation: TotalHitsRelation::Gte
+        });
+
+        let has_more = returned > 0 && match total.relation() {
+            TotalHitsRelation::Eq => from + returned < total.value(),
+            TotalHitsRelation::Gte => returned == size || from + returned < total.value()
+        };
+
+        PageInfo {
+            total: total.value(),
+            returned: returned,
+            has_more: has_more,
+            next_from: if has_more { Some(from + returned) } else { None }
+        }
+    }
+
+    /// Returns an Iterator over the hits paired with their rank in the full result set, for a
+    /// page requested with `from`.
+    ///
+    /// Ranks are 1-based, as displayed, while `from` is the 0-based offset sent with the search,
+    /// so the first hit of the page requested with `from: 10` has rank 11.
+    pub fn ranked_hits(&self, from: u64) -> RankedHits<Iter<Hit<T>>> {
+        RankedHits::new(self.hits.iter(), from)
+    }
+
+    /// Maps the slot of each document in a percolate query to the ids of the stored queries that
+    /// matched it, in hit order.
+    pub fn percolator_matches(&self) -> BTreeMap<u64, Vec<&str>> {
+        let mut matches = BTreeMap::new();
+        for hit in &self.hits {
+            for slot in hit.percolator_slots().unwrap_or(&[]) {
+                matches.entry(*slot).or_insert_with(Vec::new).push(hit.id());
+            }
+        }
+        matches
+    }
+
+    /// Returns an Iterator to the search results or aggregations part of the response.
+    ///
+    /// This Iterator transforms the tree-like JSON object into a row/table based format for use with standard iterator adaptors.
+    pub fn aggs(&self) -> &Aggregations {
+        &self.aggregations
+    }
+}
+
+/// A search response for searches sent with `"size": 0`, which only reads the aggregations.
+///
+/// It parses the same bodies as `Response`, but doesn't take a source type and skips over the
+/// hits array entirely, so no `Value`s are built for hits that are there regardless. The total
+/// and the highest score are still read.
+#[derive(Deserialize, Debug)]
+pub struct AggsResponse {
+    took: u64,
+    timed_out: bool,
+    _shards: Shards,
+    #[serde(default)]
+    hits: HitsSummary,
+    #[serde(default)]
+    aggregations: Aggregations
+}
+
+/// The `hits` object without its hits, which are ignored as an unknown field.
+#[derive(Deserialize, Debug, Default)]
+struct HitsSummary {
+    total: Option<TotalHits>,
+    max_score: Option<f64>
+}
+
+impl AggsResponse {
+    /// Returns the aggregations of the response, the same way as `ResponseOf::aggs`.
+    pub fn aggs(&self) -> &Aggregations {
+        &self.aggregations
+    }
+
+    /// Returns the time in milliseconds Elasticsearch took to execute the search.
+    pub fn took(&self) -> u64 {
+        self.took
+    }
+
+    /// Returns `true` if the search timed out before every shard answered.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns the summary of the shards the search ran on.
+    pub fn shards(&self) -> &Shards {
+        &self._shards
+    }
+
+    /// Returns the total number of hits matching the search, see `HitsEnvelope::total`.
+    pub fn total(&self) -> Option<TotalHits> {
+        self.hits.total
+    }
+
+    /// Returns the highest score of any hit, if the hits were scored.
+    pub fn max_score(&self) -> Option<f64> {
+        self.hits.max_score
+    }
+}
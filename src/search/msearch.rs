@@ -0,0 +1,267 @@
+//! Response of the `_msearch` API.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::{self, Value};
+use std::iter::Enumerate;
+use std::marker::PhantomData;
+use std::slice::Iter;
+use std::vec::IntoIter;
+
+use {ApiError, Error, ResponseParser, parse_typed};
+use super::ResponseOf;
+
+/// Response of a multi search request, with one slot per search in request order.
+#[derive(Deserialize, Debug)]
+pub struct MsearchResponseOf<T: Deserialize> {
+    took: Option<u64>,
+    responses: Vec<MsearchSlot<T>>
+}
+
+pub type MsearchResponse = MsearchResponseOf<Value>;
+
+impl<T: Deserialize> MsearchResponseOf<T> {
+    /// Returns the time in milliseconds the whole request took, as reported by Elasticsearch 7
+    /// and later.
+    ///
+    /// The searches run concurrently, so this is usually less than the sum of the `took` of each
+    /// slot, and it also covers the time spent coordinating them.
+    pub fn took(&self) -> Option<u64> {
+        self.took
+    }
+
+    /// Returns the slots, in the order the searches were sent in.
+    pub fn slots(&self) -> &[MsearchSlot<T>] {
+        &self.responses
+    }
+
+    /// Returns an Iterator over the slots.
+    pub fn iter(&self) -> Iter<MsearchSlot<T>> {
+        self.responses.iter()
+    }
+
+    /// Returns the number of slots.
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Returns `true` if there are no slots.
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// Takes the slots out of the response.
+    pub fn into_slots(self) -> Vec<MsearchSlot<T>> {
+        self.responses
+    }
+
+    /// Returns the `took` of every slot, in slot order, with `None` for the slots that failed.
+    pub fn slot_timings(&self) -> Vec<Option<u64>> {
+        self.responses.iter().map(MsearchSlot::took).collect()
+    }
+
+    /// Returns the position and `took` of the slowest successful slot; the first one wins a tie.
+    pub fn slowest_slot(&self) -> Option<(usize, u64)> {
+        let mut slowest: Option<(usize, u64)> = None;
+        for (i, took) in self.slot_timings().into_iter().enumerate() {
+            if let Some(took) = took {
+                if slowest.map_or(true, |(_, max)| took > max) {
+                    slowest = Some((i, took));
+                }
+            }
+        }
+        slowest
+    }
+}
+
+impl<'a, T: Deserialize> IntoIterator for &'a MsearchResponseOf<T> {
+    type Item = &'a MsearchSlot<T>;
+    type IntoIter = Iter<'a, MsearchSlot<T>>;
+
+    fn into_iter(self) -> Iter<'a, MsearchSlot<T>> {
+        self.iter()
+    }
+}
+
+/// The outcome of one search of a multi search: either a search response or the error it
+/// failed with.
+///
+/// The status and timing can be read without looking at which of the two it is.
+#[derive(Debug)]
+pub struct MsearchSlot<T: Deserialize> {
+    status: Option<u16>,
+    result: Result<ResponseOf<T>, ApiError>
+}
+
+impl<T: Deserialize> MsearchSlot<T> {
+    /// Returns the HTTP status of the search, as reported by Elasticsearch 6 and later.
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Returns the time in milliseconds the search took, if it succeeded.
+    pub fn took(&self) -> Option<u64> {
+        self.response().map(ResponseOf::took)
+    }
+
+    /// Returns `true` if the search succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Returns the search response, if the search succeeded.
+    pub fn response(&self) -> Option<&ResponseOf<T>> {
+        self.result.as_ref().ok()
+    }
+
+    /// Returns the error the search failed with.
+    pub fn error(&self) -> Option<&ApiError> {
+        self.result.as_ref().err()
+    }
+
+    /// Returns the outcome of the search as a `Result`.
+    pub fn as_result(&self) -> Result<&ResponseOf<T>, &ApiError> {
+        self.result.as_ref()
+    }
+
+    /// Returns the outcome of the search as a `Result`, consuming the slot.
+    pub fn into_result(self) -> Result<ResponseOf<T>, ApiError> {
+        self.result
+    }
+}
+
+impl<T: Deserialize> Deserialize for MsearchSlot<T> {
+    fn deserialize<D>(deserializer: &mut D) -> Result<MsearchSlot<T>, D::Error>
+        where D: Deserializer
+    {
+        let value = Value::deserialize(deserializer)?;
+        let status = value.find("status").and_then(Value::as_u64).map(|s| s as u16);
+
+        let result = match value.find("error") {
+            Some(error) => {
+                let error = ApiError::from_value(error)
+                    .ok_or_else(|| D::Error::invalid_value("expected an error object with a `type`"))?;
+                Err(error)
+            }
+            None => Ok(serde_json::from_value(value).map_err(|e| D::Error::custom(e.to_string()))?)
+        };
+
+        Ok(MsearchSlot {
+            status: status,
+            result: result
+        })
+    }
+}
+
+/// Parses a multi search response whose searches return different document types, with each
+/// slot deserialized into its own type.
+///
+/// The types are added with `slot` in the order the searches were sent in, and `parse` returns
+/// a tuple with a `MsearchSlot` of each, such as `(MsearchSlot<LogDoc>, MsearchSlot<MetricDoc>)`
+/// for `MsearchParts::new().slot::<LogDoc>().slot::<MetricDoc>()`.
+///
+/// Up to 8 slots are supported.
+#[derive(Debug)]
+pub struct MsearchParts<S> {
+    _marker: PhantomData<S>
+}
+
+impl MsearchParts<()> {
+    /// Creates a parser without any slots.
+    pub fn new() -> MsearchParts<()> {
+        MsearchParts { _marker: PhantomData }
+    }
+}
+
+impl Default for MsearchParts<()> {
+    fn default() -> MsearchParts<()> {
+        MsearchParts::new()
+    }
+}
+
+impl<S> MsearchParts<S> {
+    /// Adds the next slot, with hits deserialized into `T`.
+    pub fn slot<T: Deserialize>(self) -> MsearchParts<S::Output>
+        where S: PushSlot<T>
+    {
+        MsearchParts { _marker: PhantomData }
+    }
+
+    /// Parses a multi search response body, which has to have exactly as many slots as were
+    /// added.
+    pub fn parse(&self, body: &[u8]) -> Result<S::Slots, Error>
+        where S: MsearchSlots
+    {
+        let raw: RawMsearch = parse_typed(body, &ResponseParser::new())?;
+        if raw.responses.len() != S::len() {
+            let message = format!("expected {} msearch slots, found {}", S::len(), raw.responses.len());
+            return Err(Error::Json(serde_json::Error::custom(message)));
+        }
+        S::parse(&mut raw.responses.into_iter().enumerate())
+    }
+}
+
+#[derive(Deserialize)]
+struct RawMsearch {
+    responses: Vec<Value>
+}
+
+/// The document types of the slots of a `MsearchParts`, as a tuple.
+pub trait MsearchSlots {
+    /// The tuple with a `MsearchSlot` of each type.
+    type Slots;
+
+    /// Returns the number of slots.
+    fn len() -> usize;
+
+    /// Parses the slots from the `responses` of a body, which has as many of them as there are
+    /// slots.
+    fn parse(responses: &mut Enumerate<IntoIter<Value>>) -> Result<Self::Slots, Error>;
+}
+
+/// Appends a document type to a tuple of them, see `MsearchParts::slot`.
+pub trait PushSlot<T> {
+    /// The tuple with `T` appended.
+    type Output;
+}
+
+impl<T> PushSlot<T> for () {
+    type Output = (T,);
+}
+
+fn parse_slot<T: Deserialize>(responses: &mut Enumerate<IntoIter<Value>>) -> Result<MsearchSlot<T>, Error> {
+    let (position, value) = responses.next().expect("as many responses as slots");
+    serde_json::from_value(value)
+        .map_err(|e| Error::Json(serde_json::Error::custom(format!("msearch slot {}: {}", position, e))))
+}
+
+macro_rules! msearch_slots {
+    ($len:expr; $($t:ident),+) => {
+        impl<$($t: Deserialize),+> MsearchSlots for ($($t,)+) {
+            type Slots = ($(MsearchSlot<$t>,)+);
+
+            fn len() -> usize {
+                $len
+            }
+
+            fn parse(responses: &mut Enumerate<IntoIter<Value>>) -> Result<Self::Slots, Error> {
+                Ok(($(parse_slot::<$t>(responses)?,)+))
+            }
+        }
+    };
+    ($len:expr; $($t:ident),+ => $next:ident) => {
+        msearch_slots!($len; $($t),+);
+
+        impl<$($t,)+ $next> PushSlot<$next> for ($($t,)+) {
+            type Output = ($($t,)+ $next);
+        }
+    };
+}
+
+msearch_slots!(1; A => B);
+msearch_slots!(2; A, B => C);
+msearch_slots!(3; A, B, C => D);
+msearch_slots!(4; A, B, C, D => E);
+msearch_slots!(5; A, B, C, D, E => F);
+msearch_slots!(6; A, B, C, D, E, F => G);
+msearch_slots!(7; A, B, C, D, E, F, G => H);
+msearch_slots!(8; A, B, C, D, E, F, G, H);
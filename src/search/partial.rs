@@ -0,0 +1,248 @@
+//! Deserializing the sources of hits trimmed by source filtering.
+
+use serde::de::{self, Deserialize, Error as DeError};
+use serde::de::value::ValueDeserializer;
+use serde_json::{self, Value};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use Error;
+use super::Hit;
+
+impl Hit<Value> {
+    /// Deserializes the hit's source into `T`, reporting every required field the source lacks
+    /// as `Error::MissingFields`, rather than failing on the first one.
+    ///
+    /// This is meant for sources narrowed down with `_source` filtering: fields of `T` that are
+    /// `Option`s are `None` when filtered out, and the error lists the paths of the others, such
+    /// as `user.name` for a field of a nested struct. A hit without a source is treated like an
+    /// empty one. Any other mismatch between the source and `T` is reported as `Error::Json`.
+    pub fn parse_source_partial<T: Deserialize>(&self) -> Result<T, Error> {
+        let empty = Value::Object(BTreeMap::new());
+        let missing = RefCell::new(Vec::new());
+        let parsed = T::deserialize(&mut Lenient {
+            value: self.source().unwrap_or(&empty),
+            path: String::new(),
+            missing: Some(&missing),
+            depth: 0
+        });
+
+        let missing = missing.into_inner();
+        if !missing.is_empty() {
+            return Err(Error::MissingFields(missing));
+        }
+        Ok(parsed?)
+    }
+}
+
+/// How deep placeholders for the fields of missing structs are built, to stop on recursive types.
+const MAX_PLACEHOLDER_DEPTH: usize = 8;
+
+/// Values a missing required field is tried with, so that deserialization gets past it and the
+/// fields after it are checked too. Placeholders are never returned to the caller.
+fn placeholders() -> Vec<Value> {
+    vec![Value::String(String::new()),
+         Value::U64(0),
+         Value::I64(-1),
+         Value::F64(0.0),
+         Value::Bool(false),
+         Value::Array(Vec::new()),
+         Value::Object(BTreeMap::new()),
+         Value::Null]
+}
+
+/// Deserializes a borrowed `Value`, recording the path of missing struct fields in `missing`.
+///
+/// While building placeholders `missing` is `None`, as the fields of a missing struct aren't
+/// reported on their own, and `depth` counts the placeholders they are nested in.
+struct Lenient<'v, 'm> {
+    value: &'v Value,
+    path: String,
+    missing: Option<&'m RefCell<Vec<String>>>,
+    depth: usize
+}
+
+impl<'v, 'm> Lenient<'v, 'm> {
+    fn child(&self, value: &'v Value, segment: &str) -> Lenient<'v, 'm> {
+        let path = if self.path.is_empty() {
+            segment.to_owned()
+        } else if segment.starts_with('[') {
+            format!("{}{}", self.path, segment)
+        } else {
+            format!("{}.{}", self.path, segment)
+        };
+        Lenient {
+            value: value,
+            path: path,
+            missing: self.missing,
+            depth: self.depth
+        }
+    }
+}
+
+impl<'v, 'm> de::Deserializer for Lenient<'v, 'm> {
+    type Error = serde_json::Error;
+
+    fn deserialize<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        match *self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::I64(n) => visitor.visit_i64(n),
+            Value::U64(n) => visitor.visit_u64(n),
+            Value::F64(n) => visitor.visit_f64(n),
+            Value::String(ref s) => visitor.visit_str(s),
+            Value::Array(ref values) => {
+                visitor.visit_seq(LenientSeq {
+                    parent: self,
+                    values: values,
+                    pos: 0
+                })
+            }
+            Value::Object(ref object) => {
+                visitor.visit_map(LenientMap {
+                    parent: self,
+                    entries: object.iter(),
+                    value: None
+                })
+            }
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        match *self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(&mut self, _name: &'static str, mut visitor: V) -> Result<V::Value, serde_json::Error>
+        where V: de::Visitor
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(&mut self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, serde_json::Error>
+        where V: de::EnumVisitor
+    {
+        // enums can't be missing fields of their own here, so serde_json handles them as usual
+        let value = self.value.clone();
+        de::Deserializer::deserialize_enum(&mut serde_json::value::Deserializer::new(value), name, variants, visitor)
+    }
+
+    forward_to_deserialize! {
+        bool usize u8 u16 u32 u64 isize i8 i16 i32 i64 f32 f64 char str string
+        unit seq seq_fixed_size bytes map unit_struct tuple_struct struct
+        struct_field tuple ignored_any
+    }
+}
+
+struct LenientSeq<'p, 'v: 'p, 'm: 'p> {
+    parent: &'p Lenient<'v, 'm>,
+    values: &'v [Value],
+    pos: usize
+}
+
+impl<'p, 'v, 'm> de::SeqVisitor for LenientSeq<'p, 'v, 'm> {
+    type Error = serde_json::Error;
+
+    fn visit<T: Deserialize>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        let value = match self.values.get(self.pos) {
+            Some(value) => value,
+            None => return Ok(None)
+        };
+        let segment = format!("[{}]", self.pos);
+        self.pos += 1;
+        T::deserialize(&mut self.parent.child(value, &segment)).map(Some)
+    }
+
+    fn end(&mut self) -> Result<(), serde_json::Error> {
+        match self.values.len() - self.pos {
+            0 => Ok(()),
+            remaining => Err(serde_json::Error::invalid_length(remaining))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.values.len() - self.pos;
+        (remaining, Some(remaining))
+    }
+}
+
+struct LenientMap<'p, 'v: 'p, 'm: 'p> {
+    parent: &'p Lenient<'v, 'm>,
+    entries: ::std::collections::btree_map::Iter<'v, String, Value>,
+    value: Option<(&'v String, &'v Value)>
+}
+
+impl<'p, 'v, 'm> de::MapVisitor for LenientMap<'p, 'v, 'm> {
+    type Error = serde_json::Error;
+
+    fn visit_key<K: Deserialize>(&mut self) -> Result<Option<K>, serde_json::Error> {
+        let (key, value) = match self.entries.next() {
+            Some(entry) => entry,
+            None => return Ok(None)
+        };
+        self.value = Some((key, value));
+        K::deserialize(&mut ValueDeserializer::<serde_json::Error>::into_deserializer(key.as_str())).map(Some)
+    }
+
+    fn visit_value<V: Deserialize>(&mut self) -> Result<V, serde_json::Error> {
+        let (key, value) = self.value.take().ok_or_else(|| serde_json::Error::custom("value without a key"))?;
+        V::deserialize(&mut self.parent.child(value, key))
+    }
+
+    fn end(&mut self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+
+    fn missing_field<V: Deserialize>(&mut self, field: &'static str) -> Result<V, serde_json::Error> {
+        if let Ok(value) = V::deserialize(&mut MissingField(field)) {
+            return Ok(value);
+        }
+
+        if let Some(missing) = self.parent.missing {
+            missing.borrow_mut().push(self.parent.child(&Value::Null, field).path);
+        }
+        if self.parent.depth == MAX_PLACEHOLDER_DEPTH {
+            return Err(serde_json::Error::missing_field(field));
+        }
+
+        for value in &placeholders() {
+            let mut placeholder = Lenient {
+                value: value,
+                path: String::new(),
+                missing: None,
+                depth: self.parent.depth + 1
+            };
+            if let Ok(value) = V::deserialize(&mut placeholder) {
+                return Ok(value);
+            }
+        }
+        Err(serde_json::Error::missing_field(field))
+    }
+}
+
+/// Lets missing `Option` fields be `None` and fails for any other type, like serde_json does.
+pub(crate) struct MissingField(pub(crate) &'static str);
+
+impl de::Deserializer for MissingField {
+    type Error = serde_json::Error;
+
+    fn deserialize<V: de::Visitor>(&mut self, _visitor: V) -> Result<V::Value, serde_json::Error> {
+        Err(serde_json::Error::missing_field(self.0))
+    }
+
+    fn deserialize_option<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize! {
+        bool usize u8 u16 u32 u64 isize i8 i16 i32 i64 f32 f64 char str string
+        unit seq seq_fixed_size bytes map unit_struct newtype_struct tuple_struct
+        struct struct_field tuple enum ignored_any
+    }
+}
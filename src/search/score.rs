@@ -0,0 +1,87 @@
+//! Filtering and summarising hits by their relevance score.
+
+use serde::Deserialize;
+use std::slice::Iter;
+
+use super::{Hit, ResponseOf};
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Returns an iterator over the hits on this page with a score of at least `min`.
+    ///
+    /// Hits without a score, as returned when sorting on a field, are skipped unless
+    /// `include_unscored` is set on the iterator.
+    pub fn hits_above_score(&self, min: f64) -> HitsAboveScore<T> {
+        HitsAboveScore {
+            hits: self.hits.iter(),
+            min: min,
+            include_unscored: false
+        }
+    }
+
+    /// Returns the lowest, highest and mean score of the hits on this page, or `None` if none of
+    /// them were scored.
+    pub fn score_stats(&self) -> Option<ScoreStats> {
+        let (mut min, mut max, mut sum, mut count) = (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0);
+        for score in self.hits.iter().filter_map(Hit::score) {
+            min = min.min(score);
+            max = max.max(score);
+            sum += score;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        Some(ScoreStats {
+            min: min,
+            max: max,
+            mean: sum / count as f64,
+            count: count
+        })
+    }
+}
+
+/// Iterator over the hits with a score of at least some minimum, see
+/// `ResponseOf::hits_above_score`.
+#[derive(Debug)]
+pub struct HitsAboveScore<'a, T: Deserialize + 'a> {
+    hits: Iter<'a, Hit<T>>,
+    min: f64,
+    include_unscored: bool
+}
+
+impl<'a, T: Deserialize> HitsAboveScore<'a, T> {
+    /// Sets whether hits without a score are returned as well, which they aren't by default.
+    pub fn include_unscored(mut self, include: bool) -> Self {
+        self.include_unscored = include;
+        self
+    }
+}
+
+impl<'a, T: Deserialize> Iterator for HitsAboveScore<'a, T> {
+    type Item = &'a Hit<T>;
+
+    fn next(&mut self) -> Option<&'a Hit<T>> {
+        let (min, include_unscored) = (self.min, self.include_unscored);
+        self.hits.find(|hit| match hit.score() {
+            Some(score) => score >= min,
+            None => include_unscored
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.hits.size_hint().1)
+    }
+}
+
+/// The spread of the scores of a page of hits, see `ResponseOf::score_stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreStats {
+    /// The lowest score.
+    pub min: f64,
+    /// The highest score.
+    pub max: f64,
+    /// The mean of the scores.
+    pub mean: f64,
+    /// The number of hits with a score, which leaves out unscored hits.
+    pub count: usize
+}
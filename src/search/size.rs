@@ -0,0 +1,136 @@
+//! The sizes of hits as JSON, to find the documents that make a page of hits heavy.
+
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::io::{self, Write};
+
+use super::{Hit, ResponseOf};
+
+impl ResponseOf<Value> {
+    /// Returns the smallest, largest, mean and percentile sizes of the hits on this page, in
+    /// bytes of JSON, or `None` if there are no hits.
+    ///
+    /// The sizes are those of `Hit::json_size`, so they are estimates of the bytes sent. The
+    /// percentiles are of the nearest rank, so each is the size of one of the hits.
+    pub fn hit_size_stats(&self) -> Option<HitSizeStats> {
+        let mut sizes: Vec<usize> = self.hits.iter().map(Hit::json_size).collect();
+        if sizes.is_empty() {
+            return None;
+        }
+        sizes.sort();
+
+        let total = sizes.iter().sum();
+        let percentile = |p: usize| sizes[(p * sizes.len() + 99) / 100 - 1];
+        Some(HitSizeStats {
+            min: sizes[0],
+            max: sizes[sizes.len() - 1],
+            mean: total as f64 / sizes.len() as f64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            total: total,
+            count: sizes.len()
+        })
+    }
+
+    /// Returns the ids of the `n` largest hits on this page by `Hit::json_size`, largest first.
+    ///
+    /// Hits of the same size are in the order of the page.
+    pub fn largest_hits(&self, n: usize) -> Vec<&str> {
+        let mut hits: Vec<(usize, &str)> = self.hits.iter().map(|hit| (hit.json_size(), hit.id())).collect();
+        // the sort is stable, which keeps ties in page order
+        hits.sort_by(|a, b| b.0.cmp(&a.0));
+        hits.into_iter().take(n).map(|(_, id)| id).collect()
+    }
+}
+
+impl Hit<Value> {
+    /// Returns the size of the hit in bytes of compact JSON.
+    ///
+    /// The hit is parsed into values, so its size is that of serializing them again rather
+    /// than of the bytes it was sent as: whitespace isn't counted, escapes and numbers may be
+    /// written differently, and a `null` score or type isn't counted at all. It's close enough
+    /// to tell which documents are large.
+    pub fn json_size(&self) -> usize {
+        let mut size = ObjectSize::default();
+        size.entry("_index", &self._index);
+        if let Some(ref ty) = self._type {
+            size.entry("_type", ty);
+        }
+        size.entry("_id", &self._id);
+        if let Some(score) = self._score {
+            size.entry("_score", &score);
+        }
+        if let Some(ref source) = self._source {
+            size.entry("_source", source);
+        }
+        if let Some(ref fields) = self.fields {
+            size.entry("fields", &fields.values);
+        }
+        for (key, value) in &self.extra {
+            size.entry(key, value);
+        }
+        size.len()
+    }
+}
+
+/// The sizes of a page of hits in bytes, see `ResponseOf::hit_size_stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitSizeStats {
+    /// The size of the smallest hit.
+    pub min: usize,
+    /// The size of the largest hit.
+    pub max: usize,
+    /// The mean size of the hits.
+    pub mean: f64,
+    /// The median size.
+    pub p50: usize,
+    /// The size 90% of the hits are no larger than.
+    pub p90: usize,
+    /// The size 99% of the hits are no larger than.
+    pub p99: usize,
+    /// The size of all the hits together.
+    pub total: usize,
+    /// The number of hits.
+    pub count: usize
+}
+
+/// The size of a JSON object written entry by entry, which saves building it as a value.
+#[derive(Default)]
+struct ObjectSize {
+    len: usize,
+    entries: usize
+}
+
+impl ObjectSize {
+    fn entry<V: Serialize>(&mut self, key: &str, value: &V) {
+        // a comma before all but the first entry, and a colon in each
+        self.len += json_len(&key) + json_len(value) + if self.entries > 0 { 2 } else { 1 };
+        self.entries += 1;
+    }
+
+    fn len(&self) -> usize {
+        self.len + 2
+    }
+}
+
+/// Returns the length of `value` as compact JSON, without writing it anywhere.
+fn json_len<V: Serialize>(value: &V) -> usize {
+    let mut counter = Counter(0);
+    // counting never fails, and values always serialize
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
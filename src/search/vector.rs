@@ -0,0 +1,49 @@
+//! Vector fields and rank fusion metadata of the hits of kNN and hybrid searches.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::Hit;
+
+impl<T: Deserialize> Hit<T> {
+    /// Returns the values of the vector field `name` in the hit's `fields` section.
+    ///
+    /// The numbers are converted in a single pass into a vector of the right size. Returns
+    /// `None` if the field is missing or any of its values isn't a number.
+    pub fn vector_field(&self, name: &str) -> Option<Vec<f32>> {
+        self.field(name).and_then(to_vector)
+    }
+
+    /// Returns the position of the hit in the fused ranking of a hybrid search, reported in
+    /// `_rank` when results are combined with reciprocal rank fusion.
+    ///
+    /// The `_score` of such hits comes from the fusion rather than from any single query.
+    pub fn rank(&self) -> Option<u64> {
+        self.extra.get("_rank").and_then(Value::as_u64)
+    }
+}
+
+impl Hit<Value> {
+    /// Returns the vector at `path` in the hit's source, see `source_path` and `vector_field`.
+    pub fn source_vector(&self, path: &str) -> Option<Vec<f32>> {
+        self.source_path(path).and_then(to_vector)
+    }
+}
+
+/// Converts an array of numbers, possibly wrapped in a single-element array the way some
+/// versions return fields, into a vector.
+fn to_vector(value: &Value) -> Option<Vec<f32>> {
+    let values = match *value {
+        Value::Array(ref values) => values,
+        _ => return None
+    };
+    if values.len() == 1 && values[0].is_array() {
+        return to_vector(&values[0]);
+    }
+
+    let mut vector = Vec::with_capacity(values.len());
+    for value in values {
+        vector.push(value.as_f64()? as f32);
+    }
+    Some(vector)
+}
@@ -0,0 +1,213 @@
+//! Parsing of large response bodies with simd-json.
+//!
+//! simd-json parses a body into a flat tape of nodes, which is deserialized straight into the
+//! response types here rather than through a `serde_json::Value` tree.
+
+use serde::de::{self, Deserialize, Error as DeError};
+use serde_json::{self, Value};
+use simd_json::{self, Node, StaticNode};
+use std::collections::BTreeMap;
+
+use Error;
+
+/// Parses `body` in place and deserializes the result into `T`.
+///
+/// Floats are rounded correctly, where serde_json can be off by the last bit.
+pub fn decode<T: Deserialize>(body: &mut [u8]) -> Result<T, Error> {
+    let tape = simd_json::to_tape(body).map_err(|e| Error::Json(serde_json::Error::custom(e.to_string())))?;
+    let mut de = TapeDeserializer {
+        nodes: &tape.0,
+        pos: 0
+    };
+    Ok(T::deserialize(&mut de)?)
+}
+
+struct TapeDeserializer<'t, 'a: 't> {
+    nodes: &'t [Node<'a>],
+    pos: usize
+}
+
+impl<'t, 'a> TapeDeserializer<'t, 'a> {
+    fn next(&mut self) -> Result<Node<'a>, serde_json::Error> {
+        let node = self.nodes.get(self.pos).cloned().ok_or_else(serde_json::Error::end_of_stream)?;
+        self.pos += 1;
+        Ok(node)
+    }
+
+    /// Skips the value at the current node, along with all of its children.
+    fn skip(&mut self) -> Result<(), serde_json::Error> {
+        match self.next()? {
+            Node::Array { count, .. } | Node::Object { count, .. } => self.pos += count,
+            _ => ()
+        }
+        Ok(())
+    }
+
+    /// Builds a `Value` of the value at the current node.
+    fn value(&mut self) -> Result<Value, serde_json::Error> {
+        Ok(match self.next()? {
+            Node::Static(node) => static_value(node),
+            Node::String(s) => Value::String(s.to_owned()),
+            Node::Array { len, .. } => Value::Array((0..len).map(|_| self.value()).collect::<Result<_, _>>()?),
+            Node::Object { len, .. } => {
+                let mut object = BTreeMap::new();
+                for _ in 0..len {
+                    let key = match self.next()? {
+                        Node::String(key) => key.to_owned(),
+                        _ => return Err(serde_json::Error::invalid_type(de::Type::Str))
+                    };
+                    object.insert(key, self.value()?);
+                }
+                Value::Object(object)
+            }
+        })
+    }
+}
+
+fn static_value(node: StaticNode) -> Value {
+    match node {
+        StaticNode::Null => Value::Null,
+        StaticNode::Bool(b) => Value::Bool(b),
+        // serde_json only uses signed integers for negative numbers
+        StaticNode::I64(n) if n >= 0 => Value::U64(n as u64),
+        StaticNode::I64(n) => Value::I64(n),
+        StaticNode::U64(n) => Value::U64(n),
+        StaticNode::F64(n) => Value::F64(n)
+    }
+}
+
+impl<'t, 'a> de::Deserializer for TapeDeserializer<'t, 'a> {
+    type Error = serde_json::Error;
+
+    fn deserialize<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        match self.next()? {
+            Node::Static(StaticNode::Null) => visitor.visit_unit(),
+            Node::Static(StaticNode::Bool(b)) => visitor.visit_bool(b),
+            Node::Static(StaticNode::I64(n)) if n >= 0 => visitor.visit_u64(n as u64),
+            Node::Static(StaticNode::I64(n)) => visitor.visit_i64(n),
+            Node::Static(StaticNode::U64(n)) => visitor.visit_u64(n),
+            Node::Static(StaticNode::F64(n)) => visitor.visit_f64(n),
+            Node::String(s) => visitor.visit_str(s),
+            Node::Array { len, .. } => visitor.visit_seq(TapeSeq { de: self, remaining: len }),
+            Node::Object { len, .. } => visitor.visit_map(TapeMap { de: self, remaining: len })
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        match self.nodes.get(self.pos) {
+            Some(&Node::Static(StaticNode::Null)) => {
+                self.pos += 1;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor>(&mut self, _name: &'static str, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::EnumVisitor>(&mut self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, serde_json::Error> {
+        // enums are rare and small in responses, so they go through serde_json's own handling
+        let value = self.value()?;
+        de::Deserializer::deserialize_enum(&mut serde_json::value::Deserializer::new(value), name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        self.skip()?;
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize! {
+        bool usize u8 u16 u32 u64 isize i8 i16 i32 i64 f32 f64 char str string
+        unit seq seq_fixed_size bytes map unit_struct tuple_struct struct
+        struct_field tuple
+    }
+}
+
+struct TapeSeq<'d, 't: 'd, 'a: 't> {
+    de: &'d mut TapeDeserializer<'t, 'a>,
+    remaining: usize
+}
+
+impl<'d, 't, 'a> de::SeqVisitor for TapeSeq<'d, 't, 'a> {
+    type Error = serde_json::Error;
+
+    fn visit<T: Deserialize>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        T::deserialize(self.de).map(Some)
+    }
+
+    fn end(&mut self) -> Result<(), serde_json::Error> {
+        if self.remaining == 0 {
+            Ok(())
+        } else {
+            Err(serde_json::Error::invalid_length(self.remaining))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+struct TapeMap<'d, 't: 'd, 'a: 't> {
+    de: &'d mut TapeDeserializer<'t, 'a>,
+    remaining: usize
+}
+
+impl<'d, 't, 'a> de::MapVisitor for TapeMap<'d, 't, 'a> {
+    type Error = serde_json::Error;
+
+    fn visit_key<K: Deserialize>(&mut self) -> Result<Option<K>, serde_json::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        K::deserialize(self.de).map(Some)
+    }
+
+    fn visit_value<V: Deserialize>(&mut self) -> Result<V, serde_json::Error> {
+        V::deserialize(self.de)
+    }
+
+    fn end(&mut self) -> Result<(), serde_json::Error> {
+        if self.remaining == 0 {
+            Ok(())
+        } else {
+            Err(serde_json::Error::invalid_length(self.remaining))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn missing_field<V: Deserialize>(&mut self, field: &'static str) -> Result<V, serde_json::Error> {
+        V::deserialize(&mut MissingField(field))
+    }
+}
+
+/// Lets missing `Option` fields be `None` and fails for any other type, like serde_json does.
+struct MissingField(&'static str);
+
+impl de::Deserializer for MissingField {
+    type Error = serde_json::Error;
+
+    fn deserialize<V: de::Visitor>(&mut self, _visitor: V) -> Result<V::Value, serde_json::Error> {
+        Err(serde_json::Error::missing_field(self.0))
+    }
+
+    fn deserialize_option<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, serde_json::Error> {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize! {
+        bool usize u8 u16 u32 u64 isize i8 i16 i32 i64 f32 f64 char str string
+        unit seq seq_fixed_size bytes map unit_struct newtype_struct tuple_struct
+        struct struct_field tuple enum ignored_any
+    }
+}
@@ -0,0 +1,149 @@
+//! Sorting flattened aggregation rows by a column, and hits by a field of their source.
+
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::vec::IntoIter;
+
+use {Aggregations, Hit, OwnedRow, to_owned_row};
+
+/// The direction to sort in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    /// Smallest first.
+    Asc,
+    /// Largest first.
+    Desc
+}
+
+/// Compares two JSON values, ordering numbers numerically regardless of whether they were
+/// parsed as integers or floats, then strings, then booleans. Anything else compares equal.
+pub fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (&Value::U64(x), &Value::U64(y)) => x.cmp(&y),
+        (&Value::I64(x), &Value::I64(y)) => x.cmp(&y),
+        (&Value::I64(x), &Value::U64(y)) => if x < 0 { Ordering::Less } else { (x as u64).cmp(&y) },
+        (&Value::U64(x), &Value::I64(y)) => if y < 0 { Ordering::Greater } else { x.cmp(&(y as u64)) },
+        (&Value::String(ref x), &Value::String(ref y)) => x.cmp(y),
+        (&Value::Bool(x), &Value::Bool(y)) => x.cmp(&y),
+        _ => match (a.as_f64(), b.as_f64()) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => rank(a).cmp(&rank(b))
+        }
+    }
+}
+
+/// Compares two hits by the value at the dotted `path` of their source, see `Hit::source_path`.
+///
+/// Values are compared with `compare_values`, so numbers come before strings. Hits without the
+/// value, or where it's `null`, come last in either order.
+fn compare_hits(a: &Hit<Value>, b: &Hit<Value>, path: &str, order: Order) -> Ordering {
+    match (a.source_path(path).filter(|v| !v.is_null()), b.source_path(path).filter(|v| !v.is_null())) {
+        (Some(x), Some(y)) => match order {
+            Order::Asc => compare_values(x, y),
+            Order::Desc => compare_values(y, x)
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal
+    }
+}
+
+/// Sorts hits by the value at the dotted `path` of their source, like `timestamp` or
+/// `user.address.city`.
+///
+/// Numbers are compared numerically whether they were sent as integers or floats, and come
+/// before strings, then booleans. Hits without the value, or where it's `null`, come last in
+/// either order. The sort is stable, so tied hits keep their order.
+pub fn sort_hits_by_path(hits: &mut Vec<Hit<Value>>, path: &str, order: Order) {
+    hits.sort_by(|a, b| compare_hits(a, b, path, order));
+}
+
+/// Merges pages of hits that are each sorted by the value at `path` into a single sorted list,
+/// ordering values like `sort_hits_by_path`.
+///
+/// Tied hits are taken from the earlier page first, so merging pages in the order they were
+/// fetched keeps the order Elasticsearch returned them in.
+pub fn merge_sorted_hits(pages: Vec<Vec<Hit<Value>>>, path: &str, order: Order) -> Vec<Hit<Value>> {
+    let len = pages.iter().map(Vec::len).sum();
+    let mut pages: Vec<VecDeque<Hit<Value>>> = pages.into_iter().map(VecDeque::from).collect();
+    let mut merged = Vec::with_capacity(len);
+
+    loop {
+        // there are only ever a few pages, so the next hit is found by looking at each head
+        let mut next: Option<usize> = None;
+        for (i, page) in pages.iter().enumerate() {
+            let hit = match page.front() {
+                Some(hit) => hit,
+                None => continue
+            };
+            let better = next.map_or(true, |n| compare_hits(hit, &pages[n][0], path, order) == Ordering::Less);
+            if better {
+                next = Some(i);
+            }
+        }
+        match next.and_then(|i| pages[i].pop_front()) {
+            Some(hit) => merged.push(hit),
+            None => return merged
+        }
+    }
+}
+
+fn rank(v: &Value) -> u8 {
+    match *v {
+        Value::I64(_) | Value::U64(_) | Value::F64(_) => 0,
+        Value::String(_) => 1,
+        Value::Bool(_) => 2,
+        _ => 3
+    }
+}
+
+impl Aggregations {
+    /// Returns the rows sorted by the value of `column`.
+    ///
+    /// Rows without the column, or where it's `null`, come last in either order. Ties are
+    /// broken by the bucket key columns, see `key_columns`, compared in ascending order; rows
+    /// that are still tied keep the order they were flattened in.
+    pub fn sorted_rows(&self, column: &str, order: Order) -> IntoIter<OwnedRow> {
+        let keys = self.key_columns();
+        let mut rows: Vec<OwnedRow> = self.into_iter().map(|row| to_owned_row(&row)).collect();
+
+        rows.sort_by(|a, b| {
+            let by_column = match (a.get(column).filter(|v| !v.is_null()), b.get(column).filter(|v| !v.is_null())) {
+                (Some(x), Some(y)) => match order {
+                    Order::Asc => compare_values(x, y),
+                    Order::Desc => compare_values(y, x)
+                },
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal
+            };
+
+            keys.iter().fold(by_column, |ord, key| {
+                ord.then_with(|| match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => compare_values(x, y),
+                    _ => Ordering::Equal
+                })
+            })
+        });
+
+        rows.into_iter()
+    }
+
+    /// Returns the first `n` rows when sorted by `column`, see `sorted_rows`.
+    pub fn top_n(&self, column: &str, n: usize, order: Order) -> Vec<OwnedRow> {
+        self.sorted_rows(column, order).take(n).collect()
+    }
+
+    /// Returns the rows of the `n` buckets of the `significant_terms` or `significant_text`
+    /// aggregation `agg` with the highest `score`, see `RowAccess::significance_lift`.
+    ///
+    /// Rows of other aggregations are left out.
+    pub fn top_significant(&self, agg: &str, n: usize) -> Vec<OwnedRow> {
+        let score = format!("{}_score", agg);
+        self.sorted_rows(&score, Order::Desc)
+            .filter(|row| row.get(&score).map_or(false, Value::is_number))
+            .take(n)
+            .collect()
+    }
+}
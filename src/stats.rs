@@ -0,0 +1,85 @@
+//! Summary statistics of the values of a column of aggregation rows.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use {Aggregations, RowAccess};
+
+/// The spread of the values of a column, see `Aggregations::column_stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnStats {
+    /// The number of rows with a number in the column.
+    pub count: usize,
+    /// The number of rows with anything else in the column, such as `null`, `NaN` or a string.
+    pub nulls: usize,
+    /// The smallest number, if there is any.
+    pub min: Option<f64>,
+    /// The largest number, if there is any.
+    pub max: Option<f64>,
+    /// The mean of the numbers, if there is any.
+    pub mean: Option<f64>,
+    /// The sum of the numbers, which is `0` if there are none.
+    pub sum: f64
+}
+
+impl Default for ColumnStats {
+    fn default() -> ColumnStats {
+        ColumnStats {
+            count: 0,
+            nulls: 0,
+            min: None,
+            max: None,
+            mean: None,
+            sum: 0.0
+        }
+    }
+}
+
+impl ColumnStats {
+    fn add(&mut self, value: &Value) {
+        let n = match value.as_f64() {
+            Some(n) if !n.is_nan() => n,
+            _ => {
+                self.nulls += 1;
+                return;
+            }
+        };
+        self.count += 1;
+        self.sum += n;
+        self.min = Some(self.min.map_or(n, |min| min.min(n)));
+        self.max = Some(self.max.map_or(n, |max| max.max(n)));
+        self.mean = Some(self.sum / self.count as f64);
+    }
+}
+
+impl Aggregations {
+    /// Returns summary statistics of the values of `column` over all rows, in a single pass.
+    ///
+    /// Numbers are read as floats whether they were parsed as integers or not. Rows without the
+    /// column aren't counted, while a `null`, a `NaN` or a string such as Elasticsearch's `"NaN"`
+    /// counts as a null.
+    pub fn column_stats(&self, column: &str) -> ColumnStats {
+        let mut stats = ColumnStats::default();
+        for row in self {
+            if let Some(value) = row.column(column) {
+                stats.add(value);
+            }
+        }
+        stats
+    }
+
+    /// Returns summary statistics of `column` for each key of the bucket aggregation `group`,
+    /// like `column_stats`.
+    ///
+    /// Keys are rendered with `RowAccess::key_string`, and rows without a `group` key are left
+    /// out.
+    pub fn column_stats_grouped(&self, group: &str, column: &str) -> BTreeMap<String, ColumnStats> {
+        let mut groups = BTreeMap::new();
+        for row in self {
+            if let (Some(key), Some(value)) = (row.key_string(group), row.column(column)) {
+                groups.entry(key).or_insert_with(ColumnStats::default).add(value);
+            }
+        }
+        groups
+    }
+}
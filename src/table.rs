@@ -0,0 +1,115 @@
+//! Columnar views of search hits.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use {Hit, ResponseOf};
+
+impl ResponseOf<Value> {
+    /// Extracts source paths of every hit into columns aligned by hit order.
+    ///
+    /// Paths are looked up like `Hit::source_path`; hits without a value, or with `null`, get
+    /// `None` in that column.
+    pub fn hits_table(&self, paths: &[&str]) -> HitsTable {
+        let mut table = HitsTable::default();
+        table.fill(self, paths);
+        table
+    }
+}
+
+impl<T: Deserialize> ResponseOf<T> {
+    /// Extracts the `fields` of every hit into columns aligned by hit order, like `hits_table`
+    /// does for source paths.
+    ///
+    /// Fields are looked up by their full name, like `host.name`. A field with a single value
+    /// gets that value, one with several gets the array of them, and hits without the field get
+    /// `None`.
+    pub fn fields_table(&self, fields: &[&str]) -> HitsTable {
+        let mut table = HitsTable::default();
+        table.fill_fields(self, fields);
+        table
+    }
+}
+
+/// Columns of source values, one entry per hit, see `ResponseOf::hits_table`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HitsTable {
+    columns: Vec<(String, Vec<Option<Value>>)>,
+    len: usize
+}
+
+impl HitsTable {
+    /// Replaces the contents of the table with the columns of `response`, like
+    /// `ResponseOf::hits_table`, reusing the allocations of the columns it already has.
+    pub fn fill(&mut self, response: &ResponseOf<Value>, paths: &[&str]) {
+        self.fill_with(response, paths, |hit, path| hit.source_path(path).cloned());
+    }
+
+    /// Replaces the contents of the table with the fields of the hits of `response`, like
+    /// `ResponseOf::fields_table`, reusing the allocations of the columns it already has.
+    pub fn fill_fields<T: Deserialize>(&mut self, response: &ResponseOf<T>, fields: &[&str]) {
+        self.fill_with(response, fields, |hit, field| {
+            let values = hit.field_values(field);
+            match values.len() {
+                0 => None,
+                1 => Some(values[0].clone()),
+                _ => Some(Value::Array(values.to_vec()))
+            }
+        });
+    }
+
+    fn fill_with<T, F>(&mut self, response: &ResponseOf<T>, paths: &[&str], value: F)
+        where T: Deserialize,
+              F: Fn(&Hit<T>, &str) -> Option<Value>
+    {
+        self.columns.truncate(paths.len());
+        while self.columns.len() < paths.len() {
+            self.columns.push((String::new(), Vec::new()));
+        }
+
+        for (&mut (ref mut name, ref mut values), &path) in self.columns.iter_mut().zip(paths) {
+            name.clear();
+            name.push_str(path);
+            values.clear();
+            values.extend(response.hits()
+                .iter()
+                .map(|h| value(h, path).and_then(|v| if v.is_null() { None } else { Some(v) })));
+        }
+        self.len = response.hits().len();
+    }
+
+    /// Returns the number of rows, which is the number of hits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the column names in the order they were requested.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|&(ref name, _)| &**name).collect()
+    }
+
+    /// Returns the values of a column.
+    pub fn column(&self, name: &str) -> Option<&[Option<Value>]> {
+        self.columns.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref values)| &**values)
+    }
+
+    /// Returns a column as numbers; values that aren't numbers become `None`.
+    pub fn column_f64(&self, name: &str) -> Option<Vec<Option<f64>>> {
+        self.column(name).map(|values| values.iter().map(|v| v.as_ref().and_then(Value::as_f64)).collect())
+    }
+
+    /// Returns a column as strings; values that aren't strings become `None`.
+    pub fn column_str(&self, name: &str) -> Option<Vec<Option<&str>>> {
+        self.column(name).map(|values| values.iter().map(|v| v.as_ref().and_then(Value::as_str)).collect())
+    }
+
+    /// Returns the number of missing values in a column.
+    pub fn null_count(&self, name: &str) -> Option<usize> {
+        self.column(name).map(|values| values.iter().filter(|v| v.is_none()).count())
+    }
+}
@@ -0,0 +1,302 @@
+//! Responses of the update by query, delete by query and reindex APIs, and of the task API that
+//! reports on them while they run in the background.
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json::{self, Value};
+use std::time::Duration;
+
+use {ApiError, Object, typed_section};
+
+/// How far an update by query, delete by query or reindex got.
+///
+/// This is the body of a finished request sent with `wait_for_completion=true`, and the `status`
+/// of its task while it runs in the background.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BulkByScrollStatus {
+    total: Option<u64>,
+    #[serde(default)]
+    created: u64,
+    #[serde(default)]
+    updated: u64,
+    #[serde(default)]
+    deleted: u64,
+    #[serde(default)]
+    noops: u64,
+    #[serde(default)]
+    batches: u64,
+    #[serde(default)]
+    version_conflicts: u64,
+    #[serde(default)]
+    retries: Retries,
+    #[serde(default)]
+    throttled_millis: u64,
+    requests_per_second: Option<f64>,
+    #[serde(default)]
+    throttled_until_millis: u64
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+struct Retries {
+    #[serde(default)]
+    bulk: u64,
+    #[serde(default)]
+    search: u64
+}
+
+impl BulkByScrollStatus {
+    /// Returns the number of documents the request is going to process, if it is known yet.
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Returns the number of documents created, which only reindexing does.
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    /// Returns the number of documents updated.
+    pub fn updated(&self) -> u64 {
+        self.updated
+    }
+
+    /// Returns the number of documents deleted.
+    pub fn deleted(&self) -> u64 {
+        self.deleted
+    }
+
+    /// Returns the number of documents left alone, e.g. by a script setting `ctx.op` to `noop`.
+    pub fn noops(&self) -> u64 {
+        self.noops
+    }
+
+    /// Returns the number of scroll batches pulled so far.
+    pub fn batches(&self) -> u64 {
+        self.batches
+    }
+
+    /// Returns the number of version conflicts hit.
+    pub fn version_conflicts(&self) -> u64 {
+        self.version_conflicts
+    }
+
+    /// Returns the number of bulk requests that were retried.
+    pub fn bulk_retries(&self) -> u64 {
+        self.retries.bulk
+    }
+
+    /// Returns the number of search requests that were retried.
+    pub fn search_retries(&self) -> u64 {
+        self.retries.search
+    }
+
+    /// Returns how long the request slept to keep to `requests_per_second`.
+    pub fn throttled(&self) -> Duration {
+        Duration::from_millis(self.throttled_millis)
+    }
+
+    /// Returns the throttle of the request, `None` when it isn't throttled.
+    ///
+    /// Elasticsearch reports an unthrottled request as `-1`, or as infinity in some versions.
+    pub fn requests_per_second(&self) -> Option<f64> {
+        self.requests_per_second.and_then(|r| if r > 0.0 && r.is_finite() { Some(r) } else { None })
+    }
+
+    /// Returns the number of documents processed so far, whatever was done with them.
+    pub fn processed(&self) -> u64 {
+        self.created + self.updated + self.deleted + self.noops
+    }
+
+    /// Returns the share of the documents processed so far, between `0` and `1`.
+    ///
+    /// This is `None` when the total isn't known yet, or is `0` as nothing matched.
+    pub fn progress(&self) -> Option<f64> {
+        match self.total {
+            Some(total) if total > 0 => Some(self.processed() as f64 / total as f64),
+            _ => None
+        }
+    }
+
+    /// Returns the number of documents processed per second over `elapsed`, e.g. the running
+    /// time of the task, or `None` if no time has elapsed.
+    pub fn throughput_per_sec(&self, elapsed: Duration) -> Option<f64> {
+        let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        if secs > 0.0 {
+            Some(self.processed() as f64 / secs)
+        } else {
+            None
+        }
+    }
+}
+
+/// Response of an update by query, delete by query or reindex request that waited for completion.
+#[derive(Debug, Clone)]
+pub struct ByQueryResponse {
+    took: u64,
+    timed_out: bool,
+    status: BulkByScrollStatus,
+    failures: Vec<Value>
+}
+
+impl ByQueryResponse {
+    /// Returns the time in milliseconds the whole request took.
+    pub fn took(&self) -> u64 {
+        self.took
+    }
+
+    /// Returns `true` if a search or bulk request timed out.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns the counts of what was done, shared with the task view of the request.
+    pub fn status(&self) -> &BulkByScrollStatus {
+        &self.status
+    }
+
+    /// Returns the failures that aborted the request.
+    ///
+    /// Bulk failures carry the `index`, `id` and `cause` of a document, search failures the
+    /// `shard` and `reason` of a shard, so both are left as JSON.
+    pub fn failures(&self) -> &[Value] {
+        &self.failures
+    }
+
+    /// Returns `true` if the request neither timed out nor failed.
+    pub fn is_success(&self) -> bool {
+        !self.timed_out && self.failures.is_empty()
+    }
+}
+
+impl Deserialize for ByQueryResponse {
+    fn deserialize<D>(deserializer: &mut D) -> Result<ByQueryResponse, D::Error>
+        where D: Deserializer
+    {
+        let fields = Object::deserialize(deserializer)?;
+        let took: Option<u64> = typed_section(&fields, "took")?;
+        let timed_out: Option<bool> = typed_section(&fields, "timed_out")?;
+        let failures: Option<Vec<Value>> = typed_section(&fields, "failures")?;
+        // the counts sit next to `took` rather than in a section of their own
+        let status = serde_json::from_value(Value::Object(fields)).map_err(|e| D::Error::custom(e.to_string()))?;
+
+        Ok(ByQueryResponse {
+            took: took.ok_or_else(|| D::Error::missing_field("took"))?,
+            timed_out: timed_out.unwrap_or(false),
+            status: status,
+            failures: failures.unwrap_or_default()
+        })
+    }
+}
+
+/// Response of a get task request, e.g. for a reindex started with `wait_for_completion=false`.
+///
+/// Until the task completes only `task` is set, with its status so far. Once it completes,
+/// `response` holds what the request would have answered, or `error` why it failed.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TaskResponse {
+    completed: bool,
+    task: TaskInfo,
+    response: Option<ByQueryResponse>,
+    error: Option<ApiError>
+}
+
+impl TaskResponse {
+    /// Returns `true` once the task finished, successfully or not.
+    pub fn completed(&self) -> bool {
+        self.completed
+    }
+
+    /// Returns the task itself.
+    pub fn task(&self) -> &TaskInfo {
+        &self.task
+    }
+
+    /// Returns the response of the completed request.
+    pub fn response(&self) -> Option<&ByQueryResponse> {
+        self.response.as_ref()
+    }
+
+    /// Returns the error the task failed with.
+    pub fn error(&self) -> Option<&ApiError> {
+        self.error.as_ref()
+    }
+
+    /// Returns the latest counts of the request: those of its response when it completed, and
+    /// the task status otherwise.
+    pub fn status(&self) -> Option<&BulkByScrollStatus> {
+        self.response.as_ref().map(ByQueryResponse::status).or_else(|| self.task.status())
+    }
+}
+
+/// A task running on a node.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TaskInfo {
+    node: String,
+    id: u64,
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    action: String,
+    status: Option<BulkByScrollStatus>,
+    description: Option<String>,
+    start_time_in_millis: Option<u64>,
+    running_time_in_nanos: Option<u64>,
+    #[serde(default)]
+    cancellable: bool,
+    parent_task_id: Option<String>
+}
+
+impl TaskInfo {
+    /// Returns the id of the node the task runs on.
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the id of the task on its node.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the id of the task as it's written in task requests, e.g. `oTUltX4IQMOUUVeiohTt8A:12345`.
+    pub fn task_id(&self) -> String {
+        format!("{}:{}", self.node, self.id)
+    }
+
+    /// Returns the type of the task, e.g. `transport`.
+    pub fn task_type(&self) -> Option<&str> {
+        self.ty.as_ref().map(|t| &**t)
+    }
+
+    /// Returns the action the task runs, e.g. `indices:data/write/reindex`.
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Returns the status of the task so far.
+    pub fn status(&self) -> Option<&BulkByScrollStatus> {
+        self.status.as_ref()
+    }
+
+    /// Returns the description of the task, such as the source and destination of a reindex.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(|d| &**d)
+    }
+
+    /// Returns when the task started, in milliseconds since the epoch.
+    pub fn start_time_in_millis(&self) -> Option<u64> {
+        self.start_time_in_millis
+    }
+
+    /// Returns how long the task has been running, to pass to `throughput_per_sec`.
+    pub fn running_time(&self) -> Option<Duration> {
+        self.running_time_in_nanos.map(|n| Duration::new(n / 1_000_000_000, (n % 1_000_000_000) as u32))
+    }
+
+    /// Returns `true` if the task can be cancelled.
+    pub fn cancellable(&self) -> bool {
+        self.cancellable
+    }
+
+    /// Returns the id of the parent task, for the slices of a sliced request.
+    pub fn parent_task_id(&self) -> Option<&str> {
+        self.parent_task_id.as_ref().map(|p| &**p)
+    }
+}
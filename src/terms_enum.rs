@@ -0,0 +1,39 @@
+//! Response of the `_terms_enum` API, which lists the terms of a field that start with a prefix,
+//! as for typeahead.
+
+use Shards;
+
+/// Response of a `_terms_enum` request.
+///
+/// A response without `terms` is read as having none, and one without `complete` as incomplete.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TermsEnumResponse {
+    _shards: Shards,
+    #[serde(default)]
+    terms: Vec<String>,
+    #[serde(default)]
+    complete: bool
+}
+
+impl TermsEnumResponse {
+    /// Returns the terms found, in their sort order.
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    /// Returns `true` if no terms were found.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Returns `true` if every shard was searched in full, and `false` if the terms may be
+    /// missing some, as when the request timed out or shards failed.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Returns the shard summary of the request.
+    pub fn shards(&self) -> &Shards {
+        &self._shards
+    }
+}
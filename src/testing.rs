@@ -0,0 +1,560 @@
+//! Builders for response bodies, to write fixtures for code that consumes this crate, and helpers
+//! for testing code that works with aggregations.
+//!
+//! Every body built here is accepted by the matching parser of the crate.
+
+use serde_json::{self, Value};
+
+use {Aggregations, BulkAction, Object, OwnedRow, TotalHitsRelation};
+use intern::Interner;
+use search::aggregations::{RowMap, flatten_bucket, root_buckets, root_row, to_owned_row};
+
+fn object(pairs: Vec<(&str, Value)>) -> Value {
+    Value::Object(pairs.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+}
+
+fn string(s: &str) -> Value {
+    Value::String(s.to_owned())
+}
+
+fn error_object(ty: &str, reason: &str) -> Value {
+    object(vec![("type", string(ty)), ("reason", string(reason))])
+}
+
+/// The `_shards` of a request that a single shard answered.
+fn shards() -> Value {
+    object(vec![("total", Value::U64(1)), ("successful", Value::U64(1)), ("failed", Value::U64(0))])
+}
+
+/// Builds the body of a search response.
+#[derive(Debug, Clone)]
+pub struct SearchResponseBuilder {
+    took: u64,
+    timed_out: bool,
+    total: Option<(u64, TotalHitsRelation)>,
+    hits: Vec<(String, Value)>,
+    aggregations: Object,
+    scroll_id: Option<String>
+}
+
+impl SearchResponseBuilder {
+    /// Starts an empty response from one successful shard.
+    pub fn new() -> SearchResponseBuilder {
+        SearchResponseBuilder {
+            took: 1,
+            timed_out: false,
+            total: None,
+            hits: Vec::new(),
+            aggregations: Object::new(),
+            scroll_id: None
+        }
+    }
+
+    /// Sets the time the search took.
+    pub fn took(mut self, took: u64) -> Self {
+        self.took = took;
+        self
+    }
+
+    /// Marks the search as timed out.
+    pub fn timed_out(mut self, timed_out: bool) -> Self {
+        self.timed_out = timed_out;
+        self
+    }
+
+    /// Sets the total number of hits; it defaults to the exact number of hits added.
+    pub fn total(mut self, value: u64, relation: TotalHitsRelation) -> Self {
+        self.total = Some((value, relation));
+        self
+    }
+
+    /// Adds a hit with the given source, using its position as id.
+    pub fn hit(self, source: Value) -> Self {
+        let id = self.hits.len().to_string();
+        self.hit_with_id(&id, source)
+    }
+
+    /// Adds a hit with the given id and source.
+    pub fn hit_with_id(mut self, id: &str, source: Value) -> Self {
+        self.hits.push((id.to_owned(), source));
+        self
+    }
+
+    /// Adds a terms aggregation with one bucket per `(key, doc_count)`.
+    pub fn agg_terms<I, K>(self, name: &str, buckets: I) -> Self
+        where I: IntoIterator<Item = (K, u64)>,
+              K: AsRef<str>
+    {
+        let buckets = buckets.into_iter()
+            .map(|(key, count)| object(vec![("key", string(key.as_ref())), ("doc_count", Value::U64(count))]))
+            .collect();
+        self.agg(name, object(vec![("doc_count_error_upper_bound", Value::U64(0)),
+                                   ("sum_other_doc_count", Value::U64(0)),
+                                   ("buckets", Value::Array(buckets))]))
+    }
+
+    /// Adds an aggregation result as is.
+    pub fn agg(mut self, name: &str, agg: Value) -> Self {
+        self.aggregations.insert(name.to_owned(), agg);
+        self
+    }
+
+    /// Sets the scroll id, making this a page of a scroll.
+    pub fn scroll_id(mut self, scroll_id: &str) -> Self {
+        self.scroll_id = Some(scroll_id.to_owned());
+        self
+    }
+
+    /// Builds the body as a JSON value.
+    pub fn build(&self) -> Value {
+        let (total, relation) = self.total.unwrap_or((self.hits.len() as u64, TotalHitsRelation::Eq));
+        let relation = match relation {
+            TotalHitsRelation::Eq => "eq",
+            TotalHitsRelation::Gte => "gte"
+        };
+        let hits = self.hits
+            .iter()
+            .map(|&(ref id, ref source)| {
+                object(vec![("_index", string("test")),
+                            ("_id", string(id)),
+                            ("_score", Value::F64(1.0)),
+                            ("_source", source.clone())])
+            })
+            .collect();
+        let max_score = if self.hits.is_empty() { Value::Null } else { Value::F64(1.0) };
+
+        let mut body = vec![("took", Value::U64(self.took)),
+                            ("timed_out", Value::Bool(self.timed_out)),
+                            ("_shards", shards()),
+                            ("hits",
+                             object(vec![("total",
+                                          object(vec![("value", Value::U64(total)),
+                                                      ("relation", string(relation))])),
+                                         ("max_score", max_score),
+                                         ("hits", Value::Array(hits))]))];
+        if !self.aggregations.is_empty() {
+            body.push(("aggregations", Value::Object(self.aggregations.clone())));
+        }
+        if let Some(ref id) = self.scroll_id {
+            body.push(("_scroll_id", string(id)));
+        }
+        object(body)
+    }
+
+    /// Builds the body as a JSON string.
+    pub fn build_string(&self) -> String {
+        serde_json::to_string(&self.build()).unwrap_or_default()
+    }
+}
+
+impl Default for SearchResponseBuilder {
+    fn default() -> Self {
+        SearchResponseBuilder::new()
+    }
+}
+
+/// Builds the body of a bulk response.
+#[derive(Debug, Clone)]
+pub struct BulkResponseBuilder {
+    took: u64,
+    index: String,
+    items: Vec<Value>,
+    errors: bool
+}
+
+impl BulkResponseBuilder {
+    /// Starts a response without items, for operations on the `test` index.
+    pub fn new() -> BulkResponseBuilder {
+        BulkResponseBuilder {
+            took: 1,
+            index: "test".to_owned(),
+            items: Vec::new(),
+            errors: false
+        }
+    }
+
+    /// Sets the time the request took.
+    pub fn took(mut self, took: u64) -> Self {
+        self.took = took;
+        self
+    }
+
+    /// Sets the index of the items added from now on.
+    pub fn index_name(mut self, index: &str) -> Self {
+        self.index = index.to_owned();
+        self
+    }
+
+    /// Adds a successful operation.
+    ///
+    /// Index and create operations report `created` with status `201`, updates `updated` and
+    /// deletes `deleted` with status `200`.
+    pub fn ok(mut self, action: BulkAction, id: &str) -> Self {
+        let (result, status) = match action {
+            BulkAction::Index | BulkAction::Create => ("created", 201),
+            BulkAction::Update => ("updated", 200),
+            BulkAction::Delete => ("deleted", 200)
+        };
+        let seq_no = self.items.len() as u64;
+        let item = object(vec![("_index", string(&self.index)),
+                               ("_id", string(id)),
+                               ("_version", Value::U64(1)),
+                               ("_seq_no", Value::U64(seq_no)),
+                               ("_primary_term", Value::U64(1)),
+                               ("result", string(result)),
+                               ("status", Value::U64(status))]);
+        self.items.push(object(vec![(action.as_str(), item)]));
+        self
+    }
+
+    /// Adds a failed operation with the given status and error.
+    pub fn failed(mut self, action: BulkAction, id: &str, status: u16, error_type: &str, reason: &str) -> Self {
+        let item = object(vec![("_index", string(&self.index)),
+                               ("_id", string(id)),
+                               ("status", Value::U64(status as u64)),
+                               ("error", error_object(error_type, reason))]);
+        self.items.push(object(vec![(action.as_str(), item)]));
+        self.errors = true;
+        self
+    }
+
+    /// Builds the body as a JSON value.
+    pub fn build(&self) -> Value {
+        object(vec![("took", Value::U64(self.took)),
+                    ("errors", Value::Bool(self.errors)),
+                    ("items", Value::Array(self.items.clone()))])
+    }
+
+    /// Builds the body as a JSON string.
+    pub fn build_string(&self) -> String {
+        serde_json::to_string(&self.build()).unwrap_or_default()
+    }
+}
+
+impl Default for BulkResponseBuilder {
+    fn default() -> Self {
+        BulkResponseBuilder::new()
+    }
+}
+
+/// Builds the body of a get document response.
+#[derive(Debug, Clone)]
+pub struct GetResponseBuilder {
+    index: String,
+    id: String,
+    version: u64,
+    source: Option<Value>
+}
+
+impl GetResponseBuilder {
+    /// Starts a response for a document that wasn't found.
+    pub fn new(index: &str, id: &str) -> GetResponseBuilder {
+        GetResponseBuilder {
+            index: index.to_owned(),
+            id: id.to_owned(),
+            version: 1,
+            source: None
+        }
+    }
+
+    /// Marks the document as found with the given source.
+    pub fn source(mut self, source: Value) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Sets the version of a found document.
+    pub fn version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Builds the body as a JSON value.
+    pub fn build(&self) -> Value {
+        let mut body = vec![("_index", string(&self.index)), ("_id", string(&self.id))];
+        match self.source {
+            Some(ref source) => {
+                body.push(("_version", Value::U64(self.version)));
+                body.push(("_seq_no", Value::U64(self.version - 1)));
+                body.push(("_primary_term", Value::U64(1)));
+                body.push(("found", Value::Bool(true)));
+                body.push(("_source", source.clone()));
+            }
+            None => body.push(("found", Value::Bool(false)))
+        }
+        object(body)
+    }
+
+    /// Builds the body as a JSON string.
+    pub fn build_string(&self) -> String {
+        serde_json::to_string(&self.build()).unwrap_or_default()
+    }
+}
+
+/// Builds the body of an error response, such as `{"error": {...}, "status": 404}`.
+#[derive(Debug, Clone)]
+pub struct ErrorResponseBuilder {
+    status: u16,
+    error: Object,
+    caused_by: Vec<(String, String)>
+}
+
+impl ErrorResponseBuilder {
+    /// Starts an error with the given status, type and reason; it is its own root cause.
+    pub fn new(status: u16, error_type: &str, reason: &str) -> ErrorResponseBuilder {
+        let mut error = Object::new();
+        error.insert("type".to_owned(), string(error_type));
+        error.insert("reason".to_owned(), string(reason));
+
+        ErrorResponseBuilder {
+            status: status,
+            error: error,
+            caused_by: Vec::new()
+        }
+    }
+
+    /// Adds a cause, nested below the causes added before; the innermost cause becomes the
+    /// root cause.
+    pub fn caused_by(mut self, error_type: &str, reason: &str) -> Self {
+        self.caused_by.push((error_type.to_owned(), reason.to_owned()));
+        self
+    }
+
+    /// Adds another field to the error object, such as `index`.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.error.insert(name.to_owned(), value);
+        self
+    }
+
+    /// Builds the body as a JSON value.
+    pub fn build(&self) -> Value {
+        let mut error = self.error.clone();
+        let root = match self.caused_by.last() {
+            Some(&(ref ty, ref reason)) => error_object(ty, reason),
+            None => error_object(error["type"].as_str().unwrap_or(""), error["reason"].as_str().unwrap_or(""))
+        };
+
+        let mut cause: Option<Value> = None;
+        for &(ref ty, ref reason) in self.caused_by.iter().rev() {
+            let mut c = error_object(ty, reason);
+            if let (Some(inner), Some(o)) = (cause.take(), c.as_object_mut()) {
+                o.insert("caused_by".to_owned(), inner);
+            }
+            cause = Some(c);
+        }
+        if let Some(cause) = cause {
+            error.insert("caused_by".to_owned(), cause);
+        }
+        error.insert("root_cause".to_owned(), Value::Array(vec![root]));
+
+        object(vec![("error", Value::Object(error)), ("status", Value::U64(self.status as u64))])
+    }
+
+    /// Builds the body as a JSON string.
+    pub fn build_string(&self) -> String {
+        serde_json::to_string(&self.build()).unwrap_or_default()
+    }
+}
+
+/// Builds the body of a `_terms_enum` response.
+#[derive(Debug, Clone)]
+pub struct TermsEnumResponseBuilder {
+    terms: Vec<String>,
+    complete: bool
+}
+
+impl TermsEnumResponseBuilder {
+    /// Starts a complete response without any terms.
+    pub fn new() -> TermsEnumResponseBuilder {
+        TermsEnumResponseBuilder {
+            terms: Vec::new(),
+            complete: true
+        }
+    }
+
+    /// Adds a term, after the terms added before.
+    pub fn term(mut self, term: &str) -> Self {
+        self.terms.push(term.to_owned());
+        self
+    }
+
+    /// Sets whether the terms are complete, which they are by default.
+    pub fn complete(mut self, complete: bool) -> Self {
+        self.complete = complete;
+        self
+    }
+
+    /// Builds the body as a JSON value.
+    pub fn build(&self) -> Value {
+        object(vec![("_shards", shards()),
+                    ("terms", Value::Array(self.terms.iter().map(|t| string(t)).collect())),
+                    ("complete", Value::Bool(self.complete))])
+    }
+
+    /// Builds the body as a JSON string.
+    pub fn build_string(&self) -> String {
+        serde_json::to_string(&self.build()).unwrap_or_default()
+    }
+}
+
+impl Default for TermsEnumResponseBuilder {
+    fn default() -> Self {
+        TermsEnumResponseBuilder::new()
+    }
+}
+
+/// Flattens `aggs` into rows with a plain recursive walk over the buckets, as a reference for
+/// the iterator of `Aggregations`.
+///
+/// It extracts the columns of each bucket the same way the iterator does, but descends the tree
+/// by recursing rather than with an explicit stack, so the two can be compared to catch
+/// mistakes in the traversal. It returns the same rows in the same order, without the
+/// buckets that aren't objects.
+pub fn flatten_reference(aggs: &Aggregations) -> Vec<OwnedRow> {
+    let mut rows = Vec::new();
+    let mut names = Interner::new();
+    if let Some(o) = aggs.0.as_object() {
+        for (name, (agg, buckets)) in o.iter().filter_map(|(name, agg)| root_buckets(agg).map(|r| (name, r))) {
+            let row = root_row(name, agg, &mut names);
+            reference_rows(name, buckets, &row, &mut names, &mut rows);
+        }
+    }
+    rows
+}
+
+fn reference_rows<'a>(name: &'a String, buckets: &'a [Value], parent: &RowMap<'a>, names: &mut Interner<'a>, rows: &mut Vec<OwnedRow>) {
+    for bucket in buckets.iter().filter_map(Value::as_object) {
+        let mut row = parent.clone();
+        let children = flatten_bucket(name, bucket, &mut row, names);
+        if children.is_empty() {
+            rows.push(to_owned_row(&row));
+        }
+        for (child, buckets) in children {
+            reference_rows(child, buckets, &row, names, rows);
+        }
+    }
+}
+
+/// Generates random but well-formed aggregation trees, for differential tests of code that
+/// flattens or transforms aggregations.
+///
+/// Trees mix terms and date histogram buckets with single value, stats, extended stats and
+/// percentiles metrics, and nest sibling bucket aggregations. The same seed always generates
+/// the same trees.
+#[derive(Debug, Clone)]
+pub struct AggTreeGenerator {
+    state: u64,
+    max_depth: usize,
+    max_buckets: usize
+}
+
+impl AggTreeGenerator {
+    /// Starts generating from `seed`, with up to 3 levels of up to 4 buckets.
+    pub fn new(seed: u64) -> AggTreeGenerator {
+        AggTreeGenerator {
+            // xorshift gets stuck at 0
+            state: seed | 1,
+            max_depth: 3,
+            max_buckets: 4
+        }
+    }
+
+    /// Sets the deepest nesting of bucket aggregations.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the most buckets of a single aggregation.
+    pub fn max_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_buckets = max_buckets;
+        self
+    }
+
+    /// Generates the `aggregations` object of a response.
+    pub fn generate(&mut self) -> Value {
+        let max_depth = self.max_depth;
+        let mut aggs = self.bucket_aggs("", max_depth);
+        if aggs.is_empty() || self.below(4) == 0 {
+            let name = "root_metric".to_owned();
+            let metric = self.metric();
+            aggs.insert(name, metric);
+        }
+        Value::Object(aggs)
+    }
+
+    /// Generates up to two sibling bucket aggregations, with names unique along the path so that
+    /// their columns don't clash.
+    fn bucket_aggs(&mut self, prefix: &str, depth: usize) -> Object {
+        let mut aggs = Object::new();
+        if depth == 0 {
+            return aggs;
+        }
+        for sibling in 0..self.below(3) {
+            let name = format!("{}{}", prefix, ["a", "b"][sibling as usize]);
+            let agg = self.bucket_agg(&name, depth);
+            aggs.insert(name, agg);
+        }
+        aggs
+    }
+
+    fn bucket_agg(&mut self, name: &str, depth: usize) -> Value {
+        let histogram = self.below(2) == 0;
+        let max_buckets = self.max_buckets as u64 + 1;
+        let buckets = (0..self.below(max_buckets))
+            .map(|i| {
+                let mut bucket = self.bucket_aggs(&format!("{}_", name), depth - 1);
+                if histogram {
+                    bucket.insert("key".to_owned(), Value::U64(1483228800000 + i * 86400000));
+                    bucket.insert("key_as_string".to_owned(), Value::String(format!("2017-01-{:02}", i + 1)));
+                } else {
+                    bucket.insert("key".to_owned(), Value::String(format!("{}-{}", name, self.below(100))));
+                }
+                bucket.insert("doc_count".to_owned(), Value::U64(self.below(1000)));
+                for metric in 0..self.below(3) {
+                    let value = self.metric();
+                    bucket.insert(format!("{}_m{}", name, metric), value);
+                }
+                Value::Object(bucket)
+            })
+            .collect();
+
+        let mut agg = Object::new();
+        agg.insert("buckets".to_owned(), Value::Array(buckets));
+        Value::Object(agg)
+    }
+
+    fn metric(&mut self) -> Value {
+        let number = |g: &mut AggTreeGenerator| Value::F64(g.below(10000) as f64 / 8.0);
+        match self.below(4) {
+            0 => object(vec![("value", number(self))]),
+            1 => {
+                object(vec![("count", Value::U64(self.below(100))),
+                            ("min", number(self)),
+                            ("max", number(self)),
+                            ("avg", number(self)),
+                            ("sum", number(self))])
+            }
+            2 => {
+                object(vec![("count", Value::U64(self.below(100))),
+                            ("min", number(self)),
+                            ("max", number(self)),
+                            ("avg", number(self)),
+                            ("sum", number(self)),
+                            ("sum_of_squares", number(self)),
+                            ("variance", number(self)),
+                            ("std_deviation", number(self)),
+                            ("std_deviation_bounds", object(vec![("upper", number(self)), ("lower", number(self))]))])
+            }
+            _ => object(vec![("values", object(vec![("50.0", number(self)), ("99.0", number(self))]))])
+        }
+    }
+
+    /// Returns a number below `n`, from a xorshift generator.
+    fn below(&mut self, n: u64) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state % n
+    }
+}
@@ -0,0 +1,315 @@
+//! Decoding an aggregation tree into owned, typed values rather than rows.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use {AggShape, Aggregations, BucketKey, Object};
+
+/// A decoded aggregation, see `Aggregations::parse_typed`.
+///
+/// Aggregations that aren't one of the typed variants, or don't have the fields their type
+/// calls for, are kept as `Raw` JSON.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum AggValue {
+    /// A `terms`, `rare_terms` or `significant_terms` aggregation.
+    Terms(TermsAgg),
+    /// A `date_histogram` or `auto_date_histogram` aggregation.
+    DateHistogram(DateHistogramAgg),
+    /// A `histogram` aggregation.
+    Histogram(HistogramAgg),
+    /// A `stats` or `extended_stats` metric.
+    Stats(StatsAgg),
+    /// A single value metric or pipeline aggregation, e.g. `avg`, `cardinality` or `derivative`.
+    SingleValue(SingleValueAgg),
+    /// A single bucket aggregation such as `filter`, `missing` or `nested`.
+    Filter(FilterAgg),
+    /// Any other aggregation, as it was sent.
+    Raw(Value)
+}
+
+/// The buckets of a `terms` aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TermsAgg {
+    /// The upper bound of the error on the document counts of the buckets.
+    pub doc_count_error_upper_bound: Option<u64>,
+    /// The number of documents that aren't in any of the buckets.
+    pub sum_other_doc_count: Option<u64>,
+    /// The buckets, in the order they were sent.
+    pub buckets: Vec<TermsBucket>
+}
+
+/// A bucket of a `terms` aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TermsBucket {
+    /// The term.
+    pub key: BucketKey,
+    /// The term as formatted by Elasticsearch, e.g. for terms on a date field.
+    pub key_as_string: Option<String>,
+    /// The number of documents with the term.
+    pub doc_count: u64,
+    /// The sub-aggregations of the bucket, by name.
+    pub aggs: BTreeMap<String, AggValue>
+}
+
+/// The buckets of a `date_histogram` aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DateHistogramAgg {
+    /// The buckets, in the order they were sent.
+    pub buckets: Vec<DateHistogramBucket>
+}
+
+/// A bucket of a `date_histogram` aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DateHistogramBucket {
+    /// The start of the bucket, in milliseconds since the epoch.
+    pub key: i64,
+    /// The start of the bucket as formatted by Elasticsearch.
+    pub key_as_string: Option<String>,
+    /// The number of documents in the bucket.
+    pub doc_count: u64,
+    /// The sub-aggregations of the bucket, by name.
+    pub aggs: BTreeMap<String, AggValue>
+}
+
+/// The buckets of a `histogram` aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HistogramAgg {
+    /// The buckets, in the order they were sent.
+    pub buckets: Vec<HistogramBucket>
+}
+
+/// A bucket of a `histogram` aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HistogramBucket {
+    /// The lower bound of the bucket.
+    pub key: f64,
+    /// The lower bound as formatted by Elasticsearch, if a `format` was given.
+    pub key_as_string: Option<String>,
+    /// The number of documents in the bucket.
+    pub doc_count: u64,
+    /// The sub-aggregations of the bucket, by name.
+    pub aggs: BTreeMap<String, AggValue>
+}
+
+/// The values of a `stats` or `extended_stats` metric.
+///
+/// `min`, `max` and `avg` are `None` over no documents; the extended fields are `None` for
+/// plain `stats`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StatsAgg {
+    /// The number of values.
+    pub count: u64,
+    /// The smallest value.
+    pub min: Option<f64>,
+    /// The largest value.
+    pub max: Option<f64>,
+    /// The mean of the values.
+    pub avg: Option<f64>,
+    /// The sum of the values.
+    pub sum: f64,
+    /// The sum of the squares of the values.
+    pub sum_of_squares: Option<f64>,
+    /// The population variance of the values.
+    pub variance: Option<f64>,
+    /// The population standard deviation of the values.
+    pub std_deviation: Option<f64>
+}
+
+/// The value of a single value metric or pipeline aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SingleValueAgg {
+    /// The value, `None` when there was nothing to compute it from.
+    pub value: Option<f64>,
+    /// The value as formatted by Elasticsearch, e.g. for a `max` of a date field.
+    pub value_as_string: Option<String>
+}
+
+/// A single bucket aggregation.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FilterAgg {
+    /// The number of documents in the bucket.
+    pub doc_count: u64,
+    /// The sub-aggregations of the bucket, by name.
+    pub aggs: BTreeMap<String, AggValue>
+}
+
+/// The type of an aggregation as far as decoding goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Terms,
+    DateHistogram,
+    Histogram,
+    Stats,
+    SingleValue,
+    Filter,
+    Raw
+}
+
+/// Returns the kind of an aggregation from the type prefix `typed_keys` adds to its name.
+fn kind_of_type(ty: &str) -> Kind {
+    match ty {
+        "sterms" | "lterms" | "dterms" | "umterms" | "srareterms" | "lrareterms" | "sigsterms" | "siglterms" => Kind::Terms,
+        "date_histogram" | "auto_date_histogram" => Kind::DateHistogram,
+        "histogram" => Kind::Histogram,
+        "stats" | "extended_stats" => Kind::Stats,
+        "avg" | "sum" | "min" | "max" | "value_count" | "cardinality" | "weighted_avg" | "median_absolute_deviation" |
+        "simple_value" | "derivative" | "bucket_metric_value" => Kind::SingleValue,
+        "filter" | "missing" | "nested" | "reverse_nested" | "global" | "sampler" => Kind::Filter,
+        _ => Kind::Raw
+    }
+}
+
+/// Splits a name sent with `typed_keys`, like `sterms#per_host`, into its type and name.
+fn split_typed_key(name: &str) -> (Option<&str>, &str) {
+    match name.find('#') {
+        Some(i) if i > 0 && name[..i].bytes().all(|b| b == b'_' || b.is_ascii_lowercase()) => (Some(&name[..i]), &name[i + 1..]),
+        _ => (None, name)
+    }
+}
+
+/// Guesses the kind of an aggregation sent without `typed_keys` from its fields.
+fn sniff(agg: &Object) -> Kind {
+    match AggShape::of(agg) {
+        AggShape::Buckets(buckets) => {
+            let buckets: Vec<&Object> = buckets.iter().filter_map(Value::as_object).collect();
+            // e.g. `range` buckets, or the object keys of `composite` buckets
+            let other = buckets.iter().any(|b| {
+                b.contains_key("from") || b.contains_key("to") || b.get("key").map_or(true, |k| k.is_object() || k.is_array())
+            });
+            if other {
+                Kind::Raw
+            } else if agg.contains_key("sum_other_doc_count") || agg.contains_key("doc_count_error_upper_bound") ||
+                      agg.contains_key("bg_count") ||
+                      !buckets.iter().all(|b| b.get("key").map_or(false, Value::is_number)) {
+                Kind::Terms
+            } else if !buckets.is_empty() && buckets.iter().all(|b| b.contains_key("key_as_string")) {
+                Kind::DateHistogram
+            } else {
+                Kind::Histogram
+            }
+        }
+        AggShape::Stats => Kind::Stats,
+        AggShape::Value(_) => Kind::SingleValue,
+        AggShape::SingleBucket => Kind::Filter,
+        _ => Kind::Raw
+    }
+}
+
+impl Aggregations {
+    /// Decodes the whole tree into owned, typed aggregations by name.
+    ///
+    /// The type of each aggregation is taken from its name when the search was sent with
+    /// `typed_keys`, which is then stripped from it, so `sterms#per_host` is the `Terms`
+    /// aggregation `per_host`. Without it the type is guessed from the fields: buckets with
+    /// numeric keys are a histogram, and a date histogram if they all have a `key_as_string`,
+    /// unless the aggregation has the `sum_other_doc_count` of a `terms` aggregation. Anything
+    /// that doesn't decode as its type is `AggValue::Raw`.
+    pub fn parse_typed(&self) -> BTreeMap<String, AggValue> {
+        self.0.as_object().map_or_else(BTreeMap::new, decode_aggs)
+    }
+}
+
+/// Decodes the sub-aggregations among the fields of a bucket, or the aggregations at the root.
+fn decode_aggs(fields: &Object) -> BTreeMap<String, AggValue> {
+    fields.iter()
+        .filter(|&(name, value)| name != "key" && name != "meta" && value.is_object())
+        .map(|(name, value)| {
+            let (ty, name) = split_typed_key(name);
+            let agg = value.as_object().unwrap();
+            let kind = ty.map_or_else(|| sniff(agg), kind_of_type);
+            (name.to_owned(), decode(kind, agg).unwrap_or_else(|| AggValue::Raw(value.clone())))
+        })
+        .collect()
+}
+
+fn decode(kind: Kind, agg: &Object) -> Option<AggValue> {
+    let number = |field: &str| agg.get(field).and_then(Value::as_f64);
+    let string = |field: &str| agg.get(field).and_then(Value::as_str).map(str::to_owned);
+
+    match kind {
+        Kind::Terms => {
+            let buckets = bucket_objects(agg)?.into_iter()
+                .map(|b| {
+                    Some(TermsBucket {
+                        key: BucketKey::from(b.get("key")?),
+                        key_as_string: b.get("key_as_string").and_then(Value::as_str).map(str::to_owned),
+                        doc_count: b.get("doc_count")?.as_u64()?,
+                        aggs: decode_aggs(b)
+                    })
+                })
+                .collect::<Option<_>>()?;
+            Some(AggValue::Terms(TermsAgg {
+                doc_count_error_upper_bound: agg.get("doc_count_error_upper_bound").and_then(Value::as_u64),
+                sum_other_doc_count: agg.get("sum_other_doc_count").and_then(Value::as_u64),
+                buckets: buckets
+            }))
+        }
+        Kind::DateHistogram => {
+            let buckets = bucket_objects(agg)?.into_iter()
+                .map(|b| {
+                    Some(DateHistogramBucket {
+                        key: b.get("key").and_then(as_millis)?,
+                        key_as_string: b.get("key_as_string").and_then(Value::as_str).map(str::to_owned),
+                        doc_count: b.get("doc_count")?.as_u64()?,
+                        aggs: decode_aggs(b)
+                    })
+                })
+                .collect::<Option<_>>()?;
+            Some(AggValue::DateHistogram(DateHistogramAgg { buckets: buckets }))
+        }
+        Kind::Histogram => {
+            let buckets = bucket_objects(agg)?.into_iter()
+                .map(|b| {
+                    Some(HistogramBucket {
+                        key: b.get("key")?.as_f64()?,
+                        key_as_string: b.get("key_as_string").and_then(Value::as_str).map(str::to_owned),
+                        doc_count: b.get("doc_count")?.as_u64()?,
+                        aggs: decode_aggs(b)
+                    })
+                })
+                .collect::<Option<_>>()?;
+            Some(AggValue::Histogram(HistogramAgg { buckets: buckets }))
+        }
+        Kind::Stats => {
+            Some(AggValue::Stats(StatsAgg {
+                count: agg.get("count")?.as_u64()?,
+                min: number("min"),
+                max: number("max"),
+                avg: number("avg"),
+                sum: number("sum").unwrap_or(0.0),
+                sum_of_squares: number("sum_of_squares"),
+                variance: number("variance"),
+                std_deviation: number("std_deviation")
+            }))
+        }
+        Kind::SingleValue => {
+            let value = agg.get("value")?;
+            if !value.is_number() && !value.is_null() {
+                return None;
+            }
+            Some(AggValue::SingleValue(SingleValueAgg {
+                value: value.as_f64(),
+                value_as_string: string("value_as_string")
+            }))
+        }
+        Kind::Filter => {
+            Some(AggValue::Filter(FilterAgg {
+                doc_count: agg.get("doc_count")?.as_u64()?,
+                aggs: decode_aggs(agg)
+            }))
+        }
+        Kind::Raw => None
+    }
+}
+
+/// Returns the buckets of a bucket aggregation, if they're all objects.
+fn bucket_objects(agg: &Object) -> Option<Vec<&Object>> {
+    agg.get("buckets")?.as_array()?.iter().map(Value::as_object).collect()
+}
+
+fn as_millis(key: &Value) -> Option<i64> {
+    match *key {
+        Value::F64(n) if n.fract() == 0.0 => Some(n as i64),
+        ref other => other.as_i64()
+    }
+}
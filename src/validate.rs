@@ -0,0 +1,131 @@
+//! Checking an aggregation tree for shapes this crate doesn't understand, e.g. in CI against a
+//! new Elasticsearch version.
+
+use serde_json::Value;
+use std::fmt;
+
+use {AggShape, Aggregations, Object};
+
+/// An aggregation whose results match none of the known shapes, see `Aggregations::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationWarning {
+    path: String,
+    guess: String
+}
+
+impl ValidationWarning {
+    /// Returns a JSON pointer to the aggregation within the aggregations, such as
+    /// `/per_day/buckets/0/weird`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns a guess at what the aggregation might be.
+    pub fn guess(&self) -> &str {
+        &self.guess
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown aggregation at `{}`: {}", self.path, self.guess)
+    }
+}
+
+impl Aggregations {
+    /// Walks the whole aggregation tree, reporting every aggregation that isn't a bucket
+    /// aggregation (with a `buckets` array or keyed `buckets`), a single bucket aggregation, or
+    /// one of the known metric and pipeline layouts.
+    ///
+    /// An empty result means every aggregation was recognized, although only bucket aggregations
+    /// and single value or stats-like metrics end up in the rows.
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+        match self.0.as_object() {
+            Some(aggs) => validate_aggs("", aggs, &mut warnings),
+            None => warnings.push(warning(String::new(), "the aggregations root is not an object"))
+        }
+        warnings
+    }
+
+    /// Returns a warning for every entry of a `buckets` array or object that isn't a bucket,
+    /// such as a stray `null` or number, which the rows skip.
+    pub(crate) fn stray_buckets(&self) -> Vec<ValidationWarning> {
+        self.validate().into_iter().filter(|w| w.guess == NOT_AN_OBJECT).collect()
+    }
+}
+
+const NOT_AN_OBJECT: &'static str = "a bucket that is not an object";
+
+/// Validates the sub-aggregations of a bucket, or the aggregations at the root.
+fn validate_aggs(path: &str, aggs: &Object, warnings: &mut Vec<ValidationWarning>) {
+    for (name, agg) in aggs {
+        // composite keys are objects too, and any aggregation can carry `meta`
+        if name == "key" || name == "meta" {
+            continue;
+        }
+        if let Some(agg) = agg.as_object() {
+            validate_agg(&segment(path, name), agg, warnings);
+        }
+    }
+}
+
+fn validate_agg(path: &str, agg: &Object, warnings: &mut Vec<ValidationWarning>) {
+    match AggShape::of(agg) {
+        AggShape::Buckets(buckets) => {
+            let path = segment(path, "buckets");
+            for (i, bucket) in buckets.iter().enumerate() {
+                match bucket.as_object() {
+                    Some(bucket) => validate_aggs(&segment(&path, &i.to_string()), bucket, warnings),
+                    None => warnings.push(warning(segment(&path, &i.to_string()), NOT_AN_OBJECT))
+                }
+            }
+        }
+        AggShape::KeyedBuckets(buckets) => {
+            let path = segment(path, "buckets");
+            for (key, bucket) in buckets {
+                match bucket.as_object() {
+                    Some(bucket) => validate_aggs(&segment(&path, key), bucket, warnings),
+                    None => warnings.push(warning(segment(&path, key), NOT_AN_OBJECT))
+                }
+            }
+        }
+        AggShape::MalformedBuckets => {
+            let guess = "a bucket aggregation whose `buckets` is neither an array nor an object";
+            warnings.push(warning(path.to_owned(), guess))
+        }
+        AggShape::SingleBucket => validate_aggs(path, agg, warnings),
+        AggShape::Unknown => warnings.push(warning(path.to_owned(), guess(agg))),
+        AggShape::Value(_) | AggShape::Stats | AggShape::Geo | AggShape::Percentiles | AggShape::TopHits => ()
+    }
+}
+
+fn warning<G: Into<String>>(path: String, guess: G) -> ValidationWarning {
+    ValidationWarning {
+        path: path,
+        guess: guess.into()
+    }
+}
+
+fn guess(agg: &Object) -> String {
+    let fields = || agg.keys().map(|k| format!("`{}`", k)).collect::<Vec<_>>().join(", ");
+
+    if agg.is_empty() {
+        return "an empty object, possibly an aggregation without results".to_owned();
+    }
+    let bucket_like = agg.iter().find(|&(_, v)| {
+        v.as_array().map_or(false, |a| !a.is_empty() && a.iter().all(Value::is_object))
+    });
+    if let Some((name, _)) = bucket_like {
+        return format!("possibly a bucket aggregation that calls its buckets `{}`", name);
+    }
+    if agg.values().all(|v| v.is_number() || v.is_null()) {
+        return format!("possibly a multi-value metric with fields {}", fields());
+    }
+    format!("an object with unrecognized fields {}", fields())
+}
+
+/// Appends `name` to a JSON pointer.
+pub(crate) fn segment(path: &str, name: &str) -> String {
+    format!("{}/{}", path, name.replace('~', "~0").replace('/', "~1"))
+}
@@ -0,0 +1,69 @@
+//! Response of the `_validate/query` API.
+
+use {RewrittenQuery, Shards};
+
+/// Response of a `_validate/query` request.
+///
+/// With `explain` or `rewrite`, it has an explanation for each index, or for each shard with
+/// `all_shards`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidateQueryResponse {
+    _shards: Option<Shards>,
+    valid: bool,
+    #[serde(default)]
+    explanations: Vec<QueryExplanation>
+}
+
+impl ValidateQueryResponse {
+    /// Returns `true` if the query is valid on every index.
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Returns the shard summary of the request.
+    pub fn shards(&self) -> Option<&Shards> {
+        self._shards.as_ref()
+    }
+
+    /// Returns the explanations, which are only sent with `explain` or `rewrite`.
+    pub fn explanations(&self) -> &[QueryExplanation] {
+        &self.explanations
+    }
+}
+
+/// How the query was rewritten on an index, or why it's invalid there.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct QueryExplanation {
+    index: Option<String>,
+    shard: Option<i64>,
+    valid: bool,
+    explanation: Option<RewrittenQuery>,
+    error: Option<String>
+}
+
+impl QueryExplanation {
+    /// Returns the index the query was validated against.
+    pub fn index(&self) -> Option<&str> {
+        self.index.as_ref().map(|i| &**i)
+    }
+
+    /// Returns the shard the query was validated against, with `all_shards`.
+    pub fn shard(&self) -> Option<i64> {
+        self.shard
+    }
+
+    /// Returns `true` if the query is valid on the index.
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Returns the query as it was rewritten for the index.
+    pub fn rewritten(&self) -> Option<&RewrittenQuery> {
+        self.explanation.as_ref()
+    }
+
+    /// Returns why the query is invalid on the index.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_ref().map(|e| &**e)
+    }
+}
@@ -0,0 +1,99 @@
+//! The walk over an aggregation tree that the rows take, and the number of buckets found on it.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use {AggShape, Aggregations, Object};
+
+impl Aggregations {
+    /// Returns the number of buckets of every bucket aggregation by its path, like
+    /// `per_day>per_host`, summed over all the buckets it is nested in.
+    ///
+    /// The tree is walked the way the rows descend it, so the paths go through single bucket
+    /// aggregations such as `filter` as well, but only aggregations with buckets of their own
+    /// are counted. Aggregations requested with `keyed: true` are counted, although the rows
+    /// don't include them, and nothing below them is.
+    pub fn bucket_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        each_agg(self, &mut |path, _, shape| {
+            let buckets = match *shape {
+                AggShape::Buckets(buckets) => buckets.len(),
+                AggShape::KeyedBuckets(buckets) => buckets.len(),
+                _ => return
+            };
+            *counts.entry(path.join(">")).or_insert(0) += buckets;
+        });
+        counts
+    }
+
+    /// Returns the number of buckets in the tree, the sum of `bucket_counts`.
+    pub fn total_buckets(&self) -> usize {
+        self.bucket_counts().values().sum()
+    }
+}
+
+/// Calls `f` with the path to every aggregation that the rows descend into or take columns from,
+/// its name in the tree and its shape, parents before the aggregations below them.
+///
+/// Bucket aggregations requested with `keyed: true` are passed to `f` too, but not descended
+/// into.
+pub(crate) fn each_agg<'a, F>(aggs: &'a Aggregations, f: &mut F)
+    where F: FnMut(&[&'a str], &'a String, &AggShape<'a>)
+{
+    let aggs = match aggs.0.as_object() {
+        Some(aggs) => aggs,
+        None => return
+    };
+    for (name, agg) in aggs {
+        let agg = match agg.as_object() {
+            Some(agg) => agg,
+            None => continue
+        };
+        let shape = AggShape::of(agg);
+        let mut path = vec![&**name];
+        match shape {
+            AggShape::Buckets(buckets) => {
+                f(&path, name, &shape);
+                for bucket in buckets.iter().filter_map(Value::as_object) {
+                    visit_bucket(bucket, &mut path, f);
+                }
+            }
+            AggShape::KeyedBuckets(_) => f(&path, name, &shape),
+            AggShape::SingleBucket => {
+                f(&path, name, &shape);
+                visit_bucket(agg, &mut path, f);
+            }
+            _ => ()
+        }
+    }
+}
+
+fn visit_bucket<'a, F>(bucket: &'a Object, path: &mut Vec<&'a str>, f: &mut F)
+    where F: FnMut(&[&'a str], &'a String, &AggShape<'a>)
+{
+    for (name, agg) in bucket {
+        let agg = match agg.as_object() {
+            Some(agg) => agg,
+            None => continue
+        };
+        let shape = AggShape::of(agg);
+        match shape {
+            AggShape::Buckets(_) | AggShape::KeyedBuckets(_) | AggShape::SingleBucket | AggShape::Value(_) |
+            AggShape::Stats | AggShape::Geo | AggShape::TopHits => (),
+            _ => continue
+        }
+
+        path.push(name);
+        f(path, name, &shape);
+        match shape {
+            AggShape::Buckets(buckets) => {
+                for bucket in buckets.iter().filter_map(Value::as_object) {
+                    visit_bucket(bucket, path, f);
+                }
+            }
+            AggShape::SingleBucket => visit_bucket(agg, path, f),
+            _ => ()
+        }
+        path.pop();
+    }
+}
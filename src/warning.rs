@@ -0,0 +1,131 @@
+//! Parsing of `Warning` response headers, as described in RFC 7234, section 5.5.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A warning sent in a `Warning` header, such as an Elasticsearch deprecation notice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    code: u16,
+    agent: String,
+    text: String,
+    date: Option<String>
+}
+
+impl Warning {
+    /// Parses all warnings of a `Warning` header value.
+    ///
+    /// A header can carry several comma separated warnings. Parsing stops at the first
+    /// malformed warning; the warnings before it are kept.
+    pub fn parse_header(value: &str) -> Vec<Warning> {
+        let mut chars = value.chars().peekable();
+        let mut warnings = Vec::new();
+
+        loop {
+            skip_separators(&mut chars);
+            if chars.peek().is_none() {
+                break;
+            }
+            match parse_warning(&mut chars) {
+                Some(w) => warnings.push(w),
+                None => {
+                    debug! ("WARNING: Malformed warning header: {:?}", value);
+                    break;
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Returns the warning code; Elasticsearch uses `299` for deprecations.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// Returns the agent that added the warning, e.g. `Elasticsearch-7.17.0-bee8632`.
+    pub fn agent(&self) -> &str {
+        &self.agent
+    }
+
+    /// Returns the unescaped warning text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the date of the warning, if the agent sent one.
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_ref().map(|d| &**d)
+    }
+}
+
+type Input<'a> = Peekable<Chars<'a>>;
+
+fn skip_separators(chars: &mut Input) {
+    while chars.peek().map_or(false, |&c| c == ',' || c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn skip_spaces(chars: &mut Input) -> bool {
+    let mut skipped = false;
+    while chars.peek() == Some(&' ') || chars.peek() == Some(&'\t') {
+        chars.next();
+        skipped = true;
+    }
+    skipped
+}
+
+fn parse_warning(chars: &mut Input) -> Option<Warning> {
+    let mut code = String::new();
+    while chars.peek().map_or(false, |c| c.is_digit(10)) {
+        code.push(chars.next().unwrap());
+    }
+    if code.len() != 3 || !skip_spaces(chars) {
+        return None;
+    }
+
+    let mut agent = String::new();
+    while chars.peek().map_or(false, |&c| !c.is_whitespace() && c != '"' && c != ',') {
+        agent.push(chars.next().unwrap());
+    }
+    if agent.is_empty() || !skip_spaces(chars) {
+        return None;
+    }
+
+    let text = quoted_string(chars)?;
+
+    let date = if skip_spaces(chars) && chars.peek() == Some(&'"') {
+        Some(quoted_string(chars)?)
+    } else {
+        None
+    };
+
+    skip_spaces(chars);
+    match chars.peek() {
+        None | Some(&',') => (),
+        Some(_) => return None
+    }
+
+    Some(Warning {
+        code: code.parse().ok()?,
+        agent: agent,
+        text: text,
+        date: date
+    })
+}
+
+fn quoted_string(chars: &mut Input) -> Option<String> {
+    if chars.next() != Some('"') {
+        return None;
+    }
+
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => s.push(chars.next()?),
+            c => s.push(c)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Responses of the APIs that write a single document, and the outcome of a write.
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use Shards;
+
+/// What a write did to a document, as reported in `result`.
+///
+/// Outcomes added by later Elasticsearch versions are kept as `Unknown` with the original
+/// string, so they serialize back unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteResult {
+    /// A new document was written.
+    Created,
+    /// An existing document was replaced or changed.
+    Updated,
+    /// The document was deleted.
+    Deleted,
+    /// The document to delete or update didn't exist.
+    NotFound,
+    /// An update didn't change the document.
+    Noop,
+    /// Any other outcome.
+    Unknown(String)
+}
+
+impl WriteResult {
+    /// Returns the outcome as it is written in responses.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            WriteResult::Created => "created",
+            WriteResult::Updated => "updated",
+            WriteResult::Deleted => "deleted",
+            WriteResult::NotFound => "not_found",
+            WriteResult::Noop => "noop",
+            WriteResult::Unknown(ref result) => result
+        }
+    }
+
+    /// Returns `true` if the document was changed, that is created, updated or deleted.
+    pub fn did_write(&self) -> bool {
+        match *self {
+            WriteResult::Created | WriteResult::Updated | WriteResult::Deleted => true,
+            _ => false
+        }
+    }
+}
+
+impl From<String> for WriteResult {
+    fn from(result: String) -> WriteResult {
+        match &*result {
+            "created" => WriteResult::Created,
+            "updated" => WriteResult::Updated,
+            "deleted" => WriteResult::Deleted,
+            "not_found" => WriteResult::NotFound,
+            "noop" => WriteResult::Noop,
+            _ => WriteResult::Unknown(result)
+        }
+    }
+}
+
+impl Deserialize for WriteResult {
+    fn deserialize<D>(deserializer: &mut D) -> Result<WriteResult, D::Error>
+        where D: Deserializer
+    {
+        String::deserialize(deserializer).map(WriteResult::from)
+    }
+}
+
+impl Serialize for WriteResult {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Response of an index, create, update or delete document request.
+#[derive(Deserialize, Debug)]
+pub struct DocWriteResponse {
+    _index: String,
+    _type: Option<String>,
+    _id: String,
+    _version: Option<u64>,
+    _seq_no: Option<u64>,
+    _primary_term: Option<u64>,
+    result: WriteResult,
+    _shards: Shards
+}
+
+impl DocWriteResponse {
+    /// Returns the index the document was written to.
+    pub fn index(&self) -> &str {
+        &self._index
+    }
+
+    /// Returns the id of the document; auto-generated ids are reported too.
+    pub fn id(&self) -> &str {
+        &self._id
+    }
+
+    /// Returns the version of the document after the write.
+    pub fn version(&self) -> Option<u64> {
+        self._version
+    }
+
+    /// Returns the sequence number assigned to the write.
+    pub fn seq_no(&self) -> Option<u64> {
+        self._seq_no
+    }
+
+    /// Returns the primary term the write was executed in.
+    pub fn primary_term(&self) -> Option<u64> {
+        self._primary_term
+    }
+
+    /// Returns what the write did to the document.
+    pub fn result(&self) -> &WriteResult {
+        &self.result
+    }
+
+    /// Returns the shard copies the write was replicated to.
+    pub fn shards(&self) -> &Shards {
+        &self._shards
+    }
+}
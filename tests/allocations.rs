@@ -0,0 +1,164 @@
+//! Counts the allocations made while parsing and iterating responses. It is a test binary of its
+//! own as the counting allocator is global; allocations are counted per thread, so the tests can
+//! still run in parallel.
+
+extern crate elastic_responses;
+extern crate serde_json;
+
+use elastic_responses::{FlattenConfig, OwnedRow, Parser, Response, parse_slice};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::Read;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// A date histogram of `rows` buckets, each with a `stats` sub-aggregation.
+fn histogram_body(rows: usize) -> String {
+    let buckets: Vec<String> = (0..rows)
+        .map(|i| {
+            format!(r#"{{"key": {}, "key_as_string": "day {}", "doc_count": {},
+                        "latency": {{"count": 2, "min": 1, "max": 3, "avg": 2, "sum": 4}}}}"#,
+                    i * 86400000,
+                    i,
+                    i % 7)
+        })
+        .collect();
+    format!(r#"{{
+        "took": 1,
+        "timed_out": false,
+        "_shards": {{ "total": 1, "successful": 1, "failed": 0 }},
+        "hits": {{ "total": 0, "max_score": null, "hits": [] }},
+        "aggregations": {{ "per_day": {{ "buckets": [{}] }} }}
+    }}"#, buckets.join(","))
+}
+
+#[test]
+fn test_row_column_names_are_interned() {
+    let rows = 10000;
+    let deserialized: Response = serde_json::from_str(&histogram_body(rows)).unwrap();
+
+    let before = allocations();
+    let mut count = 0;
+    for row in deserialized.aggs() {
+        assert_eq!(row.len(), 8);
+        count += 1;
+    }
+    let allocations = allocations() - before;
+
+    assert_eq!(count, rows);
+    // each row still allocates its own map, but none of its 7 composite column names
+    assert!(allocations < rows * 2, "{} allocations for {} rows", allocations, rows);
+}
+
+/// Reader that hands out a body in small chunks, like a network stream.
+struct Chunked<'a>(&'a [u8]);
+
+impl<'a> Read for Chunked<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1460);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn test_parser_reuses_body_buffer() {
+    let mut body = Vec::new();
+    File::open("tests/samples/aggregation_simple.json").unwrap().read_to_end(&mut body).unwrap();
+    let parses = 1000;
+
+    let before = allocations();
+    for _ in 0..parses {
+        let mut read = Vec::new();
+        Chunked(&body).read_to_end(&mut read).unwrap();
+        let response: Response = parse_slice(&read).unwrap();
+        drop(response);
+    }
+    let fresh = allocations() - before;
+
+    let mut parser = Parser::new();
+    let before = allocations();
+    for _ in 0..parses {
+        let response: Response = parser.parse(&mut Chunked(&body)).unwrap();
+        drop(response);
+    }
+    let reused = allocations() - before;
+
+    // a fresh buffer is allocated and then grown several times for every body, the parser's only
+    // for the first one; the parsed values themselves are allocated either way
+    assert!(reused + parses * 5 <= fresh, "{} allocations with a parser, {} without", reused, fresh);
+}
+
+/// A date histogram of `rows` buckets, each with a dozen metrics that flatten into 40 columns.
+fn wide_body(rows: usize) -> String {
+    let metrics: Vec<String> = (0..12).map(|i| format!(r#""metric_{}": {{"value": {}}}"#, i, i)).collect();
+    let stats: Vec<String> = (0..5)
+        .map(|i| format!(r#""stats_{}": {{"count": 2, "min": 1, "max": 3, "avg": 2, "sum": 4}}"#, i))
+        .collect();
+    let buckets: Vec<String> = (0..rows)
+        .map(|i| {
+            format!(r#"{{"key": {}, "key_as_string": "day {}", "doc_count": 2, {}, {}}}"#,
+                    i * 86400000,
+                    i,
+                    metrics.join(", "),
+                    stats.join(", "))
+        })
+        .collect();
+    format!(r#"{{
+        "took": 1,
+        "timed_out": false,
+        "_shards": {{ "total": 1, "successful": 1, "failed": 0 }},
+        "hits": {{ "total": 0, "max_score": null, "hits": [] }},
+        "aggregations": {{ "per_day": {{ "buckets": [{}] }} }}
+    }}"#, buckets.join(","))
+}
+
+#[test]
+fn test_projection_skips_columns() {
+    let rows = 1000;
+    let deserialized: Response = serde_json::from_str(&wide_body(rows)).unwrap();
+
+    let before = allocations();
+    let full: Vec<OwnedRow> = deserialized.aggs().rows_with(FlattenConfig::new()).collect();
+    let unprojected = allocations() - before;
+
+    let before = allocations();
+    let config = FlattenConfig::new().project(&["per_day", "metric_0", "stats_0_avg"]);
+    let projected: Vec<OwnedRow> = deserialized.aggs().rows_with(config).collect();
+    let projected_allocations = allocations() - before;
+
+    assert_eq!(full[0].len(), 40);
+    assert_eq!((full.len(), projected.len(), projected[0].len()), (rows, rows, 3));
+    // owned rows allocate the name of every column they keep, and the nodes of their map
+    assert!(projected_allocations * 5 < unprojected,
+            "{} allocations projected, {} without", projected_allocations, unprojected);
+}
@@ -0,0 +1,43 @@
+//! Runs every benchmark of `benches/hot_paths.rs` once on small bodies, so the benchmarks keep
+//! compiling and their bodies keep parsing without running them on nightly.
+
+extern crate elastic_responses;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+#[path = "../benches/support/mod.rs"]
+mod support;
+
+#[test]
+fn test_generated_bodies_are_deterministic() {
+    assert_eq!(support::hits_body(4096, 1), support::hits_body(4096, 1));
+    assert!(support::hits_body(4096, 1) != support::hits_body(4096, 2));
+    assert_eq!(support::buckets_body(3, 4, 2), support::buckets_body(3, 4, 2));
+}
+
+#[test]
+fn test_parse_hits() {
+    let body = support::hits_body(64 * 1024, 1);
+    assert!(body.len() >= 64 * 1024);
+    assert!(support::parse_hits(&body) > 100);
+}
+
+#[test]
+fn test_bucket_paths() {
+    let response = support::parse_aggs(&support::buckets_body(10, 24, 2));
+    assert_eq!(response.aggs().columns(),
+               vec!["bytes", "hosts", "hosts_doc_count", "per_hour", "per_hour_doc_count", "per_hour_key_as_string"]);
+    assert_eq!(support::flatten(&response), 240 * 6);
+
+    let mut out = Vec::new();
+    support::csv(&response, &mut out);
+    let csv = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 241);
+    assert_eq!(lines[0], "bytes,hosts,hosts_doc_count,per_hour,per_hour_doc_count,per_hour_key_as_string");
+    assert!(lines[1].contains(",host-0,"));
+
+    let expected: f64 = response.aggs().into_iter().filter_map(|row| row["bytes"].as_f64()).sum();
+    assert_eq!(support::typed_rows(&response), expected);
+}
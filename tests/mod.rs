@@ -1,6 +1,8 @@
 
 extern crate elastic_responses;
 
+extern crate proptest;
+
 #[macro_use]
 extern crate log;
 
@@ -16,9 +18,41 @@ extern crate serde_json;
 extern crate slog_stdlog;
 extern crate slog_envlogger;
 
-use elastic_responses::Response;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+
+#[cfg(feature = "cbor")]
+extern crate serde_cbor;
+
+#[cfg(feature = "arrow")]
+extern crate arrow;
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+use elastic_responses::{AcknowledgedResponse, AggDiff, AggError, AggValue, Aggregations, AggsResponse, AliasesResponse,
+                        AllocationExplainResponse, ApiError, BodyFormat, BucketFilter, BucketKey, BulkAction,
+                        BulkResponse, ByQueryResponse, CatAlias, CatCount, CatHealth, CatNode, CatRow, Clause,
+                        ClauseKind, ClusterHealthResponse, ClusterStateResponse, ColumnStats, Comparator, Condition,
+                        Decision, DedupHits, DocWriteResponse, DuplicateNames, Error, ExistsResponse, FillStrategy,
+                        FlattenConfig, Flavor, GeoPoint, GetResponse, GetScriptResponse, Hit, HitSizeStats,
+                        HitsEnvelope, IlmExplainResponse, IndexState, IndexStatsResponse, KeyType, Limit, MergeStrategy,
+                        MsearchParts, MsearchResponse, NoopObserver, Occur, Order, OwnedAggregations, OwnedRow,
+                        PageInfo, ParseContext, ParsedQuery, Parser, PartialReason, PendingTasksResponse, PingResponse,
+                        PivotError, PositionedItem, Priority, ProblemKind, RankEvalResponse, RecordingObserver,
+                        RenderTemplateResponse, Response, ResponseError, ResponseObserver, ResponseOf, ResponseParser,
+                        RewrittenQuery, Row, RowAccess, ScoreStats, ScriptPosition, ScrollStitcher, Shards,
+                        SimulatePipelineResponse, SingleValueAgg, TaskResponse, TermsEnumResponse, TotalHitsRelation,
+                        TypedByIndex, ValidateQueryResponse, Warning, WriteResult, merge_sorted_hits,
+                        parse_http_response, parse_http_response_with_headers, parse_slice_as, pivot_rows,
+                        responses_from_ndjson, sort_hits_by_path};
+use proptest::prelude::*;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
 
 fn load_file(p :&str) -> String {
     let mut f = File::open(p).unwrap();
@@ -41,6 +75,7 @@ fn test_parse_simple_aggs() {
     let deserialized: Response = serde_json::from_str(&s).unwrap();
 
     assert_eq!(deserialized.aggs().into_iter().count(), 124);
+    assert_rows_in_bucket_order(deserialized.aggs());
 }
 
 #[test]
@@ -49,6 +84,7 @@ fn test_parse_3level_aggs() {
     let deserialized: Response = serde_json::from_str(&s).unwrap();
 
     assert_eq!(deserialized.aggs().into_iter().count(), 201);
+    assert_rows_in_bucket_order(deserialized.aggs());
 }
 
 #[test]
@@ -71,6 +107,7 @@ fn test_parse_3level_multichild_aggs() {
         }
     }
     assert_eq!(count, 201);
+    assert_rows_in_bucket_order(deserialized.aggs());
 }
 
 #[test]
@@ -95,6 +132,109 @@ fn test_parse_3level_multistats_aggs() {
         }
     }
     assert_eq!(count, 61);
+    assert_rows_in_bucket_order(deserialized.aggs());
+}
+
+/// Lists the bucket keys along the path to every innermost bucket of `aggs`, depth first.
+fn bucket_paths(aggs: &serde_json::Map<String, Value>, path: &mut Vec<(String, Value)>, paths: &mut Vec<Vec<(String, Value)>>) {
+    let mut innermost = true;
+    for (name, agg) in aggs {
+        if let Some(buckets) = agg.find("buckets").and_then(Value::as_array) {
+            innermost = false;
+            for bucket in buckets {
+                path.push((name.clone(), bucket.find("key").unwrap().clone()));
+                bucket_paths(bucket.as_object().unwrap(), path, paths);
+                path.pop();
+            }
+        }
+    }
+    if innermost && !path.is_empty() {
+        paths.push(path.clone());
+    }
+}
+
+/// Checks that the rows come out depth first, in the order of the `buckets` arrays.
+fn assert_rows_in_bucket_order(aggs: &Aggregations) {
+    let mut paths = Vec::new();
+    bucket_paths(aggs.raw().as_object().unwrap(), &mut Vec::new(), &mut paths);
+    let rows: Vec<_> = aggs.into_iter().collect();
+
+    assert_eq!(rows.len(), paths.len());
+    for (i, (row, path)) in rows.iter().zip(&paths).enumerate() {
+        for &(ref name, ref key) in path {
+            assert_eq!(row.get(&**name).cloned(), Some(key), "row {} of {:?}", i, path);
+        }
+    }
+}
+
+#[test]
+fn test_rows_in_bucket_order() {
+    let s = load_file("tests/samples/aggregation_siblings.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_rows_in_bucket_order(deserialized.aggs());
+
+    let keys: Vec<Vec<String>> = deserialized.aggs()
+        .into_iter()
+        .map(|row| {
+            ["by_host", "per_path", "per_status", "by_level"]
+                .iter()
+                .filter_map(|agg| row.key_string(agg))
+                .collect()
+        })
+        .collect();
+    assert_eq!(keys,
+               vec![vec!["web-02", "/search"],
+                    vec!["web-02", "/about"],
+                    vec!["web-02", "200"],
+                    vec!["web-02", "500"],
+                    vec!["web-01", "/login"],
+                    vec!["web-01", "404"],
+                    vec!["info"],
+                    vec!["error"]]);
+
+    for sample in &["aggregation_date_terms_avg.json", "aggregation_diff_before.json", "aggregation_diff_after.json"] {
+        let s = load_file(&format!("tests/samples/{}", sample));
+        let deserialized: Response = serde_json::from_str(&s).unwrap();
+        assert_rows_in_bucket_order(deserialized.aggs());
+    }
+}
+
+fn owned_rows(aggs: &Aggregations) -> Vec<OwnedRow> {
+    aggs.into_iter().map(|row| row.into_iter().map(|(k, v)| (k.into_owned(), v.clone())).collect()).collect()
+}
+
+fn owned_row(columns: Vec<(&str, Value)>) -> OwnedRow {
+    columns.into_iter().map(|(k, v)| (k.to_owned(), v)).collect()
+}
+
+#[test]
+fn test_first_and_last_rows_of_3level_aggs() {
+    let s = load_file("tests/samples/aggregation_3level_uneven.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let rows = owned_rows(deserialized.aggs());
+    assert_eq!(rows.len(), 5);
+
+    let string = |s: &str| Value::String(s.to_owned());
+    assert_eq!(rows[0],
+               owned_row(vec![("region", string("eu")),
+                              ("region_doc_count", Value::U64(7)),
+                              ("avg_latency", Value::F64(12.5)),
+                              ("host", string("eu-1")),
+                              ("host_doc_count", Value::U64(4)),
+                              ("path", string("/a")),
+                              ("path_doc_count", Value::U64(3)),
+                              ("bytes", Value::U64(100))]));
+    // neither the metric of the previous path nor that of the previous region leak into a row
+    assert_eq!(rows[1].get("bytes"), None);
+    assert_eq!(rows[2].get("bytes"), Some(&Value::U64(7)));
+    assert_eq!(rows[4],
+               owned_row(vec![("region", string("us")),
+                              ("region_doc_count", Value::U64(2)),
+                              ("host", string("us-1")),
+                              ("host_doc_count", Value::U64(2)),
+                              ("path", string("/z")),
+                              ("path_doc_count", Value::U64(1))]));
+    assert_rows_in_bucket_order(deserialized.aggs());
 }
 
 #[test]
@@ -111,3 +251,4636 @@ fn test_parse_simple_aggs_no_empty_first_record() {
         }
     }
 }
+
+#[test]
+fn test_parse_malformed_buckets_no_panic() {
+    let s = json_str!({
+        "took": 1,
+        "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 },
+        "hits": { "total": 0, "max_score": 0, "hits": [] },
+        "aggregations": {
+            "hosts": {
+                "buckets": [
+                    null,
+                    {
+                        "key": "a",
+                        "doc_count": 1,
+                        "stats": { "std_deviation_bounds": { "upper": null } }
+                    },
+                    3
+                ]
+            }
+        }
+    });
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.aggs().into_iter().count(), 1);
+
+    let rows: Vec<_> = deserialized.aggs().try_rows().collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], Err(AggError::BucketNotObject("hosts".to_owned())));
+    assert!(rows[1].as_ref().unwrap().contains_key("stats_std_deviation_bounds_upper"));
+    assert!(!rows[1].as_ref().unwrap().contains_key("stats_std_deviation_bounds_lower"));
+    assert_eq!(rows[2], Err(AggError::BucketNotObject("hosts".to_owned())));
+}
+
+#[test]
+fn test_parse_missing_aggs_is_empty() {
+    let s = load_file("tests/samples/hits_only.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.aggs().into_iter().count(), 0);
+}
+
+/// Small xorshift generator so the mutation corpus is the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn mutate(v: &mut Value, rng: &mut Rng) {
+    let replace = rng.below(8) == 0;
+    if replace {
+        *v = match rng.below(5) {
+            0 => Value::Null,
+            1 => Value::U64(3),
+            2 => Value::String("x".to_owned()),
+            3 => Value::Array(vec![Value::Null]),
+            _ => Value::Object(Default::default())
+        };
+        return;
+    }
+    match *v {
+        Value::Array(ref mut a) if !a.is_empty() => {
+            let i = rng.below(a.len());
+            mutate(&mut a[i], rng)
+        },
+        Value::Object(ref mut o) if !o.is_empty() => {
+            let i = rng.below(o.len());
+            let key = o.keys().nth(i).unwrap().clone();
+            if rng.below(10) == 0 {
+                o.remove(&key);
+            } else {
+                mutate(o.get_mut(&key).unwrap(), rng)
+            }
+        },
+        _ => ()
+    }
+}
+
+#[test]
+fn test_parse_mutated_aggs_no_panic() {
+    let samples = ["tests/samples/aggregation_simple.json",
+                   "tests/samples/aggregation_3level.json",
+                   "tests/samples/aggregation_3level_multichild.json",
+                   "tests/samples/aggregation_3level_multistats.json"];
+    let mut rng = Rng(0x2545F4914F6CDD1D);
+
+    for sample in samples.iter() {
+        let s = load_file(sample);
+        let original: Value = serde_json::from_str(&s).unwrap();
+
+        for _ in 0..50 {
+            let mut v = original.clone();
+            for _ in 0..(1 + rng.below(20)) {
+                mutate(&mut v, &mut rng);
+            }
+            if let Ok(deserialized) = serde_json::from_value::<Response>(v) {
+                for _ in deserialized.aggs() {}
+                for _ in deserialized.aggs().try_rows() {}
+            }
+
+            let end = rng.below(s.len());
+            if let Ok(deserialized) = serde_json::from_str::<Response>(&s[..end]) {
+                for _ in deserialized.aggs() {}
+            }
+        }
+    }
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+}
+
+fn arb_scalar() -> BoxedStrategy<Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        (0..1000u64).prop_map(Value::U64),
+        (-1000..0i64).prop_map(Value::I64),
+        (-1e6..1e6f64).prop_map(Value::F64),
+        "[a-z]{0,4}".prop_map(Value::String),
+    ].boxed()
+}
+
+fn arb_name() -> BoxedStrategy<String> {
+    prop::sample::select(vec!["a", "b", "c", "key", "doc_count", "value", "buckets"])
+        .prop_map(String::from)
+        .boxed()
+}
+
+fn arb_metric() -> BoxedStrategy<Value> {
+    let stats = prop::collection::btree_map(
+        prop::sample::select(vec!["count", "min", "max", "avg", "sum", "variance"]).prop_map(String::from),
+        arb_scalar(),
+        0..4);
+    prop_oneof![
+        arb_scalar().prop_map(|v| object(vec![("value", v)])),
+        stats.prop_map(Value::Object),
+        (arb_scalar(), arb_scalar()).prop_map(|(u, l)| {
+            object(vec![("std_deviation_bounds", object(vec![("upper", u), ("lower", l)]))])
+        }),
+        arb_scalar(),
+    ].boxed()
+}
+
+fn arb_agg() -> BoxedStrategy<Value> {
+    arb_metric().prop_recursive(4, 64, 4, |inner| {
+        let bucket = prop_oneof![
+            9 => (arb_scalar(), arb_scalar(), prop::collection::btree_map(arb_name(), inner, 0..3))
+                .prop_map(|(key, count, mut subs)| {
+                    subs.insert("key".to_owned(), key);
+                    subs.insert("doc_count".to_owned(), count);
+                    Value::Object(subs)
+                }),
+            1 => arb_scalar(),
+        ];
+        prop_oneof![
+            8 => prop::collection::vec(bucket, 0..4).prop_map(|b| object(vec![("buckets", Value::Array(b))])),
+            1 => arb_scalar().prop_map(|v| object(vec![("buckets", v)])),
+        ]
+    }).boxed()
+}
+
+fn arb_body() -> BoxedStrategy<Value> {
+    let aggs = prop_oneof![
+        9 => prop::collection::btree_map(arb_name(), arb_agg(), 0..4).prop_map(Value::Object),
+        1 => arb_scalar(),
+    ];
+    aggs.prop_map(|aggs| {
+        let mut body: BTreeMap<String, Value> = serde_json::from_str(&load_file("tests/samples/hits_only.json")).unwrap();
+        body.insert("aggregations".to_owned(), aggs);
+        Value::Object(body)
+    }).boxed()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn test_parse_generated_bodies(body in arb_body(), cut in any::<prop::sample::Index>()) {
+        let s = serde_json::to_string(&body).unwrap();
+
+        if let Ok(deserialized) = elastic_responses::parse_slice::<Value>(s.as_bytes()) {
+            let columns: BTreeSet<String> = deserialized.aggs().columns().into_iter().collect();
+            for row in deserialized.aggs() {
+                for key in row.keys() {
+                    prop_assert!(columns.contains(&**key), "column {} missing from {:?}", key, columns);
+                }
+            }
+            for row in deserialized.aggs().try_rows().filter_map(Result::ok) {
+                for key in row.keys() {
+                    prop_assert!(columns.contains(&**key), "column {} missing from {:?}", key, columns);
+                }
+            }
+        }
+
+        let end = cut.index(s.len());
+        let _ = elastic_responses::parse_slice::<Value>(s[..end].as_bytes());
+    }
+}
+
+/// Generates well-formed bucket aggregations, with `value` metrics next to the sub-aggregations.
+fn arb_bucket_agg() -> BoxedStrategy<Value> {
+    fn agg(subs: BoxedStrategy<BTreeMap<String, Value>>) -> BoxedStrategy<Value> {
+        let metrics = prop::collection::btree_map(prop::sample::select(vec!["m", "n"]).prop_map(String::from),
+                                                  (0..100u64).prop_map(|v| object(vec![("value", Value::U64(v))])),
+                                                  0..3);
+        let bucket = (0..5u64, 0..50u64, metrics, subs).prop_map(|(key, count, metrics, subs)| {
+            let mut bucket = metrics;
+            bucket.extend(subs);
+            bucket.insert("key".to_owned(), Value::U64(key));
+            bucket.insert("doc_count".to_owned(), Value::U64(count));
+            Value::Object(bucket)
+        });
+        prop::collection::vec(bucket, 0..4).prop_map(|b| object(vec![("buckets", Value::Array(b))])).boxed()
+    }
+
+    agg(Just(BTreeMap::new()).boxed()).prop_recursive(3, 32, 3, |inner| {
+        let names = prop::sample::select(vec!["a", "b", "c"]).prop_map(String::from);
+        agg(prop::collection::btree_map(names, inner, 0..3).boxed())
+    }).boxed()
+}
+
+/// Flattens the buckets below `aggs` recursively, adding the columns of each bucket to those of
+/// the buckets it is nested in.
+fn reference_rows(aggs: &serde_json::Map<String, Value>, parent: &OwnedRow, rows: &mut Vec<OwnedRow>) {
+    for (name, agg) in aggs {
+        let buckets = match agg.find("buckets").and_then(Value::as_array) {
+            Some(buckets) => buckets,
+            None => continue
+        };
+        for bucket in buckets {
+            let bucket = bucket.as_object().unwrap();
+            let mut row = parent.clone();
+            row.insert(name.clone(), bucket["key"].clone());
+            row.insert(format!("{}_doc_count", name), bucket["doc_count"].clone());
+            for (metric, value) in bucket {
+                if let Some(value) = value.find("value") {
+                    row.insert(metric.clone(), value.clone());
+                }
+            }
+
+            if bucket.values().any(|v| v.find("buckets").is_some()) {
+                reference_rows(bucket, &row, rows);
+            } else {
+                rows.push(row);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(500))]
+
+    #[test]
+    fn test_rows_match_reference_flattener(
+        aggs in prop::collection::btree_map(prop::sample::select(vec!["a", "b", "c"]).prop_map(String::from),
+                                            arb_bucket_agg(),
+                                            1..3)) {
+        let mut expected = Vec::new();
+        reference_rows(&aggs, &OwnedRow::new(), &mut expected);
+
+        let mut body: BTreeMap<String, Value> = serde_json::from_str(&load_file("tests/samples/hits_only.json")).unwrap();
+        body.insert("aggregations".to_owned(), Value::Object(aggs));
+        let deserialized: Response = serde_json::from_value(Value::Object(body)).unwrap();
+
+        prop_assert_eq!(owned_rows(deserialized.aggs()), expected);
+    }
+}
+
+#[test]
+fn test_columns_3level_multistats() {
+    let s = load_file("tests/samples/aggregation_3level_multistats.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let columns = deserialized.aggs().columns();
+    assert!(columns.contains(&"extstats_ack_pkts_sent_std_deviation_bounds_upper".to_owned()));
+    assert!(columns.contains(&"timechart_doc_count".to_owned()));
+    for row in deserialized.aggs() {
+        assert!(row.keys().all(|k| columns.iter().any(|c| c == k)));
+    }
+}
+
+fn hits_body(total: &str, returned: usize) -> String {
+    let hits: Vec<String> = (0..returned)
+        .map(|i| format!(r#"{{"_index": "test", "_type": "doc", "_id": "{}", "_score": 1, "_source": {{}}}}"#, i))
+        .collect();
+    format!(r#"{{
+        "took": 1,
+        "timed_out": false,
+        "_shards": {{ "total": 1, "successful": 1, "failed": 0 }},
+        "hits": {{ "total": {}, "max_score": 1, "hits": [{}] }}
+    }}"#, total, hits.join(","))
+}
+
+#[test]
+fn test_page_info_exact_total() {
+    let deserialized: Response = serde_json::from_str(&hits_body("25", 10)).unwrap();
+
+    assert_eq!(deserialized.hits().len(), 10);
+    assert!(!deserialized.hits().is_empty());
+    assert_eq!(deserialized.hits().total().unwrap().value(), 25);
+    assert_eq!(deserialized.hits().total().unwrap().relation(), TotalHitsRelation::Eq);
+
+    let page = deserialized.page_info(10, 10);
+    assert_eq!(page, PageInfo { total: 25, returned: 10, has_more: true, next_from: Some(20) });
+}
+
+#[test]
+fn test_page_info_final_partial_page() {
+    let deserialized: Response = serde_json::from_str(&hits_body("25", 5)).unwrap();
+
+    let page = deserialized.page_info(20, 10);
+    assert_eq!(page, PageInfo { total: 25, returned: 5, has_more: false, next_from: None });
+
+    let deserialized: Response = serde_json::from_str(&hits_body("25", 0)).unwrap();
+    assert!(deserialized.hits().is_empty());
+    assert!(!deserialized.page_info(30, 10).has_more);
+}
+
+#[test]
+fn test_page_info_gte_total() {
+    let body = hits_body(r#"{"value": 20, "relation": "gte"}"#, 10);
+    let deserialized: Response = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(deserialized.hits().total().unwrap().relation(), TotalHitsRelation::Gte);
+
+    let page = deserialized.page_info(10, 10);
+    assert_eq!(page, PageInfo { total: 20, returned: 10, has_more: true, next_from: Some(20) });
+
+    let body = hits_body(r#"{"value": 20, "relation": "eq"}"#, 10);
+    let deserialized: Response = serde_json::from_str(&body).unwrap();
+    assert!(!deserialized.page_info(10, 10).has_more);
+}
+
+#[test]
+fn test_hits_total_tracking() {
+    let absent: Response = serde_json::from_str(&load_file("tests/samples/hits_total_absent.json")).unwrap();
+    assert_eq!(absent.hits().total(), None);
+    assert_eq!(absent.hits().exact_total(), None);
+    assert_eq!(absent.hits().len(), 2);
+    assert_eq!(absent.page_info(0, 2), PageInfo { total: 2, returned: 2, has_more: true, next_from: Some(2) });
+    assert!(absent.pretty().to_string().starts_with("took 4ms, untracked hits,"));
+
+    let gte: Response = serde_json::from_str(&load_file("tests/samples/hits_total_gte.json")).unwrap();
+    assert_eq!(gte.hits().total().unwrap().value(), 10000);
+    assert_eq!(gte.hits().total().unwrap().relation(), TotalHitsRelation::Gte);
+    assert_eq!(gte.hits().exact_total(), None);
+
+    let exact: Response = serde_json::from_str(&load_file("tests/samples/hits_total_exact.json")).unwrap();
+    assert_eq!(exact.hits().exact_total(), Some(2));
+    assert!(!exact.page_info(0, 2).has_more);
+}
+
+#[test]
+fn test_hits_absent() {
+    let s = load_file("tests/samples/aggregation_hits_absent.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    assert!(!deserialized.has_hits());
+    assert!(deserialized.hits().is_empty());
+    assert_eq!(deserialized.hits().total(), None);
+    assert_eq!(deserialized.hits().max_score(), None);
+    assert_eq!(deserialized.aggs().into_iter().count(), 2);
+
+    let empty: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    assert!(empty.has_hits());
+    assert!(empty.hits().is_empty());
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Latency {
+    p50: f64,
+    p99: f64
+}
+
+#[derive(Debug, PartialEq)]
+enum Doc {
+    Log(LogLine),
+    Latency(Latency)
+}
+
+#[test]
+fn test_hits_by_index_type() {
+    let s = load_file("tests/samples/hits_mixed_indices.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let indices = deserialized.hits_dispatch(|index, source| {
+            Ok(format!("{}:{}", index, source.as_object().map_or(0, |o| o.len())))
+        })
+        .unwrap();
+    assert_eq!(indices, vec!["logs-2024.10.14:3", "metrics-latency:2", "logs-2024.10.15:2", "audit:1"]);
+
+    let types = TypedByIndex::new()
+        .source("logs-*", Doc::Log)
+        .source("metrics-*", Doc::Latency);
+    assert!(types.matches("logs-2024.10.14"));
+    assert!(!types.matches("audit"));
+
+    match types.parse_hits(&deserialized) {
+        Err(e @ Error::UnmatchedIndex(..)) => {
+            assert_eq!(e.to_string(), "no document type registered for index `audit` of hit `4`")
+        }
+        other => panic!("expected an unmatched index, got {:?}", other)
+    }
+
+    let types = types.index("*", |_| Ok(Doc::Latency(Latency { p50: 0.0, p99: 0.0 })));
+    let docs = types.parse_hits(&deserialized).unwrap();
+    assert_eq!(docs[0],
+               Doc::Log(LogLine {
+                   host: "web-01".to_owned(),
+                   level: "warn".to_owned(),
+                   message: Some("disk 91% full".to_owned())
+               }));
+    assert_eq!(docs[1], Doc::Latency(Latency { p50: 12.5, p99: 80.0 }));
+    match docs[2] {
+        Doc::Log(ref line) => assert_eq!(line.message, None),
+        ref other => panic!("expected a log line, got {:?}", other)
+    }
+    assert_eq!(docs[3], Doc::Latency(Latency { p50: 0.0, p99: 0.0 }));
+
+    let strict = TypedByIndex::new().source("metrics-*", Doc::Latency).source("logs-2024.*.15", Doc::Log);
+    assert!(strict.parse("logs-2024.10.15", "3", &Value::Null).is_err());
+    assert!(strict.matches("logs-2024.10.15"));
+    assert!(!strict.matches("logs-2024.10.14"));
+}
+
+fn scroll_body(scroll_id: &str, took: u64, ids: &[&str]) -> String {
+    let hits: Vec<String> = ids.iter()
+        .map(|id| format!(r#"{{"_index": "test", "_type": "doc", "_id": "{}", "_score": 1, "_source": {{}}}}"#, id))
+        .collect();
+    format!(r#"{{
+        "_scroll_id": "{}",
+        "took": {},
+        "timed_out": false,
+        "_shards": {{ "total": 1, "successful": 1, "failed": 0 }},
+        "hits": {{ "total": 5, "max_score": 1, "hits": [{}] }}
+    }}"#, scroll_id, took, hits.join(","))
+}
+
+#[test]
+fn test_scroll_stitcher_three_pages() {
+    let mut stitcher = ScrollStitcher::new();
+
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s1", 3, &["a", "b", "c"])).unwrap()).unwrap();
+    assert_eq!(stitcher.scroll_id(), Some("s1"));
+    assert!(!stitcher.is_finished());
+
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s2", 2, &["d", "e"])).unwrap()).unwrap();
+    let ids: Vec<_> = stitcher.hits().map(|h| h.id().to_owned()).collect();
+    assert_eq!(ids, vec!["a", "b", "c", "d", "e"]);
+
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s3", 1, &[])).unwrap()).unwrap();
+    assert!(stitcher.is_finished());
+    assert_eq!(stitcher.scroll_id(), Some("s3"));
+    assert_eq!(stitcher.took(), 6);
+    assert_eq!(stitcher.pages(), 3);
+}
+
+fn scroll_page(total: &str, ids: &[&str]) -> Response {
+    let body = scroll_body("s", 1, ids).replace(r#""total": 5,"#, &format!(r#""total": {},"#, total));
+    serde_json::from_str(&body).unwrap()
+}
+
+#[test]
+fn test_scroll_stitcher_total_drift() {
+    let mut stitcher = ScrollStitcher::new();
+    stitcher.push(scroll_page("100", &["a"])).unwrap();
+    stitcher.push(scroll_page("100", &["b"])).unwrap();
+    assert_eq!(stitcher.total_drift(), None);
+
+    stitcher.push(scroll_page("104", &["c"])).unwrap();
+    assert_eq!(stitcher.total_drift(), Some((100, 104)));
+    // a page without a total keeps the latest one
+    stitcher.push(scroll_page("null", &["d"])).unwrap();
+    assert_eq!(stitcher.total_drift(), Some((100, 104)));
+    stitcher.push(scroll_page("100", &[])).unwrap();
+    assert_eq!(stitcher.total_drift(), None);
+    assert_eq!(stitcher.hits().count(), 4);
+
+    let mut strict = ScrollStitcher::new().strict_total(2);
+    strict.push(scroll_page("100", &["a"])).unwrap();
+    strict.push(scroll_page("98", &["b"])).unwrap();
+    assert_eq!(strict.total_drift(), Some((100, 98)));
+    match strict.push(scroll_page("103", &["c"])) {
+        Err(Error::TotalDrift { first: 100, latest: 103 }) => (),
+        r => panic!("expected a drift error, got {:?}", r)
+    }
+    assert_eq!(strict.pages(), 2);
+    assert_eq!(strict.hits().count(), 2);
+    assert_eq!(strict.total_drift(), Some((100, 98)));
+    strict.push(scroll_page("101", &[])).unwrap();
+    assert!(strict.is_finished());
+}
+
+#[test]
+fn test_ranked_hits() {
+    let first: Response = serde_json::from_str(&hits_body("25", 10)).unwrap();
+    let ranks: Vec<_> = first.ranked_hits(0).map(|(rank, hit)| (rank, hit.id().to_owned())).collect();
+    assert_eq!(ranks.first(), Some(&(1, "0".to_owned())));
+    assert_eq!(ranks.last(), Some(&(10, "9".to_owned())));
+    assert_eq!(first.ranked_hits(0).len(), 10);
+
+    // the page after the first one starts right after its last rank
+    let next_from = first.page_info(0, 10).next_from.unwrap();
+    let second: Response = serde_json::from_str(&hits_body("25", 10)).unwrap();
+    let ranks: Vec<_> = second.ranked_hits(next_from).map(|(rank, _)| rank).collect();
+    assert_eq!(ranks, (11..21).collect::<Vec<_>>());
+
+    let last: Response = serde_json::from_str(&hits_body("25", 5)).unwrap();
+    assert_eq!(last.ranked_hits(20).map(|(rank, _)| rank).last(), Some(25));
+    let empty: Response = serde_json::from_str(&hits_body("25", 0)).unwrap();
+    assert!(empty.ranked_hits(25).next().is_none());
+}
+
+#[test]
+fn test_scroll_stitcher_ranked_hits() {
+    let mut stitcher = ScrollStitcher::new().dedup_by_id();
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s1", 1, &["a", "b", "c"])).unwrap()).unwrap();
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s2", 1, &["c", "d"])).unwrap()).unwrap();
+    let ranks: Vec<_> = stitcher.ranked_hits().map(|(rank, hit)| (rank, hit.id())).collect();
+    assert_eq!(ranks, vec![(1, "a"), (2, "b"), (3, "c"), (4, "d")]);
+
+    assert_eq!(stitcher.drain().count(), 4);
+    assert!(stitcher.ranked_hits().next().is_none());
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s3", 1, &["e", "f"])).unwrap()).unwrap();
+    let ranks: Vec<_> = stitcher.ranked_hits().map(|(rank, hit)| (rank, hit.id())).collect();
+    assert_eq!(ranks, vec![(5, "e"), (6, "f")]);
+}
+
+#[test]
+fn test_scroll_stitcher_drain_between_pushes() {
+    let mut stitcher = ScrollStitcher::new();
+
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s1", 1, &["a", "b"])).unwrap()).unwrap();
+    assert_eq!(stitcher.drain().count(), 2);
+    assert_eq!(stitcher.hits().count(), 0);
+
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s2", 1, &["c"])).unwrap()).unwrap();
+    let drained: Vec<_> = stitcher.drain().collect();
+    assert_eq!(drained.len(), 1);
+    assert_eq!(drained[0].id(), "c");
+
+    stitcher.push(serde_json::from_str::<Response>(&scroll_body("s3", 1, &[])).unwrap()).unwrap();
+    assert!(stitcher.is_finished());
+    assert_eq!(stitcher.drain().count(), 0);
+}
+
+fn timechart_buckets(aggs: &Value) -> &Vec<Value> {
+    aggs.lookup("timechart.buckets").and_then(Value::as_array).unwrap()
+}
+
+/// Wraps an aggregation tree in a search response and parses it again.
+fn reparse_aggs(aggs: &Value) -> Response {
+    let body = format!(r#"{{"took": 1, "timed_out": false, "_shards": {{"total": 1, "successful": 1, "failed": 0}},
+                            "aggregations": {}}}"#,
+                       serde_json::to_string(aggs).unwrap());
+    elastic_responses::parse_slice(body.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_owned_aggregations_edits_round_trip() {
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let original = owned_rows(deserialized.aggs());
+
+    let mut tree = OwnedAggregations::from(deserialized.aggs());
+    assert_eq!(tree.remove_bucket("per_host", "web-02"), 2);
+    assert_eq!(tree.remove_bucket("per_day", "2017-01-03T00:00:00.000Z"), 1);
+    assert_eq!(tree.remove_bucket("per_day", "1483315200000"), 1);
+    assert_eq!(tree.remove_bucket("no_such_agg", "web-01"), 0);
+    assert_eq!(tree.rename_agg("cpu_avg", "cpu"), 1);
+    assert_eq!(tree.rename_agg("per_host", "host"), 1);
+
+    let reparsed = reparse_aggs(&tree.to_value());
+    let rows = owned_rows(reparsed.aggs());
+    let expected: Vec<OwnedRow> = original.iter()
+        .filter(|row| row.key_string("per_day") == Some("2017-01-01T00:00:00.000Z".to_owned()))
+        .filter(|row| row.key_string("per_host") != Some("web-02".to_owned()))
+        .map(|row| {
+            row.iter()
+                .map(|(column, value)| (column.replace("per_host", "host").replace("cpu_avg", "cpu"), value.clone()))
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows, expected);
+    // the original tree is left as it was
+    assert_eq!(deserialized.aggs().into_iter().count(), original.len());
+
+    let s = load_file("tests/samples/aggregation_missing.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let mut tree = OwnedAggregations::from(deserialized.aggs());
+    // below the `errors` filter
+    assert_eq!(tree.retain_buckets("by_code", |_, bucket| bucket.find("doc_count").and_then(Value::as_u64) != Some(2)), 1);
+    assert_eq!(tree.rename_agg("doc_count", "count"), 0);
+    assert_eq!(tree.rename_agg("no_host", "unknown_host"), 2);
+    let reparsed = reparse_aggs(&tree.to_value());
+    let rows: Vec<_> = reparsed.aggs().into_iter().collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|row| row.contains_key("unknown_host_doc_count") && !row.contains_key("by_code")));
+}
+
+#[test]
+fn test_column_stats() {
+    let s = load_file("tests/samples/aggregation_column_stats.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    // the `by_level` rows have no `cpu_avg` and aren't counted; `null` and `"NaN"` are nulls
+    let stats = deserialized.aggs().column_stats("cpu_avg");
+    assert_eq!(stats,
+               ColumnStats {
+                   count: 5,
+                   nulls: 2,
+                   min: Some(10.0),
+                   max: Some(50.0),
+                   mean: Some(30.0),
+                   sum: 150.0
+               });
+
+    let by_host = deserialized.aggs().column_stats_grouped("per_host", "cpu_avg");
+    assert_eq!(by_host.keys().collect::<Vec<_>>(), vec!["db-01", "web-01", "web-02"]);
+    assert_eq!((by_host["web-01"].count, by_host["web-01"].nulls, by_host["web-01"].mean), (2, 1, Some(45.0)));
+    assert_eq!((by_host["web-02"].min, by_host["web-02"].max, by_host["web-02"].sum), (Some(20.0), Some(30.0), 50.0));
+    assert_eq!((by_host["db-01"].count, by_host["db-01"].mean), (1, Some(10.0)));
+
+    let by_day = deserialized.aggs().column_stats_grouped("per_day", "cpu_avg");
+    assert_eq!(by_day["2017-01-01"].mean, Some(35.0));
+    assert_eq!((by_day["2017-01-02"].count, by_day["2017-01-02"].nulls), (1, 1));
+
+    let doc_counts = deserialized.aggs().column_stats("by_level_doc_count");
+    assert_eq!((doc_counts.count, doc_counts.sum, doc_counts.mean), (2, 19.0, Some(9.5)));
+
+    let missing = deserialized.aggs().column_stats("no_such_column");
+    assert_eq!(missing, ColumnStats::default());
+    assert_eq!((missing.count, missing.min, missing.mean, missing.sum), (0, None, None, 0.0));
+}
+
+#[test]
+fn test_rows_for_one_root_aggregation() {
+    let s = load_file("tests/samples/aggregation_column_stats.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let all = owned_rows(deserialized.aggs());
+
+    let per_day: Vec<OwnedRow> = deserialized.aggs()
+        .rows_for("per_day")
+        .unwrap()
+        .map(|row| row.into_iter().map(|(k, v)| (k.into_owned(), v.clone())).collect())
+        .collect();
+    let by_level: Vec<_> = deserialized.aggs().rows_for("by_level").unwrap().collect();
+    assert_eq!(per_day.len(), 7);
+    assert_eq!(by_level.len(), 2);
+    assert_eq!(per_day, all.iter().filter(|row| row.contains_key("per_day")).cloned().collect::<Vec<_>>());
+    assert!(per_day.iter().all(|row| row.keys().all(|column| !column.starts_with("by_level"))));
+    assert!(by_level.iter().all(|row| row.keys().all(|column| column.starts_with("by_level"))));
+
+    match deserialized.aggs().rows_for("per_host") {
+        Err(ref err @ AggError::UnknownAggregation(_)) => {
+            assert_eq!(err.to_string(), "no bucket aggregation named `per_host` at the root")
+        }
+        other => panic!("expected an unknown aggregation, got {:?}", other.map(|rows| rows.count()))
+    }
+    let s = load_file("tests/samples/aggregation_simple.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert!(deserialized.aggs().rows_for("no_such_agg").is_err());
+}
+
+#[test]
+fn test_root_single_bucket_aggs() {
+    let s = load_file("tests/samples/aggregation_root_missing.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let rows = owned_rows(deserialized.aggs());
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], owned_row(vec![("no_host_doc_count", Value::U64(3)), ("avg_x", Value::F64(2.0))]));
+    assert_eq!(rows[1]["per_host"], Value::String("web-01".to_owned()));
+    let no_host: Vec<_> = deserialized.aggs().rows_for("no_host").unwrap().collect();
+    assert_eq!(no_host.len(), 1);
+    assert_eq!(deserialized.aggs().columns(), vec!["avg_x", "no_host_doc_count", "per_host", "per_host_doc_count"]);
+    assert_eq!(deserialized.aggs().key_columns(), vec!["per_host"]);
+
+    let s = load_file("tests/samples/aggregation_root_filter_terms.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let rows = owned_rows(deserialized.aggs());
+    assert_eq!(rows,
+               vec![owned_row(vec![("errors_doc_count", Value::U64(12)),
+                                   ("by_host", Value::String("web-01".to_owned())),
+                                   ("by_host_doc_count", Value::U64(8)),
+                                   ("latency", Value::F64(30.0))]),
+                    owned_row(vec![("errors_doc_count", Value::U64(12)),
+                                   ("by_host", Value::String("web-02".to_owned())),
+                                   ("by_host_doc_count", Value::U64(4)),
+                                   ("latency", Value::F64(50.0))])]);
+    assert_eq!(deserialized.aggs().rows_for("errors").unwrap().count(), 2);
+    assert_eq!(deserialized.aggs().key_columns(), vec!["by_host"]);
+    assert_eq!(deserialized.aggs().bucket_counts().get("errors>by_host"), Some(&2));
+}
+
+#[cfg(feature = "prometheus")]
+#[test]
+fn test_prometheus_lines() {
+    use elastic_responses::PrometheusLines;
+
+    let s = load_file("tests/samples/aggregation_prometheus.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let lines = deserialized.aggs().to_prometheus("http_latency_ms", "latency", &["region", "status"]);
+    assert_eq!(lines,
+               "http_latency_ms{region=\"eu-west\",status=\"200\"} 12.5\n\
+                http_latency_ms{region=\"us \\\"east\\\"\\\\1\",status=\"200\"} 40\n");
+
+    let lines = PrometheusLines::new("http_requests", "status_doc_count")
+        .label("status")
+        .timestamp(1528000000000)
+        .render(deserialized.aggs());
+    assert_eq!(lines,
+               "http_requests{status=\"200\"} 10 1528000000000\n\
+                http_requests{status=\"503\"} 2 1528000000000\n\
+                http_requests{status=\"200\"} 8 1528000000000\n");
+
+    // labels without a value are left out, and so are the braces without any labels
+    let lines = deserialized.aggs().to_prometheus("requests", "status_doc_count", &["host"]);
+    assert_eq!(lines.lines().next(), Some("requests 10"));
+    assert_eq!(deserialized.aggs().to_prometheus("requests", "region", &[]), "");
+}
+
+#[test]
+fn test_significant_terms_lift() {
+    let s = load_file("tests/samples/aggregation_significant_terms.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let rows = owned_rows(deserialized.aggs());
+    let columns = deserialized.aggs().columns();
+    for column in &["significant_crime_types_bg_count", "significant_crime_types_score",
+                    "significant_crime_types_subset_size", "significant_crime_types_superset_size",
+                    "significance_subset_size"] {
+        assert!(columns.contains(&column.to_string()), "{}", column);
+    }
+
+    // 3640 of the 47347 documents of the foreground set against 66799 of the 5064554 of the background
+    let crime_type = |key: &str| rows.iter().find(|row| row.get_str("significant_crime_types") == Some(key)).unwrap();
+    let bicycles = crime_type("Bicycle theft");
+    let lift = bicycles.significance_lift("significant_crime_types").unwrap();
+    assert!((lift - (3640.0 / 47347.0) / (66799.0 / 5064554.0)).abs() < 1e-9);
+    assert!((lift - 5.8287).abs() < 1e-3, "{}", lift);
+    let shoplifting = crime_type("Shoplifting").significance_lift("significant_crime_types").unwrap();
+    assert!((shoplifting - 38.5782).abs() < 1e-3, "{}", shoplifting);
+
+    // nested aggregations are normalized by the totals of their own parent bucket
+    let robbery = rows.iter().find(|row| row.get_str("significance") == Some("Robbery")).unwrap();
+    let lift = robbery.significance_lift("significance").unwrap();
+    assert!((lift - (27617.0 / 894038.0) / (53182.0 / 5064554.0)).abs() < 1e-9);
+    assert_eq!(robbery.significance_lift("significant_crime_types"), None);
+
+    let mut empty = robbery.clone();
+    empty.insert("significance_subset_size".to_owned(), Value::U64(0));
+    assert_eq!(empty.significance_lift("significance"), None);
+    empty = robbery.clone();
+    empty.insert("significance_bg_count".to_owned(), Value::U64(0));
+    assert_eq!(empty.significance_lift("significance"), None);
+
+    let top: Vec<_> = deserialized.aggs()
+        .top_significant("significant_crime_types", 2)
+        .iter()
+        .map(|row| row.get_str("significant_crime_types").unwrap().to_owned())
+        .collect();
+    assert_eq!(top, vec!["Bicycle theft", "Shoplifting"]);
+    assert_eq!(deserialized.aggs().top_significant("significance", 5).len(), 2);
+    assert!(deserialized.aggs().top_significant("forces", 5).is_empty());
+
+    let forces: Vec<OwnedRow> = deserialized.aggs()
+        .rows_for("forces")
+        .unwrap()
+        .map(|row| row.into_iter().map(|(k, v)| (k.into_owned(), v.clone())).collect())
+        .collect();
+    assert_eq!(forces, rows.iter().filter(|row| row.contains_key("forces")).cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_fill_gaps() {
+    let s = load_file("tests/samples/aggregation_gaps.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let day = Duration::from_secs(86400);
+    let first = 1527811200000u64;
+    let keys = |rows: &[OwnedRow]| -> Vec<u64> {
+        rows.iter().map(|row| (row["per_day"].as_u64().unwrap() - first) / 86400000).collect()
+    };
+
+    // June 3rd is missing, and so are June 5th and 6th
+    let zero = deserialized.aggs().fill_gaps("per_day", Some(day), FillStrategy::Zero).unwrap();
+    assert_eq!(keys(&zero), vec![0, 1, 2, 3, 4, 5, 6]);
+    let counts: Vec<_> = zero.iter().map(|row| row["per_day_doc_count"].as_u64().unwrap()).collect();
+    assert_eq!(counts, vec![4, 6, 0, 2, 0, 0, 5]);
+    assert_eq!(zero[2], owned_row(vec![("per_day", Value::U64(first + 2 * 86400000)),
+                                       ("per_day_doc_count", Value::U64(0))]));
+    assert_eq!(zero[3].get_str("per_day_key_as_string"), Some("2018-06-04"));
+
+    // the interval is inferred from the closest keys
+    assert_eq!(deserialized.aggs().fill_gaps("per_day", None, FillStrategy::Zero).unwrap(), zero);
+
+    let null = deserialized.aggs().fill_gaps("per_day", None, FillStrategy::Null).unwrap();
+    assert_eq!(null[4].get("per_day_doc_count"), Some(&Value::Null));
+    assert_eq!(null[4].get("latency"), Some(&Value::Null));
+    assert_eq!(null[4].get("per_day_key_as_string"), None);
+
+    let previous = deserialized.aggs().fill_gaps("per_day", None, FillStrategy::PreviousValue).unwrap();
+    let latencies: Vec<_> = previous.iter().map(|row| row.get_f64("latency").unwrap()).collect();
+    assert_eq!(latencies, vec![12.5, 20.0, 20.0, 8.0, 8.0, 8.0, 15.0]);
+    assert_eq!(keys(&previous), vec![0, 1, 2, 3, 4, 5, 6]);
+
+    match deserialized.aggs().fill_gaps("per_day", Some(day * 2), FillStrategy::Zero) {
+        Err(ref err @ AggError::IrregularInterval(..)) => {
+            assert!(err.to_string().starts_with("buckets of `per_day` are 86400000ms apart"), "{}", err);
+            assert!(err.to_string().contains("not calendar intervals"), "{}", err);
+        }
+        other => panic!("expected an irregular interval, got {:?}", other)
+    }
+    assert_eq!(deserialized.aggs().fill_gaps("by_host", None, FillStrategy::Zero),
+               Err(AggError::NotEpochMillis("by_host".to_owned())));
+    assert_eq!(deserialized.aggs().fill_gaps("per_week", None, FillStrategy::Zero),
+               Err(AggError::UnknownAggregation("per_week".to_owned())));
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct TopHitsLog {
+    host: String,
+    message: String
+}
+
+#[test]
+fn test_top_hits_envelope() {
+    // a numeric total outside, and total objects inside
+    let s = load_file("tests/samples/aggregation_top_hits.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(deserialized.hits().total().map(|t| (t.value(), t.relation())), Some((20, TotalHitsRelation::Eq)));
+
+    let rows = owned_rows(deserialized.aggs());
+    assert_eq!(rows[0].get("latest_total"), Some(&Value::U64(12)));
+    assert_eq!(rows[0].get_f64("latest_max_score"), Some(1.5));
+    assert_eq!(rows[1].get("latest_total"), Some(&Value::U64(8)));
+    assert_eq!(rows[1].get("latest_max_score"), Some(&Value::Null));
+    assert!(!rows[0].contains_key("total") && !rows[0].contains_key("max_score"));
+
+    let latest = deserialized.aggs().top_hits::<TopHitsLog>("latest").unwrap();
+    let totals: Vec<_> = latest.iter().map(|hits| hits.total().map(|t| (t.value(), t.relation()))).collect();
+    assert_eq!(totals, vec![Some((12, TotalHitsRelation::Eq)), Some((8, TotalHitsRelation::Gte))]);
+    assert_eq!(latest[1].exact_total(), None);
+    assert_eq!(latest[0].max_score(), Some(1.5));
+    let log = TopHitsLog { host: "web-02".to_owned(), message: "restarted".to_owned() };
+    assert_eq!(latest[1].iter().next().and_then(|hit| hit.source()), Some(&log));
+    assert!(deserialized.aggs().top_hits::<Value>("by_host").unwrap().is_empty());
+    assert!(deserialized.aggs().top_hits::<u64>("latest").is_err());
+
+    // the columns are the same with the numeric totals of older versions
+    let s = load_file("tests/samples/aggregation_top_hits_es6.json");
+    let es6: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(owned_rows(es6.aggs()), rows);
+    let totals: Vec<_> = es6.aggs().top_hits::<Value>("latest").unwrap().iter().map(|h| h.exact_total()).collect();
+    assert_eq!(totals, vec![Some(12), Some(8)]);
+}
+
+/// Splits a rewritten query into the prefix, field, text, kind and boost of each clause.
+fn rewritten_clauses(query: &str) -> Vec<(Occur, Option<String>, String, ClauseKind, Option<f64>)> {
+    let query = RewrittenQuery::new(query);
+    match query.parse() {
+        ParsedQuery::Clauses(clauses) => {
+            clauses.into_iter()
+                .map(|c| (c.occur, c.field.map(str::to_owned), c.text.to_owned(), c.kind, c.boost))
+                .collect()
+        }
+        ParsedQuery::Raw(raw) => panic!("expected clauses, got {:?}", raw),
+    }
+}
+
+#[test]
+fn test_rewritten_query_clauses() {
+    let f = |s: &str| Some(s.to_owned());
+    let t = |s: &str| s.to_owned();
+
+    assert_eq!(rewritten_clauses("+user:kimchy #tag:rust^2.0 -status:deleted title:\"quick fox\""),
+               vec![(Occur::Must, f("user"), t("kimchy"), ClauseKind::Term, None),
+                    (Occur::Filter, f("tag"), t("rust"), ClauseKind::Term, Some(2.0)),
+                    (Occur::MustNot, f("status"), t("deleted"), ClauseKind::Term, None),
+                    (Occur::Should, f("title"), t("\"quick fox\""), ClauseKind::Phrase, None)]);
+    assert_eq!(rewritten_clauses("(title:quick title:fox)~1 +age:[10 TO 20} #*:*"),
+               vec![(Occur::Should, None, t("(title:quick title:fox)~1"), ClauseKind::Group, None),
+                    (Occur::Must, f("age"), t("[10 TO 20}"), ClauseKind::Range, None),
+                    (Occur::Filter, f("*"), t("*"), ClauseKind::Term, None)]);
+    assert_eq!(rewritten_clauses("+ConstantScore(user:kimchy)^3.0 SynonymQuery(Synonyms(body:car body:automobile))"),
+               vec![(Occur::Must, None, t("ConstantScore(user:kimchy)"), ClauseKind::Query, Some(3.0)),
+                    (Occur::Should,
+                     None,
+                     t("SynonymQuery(Synonyms(body:car body:automobile))"),
+                     ClauseKind::Query,
+                     None)]);
+    assert_eq!(rewritten_clauses("FieldExistsQuery [field=user] MatchNoDocsQuery(\"no mappings (yet)\")"),
+               vec![(Occur::Should, None, t("FieldExistsQuery [field=user]"), ClauseKind::Query, None),
+                    (Occur::Should, None, t("MatchNoDocsQuery(\"no mappings (yet)\")"), ClauseKind::Query, None)]);
+    assert_eq!(rewritten_clauses("name:o\\\"neil* url:http://example.com"),
+               vec![(Occur::Should, f("name"), t("o\\\"neil*"), ClauseKind::Term, None),
+                    (Occur::Should, f("url"), t("http://example.com"), ClauseKind::Term, None)]);
+    assert_eq!(rewritten_clauses("path:c\\\\temp\\"),
+               vec![(Occur::Should, f("path"), t("c\\\\temp\\"), ClauseKind::Term, None)]);
+
+    // the spans cover whole clauses, so they can be cut out of the query for highlighting
+    let query = RewrittenQuery::new("+user:kimchy  title:fox^1.5");
+    match query.parse() {
+        ParsedQuery::Clauses(ref clauses) => {
+            let spans: Vec<&str> = clauses.iter().map(|c: &Clause| &query.as_str()[c.span.clone()]).collect();
+            assert_eq!(spans, vec!["+user:kimchy", "title:fox^1.5"]);
+        }
+        ref other => panic!("expected clauses, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_rewritten_query_gives_up() {
+    for raw in &["",
+                 "   ",
+                 "+user:(kimchy",
+                 "title:\"unterminated",
+                 "a) b",
+                 "+",
+                 "user: +",
+                 "ConstantScore(tag:rust]"] {
+        assert_eq!(RewrittenQuery::new(*raw).parse(), ParsedQuery::Raw(raw), "{:?}", raw);
+    }
+}
+
+#[test]
+fn test_validate_query_rewrite() {
+    let s = load_file("tests/samples/validate_query_rewrite.json");
+    let response: ValidateQueryResponse = parse_http_response(200, s.as_bytes()).unwrap();
+    assert!(!response.valid());
+    assert_eq!(response.shards().map(|s| s.total()), Some(2));
+
+    let explanations = response.explanations();
+    assert_eq!(explanations[0].index(), Some("tweets"));
+    assert!(explanations[0].valid());
+    let rewritten = explanations[0].rewritten().unwrap();
+    assert_eq!(rewritten.as_str(), "+user:kimchy #ConstantScore(tag:rust) -status:deleted");
+    match rewritten.parse() {
+        ParsedQuery::Clauses(clauses) => {
+            let occurs: Vec<Occur> = clauses.iter().map(|c| c.occur).collect();
+            assert_eq!(occurs, vec![Occur::Must, Occur::Filter, Occur::MustNot]);
+            assert_eq!(clauses[1].kind, ClauseKind::Query);
+        }
+        other => panic!("expected clauses, got {:?}", other),
+    }
+
+    assert!(!explanations[1].valid());
+    assert!(explanations[1].rewritten().is_none());
+    assert!(explanations[1].error().unwrap().contains("does not support term queries"));
+
+    let plain: ValidateQueryResponse = serde_json::from_str(r#"{"valid": true}"#).unwrap();
+    assert!(plain.valid());
+    assert!(plain.explanations().is_empty());
+}
+
+#[test]
+fn test_search_profile() {
+    let s = load_file("tests/samples/search_profile.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let shards = deserialized.profile().unwrap().shards();
+    assert_eq!(shards[0].id(), "[2aE02wS1R8q_QFnYu6vDVQ][tweets][0]");
+
+    let search = &shards[0].searches()[0];
+    assert_eq!(search.rewrite_time(), 51443);
+    let query = &search.queries()[0];
+    assert_eq!(query.query_type(), "BooleanQuery");
+    assert_eq!(query.time_in_nanos(), 1873811);
+    assert_eq!(rewritten_clauses(query.rewritten().as_str()),
+               vec![(Occur::Must, Some("message".to_owned()), "search".to_owned(), ClauseKind::Term, None),
+                    (Occur::Should, Some("message".to_owned()), "test".to_owned(), ClauseKind::Term, Some(2.0)),
+                    (Occur::Filter, Some("user".to_owned()), "kimchy".to_owned(), ClauseKind::Term, None)]);
+
+    let boosted = &query.children()[1];
+    assert_eq!(boosted.query_type(), "BoostQuery");
+    match boosted.rewritten().parse() {
+        ParsedQuery::Clauses(clauses) => {
+            assert_eq!((clauses[0].kind, clauses[0].text, clauses[0].boost),
+                       (ClauseKind::Group, "(message:test)", Some(2.0)));
+        }
+        other => panic!("expected clauses, got {:?}", other),
+    }
+    assert_eq!(boosted.children()[0].rewritten().as_str(), "message:test");
+
+    // the queries are held to the depth limit of the aggregations
+    let cut = ResponseParser::new().max_depth(2).parse_slice::<Value>(s.as_bytes()).unwrap();
+    let query = &cut.profile().unwrap().shards()[0].searches()[0].queries()[0];
+    assert_eq!(query.children().len(), 3);
+    assert!(query.children()[1].children().is_empty());
+    match ResponseParser::new().max_depth(2).strict_depth().parse_slice::<Value>(s.as_bytes()) {
+        Err(err @ Error::DepthExceeded { .. }) => {
+            assert_eq!(err.to_string(),
+                       "query at `/profile/shards/0/searches/0/query/0/children/1/children/0` is nested 3 levels deep")
+        }
+        other => panic!("expected the depth to be exceeded, got {:?}", other.map(|r| r.took()))
+    }
+
+    let unprofiled: Response = serde_json::from_str(&hits_body("1", 1)).unwrap();
+    assert!(unprofiled.profile().is_none());
+}
+
+/// Sums up any envelope of hits, to check that code written against one works against all.
+fn envelope_summary<T: serde::Deserialize>(envelope: &HitsEnvelope<T>) -> (Option<u64>, Option<f64>, Vec<&str>, bool) {
+    let ids = envelope.hits().iter().map(|hit| hit.id()).collect();
+    (envelope.total().map(|t| t.value()), envelope.max_score(), ids, envelope.is_empty())
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Comment {
+    author: String,
+    text: String
+}
+
+#[test]
+fn test_hits_envelopes() {
+    let s = load_file("tests/samples/hits_inner_hits.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(envelope_summary(deserialized.hits()), (Some(2), Some(2.25), vec!["1", "2"], false));
+
+    let posts = deserialized.hits().hits();
+    let comments = posts[0].inner_hits::<Comment>("comments").unwrap().unwrap();
+    assert_eq!(envelope_summary(&comments), (Some(2), Some(1.75), vec!["1", "1"], false));
+    let first = Comment { author: "bob".to_owned(), text: "thanks".to_owned() };
+    assert_eq!(comments.hits()[0].source(), Some(&first));
+    assert_eq!(comments.iter().filter_map(|hit| hit.score()).collect::<Vec<_>>(), vec![1.75, 0.5]);
+
+    let none = posts[1].inner_hits::<Comment>("comments").unwrap().unwrap();
+    assert_eq!(envelope_summary(&none), (Some(0), None, vec![], true));
+    assert!(posts[0].inner_hits::<Comment>("likes").unwrap().is_none());
+    assert!(posts[0].inner_hits::<u64>("comments").is_err());
+
+    let s = load_file("tests/samples/aggregation_top_hits.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let latest = deserialized.aggs().top_hits::<Value>("latest").unwrap();
+    assert_eq!(envelope_summary(&latest[0]), (Some(12), Some(1.5), vec!["7"], false));
+
+    // the numeric totals of older versions read the same
+    let s = load_file("tests/samples/aggregation_top_hits_es6.json");
+    let es6: Response = serde_json::from_str(&s).unwrap();
+    let latest_es6 = es6.aggs().top_hits::<Value>("latest").unwrap();
+    assert_eq!(envelope_summary(&latest_es6[0]), envelope_summary(&latest[0]));
+}
+
+#[test]
+fn test_response_emptiness() {
+    let s = load_file("tests/samples/response_empty.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(deserialized.count_returned(), 0);
+    assert!(deserialized.has_aggregations());
+    assert!(deserialized.is_empty());
+
+    // no hits on the page, but buckets with documents
+    let s = load_file("tests/samples/aggregation_simple.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(deserialized.count_returned(), 0);
+    assert!(deserialized.has_aggregations());
+    assert!(!deserialized.is_empty());
+
+    // hits, but no aggregations
+    let s = load_file("tests/samples/hits_es8.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(deserialized.count_returned(), 2);
+    assert!(!deserialized.has_aggregations());
+    assert!(!deserialized.is_empty());
+
+    // a single bucket with documents, a single non-`null` metric or keyed bucket is enough
+    let mut tree: Value = serde_json::from_str(&load_file("tests/samples/response_empty.json")).unwrap();
+    for &(agg, field, ref value) in &[("errors", "doc_count", Value::U64(3)),
+                                  ("avg_latency", "value", Value::F64(12.5)),
+                                  ("latency_stats", "count", Value::U64(1))] {
+        let mut tree = tree.clone();
+        tree.as_object_mut().unwrap().get_mut("aggregations").unwrap().as_object_mut().unwrap()
+            .get_mut(agg).unwrap().as_object_mut().unwrap().insert(field.to_owned(), value.clone());
+        let deserialized: Response = serde_json::from_value(tree).unwrap();
+        assert!(!deserialized.is_empty(), "{} with {}", agg, field);
+    }
+    {
+        let by_size = tree.as_object_mut().unwrap().get_mut("aggregations").unwrap().as_object_mut().unwrap()
+            .get_mut("by_size").unwrap().as_object_mut().unwrap();
+        let large = by_size.get_mut("buckets").unwrap().as_object_mut().unwrap().get_mut("large").unwrap();
+        large.as_object_mut().unwrap().insert("doc_count".to_owned(), Value::U64(8));
+    }
+    let deserialized: Response = serde_json::from_value(tree).unwrap();
+    assert!(!deserialized.is_empty());
+}
+
+#[test]
+fn test_merge_matching_buckets() {
+    let a: Response = serde_json::from_str(&load_file("tests/samples/aggregation_3level.json")).unwrap();
+    let b: Response = serde_json::from_str(&load_file("tests/samples/aggregation_3level_multichild.json")).unwrap();
+
+    let merged = a.aggs().merge(b.aggs(), MergeStrategy::WeightedMean).into_value();
+    let buckets = timechart_buckets(&merged);
+
+    assert_eq!(buckets.len(), 31);
+    assert_eq!(buckets[0].find("doc_count"), Some(&Value::U64(112)));
+
+    let hosts = buckets[0].lookup("hosts.buckets").and_then(Value::as_array).unwrap();
+    assert_eq!(hosts.len(), 2);
+    assert_eq!(hosts[0].find("key"), Some(&Value::String("0060e063505c".to_owned())));
+    assert_eq!(hosts[0].find("doc_count"), Some(&Value::U64(66)));
+    assert_eq!(hosts[1].find("doc_count"), Some(&Value::U64(46)));
+}
+
+#[test]
+fn test_merge_disjoint_buckets_survive() {
+    let a: Response = serde_json::from_str(&load_file("tests/samples/aggregation_simple.json")).unwrap();
+    let b: Response = serde_json::from_str(&load_file("tests/samples/aggregation_3level.json")).unwrap();
+
+    let merged = a.aggs().merge(b.aggs(), MergeStrategy::Sum);
+
+    assert_eq!(timechart_buckets(&merged.clone().into_value()).len(), 62);
+    assert_eq!(merged.into_iter().count(), 124 + 201);
+}
+
+#[test]
+fn test_merge_metrics() {
+    let a = json_str!({
+        "took": 1, "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 },
+        "hits": { "total": 4, "max_score": 0, "hits": [] },
+        "aggregations": {
+            "hosts": {
+                "sum_other_doc_count": 1,
+                "buckets": [
+                    { "key": "a", "doc_count": 1, "cpu": { "value": 10 },
+                      "bytes": { "count": 1, "min": 5, "max": 5, "avg": 5, "sum": 5 } },
+                    { "key": "b", "doc_count": 3, "cpu": { "value": 30 } }
+                ]
+            }
+        }
+    });
+    let b = json_str!({
+        "took": 1, "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 },
+        "hits": { "total": 4, "max_score": 0, "hits": [] },
+        "aggregations": {
+            "hosts": {
+                "sum_other_doc_count": 2,
+                "buckets": [
+                    { "key": "a", "doc_count": 3, "cpu": { "value": 20 },
+                      "bytes": { "count": 3, "min": 1, "max": 9, "avg": 1, "sum": 3 } },
+                    { "key": "c", "doc_count": 1, "cpu": { "value": null } }
+                ]
+            }
+        }
+    });
+    let a: Response = serde_json::from_str(&a).unwrap();
+    let b: Response = serde_json::from_str(&b).unwrap();
+
+    let mean = a.aggs().merge(b.aggs(), MergeStrategy::WeightedMean).into_value();
+    assert_eq!(mean.lookup("hosts.sum_other_doc_count"), Some(&Value::U64(3)));
+    let buckets = mean.lookup("hosts.buckets").and_then(Value::as_array).unwrap();
+    assert_eq!(buckets.len(), 3);
+    assert_eq!(buckets[0].lookup("cpu.value"), Some(&Value::F64(17.5)));
+    assert_eq!(buckets[0].lookup("bytes.count"), Some(&Value::U64(4)));
+    assert_eq!(buckets[0].lookup("bytes.min"), Some(&Value::U64(1)));
+    assert_eq!(buckets[0].lookup("bytes.max"), Some(&Value::U64(9)));
+    assert_eq!(buckets[0].lookup("bytes.avg"), Some(&Value::U64(2)));
+    assert_eq!(buckets[0].lookup("bytes.sum"), Some(&Value::U64(8)));
+    assert_eq!(buckets[1].find("key"), Some(&Value::String("b".to_owned())));
+    assert_eq!(buckets[2].find("key"), Some(&Value::String("c".to_owned())));
+
+    let max = a.aggs().merge(b.aggs(), MergeStrategy::Max).into_value();
+    assert_eq!(max.lookup("hosts.buckets").and_then(Value::as_array).unwrap()[0].lookup("cpu.value"), Some(&Value::U64(20)));
+
+    fn first(a: f64, _: u64, _: f64, _: u64) -> f64 { a }
+    let custom = a.aggs().merge(b.aggs(), MergeStrategy::Custom(first)).into_value();
+    assert_eq!(custom.lookup("hosts.buckets").and_then(Value::as_array).unwrap()[0].lookup("cpu.value"), Some(&Value::U64(10)));
+}
+
+#[test]
+fn test_merge_single_bucket_and_keyed_aggs() {
+    let a = json_str!({
+        "took": 1, "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 },
+        "hits": { "total": 4, "max_score": 0, "hits": [] },
+        "aggregations": {
+            "errors": { "doc_count": 3, "latency": { "value": 1.0 } },
+            "sizes": {
+                "buckets": {
+                    "small": { "doc_count": 1, "latency": { "value": 2.0 } },
+                    "large": { "doc_count": 2, "latency": { "value": 8.0 } }
+                }
+            }
+        }
+    });
+    let b = json_str!({
+        "took": 1, "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 },
+        "hits": { "total": 4, "max_score": 0, "hits": [] },
+        "aggregations": {
+            "errors": { "doc_count": 4, "latency": { "value": 5.0 } },
+            "sizes": {
+                "buckets": {
+                    "small": { "doc_count": 3, "latency": { "value": 4.0 } },
+                    "medium": { "doc_count": 5, "latency": { "value": 6.0 } }
+                }
+            }
+        }
+    });
+    let a: Response = serde_json::from_str(&a).unwrap();
+    let b: Response = serde_json::from_str(&b).unwrap();
+
+    let max = a.aggs().merge(b.aggs(), MergeStrategy::Max).into_value();
+    assert_eq!(max.lookup("errors.doc_count"), Some(&Value::U64(7)));
+    assert_eq!(max.lookup("errors.latency.value"), Some(&Value::F64(5.0)));
+    assert_eq!(max.lookup("sizes.buckets.small.doc_count"), Some(&Value::U64(4)));
+    assert_eq!(max.lookup("sizes.buckets.small.latency.value"), Some(&Value::F64(4.0)));
+    assert_eq!(max.lookup("sizes.buckets.large.doc_count"), Some(&Value::U64(2)));
+    assert_eq!(max.lookup("sizes.buckets.medium.doc_count"), Some(&Value::U64(5)));
+
+    let mean = a.aggs().merge(b.aggs(), MergeStrategy::WeightedMean).into_value();
+    assert_eq!(mean.lookup("errors.latency.value"), Some(&Value::F64(23.0 / 7.0)));
+    assert_eq!(mean.lookup("sizes.buckets.small.latency.value"), Some(&Value::F64(3.5)));
+}
+
+#[test]
+fn test_merge_large_counts() {
+    let half = u64::max_value() / 2;
+    let body = |count: u64| {
+        format!(r#"{{"took": 1, "timed_out": false, "_shards": {{"total": 1, "successful": 1, "failed": 0}},
+                    "aggregations": {{
+                        "hosts": {{
+                            "sum_other_doc_count": {0},
+                            "buckets": [{{ "key": "a", "doc_count": {0}, "bytes": {{ "count": {0}, "sum": {0} }} }}]
+                        }}
+                    }}}}"#,
+                count)
+    };
+    let a: Response = serde_json::from_str(&body(half)).unwrap();
+    let b: Response = serde_json::from_str(&body(half + 1)).unwrap();
+
+    let merged = a.aggs().merge(b.aggs(), MergeStrategy::Sum).into_value();
+    let total = Value::U64(u64::max_value());
+    assert_eq!(merged.lookup("hosts.sum_other_doc_count"), Some(&total));
+    let bucket = &merged.lookup("hosts.buckets").and_then(Value::as_array).unwrap()[0];
+    assert_eq!(bucket.find("doc_count"), Some(&total));
+    assert_eq!(bucket.lookup("bytes.count"), Some(&total));
+    assert_eq!(bucket.lookup("bytes.sum"), Some(&total));
+
+    // an overflowing sum is added as floats
+    let overflow = a.aggs().merge(reparse_aggs(&merged).aggs(), MergeStrategy::Sum).into_value();
+    assert!(overflow.lookup("hosts.sum_other_doc_count").map_or(false, Value::is_f64));
+}
+
+fn host_keys(rows: &[OwnedRow]) -> Vec<&str> {
+    rows.iter().map(|row| row["hosts"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn test_top_n_by_nested_avg() {
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let top = deserialized.aggs().top_n("cpu", 3, Order::Desc);
+    assert_eq!(host_keys(&top), vec!["web-01", "web-02", "web-03"]);
+    assert_eq!(top[0]["hosts_doc_count"], Value::U64(10));
+
+    let bottom = deserialized.aggs().top_n("cpu", 10, Order::Asc);
+    assert_eq!(host_keys(&bottom), vec!["cache-01", "web-03", "web-01", "web-02", "db-01"]);
+}
+
+#[test]
+fn test_sorted_rows_missing_column_last() {
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let rows: Vec<_> = deserialized.aggs().sorted_rows("hosts_doc_count", Order::Asc).collect();
+    assert_eq!(host_keys(&rows), vec!["cache-01", "web-01", "db-01", "web-02", "web-03"]);
+
+    let rows: Vec<_> = deserialized.aggs().sorted_rows("no_such_column", Order::Desc).collect();
+    assert_eq!(host_keys(&rows), vec!["cache-01", "db-01", "web-01", "web-02", "web-03"]);
+}
+
+#[test]
+fn test_pivot_date_terms_avg() {
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let raw: Value = serde_json::from_str(&s).unwrap();
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let pivot = deserialized.aggs().pivot(&["per_day", "per_host"], "cpu_avg").unwrap();
+
+    assert_eq!(pivot.len(), 3);
+    assert_eq!(pivot["2017-01-01T00:00:00.000Z"].get("web-02"),
+               raw.pointer("/aggregations/per_day/buckets/0/per_host/buckets/1/cpu_avg/value"));
+    assert_eq!(pivot["2017-01-02T00:00:00.000Z"].get("db-01"),
+               raw.pointer("/aggregations/per_day/buckets/1/per_host/buckets/1/cpu_avg/value"));
+    assert!(!pivot["2017-01-03T00:00:00.000Z"].contains_key("web-02"));
+}
+
+#[test]
+fn test_pivot_owned_rows_and_conflicts() {
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let mut rows = deserialized.aggs().sorted_rows("cpu_avg", Order::Desc).collect::<Vec<_>>();
+    let pivot = pivot_rows(&rows, &["per_day", "per_host"], "per_host_doc_count").unwrap();
+    assert_eq!(pivot["2017-01-02T00:00:00.000Z"]["web-02"], Value::U64(15));
+
+    let mut duplicate = rows[0].clone();
+    duplicate.insert("cpu_avg".to_owned(), Value::F64(1.0));
+    rows.push(duplicate);
+    assert_eq!(pivot_rows(&rows, &["per_day", "per_host"], "cpu_avg"),
+               Err(PivotError::Conflict("2017-01-02T00:00:00.000Z".to_owned(), "web-02".to_owned())));
+}
+
+#[test]
+fn test_pretty_small_response() {
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let expected = "\
+took 3ms, 105 hits, shards 5/5 successful, 0 failed, aggregations: hosts
+cpu   hosts     hosts_doc_count
+----  --------  ---------------
+12.5  web-03    40
+70    web-02    30
+      db-01     20
+70    web-01    10
+3     cache-01  5
+";
+    assert_eq!(deserialized.pretty().to_string(), expected);
+}
+
+#[test]
+fn test_pretty_truncated_large_aggs() {
+    let s = load_file("tests/samples/aggregation_simple.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let expected = "\
+avg_pkts_sent_a2b  hosts         hosts_doc_count  timechart      timechart_doc_count  timechart_key_as_string
+-----------------  ------------  ---------------  -------------  -------------------  -----------------------------
+7.666666666666667  0060e0634ff8  49               1483017510000  101                  2016-12-29T15:18:30.000+02:00
+";
+    let pretty = deserialized.aggs().pretty().max_rows(1).to_string();
+    assert!(pretty.starts_with(expected), "{}", pretty);
+    assert!(pretty.ends_with("... 123 more rows\n"), "{}", pretty);
+    assert_eq!(pretty.lines().count(), 4);
+
+    let summary = deserialized.pretty().max_rows(0).to_string();
+    assert!(summary.starts_with("took 28ms, 18959 hits, shards 86/86 successful, 0 failed, aggregations: timechart\n"));
+    assert!(summary.ends_with("... 124 more rows\n"));
+}
+
+/// Adds an empty keyed sub-aggregation to every bucket of the root aggregation `agg`, which
+/// leaves the rows as they are but takes the tree off the fast path of the exporters.
+#[test]
+fn test_bucket_counts() {
+    let s = load_file("tests/samples/aggregation_bucket_counts.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+
+    let counts = aggs.bucket_counts();
+    let expected: Vec<(&str, usize)> = vec![("per_day", 3),
+                                            ("per_day>errors>per_status", 3),
+                                            ("per_day>per_host", 5),
+                                            ("per_day>quality", 2),
+                                            ("sizes", 3)];
+    assert_eq!(counts.iter().map(|(path, &count)| (&**path, count)).collect::<Vec<_>>(), expected);
+    assert_eq!(aggs.total_buckets(), 16);
+
+    // the keyed aggregations aren't in the rows, and the day without hosts or errors has none
+    assert_eq!(aggs.into_iter().count(), 2 + 2 + 3 + 1);
+
+    let empty: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    assert!(empty.aggs().bucket_counts().is_empty());
+    assert_eq!(empty.aggs().total_buckets(), 0);
+}
+
+fn with_keyed_sub_agg(aggs: &Value, agg: &str) -> Value {
+    let mut aggs = aggs.clone();
+    let buckets = aggs.as_object_mut().unwrap().get_mut(agg).unwrap().as_object_mut().unwrap().get_mut("buckets").unwrap();
+    for bucket in buckets.as_array_mut().unwrap() {
+        let keyed: Value = serde_json::from_str(r#"{"buckets": {}}"#).unwrap();
+        bucket.as_object_mut().unwrap().insert("keyed".to_owned(), keyed);
+    }
+    aggs
+}
+
+fn flat_date_histogram() -> Value {
+    serde_json::from_str(r#"{
+        "per_day": {
+            "buckets": [
+                {"key_as_string": "2017-01-01", "key": 1483228800000, "doc_count": 12},
+                {"key_as_string": "2017-01-02", "key": 1483315200000, "doc_count": 0},
+                {"key_as_string": "2017-01-03", "key": 1483401600000, "doc_count": 7}
+            ]
+        },
+        "total": {"value": 19}
+    }"#).unwrap()
+}
+
+#[test]
+fn test_pretty_flat_aggs_match_nested_path() {
+    let aggs = flat_date_histogram();
+    let flat = reparse_aggs(&aggs);
+    let nested = reparse_aggs(&with_keyed_sub_agg(&aggs, "per_day"));
+
+    assert_eq!(owned_rows(flat.aggs()), owned_rows(nested.aggs()));
+    assert_eq!(flat.aggs().pretty().to_string(), nested.aggs().pretty().to_string());
+    assert_eq!(flat.aggs().pretty().max_rows(2).to_string(), nested.aggs().pretty().max_rows(2).to_string());
+    assert!(flat.aggs().pretty().max_rows(2).to_string().ends_with("... 1 more rows\n"));
+}
+
+#[test]
+fn test_pretty_empty_aggs() {
+    let empty: Value = serde_json::from_str(r#"{"hosts": {"buckets": []}, "total": {"value": null}}"#).unwrap();
+    assert_eq!(reparse_aggs(&empty).aggs().pretty().to_string(), "\n\n");
+
+    let significant: Value = serde_json::from_str(r#"{"rare": {"doc_count": 10, "bg_count": 100, "buckets": []}}"#).unwrap();
+    assert_eq!(reparse_aggs(&significant).aggs().pretty().to_string(),
+               "rare_subset_size  rare_superset_size\n----------------  ------------------\n");
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_row_get_datetime() {
+    use chrono::{TimeZone, Timelike, Utc};
+    use elastic_responses::RowAccess;
+
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let first = deserialized.aggs().into_iter().next().unwrap();
+    assert_eq!(first.get_datetime("per_day"), Some(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap()));
+    assert_eq!(first.get_datetime("per_day_key_as_string"), first.get_datetime("per_day"));
+    assert_eq!(first.get_datetime("per_host"), None);
+    assert_eq!(first.get_datetime("missing"), None);
+
+    let mut row = OwnedRow::new();
+    row.insert("before_epoch".to_owned(), Value::I64(-86_400_001));
+    row.insert("millis".to_owned(), Value::U64(1_483_228_800_123));
+    row.insert("fraction".to_owned(), Value::F64(-0.5));
+    row.insert("offset_key_as_string".to_owned(), Value::String("2017-01-01T02:00:00.123456+02:00".to_owned()));
+
+    assert_eq!(row.get_datetime("before_epoch"), Some(Utc.with_ymd_and_hms(1969, 12, 30, 23, 59, 59).unwrap()
+        .with_nanosecond(999_000_000).unwrap()));
+    assert_eq!(row.get_datetime("millis").unwrap().nanosecond(), 123_000_000);
+    assert_eq!(row.get_datetime("fraction"), Some(Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap()
+        .with_nanosecond(999_500_000).unwrap()));
+    assert_eq!(row.get_datetime("offset"), Some(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap()
+        .with_nanosecond(123_456_000).unwrap()));
+}
+
+#[test]
+fn test_geopoint_formats() {
+    let object: Value = serde_json::from_str(r#"{"lat": 41.12, "lon": -71.34}"#).unwrap();
+    let array: Value = serde_json::from_str("[-71.34, 41.12]").unwrap();
+    let string = Value::String("41.12, -71.34".to_owned());
+    let expected = Some(GeoPoint { lat: 41.12, lon: -71.34 });
+
+    assert_eq!(GeoPoint::from_value(&object), expected);
+    assert_eq!(GeoPoint::from_value(&array), expected);
+    assert_eq!(GeoPoint::from_value(&string), expected);
+
+    let swapped: Value = serde_json::from_str("[41.12, -71.34]").unwrap();
+    assert_eq!(GeoPoint::from_value(&swapped), Some(GeoPoint { lat: -71.34, lon: 41.12 }));
+
+    assert_eq!(GeoPoint::from_value(&Value::String("drm3btev3e86".to_owned())), None);
+    assert_eq!(GeoPoint::from_value(&serde_json::from_str::<Value>("[1, 2, 3]").unwrap()), None);
+    assert_eq!(GeoPoint::from_value(&serde_json::from_str::<Value>(r#"{"lat": 1}"#).unwrap()), None);
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn test_geo_aggregations() {
+    use elastic_responses::RowAccess;
+
+    let s = load_file("tests/samples/aggregation_geo.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let (top_left, bottom_right) = deserialized.aggs().geo_bounds("viewport").unwrap();
+    assert!(close(top_left.lat, 48.86111099738628));
+    assert!(close(bottom_right.lon, 2.3363889567553997));
+    assert!(close(deserialized.aggs().geo_centroid("centroid").unwrap().lon, 3.9662131341174245));
+    assert_eq!(deserialized.aggs().geo_centroid("viewport"), None);
+
+    let rows: Vec<_> = deserialized.aggs().into_iter().collect();
+    assert_eq!(rows.len(), 2);
+    let paris = rows[1].get_geopoint("centroid_location").unwrap();
+    assert!(close(paris.lat, 48.86055548675358) && close(paris.lon, 2.3316944623366));
+    assert_eq!(rows[1].get_f64("centroid_count"), Some(2.0));
+}
+
+#[test]
+fn test_parse_hit_metadata() {
+    let s = load_file("tests/samples/hits_only.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let hit = deserialized.hits().iter().next().unwrap();
+    assert_eq!(hit.index(), "logstash-cee-2016.09.25");
+    assert_eq!(hit.id(), "AVdepUXTLyQ-FjMslkmf");
+    assert_eq!(hit.score(), Some(1.0));
+    assert_eq!(hit.source().and_then(|s| s.find("host")), Some(&Value::String("0060e0634ff8".to_owned())));
+    assert_eq!(hit.percolator_slots(), None);
+    assert_eq!(deserialized.hits().max_score(), Some(1.0));
+}
+
+#[test]
+fn test_parse_percolate_hits() {
+    let s = load_file("tests/samples/percolate.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let slots: Vec<_> = deserialized.hits().iter().map(|h| h.percolator_slots().unwrap().to_vec()).collect();
+    assert_eq!(slots, vec![vec![0, 1], vec![1], vec![0]]);
+    assert!(deserialized.hits().iter().next().unwrap().field("_percolator_document_slot").is_some());
+
+    let matches = deserialized.percolator_matches();
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[&0], vec!["1", "3"]);
+    assert_eq!(matches[&1], vec!["1", "2"]);
+}
+
+#[test]
+fn test_parse_rank_eval() {
+    let s = load_file("tests/samples/rank_eval.json");
+    let deserialized: RankEvalResponse = serde_json::from_str(&s).unwrap();
+
+    assert!(close(deserialized.metric_score(), 0.5));
+    assert_eq!(deserialized.details().keys().collect::<Vec<_>>(), vec!["amsterdam_query", "berlin_query"]);
+
+    let amsterdam = deserialized.query("amsterdam_query").unwrap();
+    assert!(close(amsterdam.metric_score(), 0.6666666666666666));
+    assert_eq!(amsterdam.unrated_docs().len(), 1);
+    assert_eq!(amsterdam.unrated_docs()[0].index(), "cities");
+    assert_eq!(amsterdam.unrated_docs()[0].id(), "3");
+
+    let ratings: Vec<_> = amsterdam.hits().iter().map(|h| (h.hit().id(), h.rating())).collect();
+    assert_eq!(ratings, vec![("1", Some(1)), ("2", Some(0)), ("3", None)]);
+    assert_eq!(amsterdam.hits()[0].hit().index(), "cities");
+    assert!(amsterdam.hits()[0].hit().source().is_none());
+    assert_eq!(amsterdam.metric_details().and_then(|d| d.lookup("precision.docs_retrieved")),
+               Some(&Value::U64(2)));
+
+    assert!(deserialized.query("broken_query").is_none());
+    let failures = deserialized.failures();
+    assert_eq!(failures.len(), 1);
+    let error = failures["broken_query"];
+    assert_eq!(error.error_type(), "parsing_exception");
+    assert_eq!(error.reason(), Some("unknown query [mtch]"));
+    assert_eq!(error.get("line"), Some(&Value::U64(1)));
+    assert_eq!(error.root_causes().len(), 1);
+    assert_eq!(error.caused_by().map(|c| c.error_type()), Some("named_object_not_found_exception"));
+    assert_eq!(error.to_string(), "parsing_exception: unknown query [mtch]");
+}
+
+#[test]
+fn test_parse_index_stats() {
+    let s = load_file("tests/samples/index_stats.json");
+    let deserialized: IndexStatsResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.shards().successful(), 4);
+    assert_eq!(deserialized.all().primaries().docs().unwrap().count(), 1500);
+    assert_eq!(deserialized.all().docs().unwrap().deleted(), 24);
+    assert_eq!(deserialized.all().indexing().unwrap().index_failed(), 1);
+    assert_eq!(deserialized.all().search().unwrap().query_total(), 192);
+    assert_eq!(deserialized.all().uuid(), None);
+
+    let logs = deserialized.index("logs-2024").unwrap();
+    assert_eq!(logs.uuid(), Some("mJ0sOAXrQ8qQ9fM3sTnKbg"));
+    assert_eq!(logs.docs().unwrap().count(), 2000);
+    assert_eq!(logs.primaries().store().unwrap().size_in_bytes(), 786432);
+    assert!(logs.indexing().is_none());
+    assert_eq!(logs.total().section("segments").and_then(|s| s.find("count")), Some(&Value::U64(8)));
+    assert!(logs.total().section("docs").is_some());
+    assert!(deserialized.index("missing").is_none());
+
+    let names: Vec<_> = deserialized.indices().map(|(name, _)| &**name).collect();
+    assert_eq!(names, vec!["logs-2024", "metrics-2024"]);
+    let docs: u64 = deserialized.indices().map(|(_, stats)| stats.primaries().docs().unwrap().count()).sum();
+    assert_eq!(docs, deserialized.all().primaries().docs().unwrap().count());
+}
+
+#[test]
+fn test_parse_pending_tasks() {
+    let s = load_file("tests/samples/pending_tasks.json");
+    let deserialized: PendingTasksResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.len(), 3);
+    let first = &deserialized.tasks()[0];
+    assert_eq!(first.insert_order(), 101);
+    assert_eq!(first.priority(), Priority::Urgent);
+    assert!(first.executing());
+    assert_eq!(first.time_in_queue(), Some("86ms"));
+    assert!(first.source().starts_with("create-index"));
+
+    let last = &deserialized.tasks()[2];
+    assert!(!last.executing());
+    assert_eq!(last.time_in_queue(), None);
+    assert_eq!(last.time_in_queue_in_millis(), 858);
+
+    let urgent = deserialized.iter().filter(|t| t.priority() <= Priority::High).count();
+    assert_eq!(urgent, 2);
+
+    let empty: PendingTasksResponse = serde_json::from_str(r#"{"tasks":[]}"#).unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_parse_cluster_state() {
+    let s = load_file("tests/samples/cluster_state.json");
+    let deserialized: ClusterStateResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.cluster_name(), Some("prod-logging"));
+    assert_eq!(deserialized.cluster_uuid(), Some("3wVzE8PLR5GJz_KmN1e9cw"));
+    assert_eq!(deserialized.master_node(), Some("tMTocMvQQgGCkj7QDHl87Q"));
+
+    let indices: Vec<_> = deserialized.indices().map(|(name, meta)| (&**name, meta.state())).collect();
+    assert_eq!(indices, vec![("logs-2024.09.01", Some(IndexState::Close)), ("logs-2024.10.13", Some(IndexState::Open))]);
+
+    let current = deserialized.index("logs-2024.10.13").unwrap();
+    assert_eq!(current.aliases(), &["logs".to_owned(), "logs-write".to_owned()]);
+    assert_eq!(current.settings().and_then(|s| s.lookup("index.number_of_shards")), Some(&Value::String("2".to_owned())));
+    assert!(current.mappings().is_some());
+    assert!(deserialized.index("logs-2024.09.01").unwrap().aliases().is_empty());
+
+    let routing = deserialized.section("routing_table").unwrap();
+    assert_eq!(routing.pointer("/indices/logs-2024.10.13/shards/0/0/state"), Some(&Value::String("STARTED".to_owned())));
+    assert!(deserialized.section("nodes").is_some());
+}
+
+#[test]
+fn test_parse_cluster_state_filtered() {
+    let s = load_file("tests/samples/cluster_state_metadata.json");
+    let deserialized: ClusterStateResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.master_node(), None);
+    assert!(deserialized.section("routing_table").is_none());
+    assert_eq!(deserialized.indices().count(), 1);
+    let logs = deserialized.index("logs-2024.10.13").unwrap();
+    assert_eq!(logs.state(), Some(IndexState::Open));
+    assert_eq!(logs.aliases(), &["logs".to_owned()]);
+    assert!(logs.settings().is_none());
+
+    let uuid_only: ClusterStateResponse = serde_json::from_str(r#"{"metadata":{"cluster_uuid":"abc"}}"#).unwrap();
+    assert_eq!(uuid_only.cluster_uuid(), Some("abc"));
+    assert_eq!(uuid_only.indices().count(), 0);
+
+    let bare: ClusterStateResponse = serde_json::from_str(r#"{"cluster_name":"prod-logging"}"#).unwrap();
+    assert_eq!(bare.cluster_name(), Some("prod-logging"));
+    assert_eq!(bare.cluster_uuid(), None);
+}
+
+#[test]
+fn test_parse_ilm_explain() {
+    let s = load_file("tests/samples/ilm_explain.json");
+    let deserialized: IlmExplainResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.indices().count(), 3);
+    assert_eq!(deserialized.failed_indices(), vec!["logs-000004"]);
+
+    let healthy = deserialized.index("logs-000003").unwrap();
+    assert!(healthy.managed());
+    assert!(!healthy.is_failed());
+    assert_eq!(healthy.policy(), Some("logs"));
+    assert_eq!(healthy.phase(), Some("hot"));
+    assert_eq!(healthy.action(), Some("rollover"));
+    assert_eq!(healthy.step(), Some("check-rollover-ready"));
+    assert_eq!(healthy.age(), Some("2.5d"));
+    assert_eq!(healthy.step_time_millis(), Some(1728691201536));
+    assert!(healthy.error().is_none());
+
+    let stuck = deserialized.index("logs-000004").unwrap();
+    assert!(stuck.is_failed());
+    assert_eq!(stuck.failed_step(), Some("check-rollover-ready"));
+    assert_eq!(stuck.is_auto_retryable_error(), Some(true));
+    assert_eq!(stuck.failed_step_retry_count(), Some(3));
+    let error = stuck.error().unwrap();
+    assert_eq!(error.error_type(), "illegal_argument_exception");
+    assert!(error.reason().unwrap().contains("does not point to index [logs-000004]"));
+    assert!(error.get("stack_trace").is_some());
+
+    let unmanaged = deserialized.index("scratch").unwrap();
+    assert!(!unmanaged.managed());
+    assert_eq!(unmanaged.policy(), None);
+    assert!(!unmanaged.is_failed());
+}
+
+#[test]
+fn test_parse_render_template() {
+    let s = load_file("tests/samples/render_template.json");
+    let deserialized: RenderTemplateResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.template_output().find("size"), Some(&Value::U64(20)));
+    let query = deserialized.query().unwrap();
+    assert_eq!(query.pointer("/bool/filter/0/term/host"), Some(&Value::String("web-01".to_owned())));
+
+    let no_query: RenderTemplateResponse = serde_json::from_str(r#"{"template_output":{"size":0}}"#).unwrap();
+    assert!(no_query.query().is_none());
+}
+
+#[test]
+fn test_parse_get_script() {
+    let s = load_file("tests/samples/get_script.json");
+    let deserialized: GetScriptResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.id(), "host_search");
+    assert!(deserialized.found());
+    let script = deserialized.script().unwrap();
+    assert_eq!(script.lang(), "mustache");
+    assert!(script.source().contains("{{host}}"));
+
+    let s = load_file("tests/samples/get_script_not_found.json");
+    let deserialized: GetScriptResponse = serde_json::from_str(&s).unwrap();
+    assert_eq!(deserialized.id(), "missing_search");
+    assert!(!deserialized.found());
+    assert!(deserialized.script().is_none());
+
+    let stored: AcknowledgedResponse = serde_json::from_str(r#"{"acknowledged":true}"#).unwrap();
+    assert!(stored.acknowledged());
+}
+
+#[test]
+fn test_parse_aliases() {
+    let s = load_file("tests/samples/aliases.json");
+    let deserialized: AliasesResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.indices(), vec!["logs-000002", "logs-000003", "metrics-000001", "scratch"]);
+
+    let logs: Vec<_> = deserialized.indices_for_alias("logs").iter().map(|&(index, _)| index).collect();
+    assert_eq!(logs, vec!["logs-000002", "logs-000003"]);
+    assert_eq!(deserialized.write_index("logs-write"), Some("logs-000003"));
+    assert_eq!(deserialized.write_index("metrics"), Some("metrics-000001"));
+    assert_eq!(deserialized.write_index("logs"), None);
+    assert!(deserialized.indices_for_alias("missing").is_empty());
+
+    let aliases = deserialized.aliases_for_index("logs-000003");
+    let names: Vec<_> = aliases.iter().map(|&(name, _)| name).collect();
+    assert_eq!(names, vec!["logs", "logs-web", "logs-write"]);
+
+    let plain = aliases[0].1;
+    assert!(!plain.is_filtered());
+    assert!(!plain.is_routed());
+    assert_eq!(plain.is_write_index(), None);
+
+    let web = aliases[1].1;
+    assert!(web.is_filtered());
+    assert_eq!(web.filter().and_then(|f| f.pointer("/term/service")), Some(&Value::String("web".to_owned())));
+    assert_eq!(web.index_routing(), Some("web"));
+    assert_eq!(web.search_routing(), Some("web"));
+    assert_eq!(aliases[2].1.is_write_index(), Some(true));
+
+    assert!(deserialized.aliases_for_index("scratch").is_empty());
+    assert!(deserialized.aliases_for_index("missing").is_empty());
+}
+
+#[test]
+fn test_parse_cat_aliases() {
+    let body = r#"[
+        {"alias":"logs-write","index":"logs-000003","filter":"-","routing.index":"-","routing.search":"-","is_write_index":"true"},
+        {"alias":"logs-web","index":"logs-000003","filter":"*","routing.index":"web","routing.search":"web","is_write_index":"-"}
+    ]"#;
+    let rows: Vec<CatAlias> = serde_json::from_str(body).unwrap();
+
+    assert_eq!(rows[0].alias(), "logs-write");
+    assert_eq!(rows[0].index(), "logs-000003");
+    assert!(!rows[0].is_filtered());
+    assert_eq!(rows[0].index_routing(), None);
+    assert_eq!(rows[0].is_write_index(), Some(true));
+
+    assert!(rows[1].is_filtered());
+    assert_eq!(rows[1].search_routing(), Some("web"));
+    assert_eq!(rows[1].is_write_index(), None);
+}
+
+#[test]
+fn test_cat_count_nodes_health() {
+    let rows: Vec<CatCount> = serde_json::from_str(r#"[{"epoch":"1475247709","timestamp":"17:01:49","count":"121"}]"#)
+        .unwrap();
+    assert_eq!(rows[0].epoch(), Some(1475247709));
+    assert_eq!(rows[0].timestamp(), Some("17:01:49"));
+    assert_eq!(rows[0].count(), Some(121));
+
+    let body = r#"[
+        {"ip":"10.0.0.1","heap.percent":"65","ram.percent":"99","cpu":"42","load_1m":"3.07","load_5m":"2.85",
+         "load_15m":"2.43","node.role":"cdhilmrstw","master":"*","name":"es-1"},
+        {"ip":"10.0.0.2","heap.percent":"31","ram.percent":"87","cpu":"7","load_1m":"0.41","load_5m":null,
+         "load_15m":"-","node.role":"m","master":"-","name":"es-2"},
+        {"ip":"10.0.0.3","heap.percent":12,"ram.percent":"-","cpu":"","node.role":"di","master":"-","name":"es-3"}
+    ]"#;
+    let nodes: Vec<CatNode> = serde_json::from_str(body).unwrap();
+    assert_eq!(nodes[0].ip(), Some("10.0.0.1"));
+    assert_eq!(nodes[0].heap_percent(), Some(65));
+    assert_eq!(nodes[0].ram_percent(), Some(99));
+    assert_eq!(nodes[0].cpu(), Some(42));
+    assert_eq!(nodes[0].load_1m(), Some(3.07));
+    assert_eq!(nodes[0].load_15m(), Some(2.43));
+    assert!(nodes[0].is_master());
+    assert!(nodes[0].has_role('d') && nodes[0].has_role('m'));
+    assert!(!nodes[1].is_master());
+    assert!(nodes[1].has_role('m') && !nodes[1].has_role('d'));
+    assert_eq!(nodes[1].load_5m(), None);
+    assert_eq!(nodes[1].load_15m(), None);
+    assert_eq!(nodes[2].heap_percent(), Some(12));
+    assert_eq!(nodes[2].ram_percent(), None);
+    assert_eq!(nodes[2].cpu(), None);
+    assert_eq!(nodes[2].load_1m(), None);
+    assert_eq!(nodes[2].name(), Some("es-3"));
+
+    let body = r#"[{"epoch":"1475871424","timestamp":"16:17:04","cluster":"elasticsearch","status":"yellow",
+        "node.total":"3","node.data":"2","shards":"14","pri":"7","relo":"0","init":"1","unassign":"2",
+        "pending_tasks":"0","max_task_wait_time":"-","active_shards_percent":"82.4%"}]"#;
+    let health: Vec<CatHealth> = serde_json::from_str(body).unwrap();
+    assert_eq!(health[0].epoch(), Some(1475871424));
+    assert_eq!(health[0].cluster(), Some("elasticsearch"));
+    assert_eq!(health[0].status(), Some("yellow"));
+    assert_eq!(health[0].node_total(), Some(3));
+    assert_eq!(health[0].node_data(), Some(2));
+    assert_eq!(health[0].shards(), Some(14));
+    assert_eq!(health[0].primaries(), Some(7));
+    assert_eq!(health[0].relocating(), Some(0));
+    assert_eq!(health[0].initializing(), Some(1));
+    assert_eq!(health[0].unassigned(), Some(2));
+    assert_eq!(health[0].pending_tasks(), Some(0));
+    assert_eq!(health[0].active_shards_percent(), Some(82.4));
+}
+
+#[test]
+fn test_cat_rows_with_custom_columns() {
+    // `_cat/nodes?h=name,heap.max,disk.used_percent,uptime&format=json`
+    let body = r#"[
+        {"name":"es-1","heap.max":"4gb","disk.used_percent":"71.20","uptime":"12.4d"},
+        {"name":"es-2","heap.max":"4gb","disk.used_percent":"-","uptime":"3h"}
+    ]"#;
+    let rows: Vec<CatRow> = serde_json::from_str(body).unwrap();
+    assert_eq!(rows[0].columns().collect::<Vec<_>>(), vec!["disk.used_percent", "heap.max", "name", "uptime"]);
+    assert_eq!(rows[0].get("heap.max"), Some("4gb"));
+    assert_eq!(rows[0].parse::<f64>("disk.used_percent"), Some(71.2));
+    assert_eq!(rows[1].get("disk.used_percent"), None);
+    assert_eq!(rows[1].parse::<f64>("uptime"), None);
+    assert_eq!(rows[1].get("ip"), None);
+
+    // the same columns read as typed rows leave the others unset
+    let nodes: Vec<CatNode> = serde_json::from_str(body).unwrap();
+    assert_eq!(nodes[0].name(), Some("es-1"));
+    assert_eq!(nodes[0].heap_percent(), None);
+    assert!(!nodes[0].is_master());
+
+    let rows: Vec<CatRow> = serde_json::from_str(r#"[{"docs.count":1200,"health":"green","open":true}]"#).unwrap();
+    let map = rows[0].clone().into_map();
+    assert_eq!(map.get("docs.count").map(|s| &**s), Some("1200"));
+    assert_eq!(rows[0].parse::<bool>("open"), Some(true));
+    assert_eq!(CatRow::from(map), rows[0]);
+    assert!(serde_json::from_str::<Vec<CatRow>>(r#"[{"nested":{"a":"b"}}]"#).is_err());
+}
+
+#[test]
+fn test_parse_allocation_explain() {
+    let s = load_file("tests/samples/allocation_explain.json");
+    let deserialized: AllocationExplainResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.index(), "my-index-000001");
+    assert_eq!(deserialized.shard(), 0);
+    assert!(!deserialized.primary());
+    assert_eq!(deserialized.current_state(), "unassigned");
+    assert_eq!(deserialized.can_allocate(), Some("no"));
+
+    let unassigned = deserialized.unassigned_info().unwrap();
+    assert_eq!(unassigned.reason(), "NODE_LEFT");
+    assert_eq!(unassigned.at(), "2017-01-04T18:53:59.498Z");
+    assert_eq!(unassigned.details(), Some("node_left[G92ZwuuaRY-9n8_tc-IzEg]"));
+    assert_eq!(unassigned.last_allocation_status(), Some("no_attempt"));
+
+    let nodes = deserialized.node_allocation_decisions();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[1].node_id(), "7Hpn8uXyT0yqBDB2NS0Z0Q");
+    assert_eq!(nodes[1].weight_ranking(), Some(2));
+    assert_eq!(nodes[1].deciders()[2].decision(), Decision::Yes);
+
+    let no: Vec<_> = deserialized.no_decisions().into_iter().map(|(node, decider, _)| (node, decider)).collect();
+    assert_eq!(no, vec![("node-0", "disk_threshold"), ("node-1", "same_shard"), ("node-1", "disk_threshold")]);
+    assert!(deserialized.no_decisions()[0].2.contains("low watermark"));
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct LogLine {
+    host: String,
+    level: String,
+    message: Option<String>
+}
+
+#[test]
+fn test_parse_simulate_pipeline() {
+    let s = load_file("tests/samples/simulate_pipeline.json");
+    let deserialized: SimulatePipelineResponse<LogLine> = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.docs().len(), 2);
+    assert!(deserialized.has_failures());
+
+    let ok = &deserialized.docs()[0];
+    assert!(!ok.is_failed());
+    assert!(ok.processor_results().is_empty());
+    let doc = ok.doc().unwrap();
+    assert_eq!(doc.index(), Some("logs"));
+    assert_eq!(doc.id(), Some("1"));
+    assert_eq!(doc.timestamp(), Some("2024-10-14T09:12:44.108Z"));
+    assert_eq!(doc.source(),
+               &LogLine { host: "web-01".to_owned(), level: "ERROR".to_owned(), message: Some("disk full".to_owned()) });
+
+    let failed = &deserialized.docs()[1];
+    assert!(failed.is_failed());
+    assert!(failed.doc().is_none());
+    assert_eq!(failed.error().map(|e| e.error_type()), Some("illegal_argument_exception"));
+}
+
+#[test]
+fn test_parse_simulate_pipeline_verbose() {
+    let s = load_file("tests/samples/simulate_pipeline_verbose.json");
+    let deserialized: SimulatePipelineResponse<Value> = serde_json::from_str(&s).unwrap();
+
+    let first = &deserialized.docs()[0];
+    assert!(!first.is_failed());
+    let statuses: Vec<_> = first.processor_results().iter().map(|p| (p.processor_type(), p.status())).collect();
+    assert_eq!(statuses,
+               vec![(Some("uppercase"), Some("success")), (Some("rename"), Some("success")), (Some("set"), Some("skipped"))]);
+    assert_eq!(first.processor_results()[0].tag(), Some("level"));
+    assert_eq!(first.doc().unwrap().source().find("message"), Some(&Value::String("disk full".to_owned())));
+    assert!(first.processor_results()[0].doc().unwrap().source().find("message").is_none());
+
+    let second = &deserialized.docs()[1];
+    assert!(second.is_failed());
+    assert!(second.error().is_none());
+    let failed = second.failed_processors();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].processor_type(), Some("rename"));
+    assert_eq!(failed[0].error().and_then(|e| e.reason()), Some("field [msg] doesn't exist"));
+    assert_eq!(second.doc().unwrap().id(), Some("2"));
+    assert!(deserialized.has_failures());
+}
+
+#[test]
+fn test_exists_response() {
+    assert!(ExistsResponse::from_status(200).unwrap().exists());
+    assert!(!ExistsResponse::from_status(404).unwrap().exists());
+    match ExistsResponse::from_status(401) {
+        Err(ResponseError::Status(401)) => (),
+        other => panic!("expected a status error, got {:?}", other),
+    }
+
+    let exists: ExistsResponse = parse_http_response(200, b"").unwrap();
+    assert!(exists.exists());
+    let missing: ExistsResponse = parse_http_response(404, b"\n").unwrap();
+    assert!(!missing.exists());
+    match parse_http_response::<ExistsResponse>(401, b"") {
+        Err(ResponseError::Status(401)) => (),
+        other => panic!("expected a status error, got {:?}", other),
+    }
+    match parse_http_response::<ExistsResponse>(200, br#"{"acknowledged":true}"#) {
+        Err(ResponseError::UnexpectedBody(200)) => (),
+        other => panic!("expected an unexpected body error, got {:?}", other),
+    }
+    // bytes that are only whitespace as Latin-1 code points
+    for body in &[&b" \x85"[..], &b"\xa0\n"[..]] {
+        assert!(parse_http_response::<ExistsResponse>(200, body).is_err());
+    }
+}
+
+#[test]
+fn test_parse_http_response() {
+    let s = load_file("tests/samples/get_script_not_found.json");
+    let not_found: GetScriptResponse = parse_http_response(404, s.as_bytes()).unwrap();
+    assert!(!not_found.found());
+
+    let s = load_file("tests/samples/hits_only.json");
+    let response: Response = parse_http_response(200, s.as_bytes()).unwrap();
+    assert!(response.hits().len() > 0);
+
+    let body = br#"{"error":{"root_cause":[{"type":"index_not_found_exception","reason":"no such index [logs]"}],"type":"index_not_found_exception","reason":"no such index [logs]","index":"logs"},"status":404}"#;
+    match parse_http_response::<Response>(404, body) {
+        Err(ResponseError::Api(404, ref err)) => {
+            assert_eq!(err.error_type(), "index_not_found_exception");
+            assert_eq!(err.get("index"), Some(&Value::String("logs".to_owned())));
+        }
+        other => panic!("expected an api error, got {:?}", other),
+    }
+
+    match parse_http_response::<Response>(502, b"<html>Bad Gateway</html>") {
+        Err(ResponseError::Status(502)) => (),
+        other => panic!("expected a status error, got {:?}", other),
+    }
+}
+
+fn api_error(json: &str) -> ApiError {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_api_error_classification() {
+    let rejected = api_error(r#"{"type": "search_phase_execution_exception", "reason": "all shards failed",
+        "caused_by": {"type": "es_rejected_execution_exception", "reason": "rejected execution of coordinating operation"}}"#);
+    assert!(rejected.is_retryable());
+    assert!(!rejected.is_client_error());
+
+    let timeout = api_error(r#"{"type": "process_cluster_event_timeout_exception", "reason": "failed to process cluster event"}"#);
+    assert!(timeout.is_retryable());
+
+    let transient = api_error(r#"{"type": "circuit_breaking_exception", "reason": "[parent] Data too large",
+        "bytes_wanted": 1073741900, "bytes_limit": 1073741824, "durability": "TRANSIENT"}"#);
+    assert!(transient.is_retryable());
+    let breaker = transient.circuit_breaker().unwrap();
+    assert_eq!(breaker.bytes_wanted(), 1073741900);
+    assert_eq!(breaker.bytes_limit(), 1073741824);
+    assert_eq!(breaker.durability(), Some("TRANSIENT"));
+    assert!(!breaker.is_permanent());
+
+    let permanent = api_error(r#"{"type": "circuit_breaking_exception", "reason": "[fielddata] Data too large",
+        "bytes_wanted": 500, "bytes_limit": 400, "durability": "PERMANENT"}"#);
+    assert!(!permanent.is_retryable());
+    assert!(permanent.circuit_breaker().unwrap().is_permanent());
+
+    let mapping = api_error(r#"{"type": "mapper_parsing_exception", "reason": "failed to parse field [bytes]",
+        "caused_by": {"type": "illegal_argument_exception", "reason": "For input string: \"n/a\""}}"#);
+    assert!(mapping.is_client_error());
+    assert!(!mapping.is_retryable());
+    assert!(mapping.circuit_breaker().is_none());
+
+    let parsing = api_error(r#"{"type": "parsing_exception", "reason": "unknown query [match_al]"}"#);
+    assert!(parsing.is_client_error());
+
+    let missing = api_error(r#"{"type": "index_not_found_exception", "reason": "no such index [logs]"}"#);
+    assert!(!missing.is_retryable());
+    assert!(!missing.is_client_error());
+    assert!(missing.is_retryable_with(|e| if e.error_type() == "index_not_found_exception" { Some(true) } else { None }));
+    assert!(!mapping.is_client_error_with(|_| Some(false)));
+    assert!(rejected.is_retryable_with(|_| None));
+
+    assert!(ResponseError::Status(429).is_retryable());
+    assert!(ResponseError::Status(503).is_retryable());
+    assert!(!ResponseError::Status(404).is_retryable());
+    assert!(ResponseError::Api(429, missing.clone()).is_retryable());
+    assert!(ResponseError::Api(500, rejected.clone()).is_retryable());
+    assert!(!ResponseError::Api(404, missing.clone()).is_retryable());
+    assert!(!ResponseError::Api(503, missing.clone()).is_retryable_with(|status, _| Some(status != 503)));
+    assert!(ResponseError::Api(400, parsing).is_client_error());
+    assert!(ResponseError::Status(400).is_client_error());
+    assert!(!ResponseError::Api(404, missing).is_client_error());
+}
+
+#[derive(Deserialize, Debug)]
+struct TypedAggResponse {
+    aggregations: TypedAggs
+}
+
+#[derive(Deserialize, Debug)]
+struct TypedAggs {
+    by_host: TypedTerms
+}
+
+#[derive(Deserialize, Debug)]
+struct TypedTerms {
+    buckets: Vec<TypedBucket>
+}
+
+#[derive(Deserialize, Debug)]
+struct TypedBucket {
+    key: String,
+    doc_count: u64,
+    cpu: TypedMetric
+}
+
+#[derive(Deserialize, Debug)]
+struct TypedMetric {
+    value: f64
+}
+
+#[test]
+fn test_parse_error_context() {
+    let s = load_file("tests/samples/aggregation_bad_metric.json");
+    match parse_slice_as::<TypedAggResponse>(s.as_bytes()) {
+        Err(Error::Parse(_, ref context)) => {
+            assert_eq!(context.path(), "/aggregations/by_host/buckets/3/cpu/value");
+            assert!(context.snippet().contains(r#""value": "high""#), "{}", context.snippet());
+            assert!(context.line() > 1);
+        }
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+
+    // the untyped aggregations of a plain response parse fine
+    assert!(elastic_responses::parse_slice::<Value>(s.as_bytes()).is_ok());
+
+    let body = br#"{"took":1,"timed_out":false,"_shards":{"total":1,"successful":1,"failed":0},"hits":{"total":2,"max_score":1,"hits":[{"_index":"logs","_id":"1","_source":{"host":"web-01","level":"info"}},{"_index":"logs","_id":"2","_source":{"host":"web-02","level":3}}]}}"#;
+    match parse_http_response::<ResponseOf<LogLine>>(200, body) {
+        Err(ResponseError::Parse(_, ref context)) => {
+            assert_eq!(context.path(), "/hits/hits/1/_source/level");
+            assert_eq!(context.line(), 1);
+            assert!(context.to_string().starts_with("at `/hits/hits/1/_source/level` (line 1, column "));
+        }
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+
+    let escaped = br#"{"a/b": {"x~y": [1, true]}}"#;
+    match parse_slice_as::<BTreeMap<String, BTreeMap<String, Vec<u64>>>>(escaped) {
+        Err(Error::Parse(_, context)) => assert_eq!(context.path(), "/a~1b/x~0y/1"),
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+
+    let missing = br#"{"aggregations": {"by_host": {"buckets": [{"key": "web-01", "doc_count": 1}]}}}"#;
+    match parse_slice_as::<TypedAggResponse>(missing) {
+        Err(Error::Parse(_, context)) => assert_eq!(context.path(), "/aggregations/by_host/buckets/0"),
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+
+    let context: Option<ParseContext> = match serde_json::from_str::<Value>("") {
+        Err(ref e) => ParseContext::locate(b"", e),
+        Ok(_) => unreachable!()
+    };
+    assert!(context.is_none());
+}
+
+#[test]
+fn test_opensearch_compatibility() {
+    let ping = parse_slice_as::<PingResponse>(load_file("tests/samples/opensearch_ping.json").as_bytes()).unwrap();
+    assert_eq!(ping.flavor(), Flavor::OpenSearch);
+    assert_eq!((ping.version().number(), ping.version().major()), ("2.11.0", Some(2)));
+    assert_eq!(ping.version().build_flavor(), None);
+    let ping = parse_slice_as::<PingResponse>(load_file("tests/samples/elasticsearch_ping.json").as_bytes()).unwrap();
+    assert_eq!(ping.flavor(), Flavor::Elasticsearch);
+    assert_eq!(ping.version().distribution(), None);
+    assert_eq!(ping.tagline(), Some("You Know, for Search"));
+
+    let s = load_file("tests/samples/opensearch_search.json");
+    let search: Response = parse_slice_as(s.as_bytes()).unwrap();
+    assert_eq!(search.hits().total().map(|t| t.value()), Some(2));
+    assert_eq!(search.hits().iter().map(|h| h.id()).collect::<Vec<_>>(), vec!["1", "2"]);
+    assert_eq!(search.aggs().into_iter().count(), 2);
+
+    // OpenSearch renamed `es_rejected_execution_exception`, which is only retryable as its flavor
+    let s = load_file("tests/samples/opensearch_bulk.json");
+    let bulk = parse_slice_as::<BulkResponse>(s.as_bytes()).unwrap();
+    let errors: Vec<_> = bulk.failed_items().into_iter().filter_map(|item| item.error()).collect();
+    assert_eq!(errors.len(), 2);
+    assert!(!errors[0].is_retryable());
+    assert!(errors[0].is_retryable_for(Flavor::OpenSearch));
+    assert!(errors[1].is_client_error_for(Flavor::OpenSearch) && !errors[1].is_retryable_for(Flavor::OpenSearch));
+    assert_eq!(bulk.retryable_items().len(), 1);
+    assert_eq!(Flavor::OpenSearch.canonical_error_type("cluster_manager_not_discovered_exception"),
+               "master_not_discovered_exception");
+    assert_eq!(Flavor::Elasticsearch.canonical_error_type("cluster_manager_not_discovered_exception"),
+               "cluster_manager_not_discovered_exception");
+
+    let s = load_file("tests/samples/opensearch_cluster_health.json");
+    let health = parse_slice_as::<ClusterHealthResponse>(s.as_bytes()).unwrap();
+    assert_eq!((health.status(), health.active_shards()), ("green", 18));
+    assert_eq!(health.discovered_master(), Some(true));
+    let s = load_file("tests/samples/cluster_health.json");
+    assert_eq!(parse_slice_as::<ClusterHealthResponse>(s.as_bytes()).unwrap().discovered_master(), None);
+}
+
+#[test]
+fn test_parse_typed_bodies_share_error_paths() {
+    let bulk = load_file("tests/samples/bulk.json").replacen(r#""status": 201"#, r#""status": "created""#, 1);
+    match parse_slice_as::<BulkResponse>(bulk.as_bytes()) {
+        Err(Error::Parse(_, context)) => assert_eq!(context.path(), "/items/0/index/status"),
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+
+    let s = load_file("tests/samples/cluster_health.json");
+    let health = parse_slice_as::<ClusterHealthResponse>(s.as_bytes()).unwrap();
+    assert_eq!((health.cluster_name(), health.status()), ("testcluster", "yellow"));
+    assert_eq!((health.active_shards(), health.unassigned_shards()), (5, 5));
+    assert_eq!(health.active_shards_percent(), Some(50.0));
+
+    let malformed = s.replace(r#""number_of_nodes": 1"#, r#""number_of_nodes": -1"#);
+    match parse_slice_as::<ClusterHealthResponse>(malformed.as_bytes()) {
+        Err(Error::Parse(_, context)) => assert_eq!(context.path(), "/number_of_nodes"),
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+    match parse_http_response::<ClusterHealthResponse>(200, malformed.as_bytes()) {
+        Err(ResponseError::Parse(_, context)) => assert_eq!(context.path(), "/number_of_nodes"),
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+
+    // an error object sent in place of the response is reported as such
+    let error = br#"{"error": {"type": "master_not_discovered_exception", "reason": "no master"}, "status": 503}"#;
+    match parse_slice_as::<ClusterHealthResponse>(error) {
+        Err(Error::Api(_, err)) => assert_eq!(err.error_type(), "master_not_discovered_exception"),
+        other => panic!("expected an API error, got {:?}", other)
+    }
+}
+
+#[test]
+fn test_body_status() {
+    // a proxy answering 200 for an error passed on from Elasticsearch
+    let error = br#"{"error": {"type": "master_not_discovered_exception", "reason": "no master"}, "status": 503}"#;
+    match elastic_responses::parse_slice::<Value>(error) {
+        Err(Error::Api(Some(503), ref err)) => assert_eq!(err.reason(), Some("no master")),
+        other => panic!("expected an API error, got {:?}", other)
+    }
+    match parse_http_response::<ClusterHealthResponse>(200, error) {
+        Err(ResponseError::Api(503, _)) => (),
+        other => panic!("expected an API error, got {:?}", other)
+    }
+    match parse_http_response::<Response>(500, error) {
+        Err(ref err @ ResponseError::Api(503, _)) => assert!(err.is_retryable()),
+        other => panic!("expected an API error, got {:?}", other)
+    }
+    let without_status = br#"{"error": {"type": "search_phase_execution_exception", "reason": "all shards failed"}}"#;
+    match parse_http_response::<Response>(200, without_status) {
+        Err(ResponseError::Api(200, _)) => (),
+        other => panic!("expected an API error, got {:?}", other)
+    }
+    match elastic_responses::parse_slice::<Value>(without_status) {
+        Err(ref err @ Error::Api(None, _)) => {
+            assert_eq!(err.to_string(), "request failed: search_phase_execution_exception: all shards failed")
+        }
+        other => panic!("expected an API error, got {:?}", other)
+    }
+
+    // a status next to a complete response doesn't make it an error
+    let ok = hits_body("2", 2).replacen("{", r#"{"status": 200,"#, 1);
+    let deserialized = elastic_responses::parse_slice::<Value>(ok.as_bytes()).unwrap();
+    assert_eq!(deserialized.status(), Some(200));
+    assert_eq!(deserialized.hits().len(), 2);
+    assert!(parse_http_response::<Response>(200, ok.as_bytes()).is_ok());
+
+    let s = load_file("tests/samples/aggregation_simple.json");
+    assert_eq!(elastic_responses::parse_slice::<Value>(s.as_bytes()).unwrap().status(), None);
+}
+
+#[test]
+fn test_parse_warning_headers() {
+    let single = Warning::parse_header(r#"299 Elasticsearch-7.17.0-bee8632 "[types removal] Specifying types in search requests is deprecated.""#);
+    assert_eq!(single.len(), 1);
+    assert_eq!(single[0].code(), 299);
+    assert_eq!(single[0].agent(), "Elasticsearch-7.17.0-bee8632");
+    assert_eq!(single[0].text(), "[types removal] Specifying types in search requests is deprecated.");
+    assert_eq!(single[0].date(), None);
+
+    let multiple = Warning::parse_header(r#"299 Elasticsearch-6.8.0-abc "the \"fielddata\" setting, is deprecated" "Mon, 14 Oct 2024 09:00:00 GMT", 299 Elasticsearch-6.8.0-abc "a \\ b""#);
+    assert_eq!(multiple.len(), 2);
+    assert_eq!(multiple[0].text(), r#"the "fielddata" setting, is deprecated"#);
+    assert_eq!(multiple[0].date(), Some("Mon, 14 Oct 2024 09:00:00 GMT"));
+    assert_eq!(multiple[1].text(), r"a \ b");
+
+    assert!(Warning::parse_header("299 Elasticsearch").is_empty());
+    assert!(Warning::parse_header(r#"29 Elasticsearch "short code""#).is_empty());
+    assert!(Warning::parse_header(r#"299 Elasticsearch "unterminated"#).is_empty());
+    let truncated = Warning::parse_header(r#"299 Elasticsearch "kept", 299 Elasticsearch garbage"#);
+    assert_eq!(truncated.iter().map(Warning::text).collect::<Vec<_>>(), vec!["kept"]);
+
+    let s = load_file("tests/samples/hits_only.json");
+    let headers = vec![("Content-Type", "application/json"),
+                       ("Warning", r#"299 Elasticsearch-7.17.0-bee8632 "first""#),
+                       ("warning", r#"299 Elasticsearch-7.17.0-bee8632 "second", 299 Elasticsearch-7.17.0-bee8632 "third""#),
+                       ("Warning", "not a warning")];
+    let response = parse_http_response_with_headers::<Response, _, _, _>(200, headers, s.as_bytes()).unwrap();
+    let texts: Vec<_> = response.deprecation_warnings().iter().map(Warning::text).collect();
+    assert_eq!(texts, vec!["first", "second", "third"]);
+    assert!(response.hits().len() > 0);
+
+    let none = parse_http_response_with_headers::<Response, _, String, String>(200, vec![], s.as_bytes()).unwrap();
+    assert!(none.deprecation_warnings().is_empty());
+}
+
+#[test]
+fn test_body_format_from_content_type() {
+    assert_eq!(BodyFormat::from_content_type("application/json; charset=UTF-8"), BodyFormat::Json);
+    assert_eq!(BodyFormat::from_content_type("application/cbor"), BodyFormat::Cbor);
+    assert_eq!(BodyFormat::from_content_type("application/vnd.elasticsearch+cbor; compatible-with=8"), BodyFormat::Cbor);
+    assert_eq!(BodyFormat::from_content_type("Application/Smile"), BodyFormat::Smile);
+    assert_eq!(BodyFormat::from_content_type("text/plain"), BodyFormat::Json);
+
+    let headers = vec![("Content-Type", "application/smile")];
+    match parse_http_response_with_headers::<Response, _, _, _>(200, headers, b":)\n") {
+        Err(ResponseError::UnsupportedFormat(BodyFormat::Smile)) => (),
+        other => panic!("expected an unsupported format error, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_parse_cbor() {
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let json: Value = serde_json::from_str(&s).unwrap();
+    let cbor = serde_cbor::to_vec(&json).unwrap();
+
+    let from_json: Response = serde_json::from_str(&s).unwrap();
+    let from_cbor = Response::from_cbor_slice(&cbor).unwrap();
+    assert_eq!(from_cbor.took(), from_json.took());
+    assert_eq!(from_cbor.hits().total(), from_json.hits().total());
+    assert_eq!(from_cbor.aggs().columns(), from_json.aggs().columns());
+    let rows = |r: &Response| r.aggs().into_iter().map(|row| format!("{:?}", row)).collect::<Vec<_>>();
+    assert_eq!(rows(&from_cbor), rows(&from_json));
+
+    let headers = vec![("content-type", "application/cbor")];
+    let routed = parse_http_response_with_headers::<Response, _, _, _>(200, headers, &cbor).unwrap();
+    assert_eq!(routed.aggs().columns(), from_json.aggs().columns());
+
+    match parse_http_response_with_headers::<Response, _, _, _>(200, vec![("Content-Type", "application/cbor")], b"{}") {
+        Err(ResponseError::Cbor(_)) => (),
+        other => panic!("expected a CBOR error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_responses_from_ndjson() {
+    let body = format!("{}\n\n{}\n{}\n",
+                       hits_body("3", 1).replace('\n', ""),
+                       r#"{"took": 1, "timed_out": false, "hits": "#,
+                       hits_body(r#"{ "value": 7, "relation": "gte" }"#, 2).replace('\n', ""));
+    let responses: Vec<_> = responses_from_ndjson::<_, Value>(body.as_bytes()).collect();
+    assert_eq!(responses.len(), 3);
+
+    assert_eq!(responses[0].as_ref().unwrap().hits().total().unwrap().value(), 3);
+    match responses[1] {
+        Err(Error::Line(3, _)) => (),
+        ref other => panic!("expected an error on line 3, got {:?}", other),
+    }
+    assert!(responses[1].as_ref().unwrap_err().to_string().starts_with("failed to parse response on line 3"));
+    assert_eq!(responses[2].as_ref().unwrap().hits().total().unwrap().value(), 7);
+
+    assert_eq!(responses_from_ndjson::<_, Value>(&b"\n  \n"[..]).count(), 0);
+
+    // a line that isn't UTF-8 still counts towards the line numbers after it
+    let mut body = hits_body("3", 1).replace('\n', "").into_bytes();
+    body.extend_from_slice(b"\n{\"took\": \xff}\n{\"took\": 1,\n");
+    let responses: Vec<_> = responses_from_ndjson::<_, Value>(&body[..]).collect();
+    assert_eq!(responses.len(), 3);
+    assert!(responses[0].is_ok());
+    match responses[1] {
+        Err(Error::Io(_)) => (),
+        ref other => panic!("expected a read error, got {:?}", other),
+    }
+    match responses[2] {
+        Err(Error::Line(3, _)) => (),
+        ref other => panic!("expected an error on line 3, got {:?}", other),
+    }
+
+    // lines are parsed like bodies, error objects included
+    let body = br#"{"error": {"type": "index_not_found_exception"}, "status": 404}
+{"took": 1, "hits": 7}
+"#;
+    let responses: Vec<_> = responses_from_ndjson::<_, Value>(&body[..]).collect();
+    match responses[0] {
+        Err(Error::Line(1, ref err)) => match **err {
+            Error::Api(Some(404), ref api) => assert_eq!(api.error_type(), "index_not_found_exception"),
+            ref other => panic!("expected an API error, got {:?}", other),
+        },
+        ref other => panic!("expected an error on line 1, got {:?}", other),
+    }
+    match responses[1] {
+        Err(Error::Line(2, ref err)) => assert!(err.to_string().contains("at `/hits`"), "{}", err),
+        ref other => panic!("expected an error on line 2, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_bulk() {
+    let s = load_file("tests/samples/bulk.json");
+    let deserialized: BulkResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.took(), 30);
+    assert!(deserialized.errors());
+    let actions: Vec<_> = deserialized.iter().map(|i| (i.action(), i.id(), i.status())).collect();
+    assert_eq!(actions,
+               vec![(BulkAction::Index, Some("1"), 201),
+                    (BulkAction::Delete, Some("2"), 404),
+                    (BulkAction::Create, Some("3"), 409),
+                    (BulkAction::Update, Some("4"), 200)]);
+
+    let first = &deserialized.items()[0];
+    assert!(first.is_ok());
+    assert_eq!(first.index(), "logs");
+    assert_eq!(first.result(), Some(&WriteResult::Created));
+    assert_eq!(first.version(), Some(1));
+    assert_eq!(deserialized.items()[1].primary_term(), Some(2));
+
+    let failed = deserialized.failed_items();
+    assert_eq!(failed.iter().map(|i| i.id()).collect::<Vec<_>>(), vec![Some("2"), Some("3")]);
+    assert_eq!(failed[1].error().map(|e| e.error_type()), Some("version_conflict_engine_exception"));
+
+    assert!(serde_json::from_str::<BulkResponse>(r#"{"took":1,"errors":false,"items":[{"upsert":{"_index":"logs","status":200}}]}"#).is_err());
+}
+
+#[test]
+fn test_write_results() {
+    let known = vec![("created", WriteResult::Created, true),
+                     ("updated", WriteResult::Updated, true),
+                     ("deleted", WriteResult::Deleted, true),
+                     ("not_found", WriteResult::NotFound, false),
+                     ("noop", WriteResult::Noop, false),
+                     ("merged", WriteResult::Unknown("merged".to_owned()), false)];
+    for (s, result, did_write) in known {
+        let json = format!("\"{}\"", s);
+        let parsed: WriteResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, result);
+        assert_eq!(parsed.as_str(), s);
+        assert_eq!(parsed.did_write(), did_write, "{}", s);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+    assert!(serde_json::from_str::<WriteResult>("1").is_err());
+
+    let deserialized: DocWriteResponse = serde_json::from_str(r#"{
+        "_shards": { "total": 2, "failed": 0, "successful": 2 },
+        "_index": "logs",
+        "_type": "_doc",
+        "_id": "W0tpsmIBdwcYyG50zbta",
+        "_version": 3,
+        "_seq_no": 5,
+        "_primary_term": 1,
+        "result": "updated"
+    }"#).unwrap();
+    assert_eq!(deserialized.index(), "logs");
+    assert_eq!(deserialized.id(), "W0tpsmIBdwcYyG50zbta");
+    assert_eq!(deserialized.version(), Some(3));
+    assert_eq!(deserialized.seq_no(), Some(5));
+    assert_eq!(deserialized.primary_term(), Some(1));
+    assert_eq!(deserialized.result(), &WriteResult::Updated);
+    assert!(deserialized.result().did_write());
+    assert_eq!(deserialized.shards().successful(), 2);
+
+    let s = load_file("tests/samples/bulk.json");
+    let deserialized: BulkResponse = serde_json::from_str(&s).unwrap();
+    let results: Vec<_> = deserialized.iter().map(|i| i.result()).collect();
+    assert_eq!(results,
+               vec![Some(&WriteResult::Created), Some(&WriteResult::NotFound), None, Some(&WriteResult::Updated)]);
+}
+
+#[test]
+fn test_bulk_retryable_items() {
+    let s = load_file("tests/samples/bulk_rejected.json");
+    let deserialized: BulkResponse = serde_json::from_str(&s).unwrap();
+
+    let retryable: Vec<_> = deserialized.retryable_items().iter().map(|p| (p.position, p.action, p.id)).collect();
+    assert_eq!(retryable, vec![(1, BulkAction::Index, Some("a2")), (3, BulkAction::Update, Some("a4"))]);
+
+    let partition = deserialized.partition();
+    let positions = |items: &[PositionedItem]| items.iter().map(|p| p.position).collect::<Vec<_>>();
+    assert_eq!(positions(&partition.succeeded), vec![0, 4]);
+    assert_eq!(positions(&partition.retryable), vec![1, 3]);
+    assert_eq!(positions(&partition.fatal), vec![2]);
+    assert_eq!(partition.fatal[0].item.error().map(|e| e.error_type()), Some("mapper_parsing_exception"));
+    for p in partition.succeeded.iter().chain(&partition.retryable).chain(&partition.fatal) {
+        assert_eq!(p.id, deserialized.items()[p.position].id());
+    }
+
+    // a missing document and a version conflict fail for good
+    let s = load_file("tests/samples/bulk.json");
+    let deserialized: BulkResponse = serde_json::from_str(&s).unwrap();
+    assert!(deserialized.retryable_items().is_empty());
+    assert_eq!(deserialized.partition().fatal.iter().map(|p| p.position).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn test_parse_get() {
+    let s = load_file("tests/samples/get.json");
+    let deserialized: GetResponse<LogLine> = serde_json::from_str(&s).unwrap();
+
+    assert!(deserialized.found());
+    assert_eq!(deserialized.index(), "logs");
+    assert_eq!(deserialized.id(), "1");
+    assert_eq!(deserialized.version(), Some(3));
+    assert_eq!(deserialized.seq_no(), Some(10));
+    assert_eq!(deserialized.source().map(|s| &*s.host), Some("web-01"));
+
+    let missing: GetResponse<LogLine> = parse_http_response(404, br#"{"_index":"logs","_type":"_doc","_id":"9","found":false}"#).unwrap();
+    assert!(!missing.found());
+    assert!(missing.into_source().is_none());
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_rows_match_flatten_reference() {
+    use elastic_responses::testing::{AggTreeGenerator, SearchResponseBuilder, flatten_reference};
+
+    let mut samples = 0;
+    for entry in std::fs::read_dir("tests/samples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |e| e != "json") {
+            continue;
+        }
+        if let Ok(deserialized) = serde_json::from_str::<Response>(&load_file(path.to_str().unwrap())) {
+            assert_eq!(owned_rows(deserialized.aggs()), flatten_reference(deserialized.aggs()), "{}", path.display());
+            samples += 1;
+        }
+    }
+    assert!(samples > 10, "only {} samples parsed", samples);
+
+    let mut generated = 0;
+    for seed in 0..300 {
+        let mut generator = AggTreeGenerator::new(seed).max_depth(1 + seed as usize % 4);
+        let aggs = match generator.generate() {
+            Value::Object(aggs) => aggs,
+            _ => unreachable!()
+        };
+        let body = aggs.into_iter().fold(SearchResponseBuilder::new(), |b, (name, agg)| b.agg(&name, agg)).build_string();
+        let deserialized: Response = serde_json::from_str(&body).unwrap();
+
+        let rows = flatten_reference(deserialized.aggs());
+        assert_eq!(owned_rows(deserialized.aggs()), rows, "seed {}", seed);
+        generated += rows.len();
+    }
+    assert!(generated > 1000, "only {} rows generated", generated);
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_testing_builders() {
+    use elastic_responses::testing::{BulkResponseBuilder, ErrorResponseBuilder, GetResponseBuilder,
+                                     SearchResponseBuilder};
+
+    let host = |h: &str| serde_json::from_str::<Value>(&format!(r#"{{"host": "{}"}}"#, h)).unwrap();
+
+    let body = SearchResponseBuilder::new()
+        .took(5)
+        .hit(host("web-01"))
+        .hit_with_id("custom", host("web-02"))
+        .agg_terms("by_host", [("a", 3), ("b", 1)])
+        .scroll_id("scroll-1")
+        .build_string();
+    let search: Response = parse_http_response(200, body.as_bytes()).unwrap();
+    assert_eq!(search.took(), 5);
+    assert_eq!(search.hits().total().unwrap().value(), 2);
+    assert_eq!(search.hits().total().unwrap().relation(), TotalHitsRelation::Eq);
+    assert_eq!(search.hits().iter().map(|h| h.id()).collect::<Vec<_>>(), vec!["0", "custom"]);
+    assert_eq!(search.scroll_id(), Some("scroll-1"));
+    let counts: Vec<_> = search.aggs().into_iter().map(|r| r["by_host_doc_count"].clone()).collect();
+    assert_eq!(counts, vec![Value::U64(3), Value::U64(1)]);
+
+    let empty: Response = serde_json::from_str(&SearchResponseBuilder::new().total(10, TotalHitsRelation::Gte).build_string())
+        .unwrap();
+    assert!(empty.hits().is_empty());
+    assert_eq!(empty.hits().max_score(), None);
+    assert_eq!(empty.hits().total().unwrap().relation(), TotalHitsRelation::Gte);
+
+    let body = BulkResponseBuilder::new()
+        .ok(BulkAction::Index, "1")
+        .index_name("other")
+        .ok(BulkAction::Delete, "2")
+        .failed(BulkAction::Create, "3", 409, "version_conflict_engine_exception", "document already exists")
+        .build_string();
+    let bulk: BulkResponse = parse_http_response(200, body.as_bytes()).unwrap();
+    assert!(bulk.errors());
+    assert_eq!(bulk.items()[1].index(), "other");
+    assert_eq!(bulk.items()[1].result(), Some(&WriteResult::Deleted));
+    assert_eq!(bulk.failed_items().len(), 1);
+    assert!(!BulkResponseBuilder::new().ok(BulkAction::Update, "1").build().find("errors").and_then(Value::as_bool).unwrap());
+
+    let body = GetResponseBuilder::new("logs", "1").source(host("web-01")).version(4).build_string();
+    let get: GetResponse<Value> = parse_http_response(200, body.as_bytes()).unwrap();
+    assert!(get.found());
+    assert_eq!(get.version(), Some(4));
+    let body = GetResponseBuilder::new("logs", "2").build_string();
+    let missing: GetResponse<Value> = parse_http_response(404, body.as_bytes()).unwrap();
+    assert!(!missing.found());
+
+    let body = ErrorResponseBuilder::new(400, "search_phase_execution_exception", "all shards failed")
+        .caused_by("query_shard_exception", "failed to create query")
+        .caused_by("number_format_exception", "For input string: \"abc\"")
+        .field("phase", Value::String("query".to_owned()))
+        .build_string();
+    match parse_http_response::<Response>(400, body.as_bytes()) {
+        Err(ResponseError::Api(400, ref err)) => {
+            assert_eq!(err.error_type(), "search_phase_execution_exception");
+            assert_eq!(err.get("phase"), Some(&Value::String("query".to_owned())));
+            let cause = err.caused_by().unwrap();
+            assert_eq!(cause.error_type(), "query_shard_exception");
+            assert_eq!(cause.caused_by().map(|c| c.error_type()), Some("number_format_exception"));
+            assert_eq!(err.root_causes()[0].error_type(), "number_format_exception");
+        }
+        other => panic!("expected an api error, got {:?}", other),
+    }
+    let body = ErrorResponseBuilder::new(404, "index_not_found_exception", "no such index").build_string();
+    match parse_http_response::<Response>(404, body.as_bytes()) {
+        Err(ResponseError::Api(404, ref err)) => assert_eq!(err.root_causes()[0].reason(), Some("no such index")),
+        other => panic!("expected an api error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_terms_enum() {
+    let s = load_file("tests/samples/terms_enum.json");
+    let complete: TermsEnumResponse = parse_http_response(200, s.as_bytes()).unwrap();
+    assert_eq!(complete.terms(), &["kibana", "kibana_sample_data_flights", "kibana_sample_data_logs"]);
+    assert!(complete.is_complete());
+    assert!(!complete.is_empty());
+    assert_eq!(complete.shards().total(), 3);
+
+    let s = load_file("tests/samples/terms_enum_truncated.json");
+    let truncated: TermsEnumResponse = serde_json::from_str(&s).unwrap();
+    assert_eq!(truncated.terms().len(), 2);
+    assert!(!truncated.is_complete());
+    assert_eq!(truncated.shards().failed(), 1);
+    assert_eq!(truncated.shards().failures()[0].index(), Some("logs-2"));
+
+    let shards = r#""_shards": {"total": 1, "successful": 1, "failed": 0}"#;
+    let empty: TermsEnumResponse = serde_json::from_str(&format!(r#"{{{}, "terms": [], "complete": true}}"#, shards)).unwrap();
+    assert!(empty.is_empty());
+    assert!(empty.is_complete());
+    let missing: TermsEnumResponse = serde_json::from_str(&format!("{{{}}}", shards)).unwrap();
+    assert!(missing.is_empty());
+    assert!(!missing.is_complete());
+
+    let error = br#"{"error": {"type": "illegal_argument_exception", "reason": "field not found"}, "status": 400}"#;
+    match parse_http_response::<TermsEnumResponse>(400, error) {
+        Err(ResponseError::Api(400, ref err)) => assert_eq!(err.error_type(), "illegal_argument_exception"),
+        other => panic!("expected an api error, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_terms_enum_builder() {
+    use elastic_responses::testing::TermsEnumResponseBuilder;
+
+    let body = TermsEnumResponseBuilder::new().term("kibana").term("kibana_sample_data_logs").build_string();
+    let response: TermsEnumResponse = parse_http_response(200, body.as_bytes()).unwrap();
+    assert_eq!(response.terms(), &["kibana", "kibana_sample_data_logs"]);
+    assert!(response.is_complete());
+    assert_eq!(response.shards().successful(), 1);
+
+    let body = TermsEnumResponseBuilder::new().complete(false).build_string();
+    let response: TermsEnumResponse = parse_http_response(200, body.as_bytes()).unwrap();
+    assert!(response.is_empty());
+    assert!(!response.is_complete());
+}
+
+#[test]
+fn test_hit_source_path() {
+    let s = load_file("tests/samples/hits_nested_source.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let hit = deserialized.hits().iter().next().unwrap();
+    let string = |s: &str| Value::String(s.to_owned());
+
+    assert_eq!(hit.source_path("user.address.city"), Some(&string("Amsterdam")));
+    assert_eq!(hit.source_path("orders.1.items.0.sku"), Some(&string("b-1")));
+    assert_eq!(hit.source_path("orders[0].id"), Some(&Value::U64(1)));
+    assert_eq!(hit.source_path("tags.1"), Some(&string("vip")));
+    assert_eq!(hit.source_path(r#"labels["app.kubernetes.io/name"]"#), Some(&string("shop")));
+    assert_eq!(hit.source_path(r#"["labels"].team"#), Some(&string("payments")));
+    assert!(hit.source_path("user").unwrap().is_object());
+    assert_eq!(hit.source_path("orders.items"), None);
+    assert_eq!(hit.source_path("user.address.city.name"), None);
+    assert_eq!(hit.source_path("labels.app.kubernetes.io/name"), None);
+    assert_eq!(hit.source_path("user..name"), None);
+    assert_eq!(hit.source_path(r#"labels["team"#), None);
+
+    let skus: Vec<_> = hit.source_path_all("orders.items.sku").into_iter().cloned().collect();
+    assert_eq!(skus, vec![string("a-1"), string("a-2"), string("b-1")]);
+    assert_eq!(hit.source_path_all("orders.0.items.sku").len(), 2);
+    assert_eq!(hit.source_path_all("tags"), vec![&string("new"), &string("vip")]);
+    assert_eq!(hit.source_path_all("user.name"), vec![&string("kimchy")]);
+    assert!(hit.source_path_all("orders.missing").is_empty());
+    assert!(hit.source_path_all("user[").is_empty());
+}
+
+#[test]
+fn test_hit_docvalue_fields() {
+    let s = load_file("tests/samples/hits_docvalue_fields.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let hits: Vec<_> = deserialized.hits().iter().collect();
+
+    assert!(hits[0].source().is_none());
+    assert_eq!(hits[0].field_values("tags"),
+               &[Value::String("nginx".to_owned()), Value::String("prod".to_owned())]);
+    assert_eq!(hits[0].field_first_str("host"), Some("web-01"));
+    assert_eq!(hits[0].field_first_str("@timestamp"), Some("2024-10-14"));
+    assert_eq!(hits[0].field_first("bytes"), Some(&Value::U64(512)));
+    assert_eq!(hits[0].field_first_str("bytes"), None);
+
+    assert!(hits[1].field_values("host").is_empty());
+    assert_eq!(hits[1].field_first_str("host"), None);
+    assert!(hits[2].field_values("tags").is_empty());
+    assert_eq!(hits[2].field_first("@timestamp"), None);
+
+    let hosts: Vec<_> = deserialized.field_column("host").into_iter().map(|v| v.and_then(Value::as_str)).collect();
+    assert_eq!(hosts, vec![Some("web-01"), None, None]);
+    let days: Vec<_> = deserialized.field_column("@timestamp").into_iter().map(|v| v.and_then(Value::as_str)).collect();
+    assert_eq!(days, vec![Some("2024-10-14"), Some("2024-10-13"), None]);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct LatencySummary {
+    p50: f64,
+    p99: f64,
+    slow: bool
+}
+
+#[test]
+fn test_hit_script_fields() {
+    let s = load_file("tests/samples/hits_script_fields.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let hits: Vec<_> = deserialized.hits().iter().collect();
+
+    let summary: LatencySummary = hits[0].script_field("latency_summary").unwrap().unwrap();
+    assert!(summary.slow);
+    assert!(close(summary.p99, 230.0));
+    let summary: Option<LatencySummary> = hits[1].script_field("latency_summary").unwrap();
+    assert_eq!(summary.map(|s| s.slow), Some(false));
+
+    let bytes: Vec<u64> = hits[0].script_field("bytes_by_hour").unwrap().unwrap();
+    assert_eq!(bytes, vec![512, 1024, 0, 64]);
+    // a list of one, which is the element to types that accept it
+    let bytes: Vec<u64> = hits[1].script_field("bytes_by_hour").unwrap().unwrap();
+    assert_eq!(bytes, vec![7]);
+    let bytes: Value = hits[1].script_field("bytes_by_hour").unwrap().unwrap();
+    assert_eq!(bytes, Value::U64(7));
+
+    let doubled: Option<u64> = hits[0].script_field("doubled").unwrap();
+    assert_eq!(doubled, Some(10));
+    assert!(hits[1].script_field::<u64>("doubled").is_err());
+    assert_eq!(hits[0].script_field::<u64>("missing").unwrap(), None);
+
+    let raw: Value = hits[0].script_field("latency_summary").unwrap().unwrap();
+    assert!(raw.is_object());
+}
+
+#[test]
+fn test_hits_by_index() {
+    let hits: Vec<String> = [("logs-2024.10.14", "1"), ("logs-2024.10.12", "2"), ("logs-2024.10.14", "3"),
+                             ("logs-2024.10.13", "4"), ("logs-2024.10.12", "5"), ("logs-2024.10.14", "6")]
+        .iter()
+        .map(|&(index, id)| format!(r#"{{"_index": "{}", "_id": "{}", "_score": 1, "_source": {{}}}}"#, index, id))
+        .collect();
+    let body = format!(r#"{{"took": 1, "timed_out": false, "_shards": {{"total": 3, "successful": 3, "failed": 0}},
+                          "hits": {{"total": 6, "max_score": 1, "hits": [{}]}}}}"#,
+                       hits.join(","));
+    let deserialized: Response = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(deserialized.indices(), vec!["logs-2024.10.14", "logs-2024.10.12", "logs-2024.10.13"]);
+
+    let groups = deserialized.hits_by_index();
+    let ids: Vec<(&str, Vec<&str>)> = groups.iter().map(|(index, hits)| (*index, hits.iter().map(|h| h.id()).collect())).collect();
+    assert_eq!(ids,
+               vec![("logs-2024.10.12", vec!["2", "5"]),
+                    ("logs-2024.10.13", vec!["4"]),
+                    ("logs-2024.10.14", vec!["1", "3", "6"])]);
+
+    let empty: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    assert!(empty.indices().is_empty());
+    assert!(empty.hits_by_index().is_empty());
+}
+
+#[test]
+fn test_dedup_hits() {
+    let first: Response = serde_json::from_str(&scroll_body("s1", 2, &["1", "2", "3"])).unwrap();
+    let second: Response = serde_json::from_str(&scroll_body("s2", 3, &["3", "4"])).unwrap();
+    let last: Response = serde_json::from_str(&scroll_body("s3", 1, &[])).unwrap();
+
+    let mut dedup = DedupHits::new();
+    let ids: Vec<_> = dedup.filter(first.hits()).map(|h| h.id()).collect();
+    assert_eq!(ids, vec!["1", "2", "3"]);
+    let ids: Vec<_> = dedup.filter(second.hits()).map(|h| h.id()).collect();
+    assert_eq!(ids, vec!["4"]);
+    assert_eq!(dedup.duplicates_skipped(), 1);
+
+    let mut stitcher = ScrollStitcher::new().dedup_by_id();
+    stitcher.push(first).unwrap();
+    stitcher.push(second).unwrap();
+    stitcher.push(last).unwrap();
+    assert_eq!(stitcher.hits().map(|h| h.id()).collect::<Vec<_>>(), vec!["1", "2", "3", "4"]);
+    assert_eq!(stitcher.duplicates_skipped(), 1);
+    assert!(stitcher.is_finished());
+
+    let drained: Vec<_> = stitcher.drain().collect();
+    assert_eq!(drained.len(), 4);
+    let again: Response = serde_json::from_str(&scroll_body("s4", 1, &["2", "5"])).unwrap();
+    stitcher.push(again).unwrap();
+    assert_eq!(stitcher.hits().map(|h| h.id()).collect::<Vec<_>>(), vec!["5"]);
+    assert_eq!(stitcher.duplicates_skipped(), 2);
+
+    let mut plain = ScrollStitcher::new();
+    plain.push(serde_json::from_str::<Response>(&scroll_body("s1", 1, &["1"])).unwrap()).unwrap();
+    plain.push(serde_json::from_str::<Response>(&scroll_body("s2", 1, &["1"])).unwrap()).unwrap();
+    assert_eq!(plain.hits().count(), 2);
+    assert_eq!(plain.duplicates_skipped(), 0);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PartialUser {
+    name: String,
+    email: String,
+    nickname: Option<String>
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PartialAddress {
+    city: String,
+    country: String
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PartialUserWithAddress {
+    name: String,
+    address: PartialAddress
+}
+
+#[test]
+fn test_hit_types_across_versions() {
+    let types = |sample: &str| -> Vec<(Option<String>, Option<String>)> {
+        let deserialized: Response = serde_json::from_str(&load_file(sample)).unwrap();
+        deserialized.hits()
+            .iter()
+            .map(|hit| (hit.doc_type().map(str::to_owned), hit.raw_type().map(str::to_owned)))
+            .collect()
+    };
+
+    let es6 = types("tests/samples/hits_es6.json");
+    assert_eq!(es6, vec![(Some("log".to_owned()), Some("log".to_owned())); 2]);
+    let es7 = types("tests/samples/hits_es7.json");
+    assert_eq!(es7, vec![(None, Some("_doc".to_owned())); 2]);
+    let es8 = types("tests/samples/hits_es8.json");
+    assert_eq!(es8, vec![(None, None); 2]);
+
+    // the rest of the hit reads the same whatever the version
+    for sample in &["tests/samples/hits_es6.json", "tests/samples/hits_es7.json", "tests/samples/hits_es8.json"] {
+        let deserialized: Response = serde_json::from_str(&load_file(sample)).unwrap();
+        let ids: Vec<_> = deserialized.hits().iter().map(|hit| (hit.index(), hit.id())).collect();
+        assert_eq!(ids, vec![("logs-2018.06.01", "1"), ("logs-2018.06.01", "2")]);
+        assert_eq!(deserialized.hits().total().map(|t| t.value()), Some(2));
+    }
+}
+
+#[test]
+fn test_parse_source_partial() {
+    let s = load_file("tests/samples/hits_source_filtered.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let hits: Vec<_> = deserialized.hits().iter().collect();
+
+    let full: PartialUser = hits[0].parse_source_partial().unwrap();
+    assert_eq!(full.email, "ada@example.com");
+    assert_eq!(full.nickname.as_ref().map(|n| &**n), Some("countess"));
+
+    match hits[1].parse_source_partial::<PartialUser>() {
+        Err(Error::MissingFields(fields)) => assert_eq!(fields, vec!["email"]),
+        other => panic!("expected missing fields, got {:?}", other)
+    }
+    match hits[2].parse_source_partial::<PartialUser>() {
+        Err(Error::MissingFields(fields)) => assert_eq!(fields, vec!["name", "email"]),
+        other => panic!("expected missing fields, got {:?}", other)
+    }
+
+    let nested = hits[2].parse_source_partial::<PartialUserWithAddress>().unwrap_err();
+    assert_eq!(nested.to_string(), "source is missing required fields: address.country, name");
+    match hits[1].parse_source_partial::<PartialUserWithAddress>() {
+        Err(Error::MissingFields(fields)) => assert_eq!(fields, vec!["address"]),
+        other => panic!("expected missing fields, got {:?}", other)
+    }
+
+    // a wrong type is still a plain parse error
+    match hits[0].parse_source_partial::<BTreeMap<String, u64>>() {
+        Err(Error::Json(_)) => (),
+        other => panic!("expected a parse error, got {:?}", other)
+    }
+}
+
+#[test]
+fn test_hits_table() {
+    let s = load_file("tests/samples/hits_partial_fields.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let table = deserialized.hits_table(&["timestamp", "host.name", "bytes"]);
+
+    assert_eq!(table.len(), 4);
+    assert!(!table.is_empty());
+    assert_eq!(table.column_names(), vec!["timestamp", "host.name", "bytes"]);
+
+    assert_eq!(table.column_str("host.name").unwrap(), vec![Some("web-01"), Some("web-02"), None, None]);
+    assert_eq!(table.column_f64("bytes").unwrap(), vec![Some(512.0), None, Some(2048.5), None]);
+    assert_eq!(table.column("bytes").unwrap()[3], Some(Value::String("n/a".to_owned())));
+
+    assert_eq!(table.null_count("timestamp"), Some(0));
+    assert_eq!(table.null_count("host.name"), Some(2));
+    assert_eq!(table.null_count("bytes"), Some(1));
+    assert_eq!(table.null_count("missing"), None);
+    assert!(table.column("missing").is_none());
+
+    let empty: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    let table = empty.hits_table(&["host"]);
+    assert!(table.is_empty());
+    assert_eq!(table.null_count("host"), Some(0));
+}
+
+#[test]
+fn test_aggregations_diff() {
+    let before: Response = serde_json::from_str(&load_file("tests/samples/aggregation_diff_before.json")).unwrap();
+    let after: Response = serde_json::from_str(&load_file("tests/samples/aggregation_diff_after.json")).unwrap();
+    let diff: AggDiff = after.aggs().diff(before.aggs());
+
+    assert!(!diff.is_empty());
+    assert_eq!(diff.added().len(), 1);
+    assert_eq!(diff.added()[0]["per_host"], Value::String("web-04".to_owned()));
+    assert_eq!(diff.removed().len(), 1);
+    assert_eq!(diff.removed()[0]["per_host"], Value::String("web-03".to_owned()));
+
+    // the date histogram key changed from an integer to a float and its key_as_string format
+    // changed, but the buckets still match
+    assert_eq!(diff.changed().len(), 1);
+    let change = &diff.changed()[0];
+    assert_eq!(change.key()["per_day"], "1483228800000");
+    assert_eq!(change.key()["per_host"], "web-01");
+    assert_eq!(change.metrics().keys().collect::<Vec<_>>(), vec!["cpu_avg"]);
+    let cpu = change.metric("cpu_avg").unwrap();
+    assert_eq!(cpu.old().and_then(Value::as_f64), Some(41.5));
+    assert_eq!(cpu.new().and_then(Value::as_f64), Some(44.0));
+    assert_eq!(cpu.delta(), Some(2.5));
+
+    assert!(after.aggs().diff(after.aggs()).is_empty());
+
+    // keyed on the host alone, the changed key_as_string of the day is compared as a metric
+    let by_host = after.aggs().diff_by(before.aggs(), &["per_host"]);
+    assert_eq!(by_host.changed().len(), 2);
+    assert_eq!(by_host.changed()[0].key().keys().collect::<Vec<_>>(), vec!["per_host"]);
+    assert_eq!(by_host.changed()[0].metrics().keys().collect::<Vec<_>>(),
+               vec!["cpu_avg", "per_day_key_as_string"]);
+    assert_eq!(by_host.changed()[1].key()["per_host"], "web-02");
+    assert_eq!(by_host.changed()[1].metric("per_day_key_as_string").unwrap().delta(), None);
+}
+
+#[test]
+fn test_pruned_buckets_align_by_key() {
+    // days with fewer than 10 docs were dropped by a `bucket_selector`, and `by_host` was cut to
+    // its two largest buckets by a `bucket_sort`, while its sibling `by_level` kept all of them
+    let after: Response = serde_json::from_str(&load_file("tests/samples/aggregation_bucket_selector.json")).unwrap();
+    let before: Response =
+        serde_json::from_str(&load_file("tests/samples/aggregation_bucket_selector_before.json")).unwrap();
+
+    let rows: Vec<_> = after.aggs().into_iter().collect();
+    assert_eq!(rows.len(), 8);
+    assert!(rows.iter().all(|row| row.contains_key("by_host") != row.contains_key("by_level")));
+    assert!(after.aggs().validate().is_empty());
+
+    let pivot = after.aggs().pivot(&["per_day", "by_host"], "by_host_doc_count").unwrap();
+    assert_eq!(pivot.keys().collect::<Vec<_>>(), vec!["2016-11-28", "2016-11-30"]);
+    assert_eq!(pivot["2016-11-28"].keys().collect::<Vec<_>>(), vec!["web-01", "web-02"]);
+    assert_eq!(pivot["2016-11-30"]["web-01"], Value::U64(5));
+    assert!(!pivot["2016-11-30"].contains_key("web-02"));
+    let levels = after.aggs().pivot(&["per_day", "by_level"], "by_level_doc_count").unwrap();
+    assert_eq!((levels["2016-11-28"].len(), levels["2016-11-30"].len()), (3, 1));
+
+    // `bucket_sort` also reordered the hosts of the first day, which only the metrics show
+    let diff = after.aggs().diff(before.aggs());
+    let keys = |rows: &[OwnedRow]| -> Vec<(String, String)> {
+        rows.iter()
+            .map(|row| (row.key_string("per_day").unwrap(),
+                        row.key_string("by_host").or_else(|| row.key_string("by_level")).unwrap()))
+            .collect()
+    };
+    let key = |day: &str, bucket: &str| (day.to_owned(), bucket.to_owned());
+    assert_eq!(keys(diff.added()),
+               vec![key("2016-11-30", "web-03"), key("2016-11-30", "web-01"), key("2016-11-30", "info")]);
+    assert_eq!(keys(diff.removed()),
+               vec![key("2016-11-29", "web-01"), key("2016-11-29", "web-02"), key("2016-11-29", "info")]);
+    assert_eq!(diff.changed().len(), 2);
+    assert_eq!(diff.changed()[0].key()["by_host"], "web-01");
+    assert_eq!(diff.changed()[0].metric("by_host_doc_count").unwrap().delta(), Some(2.0));
+    assert_eq!(diff.changed()[1].key()["by_host"], "web-02");
+    assert_eq!(diff.changed()[1].metrics().keys().collect::<Vec<_>>(), vec!["by_host_doc_count"]);
+}
+
+#[test]
+fn test_rows_with_mapped_normalize_values() {
+    let s = load_file("tests/samples/aggregation_normalize.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    // `normalize` values flatten like any other pipeline value
+    let plain: Vec<_> = deserialized.aggs().into_iter().collect();
+    assert_eq!(plain.len(), 3);
+    let fraction = plain[0].get_f64("percent_of_total_sales").unwrap();
+    assert!((fraction - 0.5583756345177665).abs() < 1e-12);
+
+    let config = FlattenConfig::new()
+        .map_column("percent_of_total_sales", |v| v * 100.0)
+        .map_column("sales_per_month_key_as_string", |v| v + 1.0)
+        .map_column("sales", |v| v / 1000.0)
+        .map_column("sales", |v| v.round());
+    let rows: Vec<OwnedRow> = deserialized.aggs().rows_with(config).collect();
+    assert_eq!(rows.len(), plain.len());
+
+    let percentages: Vec<f64> = rows.iter().filter_map(|row| row.get_f64("percent_of_total_sales")).collect();
+    assert!((percentages[0] - 55.83756345177665).abs() < 1e-9);
+    assert!((percentages.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+    assert_eq!(rows.iter().filter_map(|row| row.get_f64("sales")).collect::<Vec<_>>(), vec![1.0, 0.0, 0.0]);
+
+    // strings aren't mapped, and unmapped columns are kept as they were
+    assert_eq!(rows[1].get_str("sales_per_month_key_as_string"), Some("2015/02/01 00:00:00"));
+    assert_eq!(rows[1]["sales_per_month"], Value::U64(1422748800000));
+    assert_eq!(rows[1]["sales_per_month_doc_count"], Value::U64(2));
+}
+
+#[test]
+fn test_validate_aggregations() {
+    let s = load_file("tests/samples/aggregation_unknown_types.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let warnings = deserialized.aggs().validate();
+
+    let paths: Vec<&str> = warnings.iter().map(|w| w.path()).collect();
+    assert_eq!(paths, vec!["/broken", "/correlation", "/per_day/buckets/0/skew", "/per_day/buckets/1/skew"]);
+    assert!(warnings[0].guess().contains("neither an array nor an object"));
+    assert_eq!(warnings[1].guess(), "possibly a bucket aggregation that calls its buckets `fields`");
+    assert_eq!(warnings[2].to_string(),
+               "unknown aggregation at `/per_day/buckets/0/skew`: possibly a multi-value metric with fields `p_high`, `p_low`");
+
+    // the rows are unaffected by the unknown aggregations
+    assert_eq!(deserialized.aggs().into_iter().count(), 2);
+
+    for sample in &["aggregation_3level.json",
+                    "aggregation_3level_multichild.json",
+                    "aggregation_3level_multistats.json",
+                    "aggregation_date_terms_avg.json",
+                    "aggregation_geo.json",
+                    "aggregation_simple.json"] {
+        let s = load_file(&format!("tests/samples/{}", sample));
+        let deserialized: Response = serde_json::from_str(&s).unwrap();
+        assert_eq!(deserialized.aggs().validate(), vec![], "{}", sample);
+    }
+}
+
+#[test]
+fn test_raw_json() {
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let fixture: Value = serde_json::from_str(&s).unwrap();
+
+    let aggs = deserialized.aggs();
+    assert_eq!(aggs.raw(), fixture.find("aggregations").unwrap());
+    assert_eq!(aggs.raw_agg("per_day"), fixture.pointer("/aggregations/per_day"));
+    assert!(aggs.raw_agg("missing").is_none());
+    let reparsed: Value = serde_json::from_str(&aggs.to_string_pretty()).unwrap();
+    assert_eq!(&reparsed, fixture.find("aggregations").unwrap());
+    assert!(aggs.to_string_pretty().contains("\n"));
+
+    let s = load_file("tests/samples/hits_raw.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let fixture: Value = serde_json::from_str(&s).unwrap();
+    for (i, hit) in deserialized.hits().iter().enumerate() {
+        let expected = fixture.pointer(&format!("/hits/hits/{}", i)).unwrap();
+        assert_eq!(&hit.raw(), expected);
+        let reparsed: Value = serde_json::from_str(&hit.to_string_pretty()).unwrap();
+        assert_eq!(&reparsed, expected);
+    }
+}
+
+#[test]
+fn test_conditions() {
+    use elastic_responses::RowAccess;
+
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let conditions: Vec<Condition> = serde_json::from_str(r#"[
+        {"name": "busy", "column": "cpu", "comparator": ">=", "threshold": 70},
+        {"column": "hosts_doc_count", "comparator": "<", "threshold": 20},
+        {"column": "hosts", "comparator": "==", "threshold": "db-01"},
+        {"column": "hosts", "comparator": "!=", "threshold": "db-01"},
+        {"column": "cpu", "comparator": "!=", "threshold": 70.0}
+    ]"#).unwrap();
+
+    assert_eq!(conditions[0].name(), Some("busy"));
+    assert_eq!(conditions[1].name(), None);
+    assert_eq!(conditions[0].comparator(), Comparator::Gte);
+
+    let hosts = |condition: &Condition| -> Vec<String> {
+        condition.evaluate(deserialized.aggs())
+            .iter()
+            .filter_map(|row| row.get_str("hosts").map(str::to_owned))
+            .collect()
+    };
+    assert_eq!(hosts(&conditions[0]), vec!["web-02", "web-01"]);
+    assert_eq!(hosts(&conditions[1]), vec!["web-01", "cache-01"]);
+    assert_eq!(hosts(&conditions[2]), vec!["db-01"]);
+    assert_eq!(hosts(&conditions[3]), vec!["web-03", "web-02", "web-01", "cache-01"]);
+    // rows with a null cpu never match
+    assert_eq!(hosts(&conditions[4]), vec!["web-03", "cache-01"]);
+
+    let mut row = BTreeMap::new();
+    row.insert("rate".to_owned(), Value::String("0.07".to_owned()));
+    assert!(Condition::new("rate", Comparator::Gt, Value::F64(0.05)).matches(&row));
+    assert!(!Condition::new("missing", Comparator::Ne, Value::F64(0.05)).matches(&row));
+
+    let condition = Condition::new("cpu", Comparator::Lte, Value::U64(50)).named("idle");
+    let round_trip: Condition = serde_json::from_str(&serde_json::to_string(&condition).unwrap()).unwrap();
+    assert_eq!(round_trip, condition);
+
+    assert!(serde_json::from_str::<Condition>(r#"{"column": "cpu", "comparator": "=>", "threshold": 1}"#).is_err());
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_hits_table_to_record_batch() {
+    use arrow::array::{Array, StringArray};
+    use arrow::datatypes::DataType;
+
+    let s = load_file("tests/samples/hits_partial_fields.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let export = deserialized.hits_table(&["host.name", "bytes"]).to_record_batch().unwrap();
+    let batch = export.batch();
+
+    assert_eq!(batch.num_rows(), 4);
+    let schema = batch.schema();
+    assert_eq!(schema.field(0).name(), "host.name");
+    assert_eq!(schema.field(0).data_type(), &DataType::Utf8);
+    assert_eq!(schema.field(1).name(), "bytes");
+    assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+
+    let hosts = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(hosts.value(1), "web-02");
+    assert!(hosts.is_null(2));
+    assert!(hosts.is_null(3));
+
+    let bytes = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(bytes.value(0), "512");
+    assert_eq!(bytes.value(2), "2048.5");
+    assert_eq!(bytes.value(3), "n/a");
+    assert!(bytes.is_null(1));
+
+    assert_eq!(export.warnings().len(), 1);
+    assert!(export.warnings()[0].contains("`bytes`"));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_aggregations_to_record_batch() {
+    use arrow::array::{Float64Array, Int64Array, StringArray, TimestampMillisecondArray};
+    use arrow::datatypes::{DataType, TimeUnit};
+
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let export = deserialized.aggs().to_record_batch().unwrap();
+    let batch = export.batch();
+    let schema = batch.schema();
+
+    assert!(export.warnings().is_empty());
+    assert_eq!(batch.num_rows(), 5);
+    let types: Vec<_> = schema.fields().iter().map(|f| (f.name().as_str(), f.data_type().clone())).collect();
+    assert_eq!(types,
+               vec![("cpu_avg", DataType::Float64),
+                    ("per_day", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))),
+                    ("per_day_doc_count", DataType::Int64),
+                    ("per_day_key_as_string", DataType::Utf8),
+                    ("per_host", DataType::Utf8),
+                    ("per_host_doc_count", DataType::Int64)]);
+
+    let cpu = batch.column(0).as_any().downcast_ref::<Float64Array>().unwrap();
+    assert_eq!(cpu.value(0), 41.5);
+    let days = batch.column(1).as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
+    assert_eq!(days.value(0), 1483228800000);
+    let counts = batch.column(5).as_any().downcast_ref::<Int64Array>().unwrap();
+    assert_eq!(counts.value(0), 12);
+    let hosts = batch.column(4).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(hosts.value(1), "web-02");
+}
+
+#[test]
+fn test_missing_and_filter_aggs_flatten_into_their_bucket() {
+    let s = load_file("tests/samples/aggregation_missing.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let rows: Vec<_> = deserialized.aggs().into_iter().collect();
+    // the second day has no errors, so only its hosts have rows
+    assert_eq!(rows.len(), 4);
+    let keys: Vec<_> = rows.iter()
+        .map(|row| row.key_string("by_host").or_else(|| row.key_string("by_code")).unwrap())
+        .collect();
+    assert_eq!(keys, vec!["web-01", "N/A", "500", "web-01"]);
+
+    // the placeholder bucket of `"missing": "N/A"` is a bucket like any other
+    assert_eq!(rows[1].get_f64("by_host_doc_count"), Some(3.0));
+    for row in &rows[..3] {
+        assert_eq!(row.get_f64("no_host_doc_count"), Some(3.0));
+        assert_eq!(row.get_f64("bytes"), Some(120.0));
+        assert_eq!(row.get_f64("errors_doc_count"), Some(2.0));
+    }
+    assert_eq!(rows[2].get_f64("by_code_doc_count"), Some(2.0));
+    assert_eq!(rows[3].get_f64("no_host_doc_count"), Some(0.0));
+    assert_eq!(rows[3].column("bytes"), Some(&Value::Null));
+
+    let columns = deserialized.aggs().columns();
+    assert!(columns.contains(&"no_host_doc_count".to_owned()) && columns.contains(&"errors_doc_count".to_owned()));
+    assert_eq!(deserialized.aggs().key_columns(), vec!["by_code", "by_host", "per_day"]);
+    assert_eq!(deserialized.aggs().key_types().get("by_code"), Some(KeyType::I64));
+}
+
+#[test]
+fn test_null_bucket_keys() {
+    let s = load_file("tests/samples/aggregation_null_key.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let rows: Vec<_> = deserialized.aggs().into_iter().collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1].column("by_port"), Some(&Value::Null));
+    assert_eq!(rows[1].key_string("by_port"), None);
+    assert_eq!(rows[1].bucket_key("by_port"), Some(BucketKey::Null));
+    assert_eq!(rows[1].get_f64("by_port_doc_count"), Some(2.0));
+    assert_eq!(deserialized.aggs().key_types().get("by_port"), Some(KeyType::I64));
+    assert!(deserialized.aggs().key_types().warnings().is_empty());
+
+    let skipped: Vec<OwnedRow> = deserialized.aggs().rows_with(FlattenConfig::new().skip_null_keys()).collect();
+    assert_eq!(skipped.len(), 3);
+    assert!(!skipped[1].contains_key("by_port"));
+    assert_eq!(skipped[1]["by_port_doc_count"], Value::U64(2));
+    assert_eq!(skipped[0]["by_port"], Value::U64(80));
+
+    let sorted = deserialized.aggs().sorted_rows("by_port", Order::Asc).collect::<Vec<_>>();
+    assert_eq!(sorted[2]["by_port"], Value::Null);
+    assert!(!deserialized.aggs().pretty().to_string().is_empty());
+}
+
+#[test]
+fn test_stray_bucket_entries() {
+    let s = load_file("tests/samples/aggregation_stray_buckets.json");
+
+    // lenient by default: the stray entries are skipped, but still reported
+    let observer = Arc::new(RecordingObserver::new());
+    let deserialized = ResponseParser::new().observer(observer.clone()).parse_slice::<Value>(s.as_bytes()).unwrap();
+    let keys: Vec<_> = deserialized.aggs()
+        .into_iter()
+        .map(|row| (row.key_string("by_host").unwrap(), row.key_string("by_level").unwrap()))
+        .collect();
+    assert_eq!(keys.len(), 3);
+    assert_eq!(keys[1], ("web-01".to_owned(), "warn".to_owned()));
+    assert_eq!(deserialized.aggs().try_rows().filter(Result::is_err).count(), 3);
+    let stray: Vec<_> = deserialized.aggs().validate().iter().map(|w| w.path().to_owned()).collect();
+    assert_eq!(stray, vec!["/by_host/buckets/0/by_level/buckets/1", "/by_host/buckets/1", "/by_host/buckets/2"]);
+    assert_eq!(observer.records()[0].stray_buckets, 3);
+
+    match ResponseParser::new().strict_buckets().parse_slice::<Value>(s.as_bytes()) {
+        Err(ref err @ Error::InvalidBucket(_)) => {
+            assert_eq!(err.to_string(), "bucket at `/by_host/buckets/0/by_level/buckets/1` is not an object")
+        }
+        other => panic!("expected an invalid bucket, got {:?}", other)
+    }
+    let clean = load_file("tests/samples/aggregation_missing.json");
+    assert!(ResponseParser::new().strict_buckets().parse_slice::<Value>(clean.as_bytes()).is_ok());
+}
+
+/// A response with `levels` terms aggregations nested in each other, `l0` to `l{levels - 1}`.
+fn nested_aggs(levels: usize) -> Value {
+    let mut aggs = BTreeMap::new();
+    for level in (0..levels).rev() {
+        let mut bucket = aggs;
+        bucket.insert("key".to_owned(), Value::String(format!("k{}", level)));
+        bucket.insert("doc_count".to_owned(), Value::U64(1));
+        let mut agg = BTreeMap::new();
+        agg.insert("buckets".to_owned(), Value::Array(vec![Value::Object(bucket)]));
+        aggs = BTreeMap::new();
+        aggs.insert(format!("l{}", level), Value::Object(agg));
+    }
+
+    let mut response: Value = serde_json::from_str(&hits_body("1", 0)).unwrap();
+    response.as_object_mut().unwrap().insert("aggregations".to_owned(), Value::Object(aggs));
+    response
+}
+
+#[test]
+fn test_aggregation_depth_limit() {
+    // serde_json gives up on JSON this deep by itself, before anything recurses into it
+    let mut body = String::new();
+    for level in 0..1000 {
+        body.push_str(&format!(r#"{{"l{}": {{"buckets": [{{"key": "k{}", "doc_count": 1, "#, level, level));
+    }
+    body = format!(r#"{{"took": 1, "timed_out": false, "_shards": {{"total": 1, "successful": 1, "failed": 0}},
+                        "aggregations": {}"end": 1{}}}"#, body, "}]}}".repeat(1000));
+    match ResponseParser::new().parse_slice::<Value>(body.as_bytes()) {
+        Err(Error::Parse(..)) => (),
+        other => panic!("expected a parse error, got {:?}", other.map(|r| r.took()))
+    }
+
+    // trees from elsewhere are cut off below the 64th level, by default
+    let mut deserialized: Response = serde_json::from_value(nested_aggs(200)).unwrap();
+    let mut strict: Response = serde_json::from_value(nested_aggs(200)).unwrap();
+    ResponseParser::new().limit_depth(&mut deserialized).unwrap();
+    let rows = owned_rows(deserialized.aggs());
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get_str("l63"), Some("k63"));
+    assert!(!rows[0].contains_key("l64"));
+    assert_eq!(deserialized.aggs().columns().len(), 128);
+
+    let path = (0..65).map(|level| format!("l{}", level)).collect::<Vec<_>>().join("/buckets/0/");
+    match ResponseParser::new().strict_depth().limit_depth(&mut strict) {
+        Err(Error::DepthExceeded { depth, path: ref p }) => assert_eq!((depth, p), (65, &format!("/{}", path))),
+        other => panic!("expected the depth to be exceeded, got {:?}", other)
+    }
+    assert!(ResponseParser::new().strict_depth().check(&strict).is_err());
+    assert!(ResponseParser::new().check(&strict).is_ok());
+
+    // the limit can be lowered, or raised to keep the whole tree
+    let body = serde_json::to_string(&nested_aggs(3)).unwrap();
+    let deserialized = ResponseParser::new().max_depth(2).parse_slice::<Value>(body.as_bytes()).unwrap();
+    assert_eq!(deserialized.aggs().columns(), vec!["l0", "l0_doc_count", "l1", "l1_doc_count"]);
+    assert!(ResponseParser::new().max_depth(3).strict_depth().parse_slice::<Value>(body.as_bytes()).is_ok());
+    match ResponseParser::new().max_depth(2).strict_depth().parse_slice::<Value>(body.as_bytes()) {
+        Err(err @ Error::DepthExceeded { .. }) => {
+            assert_eq!(err.to_string(), "aggregation at `/l0/buckets/0/l1/buckets/0/l2` is nested 3 levels deep")
+        }
+        other => panic!("expected the depth to be exceeded, got {:?}", other.map(|r| r.took()))
+    }
+    let lines = format!("{}\n{}\n", body, serde_json::to_string(&nested_aggs(1)).unwrap());
+    let results: Vec<_> = ResponseParser::new().max_depth(2).strict_depth().responses_from_ndjson::<_, Value>(lines.as_bytes()).collect();
+    assert!(match results[0] {
+        Err(Error::DepthExceeded { depth: 3, .. }) => true,
+        _ => false
+    });
+    assert!(results[1].is_ok());
+
+    let mut deep: Response = serde_json::from_value(nested_aggs(100)).unwrap();
+    ResponseParser::new().max_depth(100).limit_depth(&mut deep).unwrap();
+    assert_eq!(owned_rows(deep.aggs())[0].get_str("l99"), Some("k99"));
+}
+
+#[test]
+fn test_bucket_key_types() {
+    let s = load_file("tests/samples/aggregation_key_types.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    let types = deserialized.aggs().key_types();
+    let listed: Vec<_> = types.iter().map(|(name, ty)| (name.as_str(), *ty)).collect();
+    assert_eq!(listed,
+               vec![("by_flag", KeyType::Bool),
+                    ("by_host", KeyType::Str),
+                    ("by_port", KeyType::I64),
+                    ("by_ratio", KeyType::F64),
+                    ("by_script", KeyType::Str),
+                    ("by_status", KeyType::I64)]);
+    assert_eq!(types.warnings().len(), 1);
+    assert_eq!(types.warnings()[0], "bucket keys of `by_script` have different types and are treated as strings");
+
+    let keys: Vec<_> = deserialized.aggs()
+        .into_iter()
+        .filter_map(|row| row.bucket_key("by_status").map(|k| (k, row.bucket_key("by_port").unwrap())))
+        .collect();
+    assert_eq!(keys,
+               vec![(BucketKey::I64(200), BucketKey::I64(8080)),
+                    (BucketKey::I64(200), BucketKey::I64(443)),
+                    (BucketKey::I64(-1), BucketKey::I64(443))]);
+
+    let values = vec![Value::String("web-01".to_owned()), Value::I64(-3), Value::U64(u64::max_value()), Value::F64(1.5),
+                      Value::F64(2.0), Value::Bool(true), Value::Null];
+    let rendered: Vec<_> = values.iter().map(BucketKey::from).map(|k| (k.key_type(), k.to_string())).collect();
+    assert_eq!(rendered,
+               vec![(Some(KeyType::Str), "web-01".to_owned()),
+                    (Some(KeyType::I64), "-3".to_owned()),
+                    (Some(KeyType::Str), "18446744073709551615".to_owned()),
+                    (Some(KeyType::F64), "1.5".to_owned()),
+                    (Some(KeyType::F64), "2".to_owned()),
+                    (Some(KeyType::Bool), "true".to_owned()),
+                    (None, "null".to_owned())]);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_bucket_key_types_to_record_batch() {
+    use arrow::array::{BooleanArray, Float64Array, Int64Array, StringArray};
+    use arrow::datatypes::DataType;
+
+    let s = load_file("tests/samples/aggregation_key_types.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let export = deserialized.aggs().to_record_batch().unwrap();
+    let batch = export.batch();
+    let schema = batch.schema();
+    let column = |name: &str| schema.index_of(name).unwrap();
+
+    assert_eq!(schema.field(column("by_status")).data_type(), &DataType::Int64);
+    assert_eq!(schema.field(column("by_port")).data_type(), &DataType::Int64);
+    assert_eq!(schema.field(column("by_ratio")).data_type(), &DataType::Float64);
+    assert_eq!(schema.field(column("by_flag")).data_type(), &DataType::Boolean);
+    assert_eq!(schema.field(column("by_host")).data_type(), &DataType::Utf8);
+    assert_eq!(schema.field(column("by_script")).data_type(), &DataType::Utf8);
+    assert_eq!(export.warnings().len(), 1);
+    assert!(export.warnings()[0].contains("`by_script`"));
+
+    let statuses = batch.column(column("by_status")).as_any().downcast_ref::<Int64Array>().unwrap();
+    let status_values: Vec<_> = statuses.iter().flatten().collect();
+    assert_eq!(status_values, vec![200, 200, -1]);
+    let ratios = batch.column(column("by_ratio")).as_any().downcast_ref::<Float64Array>().unwrap();
+    assert_eq!(ratios.iter().flatten().collect::<Vec<_>>(), vec![0.25, 1.5]);
+    let flags = batch.column(column("by_flag")).as_any().downcast_ref::<BooleanArray>().unwrap();
+    assert_eq!(flags.iter().flatten().collect::<Vec<_>>(), vec![true, false]);
+    let scripted = batch.column(column("by_script")).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(scripted.iter().flatten().collect::<Vec<_>>(), vec!["n/a", "7", "2.5"]);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_flat_aggs_to_record_batch_match_nested_path() {
+    let mixed: Value = serde_json::from_str(r#"{
+        "by_code": {
+            "sum_other_doc_count": 0,
+            "buckets": [{"key": 200, "doc_count": 9}, {"key": "n/a", "doc_count": 2}, {"key": null, "doc_count": 1}]
+        }
+    }"#).unwrap();
+
+    for &(ref aggs, agg) in &[(flat_date_histogram(), "per_day"), (mixed, "by_code")] {
+        let flat = reparse_aggs(aggs).aggs().to_record_batch().unwrap();
+        let nested = reparse_aggs(&with_keyed_sub_agg(aggs, agg)).aggs().to_record_batch().unwrap();
+        assert_eq!(flat.batch(), nested.batch());
+        assert_eq!(flat.warnings(), nested.warnings());
+    }
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_empty_aggs_to_record_batch() {
+    let significant: Value = serde_json::from_str(r#"{"rare": {"doc_count": 10, "bg_count": 100, "buckets": []}}"#).unwrap();
+    let export = reparse_aggs(&significant).aggs().to_record_batch().unwrap();
+    let schema = export.batch().schema();
+    let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+
+    assert_eq!(export.batch().num_rows(), 0);
+    assert_eq!(names, vec!["rare_subset_size", "rare_superset_size"]);
+
+    let none: Value = serde_json::from_str("{}").unwrap();
+    let export = reparse_aggs(&none).aggs().to_record_batch().unwrap();
+    assert_eq!(export.batch().num_columns(), 0);
+}
+
+/// Reader that counts the bytes read from it.
+struct CountingReader<R> {
+    inner: R,
+    read: usize
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read;
+        Ok(read)
+    }
+}
+
+#[test]
+fn test_response_parser_limits() {
+    let large = hits_body("100000", 100000);
+    let mut reader = CountingReader { inner: large.as_bytes(), read: 0 };
+    match ResponseParser::new().max_body_bytes(4096).parse_reader::<_, Value>(&mut reader) {
+        Err(Error::LimitExceeded { kind: Limit::BodyBytes, limit: 4096 }) => (),
+        other => panic!("expected the body size limit to be exceeded, got {:?}", other)
+    }
+    assert!(reader.read <= 4097, "read {} of {} bytes", reader.read, large.len());
+
+    let body = hits_body("3", 3);
+    let exact = ResponseParser::new().max_body_bytes(body.len() as u64).max_hits(3);
+    assert_eq!(exact.parse_reader::<_, Value>(body.as_bytes()).unwrap().hits().len(), 3);
+    assert_eq!(exact.parse_slice::<Value>(body.as_bytes()).unwrap().hits().len(), 3);
+    assert!(exact.max_body_bytes(body.len() as u64 - 1).parse_slice::<Value>(body.as_bytes()).is_err());
+
+    let err = ResponseParser::new().max_hits(2).parse_slice::<Value>(body.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "response exceeds the limit of 2 hits");
+
+    let s = load_file("tests/samples/aggregation_3level.json");
+    let buckets = ResponseParser::new().max_buckets(3).parse_slice::<Value>(s.as_bytes());
+    match buckets {
+        Err(Error::LimitExceeded { kind: Limit::Buckets, limit: 3 }) => (),
+        other => panic!("expected the bucket limit to be exceeded, got {:?}", other)
+    }
+    assert!(ResponseParser::new().max_buckets(1000).parse_slice::<Value>(s.as_bytes()).is_ok());
+}
+
+#[test]
+fn test_response_parser_ndjson_line_limit() {
+    let small = hits_body("1", 1).replace('\n', "");
+    let body = format!("{}\n{}\n{}\n", small, hits_body("500", 500).replace('\n', ""), small);
+    let parser = ResponseParser::new().max_body_bytes(small.len() as u64);
+
+    let responses: Vec<_> = parser.responses_from_ndjson::<_, Value>(body.as_bytes()).collect();
+    assert_eq!(responses.len(), 3);
+    assert!(responses[0].is_ok());
+    match responses[1] {
+        Err(Error::LimitExceeded { kind: Limit::BodyBytes, .. }) => (),
+        ref other => panic!("expected the line limit to be exceeded, got {:?}", other)
+    }
+    assert_eq!(responses[2].as_ref().unwrap().hits().len(), 1);
+}
+
+#[test]
+fn test_response_parser_observer() {
+    let observer = Arc::new(RecordingObserver::new());
+    let parser = ResponseParser::new().max_hits(2).observer(observer.clone());
+
+    let s = load_file("tests/samples/aggregation_simple.json");
+    let deserialized = parser.parse_slice::<Value>(s.as_bytes()).unwrap();
+    let partial = hits_body("3", 1).replace(r#""failed": 0"#, r#""failed": 2"#);
+    parser.parse_reader::<_, Value>(partial.as_bytes()).unwrap();
+    // rejected by the hit limit, but still observed
+    assert!(parser.parse_slice::<Value>(hits_body("3", 3).as_bytes()).is_err());
+    assert!(parser.parse_slice::<Value>(b"{").is_err());
+    let lines = format!("{}\n\n{}\n", hits_body("7", 0).replace('\n', ""), hits_body("8", 0).replace('\n', ""));
+    assert_eq!(parser.responses_from_ndjson::<_, Value>(lines.as_bytes()).count(), 2);
+
+    let records = observer.records();
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[0].took, deserialized.took());
+    assert_eq!(records[0].total_hits, deserialized.hits().total().map(|t| t.value()));
+    assert_eq!(records[0].shard_failures, 0);
+    assert_eq!(records[0].body_bytes, s.len() as u64);
+    assert_eq!((records[1].total_hits, records[1].shard_failures), (Some(3), 2));
+    assert_eq!(records[1].body_bytes, partial.len() as u64);
+    assert_eq!(records[2].total_hits, Some(3));
+    assert_eq!(records[3].total_hits, Some(7));
+    assert_eq!(records[4].body_bytes, hits_body("8", 0).replace('\n', "").len() as u64);
+    assert!(records.iter().all(|r| r.parse_duration < Duration::from_secs(5)));
+
+    NoopObserver.observe(&records[0]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_rows_match_sequential_rows() {
+    use rayon::prelude::*;
+
+    for sample in &["tests/samples/aggregation_3level.json",
+                   "tests/samples/aggregation_3level_multistats.json",
+                   "tests/samples/aggregation_3level_uneven.json",
+                   "tests/samples/aggregation_date_terms_avg.json",
+                   "tests/samples/aggregation_siblings.json",
+                   "tests/samples/aggregation_significant_terms.json"] {
+        let s = load_file(sample);
+        let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+        let sequential: Vec<OwnedRow> = deserialized.aggs()
+            .into_iter()
+            .map(|row| row.into_iter().map(|(k, v)| (k.into_owned(), v.clone())).collect())
+            .collect();
+        let parallel: Vec<OwnedRow> = deserialized.aggs().par_rows().collect();
+        assert_eq!(parallel, sequential, "{}", sample);
+
+        let mut by_bucket = Vec::new();
+        deserialized.aggs().par_rows_by_bucket().collect_into_vec(&mut by_bucket);
+        assert_eq!(by_bucket.into_iter().flat_map(|rows| rows).collect::<Vec<_>>(), sequential);
+    }
+
+    let empty: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    assert_eq!(empty.aggs().par_rows().count(), 0);
+}
+
+#[cfg(feature = "simd-json")]
+fn assert_same_value(left: &Value, right: &Value, path: &str) {
+    match (left, right) {
+        (&Value::Object(ref l), &Value::Object(ref r)) => {
+            assert_eq!(l.keys().collect::<Vec<_>>(), r.keys().collect::<Vec<_>>(), "{}", path);
+            for (key, value) in l {
+                assert_same_value(value, &r[key], &format!("{}/{}", path, key));
+            }
+        }
+        (&Value::Array(ref l), &Value::Array(ref r)) => {
+            assert_eq!(l.len(), r.len(), "{}", path);
+            for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                assert_same_value(l, r, &format!("{}/{}", path, i));
+            }
+        }
+        // serde_json 0.8 can round the last bit of a float differently
+        (&Value::F64(l), &Value::F64(r)) => assert!((l - r).abs() <= l.abs() * 1e-15, "{}: {} != {}", path, l, r),
+        _ => assert_eq!(left, right, "{}", path)
+    }
+}
+
+#[cfg(feature = "simd-json")]
+#[test]
+fn test_from_slice_mut_matches_serde_json() {
+    let mut compared = 0;
+    for entry in std::fs::read_dir("tests/samples").unwrap() {
+        let path = entry.unwrap().path();
+        let s = load_file(path.to_str().unwrap());
+        let expected = match elastic_responses::parse_slice::<Value>(s.as_bytes()) {
+            Ok(response) => response,
+            Err(_) => continue
+        };
+
+        let mut body = s.into_bytes();
+        let parsed = Response::from_slice_mut(&mut body).unwrap();
+        let path = path.display().to_string();
+        assert_eq!(parsed.took(), expected.took(), "{}", path);
+        assert_eq!(parsed.hits().total(), expected.hits().total(), "{}", path);
+        assert_eq!(parsed.hits().len(), expected.hits().len(), "{}", path);
+        for (hit, expected_hit) in parsed.hits().iter().zip(expected.hits()) {
+            assert_same_value(&hit.raw(), &expected_hit.raw(), &path);
+        }
+        assert_same_value(parsed.aggs().raw(), expected.aggs().raw(), &path);
+        compared += 1;
+    }
+    assert!(compared > 20);
+
+    let mut body = br#"{"took": 1, "timed_out": false, "hits": "#.to_vec();
+    assert!(Response::from_slice_mut(&mut body).is_err());
+}
+
+#[test]
+fn test_parser_reuses_buffers() {
+    let mut parser = Parser::new();
+    let first = load_file("tests/samples/hits_partial_fields.json");
+    let second = hits_body("2", 2);
+
+    let response: Response = parser.parse(&mut first.as_bytes()).unwrap();
+    assert_eq!(parser.body(), first.as_bytes());
+    assert_eq!(parser.hits_table(&response, &["host.name", "bytes"]).column_str("host.name").unwrap(),
+               vec![Some("web-01"), Some("web-02"), None, None]);
+
+    let response: Response = parser.parse(&mut second.as_bytes()).unwrap();
+    assert_eq!(response.hits().len(), 2);
+    assert_eq!(parser.body(), second.as_bytes());
+    let table = parser.hits_table(&response, &["host"]);
+    assert_eq!(table.column_names(), vec!["host"]);
+    assert_eq!(table.len(), 2);
+    assert_eq!(table, &response.hits_table(&["host"]));
+
+    let mut limited = Parser::with_limits(ResponseParser::new().max_body_bytes(second.len() as u64 - 1));
+    match limited.parse::<Value, _>(&mut second.as_bytes()) {
+        Err(Error::LimitExceeded { kind: Limit::BodyBytes, .. }) => (),
+        other => panic!("expected the body size limit to be exceeded, got {:?}", other)
+    }
+    assert!(limited.parse::<Value, _>(&mut hits_body("1", 1).as_bytes()).is_ok());
+
+    parser.shrink();
+    assert!(parser.body().is_empty());
+}
+
+#[test]
+fn test_aggs_response_matches_response() {
+    for sample in &["aggregation_3level_multichild", "aggregation_date_terms_avg", "aggregation_geo"] {
+        let s = load_file(&format!("tests/samples/{}.json", sample));
+        let full: Response = serde_json::from_str(&s).unwrap();
+        let aggs: AggsResponse = serde_json::from_str(&s).unwrap();
+
+        assert_eq!(aggs.took(), full.took(), "{}", sample);
+        assert_eq!(aggs.total(), full.hits().total(), "{}", sample);
+        assert_eq!(aggs.max_score(), full.hits().max_score(), "{}", sample);
+        assert_eq!(aggs.aggs().columns(), full.aggs().columns(), "{}", sample);
+        let rows: Vec<_> = aggs.aggs().into_iter().collect();
+        let expected: Vec<_> = full.aggs().into_iter().collect();
+        assert!(!rows.is_empty(), "{}", sample);
+        assert_eq!(rows, expected, "{}", sample);
+    }
+
+    // hits are skipped whatever their shape, and a missing hits object has no total
+    let aggs: AggsResponse = serde_json::from_str(r#"{
+        "took": 2,
+        "timed_out": true,
+        "_shards": { "total": 2, "successful": 1, "failed": 1 },
+        "hits": { "total": { "value": 10000, "relation": "gte" }, "max_score": 1.5, "hits": [{ "not": "a hit" }] }
+    }"#).unwrap();
+    assert!(aggs.timed_out());
+    assert_eq!(aggs.shards().failed(), 1);
+    assert_eq!(aggs.total().unwrap().relation(), TotalHitsRelation::Gte);
+    assert_eq!(aggs.max_score(), Some(1.5));
+    assert_eq!(aggs.aggs().into_iter().count(), 0);
+
+    let aggs: AggsResponse = parse_slice_as(br#"{"took": 1, "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 }}"#).unwrap();
+    assert_eq!(aggs.total(), None);
+}
+
+#[test]
+fn test_hits_above_score() {
+    // the first two hits come from a sorted query on another index, so they aren't scored
+    let deserialized: Response = serde_json::from_str(r#"{
+        "took": 1,
+        "timed_out": false,
+        "_shards": { "total": 2, "successful": 2, "failed": 0 },
+        "hits": {
+            "total": 5,
+            "max_score": 3.5,
+            "hits": [
+                { "_index": "sorted", "_type": "doc", "_id": "a", "_score": null, "sort": [1] },
+                { "_index": "sorted", "_type": "doc", "_id": "b", "_score": null, "sort": [2] },
+                { "_index": "scored", "_type": "doc", "_id": "c", "_score": 3.5 },
+                { "_index": "scored", "_type": "doc", "_id": "d", "_score": 2.0 },
+                { "_index": "scored", "_type": "doc", "_id": "e", "_score": 0.5 }
+            ]
+        }
+    }"#).unwrap();
+
+    let ids: Vec<&str> = deserialized.hits_above_score(2.0).map(|h| h.id()).collect();
+    assert_eq!(ids, vec!["c", "d"]);
+    let ids: Vec<&str> = deserialized.hits_above_score(2.0).include_unscored(true).map(|h| h.id()).collect();
+    assert_eq!(ids, vec!["a", "b", "c", "d"]);
+    assert_eq!(deserialized.hits_above_score(4.0).count(), 0);
+    assert_eq!(deserialized.hits_above_score(0.0).count(), 3);
+
+    let stats = deserialized.score_stats().unwrap();
+    assert_eq!(stats, ScoreStats { min: 0.5, max: 3.5, mean: 2.0, count: 3 });
+
+    let deserialized: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    assert_eq!(deserialized.score_stats(), None);
+    assert_eq!(deserialized.hits_above_score(0.0).include_unscored(true).count(), 0);
+}
+
+#[test]
+fn test_hit_size_stats() {
+    let s = load_file("tests/samples/hits_huge_document.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+
+    // the sizes are those of the hits written out again as compact JSON
+    let raw: Value = serde_json::from_str(&s).unwrap();
+    let raw_hits = raw.find_path(&["hits", "hits"]).and_then(Value::as_array).unwrap();
+    let sizes: Vec<usize> = raw_hits.iter().map(|hit| serde_json::to_string(hit).unwrap().len()).collect();
+    for (hit, &size) in deserialized.hits().into_iter().zip(&sizes) {
+        assert_eq!(hit.json_size(), size, "{}", hit.id());
+    }
+
+    assert_eq!(deserialized.largest_hits(1), vec!["huge"]);
+    assert_eq!(deserialized.largest_hits(3), vec!["huge", "medium", "with-fields"]);
+    assert_eq!(deserialized.largest_hits(10).len(), 5);
+    assert!(deserialized.largest_hits(0).is_empty());
+
+    let stats: HitSizeStats = deserialized.hit_size_stats().unwrap();
+    let mut sorted = sizes.clone();
+    sorted.sort();
+    assert_eq!(stats.count, 5);
+    assert_eq!(stats.min, sorted[0]);
+    assert_eq!(stats.max, sizes[2]);
+    assert!(stats.max > 10 * sorted[3]);
+    assert_eq!(stats.p50, sorted[2]);
+    assert_eq!(stats.p90, sizes[2]);
+    assert_eq!(stats.p99, sizes[2]);
+    assert_eq!(stats.total, sizes.iter().sum::<usize>());
+    assert_eq!(stats.mean, stats.total as f64 / 5.0);
+
+    let deserialized: Response = serde_json::from_str(&hits_body("0", 0)).unwrap();
+    assert_eq!(deserialized.hit_size_stats(), None);
+    assert!(deserialized.largest_hits(3).is_empty());
+}
+
+#[test]
+fn test_knn_vector_fields() {
+    let s = load_file("tests/samples/knn_search.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let hits: Vec<_> = deserialized.hits().into_iter().collect();
+
+    let vector = hits[0].vector_field("embedding").unwrap();
+    assert_eq!(vector.len(), 768);
+    assert_eq!(vector.capacity(), 768);
+    let expected = hits[0].field_values("embedding");
+    for (v, e) in vector.iter().zip(expected) {
+        assert_eq!(*v, e.as_f64().unwrap() as f32);
+    }
+    assert_eq!(hits[0].source_vector("embedding"), Some(vector));
+    assert_eq!(hits[0].rank(), Some(1));
+
+    // the second hit only has its vector in the source
+    assert_eq!(hits[1].vector_field("embedding"), None);
+    assert_eq!(hits[1].source_vector("embedding").unwrap().len(), 768);
+    assert_eq!(hits[1].source_vector("title"), None);
+    assert_eq!(hits[1].rank(), Some(2));
+    assert_eq!(hits[1].score(), Some(0.8734));
+
+    // fields wrapped in an extra array are unwrapped, and anything but numbers is rejected
+    let deserialized: Response = serde_json::from_str(r#"{
+        "took": 1,
+        "timed_out": false,
+        "_shards": { "total": 1, "successful": 1, "failed": 0 },
+        "hits": { "total": 1, "max_score": 1.0, "hits": [
+            { "_index": "a", "_id": "1", "_score": 1.0,
+              "fields": { "nested": [[0.5, 1, -2.25]], "mixed": [0.5, "x"], "scalar": 1.5 } }
+        ] }
+    }"#).unwrap();
+    let hit = deserialized.hits().iter().next().unwrap();
+    assert_eq!(hit.vector_field("nested"), Some(vec![0.5, 1.0, -2.25]));
+    assert_eq!(hit.vector_field("mixed"), None);
+    assert_eq!(hit.vector_field("scalar"), None);
+    assert_eq!(hit.rank(), None);
+}
+
+#[test]
+fn test_hit_into_raw() {
+    let s = load_file("tests/samples/knn_search.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let expected: Vec<Value> = deserialized.hits().into_iter().map(|h| h.raw()).collect();
+
+    let raw: Vec<Value> = deserialized.into_hits().into_iter().map(|h| h.into_raw()).collect();
+    assert_eq!(raw, expected);
+    assert_eq!(raw[0].find_path(&["fields", "embedding"]).and_then(Value::as_array).map(Vec::len), Some(768));
+}
+
+#[test]
+fn test_msearch_slot_timings() {
+    let s = load_file("tests/samples/msearch.json");
+    let deserialized: MsearchResponse = serde_json::from_str(&s).unwrap();
+
+    assert_eq!(deserialized.len(), 3);
+    assert_eq!(deserialized.took(), Some(21));
+    assert_eq!(deserialized.slot_timings(), vec![Some(12), None, Some(17)]);
+    assert_eq!(deserialized.slowest_slot(), Some((2, 17)));
+
+    let statuses: Vec<_> = deserialized.iter().map(|slot| (slot.status(), slot.is_ok())).collect();
+    assert_eq!(statuses, vec![(Some(200), true), (Some(404), false), (Some(200), true)]);
+
+    let failed = &deserialized.slots()[1];
+    assert!(failed.response().is_none());
+    assert_eq!(failed.error().map(|e| e.error_type()), Some("index_not_found_exception"));
+    assert_eq!(failed.as_result().unwrap_err().get("index"), Some(&Value::String("metrics".to_owned())));
+
+    let first = deserialized.slots()[0].as_result().unwrap();
+    assert_eq!(first.hits().len(), 2);
+
+    let results: Vec<_> = deserialized.into_slots().into_iter().map(|slot| slot.into_result()).collect();
+    assert_eq!(results[2].as_ref().map(|r| r.hits().len()).ok(), Some(0));
+    assert!(results[1].is_err());
+
+    // responses before Elasticsearch 7 have no overall took, and all slots may fail
+    let deserialized: MsearchResponse = serde_json::from_str(r#"{"responses": [
+        { "error": { "type": "search_phase_execution_exception", "reason": "all shards failed" }, "status": 400 }
+    ]}"#).unwrap();
+    assert_eq!(deserialized.took(), None);
+    assert_eq!(deserialized.slot_timings(), vec![None]);
+    assert_eq!(deserialized.slowest_slot(), None);
+
+    assert!(serde_json::from_str::<MsearchResponse>(r#"{"responses": [{ "error": 1, "status": 500 }]}"#).is_err());
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct MsearchLog {
+    level: String,
+    message: String
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct MsearchMetric {
+    name: String,
+    value: f64
+}
+
+#[test]
+fn test_msearch_parts_typed_slots() {
+    let s = load_file("tests/samples/msearch_mixed.json");
+    let (logs, metrics, traces) = MsearchParts::new()
+        .slot::<MsearchLog>()
+        .slot::<MsearchMetric>()
+        .slot::<Value>()
+        .parse(s.as_bytes())
+        .unwrap();
+
+    let logs = logs.into_result().unwrap();
+    let log = MsearchLog { level: "error".to_owned(), message: "disk full".to_owned() };
+    assert_eq!(logs.hits().iter().next().and_then(|h| h.source()), Some(&log));
+
+    assert_eq!(metrics.status(), Some(200));
+    assert_eq!(metrics.took(), Some(6));
+    let values: Vec<_> = metrics.response().unwrap().hits().iter().map(|h| h.source().unwrap().value).collect();
+    assert_eq!(values, vec![0.75, 0.5]);
+
+    assert_eq!(traces.status(), Some(404));
+    assert_eq!(traces.error().map(|e| e.error_type()), Some("index_not_found_exception"));
+
+    // the sources of the first slot don't match the type of the second
+    let mismatched = MsearchParts::new().slot::<MsearchLog>().slot::<MsearchLog>().slot::<Value>();
+    let err = mismatched.parse(s.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("msearch slot 1"), "{}", err);
+
+    let err = MsearchParts::new().slot::<MsearchLog>().slot::<MsearchMetric>().parse(s.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("expected 2 msearch slots, found 3"), "{}", err);
+}
+
+#[test]
+fn test_task_progress() {
+    let s = load_file("tests/samples/task_in_progress.json");
+    let task = parse_slice_as::<TaskResponse>(s.as_bytes()).unwrap();
+    assert!(!task.completed() && task.response().is_none());
+    assert_eq!(task.task().task_id(), "r1A2WoRbTwKZ516z6NEs5A:36619");
+    let status = task.status().unwrap();
+    assert_eq!((status.total(), status.updated(), status.batches()), (Some(6154), 3500, 4));
+    assert_eq!(status.progress().map(|p| (p * 1000.0).round()), Some(569.0));
+    assert_eq!(status.requests_per_second(), None);
+    let elapsed = task.task().running_time().unwrap();
+    assert_eq!(status.throughput_per_sec(elapsed).map(|t| t.round()), Some(591.0));
+    assert_eq!(status.throughput_per_sec(Duration::from_secs(0)), None);
+
+    let s = load_file("tests/samples/task_completed.json");
+    let task = parse_slice_as::<TaskResponse>(s.as_bytes()).unwrap();
+    assert!(task.completed() && task.error().is_none());
+    let response = task.response().unwrap();
+    assert!(response.is_success());
+    assert_eq!(response.took(), 9811);
+    // the finished response and the final task status are the same counts
+    assert_eq!(Some(response.status()), task.task().status());
+    assert_eq!(task.status().and_then(|s| s.progress()), Some(1.0));
+
+    let s = load_file("tests/samples/delete_by_query.json");
+    let response = parse_slice_as::<ByQueryResponse>(s.as_bytes()).unwrap();
+    assert!(!response.is_success());
+    assert_eq!((response.status().deleted(), response.status().version_conflicts()), (115, 5));
+    assert_eq!(response.failures()[0].find("status"), Some(&Value::U64(409)));
+    assert_eq!(response.status().progress().map(|p| (p * 1000.0).round()), Some(958.0));
+
+    let nothing = serde_json::from_str::<ByQueryResponse>(r#"{"took": 1, "total": 0, "failures": []}"#).unwrap();
+    assert_eq!(nothing.status().progress(), None);
+    let unknown = serde_json::from_str::<ByQueryResponse>(r#"{"took": 1}"#).unwrap();
+    assert_eq!(unknown.status().progress(), None);
+}
+
+#[test]
+fn test_flatten_projection() {
+    let s = load_file("tests/samples/aggregation_wide.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+    assert!(aggs.columns().len() > 40, "{:?}", aggs.columns());
+
+    let all = owned_rows(aggs);
+    let rows = aggs.try_rows_with(FlattenConfig::new().project(&["per_day", "per_host", "cpu_avg"])).unwrap();
+    assert_eq!(rows.columns(), vec!["cpu_avg", "per_day", "per_host"]);
+    let rows: Vec<OwnedRow> = rows.collect();
+    assert_eq!(rows.len(), all.len());
+    assert_eq!(rows[1],
+               owned_row(vec![("cpu_avg", Value::F64(15.0)),
+                              ("per_day", Value::U64(1514764800000)),
+                              ("per_host", Value::String("web-02".to_owned()))]));
+    // every row is its full counterpart narrowed down to the projection
+    for (row, full) in rows.iter().zip(&all) {
+        let narrowed: OwnedRow = full.iter()
+            .filter(|&(c, _)| c == "per_day" || c == "per_host" || c == "cpu_avg")
+            .map(|(c, v)| (c.clone(), v.clone()))
+            .collect();
+        assert_eq!(row, &narrowed);
+    }
+
+    // doc counts, key strings and the columns of single bucket and stats aggregations are
+    // projected like any other, and mapping still applies to what's kept
+    let config = FlattenConfig::new()
+        .project(&["per_day_key_as_string", "per_host_doc_count", "errors_doc_count", "disk_std_deviation_bounds_upper"])
+        .map_column("disk_std_deviation_bounds_upper", |v| v.round());
+    let rows: Vec<OwnedRow> = aggs.rows_with(config).collect();
+    assert_eq!(rows[5],
+               owned_row(vec![("disk_std_deviation_bounds_upper", Value::F64(12.0)),
+                              ("errors_doc_count", Value::U64(3)),
+                              ("per_day_key_as_string", Value::String("2018-01-03T00:00:00.000Z".to_owned())),
+                              ("per_host_doc_count", Value::U64(4))]));
+
+    let err = aggs.try_rows_with(FlattenConfig::new().project(&["per_day", "cpu_mean", "disk"])).unwrap_err();
+    match err {
+        AggError::UnknownColumns(ref unknown, ref available) => {
+            assert_eq!(unknown, &vec!["cpu_mean".to_owned(), "disk".to_owned()]);
+            assert_eq!(available, &aggs.columns());
+        }
+        ref other => panic!("unexpected error: {}", other)
+    }
+    assert!(err.to_string().starts_with("no columns named cpu_mean, disk; the rows can have cpu_avg, "), "{}", err);
+    // without checking, unknown columns are left out like the others
+    let rows: Vec<OwnedRow> = aggs.rows_with(FlattenConfig::new().project(&["per_day", "cpu_mean"])).collect();
+    assert!(rows.iter().all(|row| row.keys().collect::<Vec<_>>() == vec!["per_day"]));
+}
+
+#[test]
+fn test_filter_buckets() {
+    let s = load_file("tests/samples/aggregation_wide.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+    let all = owned_rows(aggs);
+    let post_hoc = |f: &Fn(&OwnedRow) -> bool| all.iter().filter(|row| f(row)).cloned().collect::<Vec<_>>();
+
+    let config = FlattenConfig::new().filter_bucket("per_host", BucketFilter::Equals("web-01".to_owned()));
+    let rows: Vec<OwnedRow> = aggs.rows_with(config).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows, post_hoc(&|row| row.get_str("per_host") == Some("web-01")));
+
+    // filters on different levels must all match; days match by key or by `key_as_string`
+    let config = FlattenConfig::new()
+        .filter_bucket("per_day", BucketFilter::OneOf(vec!["2018-01-01T00:00:00.000Z".to_owned(), "1514937600000".to_owned()]))
+        .filter_bucket("per_host", BucketFilter::Prefix("web-0".to_owned()));
+    let rows: Vec<OwnedRow> = aggs.rows_with(config).collect();
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows,
+               post_hoc(&|row| row.get_f64("per_day") != Some(1514851200000.0) &&
+                               row.get_str("per_host").map_or(false, |h| h.starts_with("web-0"))));
+
+    // as must several filters on the same aggregation
+    let config = FlattenConfig::new()
+        .filter_bucket("per_host", BucketFilter::Prefix("web-".to_owned()))
+        .filter_bucket("per_host", BucketFilter::Equals("web-02".to_owned()))
+        .project(&["per_day", "per_host"]);
+    let rows: Vec<OwnedRow> = aggs.rows_with(config).collect();
+    assert_eq!(rows.iter().map(|row| row.get_str("per_host").unwrap()).collect::<Vec<_>>(), vec!["web-02"; 3]);
+
+    let config = FlattenConfig::new().filter_bucket("per_host", BucketFilter::OneOf(Vec::new()));
+    assert_eq!(aggs.rows_with(config).count(), 0);
+}
+
+#[test]
+fn test_lenient_rows_and_bulk_items() {
+    let s = load_file("tests/samples/aggregation_corrupted.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let (rows, problems) = deserialized.aggs().rows_lenient();
+    // problems of the buckets are only found as the iterator gets to them
+    assert_eq!(problems.len(), 1);
+    let rows: Vec<OwnedRow> = rows.map(|row| row.into_iter().map(|(k, v)| (k.into_owned(), v.clone())).collect()).collect();
+    assert_eq!(rows, owned_rows(deserialized.aggs()));
+    assert_eq!(rows.len(), 10);
+    assert_eq!(rows[3]["per_host"], Value::Null);
+
+    let problems = problems.problems();
+    assert_eq!(problems[2].snippet(), "null");
+    assert_eq!(problems[4].snippet(), "42");
+    assert!(problems[3].snippet().starts_with(r#"{"entries":"#), "{}", problems[3]);
+    let found: Vec<(&str, ProblemKind)> = problems.iter().map(|p| (p.path(), p.kind())).collect();
+    assert_eq!(found,
+               vec![("/strange", ProblemKind::UnknownShape),
+                    ("/per_host/buckets/3/key", ProblemKind::NullKey),
+                    ("/per_host/buckets/4", ProblemKind::BucketNotObject),
+                    ("/per_host/buckets/6/latency_histo", ProblemKind::UnknownShape),
+                    ("/per_host/buckets/8", ProblemKind::BucketNotObject),
+                    ("/per_host/buckets/9/per_path/buckets", ProblemKind::MalformedBuckets)]);
+
+    let s = load_file("tests/samples/aggregation_simple.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let (rows, problems) = deserialized.aggs().rows_lenient();
+    assert_eq!(rows.count(), owned_rows(deserialized.aggs()).len());
+    assert!(problems.is_empty());
+
+    let s = load_file("tests/samples/bulk_corrupted.json");
+    assert!(parse_slice_as::<BulkResponse>(s.as_bytes()).is_err());
+    let (bulk, problems) = BulkResponse::parse_lenient(s.as_bytes()).unwrap();
+    assert_eq!(bulk.items().len(), 4);
+    assert_eq!(problems.problems().iter().map(|p| (p.path().to_owned(), p.kind())).collect::<Vec<_>>(),
+               vec![("/items/1".to_owned(), ProblemKind::MalformedItem), ("/items/3".to_owned(), ProblemKind::MalformedItem)]);
+    assert!(problems.problems()[1].snippet().starts_with(r#"{"upsert":"#));
+    // positions are still those of the request
+    assert_eq!(bulk.retryable_items().iter().map(|i| (i.position, i.id)).collect::<Vec<_>>(),
+               vec![(2, Some("2")), (4, Some("4"))]);
+    assert_eq!(bulk.partition().succeeded.iter().map(|i| i.position).collect::<Vec<_>>(), vec![0, 5]);
+}
+
+#[test]
+fn test_parse_typed_aggregations() {
+    let s = load_file("tests/samples/aggregation_typed_keys.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let typed = deserialized.aggs().parse_typed();
+    assert_eq!(typed.keys().collect::<Vec<_>>(), vec!["last_seen", "load", "per_host", "sizes", "status", "users"]);
+
+    let hosts = match typed["per_host"] {
+        AggValue::Terms(ref terms) => terms,
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    };
+    assert_eq!(hosts.sum_other_doc_count, Some(4));
+    assert_eq!(hosts.buckets[0].key, BucketKey::Str("web-01".to_owned()));
+    let days = match hosts.buckets[0].aggs["per_day"] {
+        AggValue::DateHistogram(ref days) => days,
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    };
+    assert_eq!(days.buckets.iter().map(|b| (b.key, b.doc_count)).collect::<Vec<_>>(),
+               vec![(1514764800000, 2), (1514851200000, 3)]);
+    assert_eq!(days.buckets[0].aggs["cpu"], AggValue::SingleValue(SingleValueAgg { value: Some(12.5), value_as_string: None }));
+    assert_eq!(days.buckets[1].aggs["cpu"], AggValue::SingleValue(SingleValueAgg { value: None, value_as_string: None }));
+    match days.buckets[1].aggs["latency"] {
+        AggValue::Stats(ref stats) => {
+            assert_eq!((stats.count, stats.max, stats.sum, stats.variance), (3, Some(41.0), 75.0, None));
+        }
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    }
+    match hosts.buckets[0].aggs["errors"] {
+        AggValue::Filter(ref filter) => {
+            assert_eq!(filter.doc_count, 1);
+            assert_eq!(filter.aggs["count"], AggValue::SingleValue(SingleValueAgg { value: Some(1.0), value_as_string: None }));
+        }
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    }
+
+    match typed["sizes"] {
+        AggValue::Histogram(ref sizes) => assert_eq!(sizes.buckets.iter().map(|b| b.key).collect::<Vec<_>>(), vec![0.0, 500.0, 1000.0]),
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    }
+    match typed["status"] {
+        AggValue::Terms(ref status) => assert_eq!(status.buckets[1].key, BucketKey::I64(503)),
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    }
+    assert_eq!(typed["last_seen"],
+               AggValue::SingleValue(SingleValueAgg {
+                   value: Some(1514851200000.0),
+                   value_as_string: Some("2018-01-02T00:00:00.000Z".to_owned())
+               }));
+    // percentiles aren't typed
+    assert_eq!(typed["load"], AggValue::Raw(deserialized.aggs().raw_agg("tdigest_percentiles#load").unwrap().clone()));
+
+    let json = serde_json::to_value(&typed["users"]);
+    assert_eq!(json.find_path(&["SingleValue", "value"]), Some(&Value::F64(7.0)));
+
+    // without typed keys the types are guessed from the fields
+    let s = load_file("tests/samples/aggregation_3level_multistats.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let typed = deserialized.aggs().parse_typed();
+    let chart = match typed["timechart"] {
+        AggValue::DateHistogram(ref chart) => chart,
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    };
+    let hosts = match chart.buckets[0].aggs["hosts"] {
+        AggValue::Terms(ref hosts) => hosts,
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    };
+    let addresses = match hosts.buckets[0].aggs["sourceAddresses"] {
+        AggValue::Terms(ref addresses) => addresses,
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    };
+    match addresses.buckets[0].aggs["extstats_ack_pkts_sent"] {
+        AggValue::Stats(ref stats) => assert_eq!((stats.count, stats.std_deviation), (2, Some(5.0))),
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    }
+    match addresses.buckets[0].aggs["min_ack_pkts_sent"] {
+        AggValue::SingleValue(ref min) => assert_eq!(min.value, Some(2.0)),
+        ref other => panic!("unexpected aggregation: {:?}", other)
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_prelude_and_es_response_alias() {
+    use elastic_responses::prelude::*;
+
+    let s = load_file("tests/samples/aggregation_simple.json");
+    let response: EsResponse = parse_slice(s.as_bytes()).unwrap();
+    let plain: Response = response;
+    assert_eq!(plain.aggs().into_iter().count(), owned_rows(plain.aggs()).len());
+}
+
+fn timestamped_page(scroll_id: &str, docs: &[(&str, &str)]) -> Response {
+    let hits: Vec<String> = docs.iter()
+        .map(|&(id, timestamp)| {
+            format!(r#"{{"_index": "test", "_type": "doc", "_id": "{}", "_score": 1, "_source": {{"event": {{"timestamp": {}}}}}}}"#,
+                    id,
+                    timestamp)
+        })
+        .collect();
+    serde_json::from_str(&scroll_body(scroll_id, 1, &[]).replace("\"hits\": []", &format!("\"hits\": [{}]", hits.join(","))))
+        .unwrap()
+}
+
+#[test]
+fn test_sort_hits_by_path() {
+    let mut hits = timestamped_page("s", &[("str", r#""b""#),
+                                           ("null", "null"),
+                                           ("float", "2.5"),
+                                           ("int", "3"),
+                                           ("neg", "-1"),
+                                           ("other", r#""a""#)])
+        .into_hits();
+    sort_hits_by_path(&mut hits, "event.timestamp", Order::Asc);
+    let ids: Vec<_> = hits.iter().map(Hit::id).collect();
+    assert_eq!(ids, vec!["neg", "float", "int", "other", "str", "null"]);
+
+    sort_hits_by_path(&mut hits, "event.timestamp", Order::Desc);
+    let ids: Vec<_> = hits.iter().map(Hit::id).collect();
+    assert_eq!(ids, vec!["str", "other", "int", "float", "neg", "null"]);
+}
+
+#[test]
+fn test_merge_sorted_pages() {
+    let pages = || {
+        vec![timestamped_page("s1", &[("a", "90"), ("b", "60"), ("c", "10")]),
+             timestamped_page("s2", &[("d", "95"), ("e", "60"), ("f", "null")]),
+             timestamped_page("s3", &[("g", "70"), ("h", "20"), ("i", "5")])]
+    };
+
+    let merged = merge_sorted_hits(pages().into_iter().map(Response::into_hits).collect(), "event.timestamp", Order::Desc);
+    let ids: Vec<_> = merged.iter().map(Hit::id).collect();
+    // ties are taken from the earlier page, and hits without a timestamp come last
+    assert_eq!(ids, vec!["d", "a", "g", "b", "e", "h", "c", "i", "f"]);
+
+    let mut stitcher = ScrollStitcher::new().sort_by_path("event.timestamp", Order::Desc);
+    for page in pages() {
+        stitcher.push(page).unwrap();
+    }
+    let ids: Vec<_> = stitcher.hits().map(Hit::id).collect();
+    assert_eq!(ids, vec!["d", "a", "g", "b", "e", "h", "c", "i", "f"]);
+
+    // a page pushed after a drain is only merged with the hits left
+    assert_eq!(stitcher.drain().count(), 9);
+    stitcher.push(timestamped_page("s4", &[("j", "80"), ("k", "1")])).unwrap();
+    let ids: Vec<_> = stitcher.hits().map(Hit::id).collect();
+    assert_eq!(ids, vec!["j", "k"]);
+}
+
+#[test]
+fn test_script_errors() {
+    let s = load_file("tests/samples/error_painless_compile.json");
+    let err = match parse_http_response::<Response>(400, s.as_bytes()) {
+        Err(ResponseError::Api(400, err)) => err,
+        other => panic!("expected an api error, got {:?}", other),
+    };
+    let script = err.script_error().unwrap();
+    assert!(script.is_compile_error());
+    assert_eq!(script.lang(), Some("painless"));
+    assert_eq!(script.script(), Some("Math.log(doc['price'].value * params.factor"));
+    assert_eq!(script.script_stack().len(), 2);
+    assert!(script.script_stack()[1].ends_with("^---- HERE"));
+    assert_eq!(script.position(), Some(ScriptPosition { offset: 43, start: 9, end: 43 }));
+    assert_eq!(script.statement(), Some("doc['price'].value * params.factor".to_owned()));
+    // the cause is only in the reason of the failed shard
+    assert_eq!(script.caused_by().unwrap().reason(), Some("unexpected end of script."));
+    assert!(err.mapper_error().is_none());
+
+    let s = load_file("tests/samples/error_painless_runtime.json");
+    let err = match parse_http_response::<Response>(400, s.as_bytes()) {
+        Err(ResponseError::Api(400, err)) => err,
+        other => panic!("expected an api error, got {:?}", other),
+    };
+    let script = err.script_error().unwrap();
+    assert!(!script.is_compile_error());
+    assert_eq!(script.reason(), Some("runtime error"));
+    assert!(script.script_stack()[0].starts_with("org.elasticsearch.index.fielddata.ScriptDocValues"));
+    assert_eq!(script.position().map(|p| p.offset), Some(15));
+    assert_eq!(script.statement().as_ref().map(|s| &**s), script.script());
+    assert_eq!(script.caused_by().unwrap().error_type(), "illegal_state_exception");
+
+    // a bare root cause still has the script, without a cause
+    let bare = api_error(r#"{"type": "script_exception", "reason": "compile error", "lang": "painless"}"#);
+    let script = bare.script_error().unwrap();
+    assert_eq!(script.lang(), Some("painless"));
+    assert!(script.position().is_none() && script.statement().is_none() && script.caused_by().is_none());
+    assert!(api_error(r#"{"type": "index_not_found_exception", "reason": "no such index [logs]"}"#).script_error().is_none());
+}
+
+#[test]
+fn test_mapper_errors() {
+    let s = load_file("tests/samples/error_mapper_parsing.json");
+    let err = match parse_http_response::<Response>(400, s.as_bytes()) {
+        Err(ResponseError::Api(400, err)) => err,
+        other => panic!("expected an api error, got {:?}", other),
+    };
+    let mapper = err.mapper_error().unwrap();
+    assert_eq!(mapper.error_type(), "mapper_parsing_exception");
+    assert_eq!(mapper.field(), "price");
+    assert_eq!(mapper.expected_type(), Some("float"));
+    assert_eq!(mapper.provided_type(), None);
+    assert_eq!(mapper.document_id(), Some("42"));
+    assert_eq!(mapper.value_preview(), Some("twelve"));
+    assert!(err.script_error().is_none());
+
+    let located = api_error(r#"{"type": "document_parsing_exception",
+        "reason": "[1:15] failed to parse field [user.age] of type [long] in document with id 'a'b'. Preview of field's value: 'it's old'"}"#);
+    let mapper = located.mapper_error().unwrap();
+    assert_eq!(mapper.field(), "user.age");
+    assert_eq!(mapper.expected_type(), Some("long"));
+    assert_eq!(mapper.document_id(), Some("a"));
+    assert_eq!(mapper.value_preview(), Some("it's old"));
+
+    let old = api_error(r#"{"type": "mapper_parsing_exception", "reason": "failed to parse [bytes]"}"#).mapper_error().unwrap();
+    assert_eq!((old.field(), old.expected_type(), old.document_id()), ("bytes", None, None));
+
+    let changed = api_error(r#"{"type": "illegal_argument_exception",
+        "reason": "mapper [price] cannot be changed from type [float] to [keyword]"}"#);
+    let mapper = changed.mapper_error().unwrap();
+    assert_eq!((mapper.field(), mapper.expected_type(), mapper.provided_type()), ("price", Some("float"), Some("keyword")));
+
+    let merged = api_error(r#"{"type": "illegal_argument_exception",
+        "reason": "mapper [price] of different type, current_type [float], merged_type [text]"}"#);
+    let mapper = merged.mapper_error().unwrap();
+    assert_eq!((mapper.expected_type(), mapper.provided_type()), (Some("float"), Some("text")));
+
+    let strict = api_error(r#"{"type": "index_failed_exception", "reason": "failed to index",
+        "caused_by": {"type": "strict_dynamic_mapping_exception",
+            "reason": "mapping set to strict, dynamic introduction of [nickname] within [_doc] is not allowed"}}"#);
+    let mapper = strict.mapper_error().unwrap();
+    assert_eq!((mapper.error_type(), mapper.field()), ("strict_dynamic_mapping_exception", "nickname"));
+
+    let object = api_error(r#"{"type": "mapper_parsing_exception",
+        "reason": "object mapping for [user] tried to parse field [user] as object, but found a concrete value"}"#);
+    assert_eq!(object.mapper_error().unwrap().expected_type(), Some("object"));
+
+    let structured = api_error(r#"{"type": "mapper_parsing_exception", "reason": "failed to parse [bytes]", "field": "http.bytes"}"#);
+    assert_eq!(structured.mapper_error().unwrap().field(), "http.bytes");
+
+    // other illegal arguments aren't mapping errors
+    assert!(api_error(r#"{"type": "illegal_argument_exception", "reason": "unknown setting [index.foo]"}"#).mapper_error().is_none());
+}
+
+fn widths(aggs: &Aggregations, agg: &str) -> Vec<Option<f64>> {
+    let column = format!("{}_width", agg);
+    aggs.rows_with(FlattenConfig::new().emit_bucket_width(true))
+        .filter(|row| row.contains_key(agg))
+        .map(|row| row.get(&column).and_then(Value::as_f64))
+        .collect()
+}
+
+#[test]
+fn test_bucket_width_regular() {
+    let s = load_file("tests/samples/aggregation_histogram_regular.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+    let day = 86400000.0;
+
+    assert_eq!(aggs.inferred_interval("per_day"), Some(day));
+    assert_eq!(aggs.inferred_interval("by_week"), Some(7.0 * day));
+    // terms keys aren't spaced out
+    assert_eq!(aggs.inferred_interval("per_host"), None);
+    assert_eq!(aggs.inferred_interval("missing"), None);
+
+    // every host row of a day has the width of the day
+    assert_eq!(widths(aggs, "per_day"), vec![Some(day); 6]);
+    assert_eq!(widths(aggs, "by_week"), vec![Some(7.0 * day); 2]);
+    let rows = aggs.rows_with(FlattenConfig::new().emit_bucket_width(true));
+    assert!(rows.columns().contains(&"per_day_width".to_owned()));
+    assert!(!rows.columns().contains(&"per_host_width".to_owned()));
+
+    // the width is off unless asked for, and projected like any other column
+    assert!(aggs.rows_with(FlattenConfig::new()).all(|row| !row.contains_key("per_day_width")));
+    let config = FlattenConfig::new().emit_bucket_width(true).project(&["per_day", "per_day_width"]);
+    let rows: Vec<OwnedRow> = aggs.try_rows_with(config).unwrap().collect();
+    // `by_week` comes first, with none of the columns
+    assert_eq!(rows[2], owned_row(vec![("per_day", Value::U64(1700006400000)), ("per_day_width", Value::F64(day))]));
+    assert!(aggs.try_rows_with(FlattenConfig::new().project(&["per_day_width"])).is_err());
+}
+
+#[test]
+fn test_bucket_width_irregular() {
+    let s = load_file("tests/samples/aggregation_histogram_irregular.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+
+    assert_eq!(aggs.inferred_interval("latency"), Some(50.0));
+    // each bucket reaches to the next key, and the last one is as wide as most
+    assert_eq!(widths(aggs, "latency"), vec![Some(50.0), Some(50.0), Some(100.0), Some(50.0), Some(50.0)]);
+    // numeric terms keys in ascending order are still terms
+    assert_eq!(aggs.inferred_interval("status"), None);
+    assert_eq!(widths(aggs, "status"), vec![None; 3]);
+}
+
+#[test]
+fn test_bucket_width_single_bucket() {
+    let s = load_file("tests/samples/aggregation_histogram_single.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+
+    assert_eq!(aggs.inferred_interval("per_day"), None);
+    assert_eq!(widths(aggs, "per_day"), vec![None]);
+    // the interval of an `auto_date_histogram` doesn't need a second bucket
+    assert_eq!(aggs.inferred_interval("auto"), None);
+    assert_eq!(widths(aggs, "auto"), vec![Some(3600000.0)]);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct FieldsEvent {
+    #[serde(rename = "@timestamp")]
+    timestamp: String,
+    #[serde(rename = "host.name")]
+    host: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    bytes: u64,
+    #[serde(rename = "http.status")]
+    status: Option<u16>,
+    manager: FieldsManager
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct FieldsManager {
+    name: String,
+    age: u32
+}
+
+#[test]
+fn test_fields_doc() {
+    let s = load_file("tests/samples/hits_fields_api.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let hits: Vec<&Hit<Value>> = deserialized.hits().iter().collect();
+    assert!(hits.iter().all(|h| h.source().is_none()));
+
+    let docs: Vec<FieldsEvent> = hits.iter().map(|h| h.fields_doc().unwrap()).collect();
+    assert_eq!(docs[0],
+               FieldsEvent {
+                   timestamp: "2024-03-01T10:00:00.000Z".to_owned(),
+                   host: "web-01".to_owned(),
+                   tags: vec!["prod".to_owned(), "eu".to_owned()],
+                   bytes: 512,
+                   status: Some(200),
+                   manager: FieldsManager { name: "Alice".to_owned(), age: 52 }
+               });
+    // a single value stays a list for a `Vec`, and missing fields are `None` or defaulted
+    assert_eq!(docs[1].tags, vec!["prod".to_owned()]);
+    assert_eq!(docs[1].status, None);
+    assert_eq!(docs[2].tags, Vec::<String>::new());
+    assert_eq!(docs[2].manager.name, "Alice");
+
+    // several values can't be read as one
+    #[derive(Deserialize, Debug)]
+    struct OneTag {
+        #[allow(dead_code)]
+        tags: String
+    }
+    assert!(hits[1].fields_doc::<OneTag>().is_ok());
+    match hits[0].fields_doc::<OneTag>() {
+        Err(Error::Json(_)) => (),
+        other => panic!("expected a json error, got {:?}", other),
+    }
+    // what isn't deserialized into a type is left as sent
+    let raw: BTreeMap<String, Value> = hits[1].fields_doc().unwrap();
+    assert_eq!(raw["bytes"], Value::Array(vec![Value::U64(2048)]));
+
+    let table = deserialized.fields_table(&["host.name", "tags", "http.status"]);
+    assert_eq!(table.len(), 3);
+    assert_eq!(table.column_str("host.name").unwrap(), vec![Some("web-01"), Some("web-02"), Some("web-01")]);
+    assert_eq!(table.column("tags").unwrap(),
+               &[Some(Value::Array(vec![Value::String("prod".to_owned()), Value::String("eu".to_owned())])),
+                 Some(Value::String("prod".to_owned())),
+                 None][..]);
+    assert_eq!(table.column_f64("http.status").unwrap(), vec![Some(200.0), None, Some(503.0)]);
+    assert_eq!(table.null_count("http.status"), Some(1));
+}
+
+#[test]
+fn test_partial_reasons() {
+    let s = load_file("tests/samples/search_partial_results.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert!(deserialized.timed_out());
+    assert!(!deserialized.terminated_early());
+    assert_eq!(deserialized.shards().skipped(), 2);
+    let failure = &deserialized.shards().failures()[0];
+    assert_eq!((failure.index(), failure.shard()), (Some("eu:logs-2024.03.01"), Some(1)));
+    assert_eq!(failure.reason().error_type(), "illegal_argument_exception");
+
+    let clusters = deserialized.clusters().unwrap();
+    assert_eq!((clusters.total(), clusters.successful(), clusters.skipped(), clusters.partial()), (3, 1, 1, 1));
+    let apac = clusters.get("apac").unwrap();
+    assert_eq!(apac.status(), "skipped");
+    assert!(apac.took().is_none() && apac.shards().is_none());
+    assert_eq!(apac.failures()[0].shard(), None);
+    assert_eq!(clusters.get("eu").unwrap().shards().map(Shards::failed), Some(1));
+    assert!(clusters.get("(local)").unwrap().is_complete());
+
+    let reasons = deserialized.partial_reasons();
+    assert_eq!(reasons.reasons(),
+               &[PartialReason::TimedOut,
+                 PartialReason::ShardsFailed {
+                     failed: 1,
+                     total: 8,
+                     reasons: vec!["eu:logs-2024.03.01[1]: illegal_argument_exception: Text fields are not optimised for \
+                                    operations that require per-document field data"
+                                       .to_owned()]
+                 },
+                 PartialReason::ClustersSkipped {
+                     count: 2,
+                     statuses: vec![("apac".to_owned(), "skipped".to_owned()), ("eu".to_owned(), "partial".to_owned())]
+                 }][..]);
+    assert_eq!(reasons.to_string(),
+               "timed out; 1 of 8 shards failed (eu:logs-2024.03.01[1]: illegal_argument_exception: Text fields are not \
+                optimised for operations that require per-document field data); 2 clusters incomplete (apac: skipped, \
+                eu: partial)");
+
+    // summaries without details still count the clusters
+    let body = s.replace(r#""terminated_early": false"#, r#""terminated_early": true"#);
+    let mut value: Value = serde_json::from_str(&body).unwrap();
+    value.as_object_mut().unwrap().get_mut("_clusters").unwrap().as_object_mut().unwrap().remove("details");
+    let summary: Response = serde_json::from_value(value).unwrap();
+    assert_eq!(summary.partial_reasons().reasons()[1], PartialReason::TerminatedEarly);
+    assert_eq!(summary.partial_reasons().reasons()[3], PartialReason::ClustersSkipped { count: 2, statuses: vec![] });
+
+    let complete: Response = serde_json::from_str(&hits_body("3", 3)).unwrap();
+    assert!(complete.partial_reasons().is_empty());
+    assert_eq!(complete.partial_reasons().to_string(), "");
+    assert!(complete.clusters().is_none());
+}
+
+#[test]
+fn test_duplicate_names_overwrite() {
+    let s = load_file("tests/samples/aggregation_same_name_nested.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+
+    let rows: Vec<OwnedRow> = aggs.rows_with(FlattenConfig::new().duplicate_names(DuplicateNames::Overwrite)).collect();
+    assert_eq!(rows, aggs.rows_with(FlattenConfig::new()).collect::<Vec<_>>());
+    assert_eq!(rows,
+               vec![owned_row(vec![("by_host", Value::String("web-01.eu".to_owned())), ("by_host_doc_count", Value::U64(3))]),
+                    owned_row(vec![("by_host", Value::String("web-01.us".to_owned())), ("by_host_doc_count", Value::U64(1))]),
+                    owned_row(vec![("by_host", Value::String("web-02.eu".to_owned())), ("by_host_doc_count", Value::U64(3))])]);
+    assert_eq!(aggs.rows_with(FlattenConfig::new()).columns(), vec!["by_host", "by_host_doc_count"]);
+}
+
+#[test]
+fn test_duplicate_names_error() {
+    let s = load_file("tests/samples/aggregation_same_name_nested.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let config = || FlattenConfig::new().duplicate_names(DuplicateNames::Error);
+
+    let err = deserialized.aggs().try_rows_with(config()).unwrap_err();
+    assert_eq!(err, AggError::DuplicateName("by_host>by_host".to_owned()));
+    assert_eq!(err.to_string(), "aggregation `by_host>by_host` is nested in one of the same name, so their columns collide");
+
+    // names shared by aggregations that aren't nested in one another are fine
+    let s = load_file("tests/samples/aggregation_date_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    assert_eq!(deserialized.aggs().try_rows_with(config()).unwrap().count(), 5);
+}
+
+#[test]
+fn test_duplicate_names_disambiguate() {
+    let s = load_file("tests/samples/aggregation_same_name_nested.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let aggs = deserialized.aggs();
+    let config = || FlattenConfig::new().duplicate_names(DuplicateNames::Disambiguate);
+
+    let rows = aggs.rows_with(config());
+    assert_eq!(rows.columns(), vec!["by_host", "by_host.1", "by_host.1_doc_count", "by_host_doc_count"]);
+    let rows: Vec<OwnedRow> = rows.collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1],
+               owned_row(vec![("by_host", Value::String("web-01".to_owned())),
+                              ("by_host.1", Value::String("web-01.us".to_owned())),
+                              ("by_host.1_doc_count", Value::U64(1)),
+                              ("by_host_doc_count", Value::U64(4))]));
+    assert_eq!(rows, aggs.rows_with(config()).collect::<Vec<_>>());
+
+    let projected: Vec<OwnedRow> = aggs.try_rows_with(config().project(&["by_host", "by_host.1"])).unwrap().collect();
+    assert_eq!(projected[2],
+               owned_row(vec![("by_host", Value::String("web-02".to_owned())),
+                              ("by_host.1", Value::String("web-02.eu".to_owned()))]));
+    let nulls: Vec<OwnedRow> = aggs.rows_with(config().skip_null_keys()).collect();
+    assert_eq!(nulls, rows);
+}
+
+#[test]
+fn test_row_accessors() {
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let row: Row = deserialized.aggs().into_iter().next().unwrap();
+
+    assert_eq!(row.len(), 3);
+    assert!(!row.is_empty());
+    assert!(row.contains("hosts_doc_count"));
+    assert!(!row.contains("hosts_key_as_string"));
+    assert_eq!(row.value("hosts"), Some(&Value::String("web-03".to_owned())));
+    assert_eq!(row.get_f64("cpu"), Some(12.5));
+    let columns: Vec<(&str, &Value)> = row.columns().collect();
+    assert_eq!(columns.iter().map(|&(name, _)| name).collect::<Vec<_>>(), vec!["cpu", "hosts", "hosts_doc_count"]);
+    assert_eq!(row.clone().into_owned(), owned_rows(deserialized.aggs())[0]);
+
+    // values outlive the row they were read from
+    let hosts: Vec<&Value> = deserialized.aggs().into_iter().filter_map(|row| row.value("hosts")).collect();
+    assert_eq!(hosts.len(), 5);
+}
+
+/// Code written against the `RowData` map keeps compiling, with deprecation warnings.
+#[allow(deprecated)]
+#[test]
+fn test_row_data_deprecated_map() {
+    use elastic_responses::RowData;
+
+    let s = load_file("tests/samples/aggregation_terms_avg.json");
+    let deserialized: Response = serde_json::from_str(&s).unwrap();
+    let mut row = deserialized.aggs().into_iter().next().unwrap();
+
+    let map: &RowData = &row;
+    assert_eq!(map.get("hosts").map(|v| *v), Some(&Value::String("web-03".to_owned())));
+    assert_eq!(map.keys().map(|k| k.to_string()).collect::<Vec<_>>(), vec!["cpu", "hosts", "hosts_doc_count"]);
+    assert!(row.remove("cpu").is_some());
+    let names: Vec<String> = row.into_iter().map(|(name, _)| name.into_owned()).collect();
+    assert_eq!(names, vec!["hosts", "hosts_doc_count"]);
+}
+
+#[cfg(feature = "compression")]
+fn load_bytes(p: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    File::open(p).unwrap().read_to_end(&mut body).unwrap();
+    body
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_parse_compressed_bodies() {
+    use elastic_responses::Compression;
+
+    let plain: Response = serde_json::from_str(&load_file("tests/samples/aggregation_terms_avg.json")).unwrap();
+    let gzip = load_bytes("tests/compressed/aggregation_terms_avg.json.gz");
+    let deflate = load_bytes("tests/compressed/aggregation_terms_avg.json.deflate");
+
+    for response in vec![Response::from_gzip_slice(&gzip).unwrap(),
+                         Response::from_gzip_reader(&gzip[..]).unwrap(),
+                         Response::from_deflate_slice(&deflate).unwrap(),
+                         Response::from_deflate_reader(&deflate[..]).unwrap()] {
+        assert_eq!(response.took(), plain.took());
+        assert_eq!(owned_rows(response.aggs()), owned_rows(plain.aggs()));
+    }
+
+    // some servers send raw deflate streams rather than zlib ones
+    let raw = &deflate[2..deflate.len() - 4];
+    assert_eq!(owned_rows(Response::from_deflate_slice(raw).unwrap().aggs()), owned_rows(plain.aggs()));
+
+    assert_eq!(Compression::from_content_encoding("GZIP"), Some(Compression::Gzip));
+    assert_eq!(Compression::from_content_encoding("deflate"), Some(Compression::Deflate));
+    assert_eq!(Compression::from_content_encoding("br"), None);
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_parse_compressed_bodies_errors() {
+    use elastic_responses::Compression;
+
+    let mut gzip = load_bytes("tests/compressed/aggregation_terms_avg.json.gz");
+    let truncated = gzip[..gzip.len() / 2].to_vec();
+    let middle = gzip.len() / 2;
+    gzip[middle] ^= 0xff;
+
+    for body in &[gzip, truncated] {
+        match Response::from_gzip_slice(body) {
+            Err(ref err @ Error::Decompress(_)) => assert!(err.to_string().starts_with("failed to decompress response body: ")),
+            other => panic!("expected a decompression error, got {:?}", other.map(|r| r.took()))
+        }
+    }
+    assert!(match Response::from_deflate_slice(b"not deflate") {
+        Err(Error::Decompress(_)) => true,
+        _ => false
+    });
+
+    // the limit applies to the decompressed body
+    let gzip = load_bytes("tests/compressed/aggregation_terms_avg.json.gz");
+    let limited = ResponseParser::new().max_body_bytes(gzip.len() as u64 + 1).parse_compressed_reader::<_, Value>(&gzip[..], Compression::Gzip);
+    assert!(match limited {
+        Err(Error::LimitExceeded { kind: Limit::BodyBytes, .. }) => true,
+        _ => false
+    });
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_responses_from_compressed_ndjson() {
+    use elastic_responses::{Compression, responses_from_compressed_ndjson};
+
+    let gzip = load_bytes("tests/compressed/responses.ndjson.gz");
+    let responses: Vec<Response> = responses_from_compressed_ndjson(&gzip[..], Compression::Gzip).unwrap().map(Result::unwrap).collect();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[1].hits().into_iter().count(), 5);
+
+    // garbled lines can come out before the checksum fails, which ends the stream
+    let mut corrupt = gzip.clone();
+    let middle = corrupt.len() / 2;
+    corrupt[middle] ^= 0xff;
+    let results: Vec<Result<Response, Error>> = responses_from_compressed_ndjson(&corrupt[..], Compression::Gzip).unwrap().collect();
+    assert_eq!(results.iter().filter(|r| match **r {
+                   Err(Error::Decompress(_)) => true,
+                   _ => false
+               }).count(),
+               1);
+    assert!(match results.last() {
+        Some(&Err(Error::Decompress(_))) => true,
+        _ => false
+    });
+}
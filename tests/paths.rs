@@ -0,0 +1,50 @@
+//! Checks that the types exported at the root of the crate are the same as those of the modules
+//! they live in; it passes as soon as it compiles.
+
+extern crate elastic_responses;
+extern crate serde_json;
+
+use serde_json::Value;
+use std::marker::PhantomData;
+
+fn same<T>(_: PhantomData<T>, _: PhantomData<T>) {}
+
+#[test]
+fn test_search_paths() {
+    same(PhantomData::<elastic_responses::Response>, PhantomData::<elastic_responses::search::Response>);
+    same(PhantomData::<elastic_responses::ResponseOf<Value>>,
+         PhantomData::<elastic_responses::search::ResponseOf<Value>>);
+    same(PhantomData::<elastic_responses::Hits<Value>>, PhantomData::<elastic_responses::search::Hits<Value>>);
+    same(PhantomData::<elastic_responses::Hit<Value>>, PhantomData::<elastic_responses::search::Hit<Value>>);
+    same(PhantomData::<elastic_responses::TotalHits>, PhantomData::<elastic_responses::search::TotalHits>);
+    same(PhantomData::<elastic_responses::AggsResponse>, PhantomData::<elastic_responses::search::AggsResponse>);
+    same(PhantomData::<elastic_responses::MsearchResponse>, PhantomData::<elastic_responses::search::MsearchResponse>);
+    same(PhantomData::<elastic_responses::PageInfo>, PhantomData::<elastic_responses::search::PageInfo>);
+}
+
+#[test]
+fn test_aggregation_paths() {
+    use elastic_responses::search::aggregations;
+
+    same(PhantomData::<elastic_responses::Aggregations>, PhantomData::<aggregations::Aggregations>);
+    same(PhantomData::<elastic_responses::OwnedAggregations>, PhantomData::<aggregations::OwnedAggregations>);
+    same(PhantomData::<elastic_responses::AggError>, PhantomData::<aggregations::AggError>);
+    same(PhantomData::<elastic_responses::OwnedRow>, PhantomData::<aggregations::OwnedRow>);
+    same(PhantomData::<elastic_responses::AggregationIterator>,
+         PhantomData::<aggregations::AggregationIterator>);
+
+    fn row_access<R: elastic_responses::RowAccess>(row: R) -> Option<f64> {
+        aggregations::RowAccess::get_f64(&row, "doc_count")
+    }
+    assert_eq!(row_access(aggregations::OwnedRow::new()), None);
+}
+
+#[test]
+fn test_bulk_error_and_common_paths() {
+    same(PhantomData::<elastic_responses::BulkResponse>, PhantomData::<elastic_responses::bulk::BulkResponse>);
+    same(PhantomData::<elastic_responses::BulkItem>, PhantomData::<elastic_responses::bulk::BulkItem>);
+    same(PhantomData::<elastic_responses::Error>, PhantomData::<elastic_responses::error::Error>);
+    same(PhantomData::<elastic_responses::ApiError>, PhantomData::<elastic_responses::error::ApiError>);
+    same(PhantomData::<elastic_responses::ResponseError>, PhantomData::<elastic_responses::error::ResponseError>);
+    same(PhantomData::<elastic_responses::Shards>, PhantomData::<elastic_responses::common::Shards>);
+}